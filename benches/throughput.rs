@@ -0,0 +1,41 @@
+//! Criterion benchmarks of the removal path: native analysis and ExifTool removal.
+//!
+//! Run with `cargo bench`; the ExifTool group is skipped when ExifTool isn't installed.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use privacy_exif_cleaner::bench::synthetic_corpus;
+use privacy_exif_cleaner::{ExifAnalyzer, MetadataRemover, PrivacyLevel};
+
+fn native(c: &mut Criterion) {
+    let analyzer = ExifAnalyzer::new();
+    let mut group = c.benchmark_group("native");
+
+    for data in synthetic_corpus(3) {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::new("analyze", data.len()), &data, |b, data| {
+            b.iter(|| analyzer.analyze_bytes(data, &PrivacyLevel::Standard).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn exiftool(c: &mut Criterion) {
+    let remover = MetadataRemover::new();
+    if remover.get_exiftool_version().is_err() {
+        eprintln!("ExifTool not found; skipping the exiftool benchmarks");
+        return;
+    }
+    let mut group = c.benchmark_group("exiftool");
+    group.sample_size(20);
+
+    for data in synthetic_corpus(3) {
+        group.throughput(Throughput::Bytes(data.len() as u64));
+        group.bench_with_input(BenchmarkId::new("remove", data.len()), &data, |b, data| {
+            b.iter(|| remover.remove_privacy_data_from_bytes(data, &PrivacyLevel::Standard).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, native, exiftool);
+criterion_main!(benches);
@@ -0,0 +1,159 @@
+# Meldungen von privacy-exif-cleaner auf Deutsch.
+#
+# Übersetzt die Meldungen aus en.ftl; fehlende werden auf Englisch angezeigt.
+
+## Kommandozeile
+
+app-about = Entfernt datenschutzrelevante Angaben aus EXIF-Daten und erhält die technischen Metadaten
+clean-about = Bilder in einem Verzeichnis oder Archiv bereinigen (Standard ohne Unterbefehl)
+lang-help = Sprache der Meldungen, z. B. en (Standard: $PRIVACY_CLEANER_LANG, dann die Systemsprache)
+
+## Zusammenfassung
+
+summary-heading = Zusammenfassung:
+summary-processed = Verarbeitete Dateien: { $count }
+summary-privacy-found = Dateien mit personenbezogenen Daten: { $count }
+summary-already-clean = Bereits saubere Dateien (unverändert): { $count }
+summary-errors = Fehler: { $count }
+summary-fields-removed = Entfernte Datenschutzfelder: { $count }
+summary-fields-found = Gefundene Datenschutzfelder (nichts entfernt): { $count }
+summary-bytes-removed = Entfernte Metadaten: { $size }
+summary-bytes-removable = Metadaten, die entfernt würden: etwa { $size }
+summary-throughput = { $size } in { $seconds } s verarbeitet
+summary-unsupported-skipped = Übersprungene nicht unterstützte Dateien: { $count }
+summary-unsupported-copied = Unbereinigt kopierte nicht unterstützte Dateien: { $count }
+summary-not-selected = Dateien außerhalb des gewählten Datums-/Größenbereichs: { $count }
+summary-stopped = Lauf vorzeitig beendet: { $reason } (nicht alle Dateien wurden verarbeitet)
+summary-cancelled = Lauf abgebrochen (nicht alle Dateien wurden verarbeitet)
+
+## Datenschutzkategorien
+
+category-location = Standortdaten
+category-device-identifier = Gerätekennung
+category-personal-info = Persönliche Angaben
+category-temporal = Zeitstempel
+category-software = Softwareangaben
+category-metadata = Metadaten
+category-other = Sonstiges
+
+## Richtlinien erklären (`policy explain`, `policy diff`)
+
+explain-removes = { $level } entfernt:
+explain-preserves = { $level } behält:
+explain-other-tags = Alle anderen Tags
+explain-same = { $from } und { $to } entfernen dieselben Tags
+explain-removed-by = Von { $level } entfernt, aber nicht von { $other }:
+
+## Optionen der Kommandozeile
+
+log-format-help = Format der Diagnoseausgabe auf stderr: text oder json (Filter mit RUST_LOG)
+color-help = Funde nach Schweregrad einfärben: auto, always oder never (NO_COLOR schaltet es ab)
+exiftool-help = Auszuführendes ExifTool, z. B. eine mitgelieferte Kopie (Standard: $EXIFTOOL_PATH, dann eine Suche)
+input-help = Eingabeverzeichnis, ZIP-Archiv oder s3://bucket/prefix mit Bildern
+output-help = Ausgabeverzeichnis oder s3://bucket/prefix (optional – ohne Angabe werden die Dateien direkt geändert)
+backup-help = Sicherungsdateien mit der Endung .bak anlegen
+backup-keep-help = Anzahl nummerierter Sicherungen späterer Läufe; die erste .bak wird nie überschrieben
+verbose-help = Ausführlich anzeigen, welche Daten entfernt werden
+dry-run-help = Anzeigen, was entfernt würde, ohne etwas zu ändern
+stdin-help = Ein Bild von stdin lesen und das bereinigte Bild auf stdout schreiben
+bench-help = Dateien/s und MB/s von Analyse und ExifTool-Entfernung an einem synthetischen Korpus messen [Standard: 200 Dateien]
+health-check-help = ExifTool, das Temp-Verzeichnis und das Bereinigen eines eingebetteten Beispielbilds prüfen; JSON ausgeben und bei Fehlern mit Fehlerstatus enden
+audit-log-help = Einen manipulationssicheren Eintrag für jede Entfernung an FILE anhängen (JSON-Zeilen)
+audit-key-help = Einträge im Prüfprotokoll mit diesem ed25519-Schlüssel signieren (32-Byte-Seed, roh oder hexadezimal)
+duplicates-help = Bilder, deren bereinigte Kopie schon im Ausgabeverzeichnis liegt: process, skip oder link
+timeout-help = Eine Datei aufgeben, wenn ExifTool länger braucht
+quarantine-help = Dateien, deren Bereinigung fehlschlägt, mit einer Fehlernotiz nach DIR kopieren
+require-clean-help = Keine Datei ausgeben, deren Metadaten nicht gelesen und als sauber bestätigt wurden
+unsupported-help = Dateien, die keine unterstützten Bilder sind: skip, copy (unverändert, unbereinigt) oder fail
+preserve-hardlinks-help = Hardlinks zwischen Eingabedateien im Ausgabeverzeichnis erhalten, statt jede Kopie zu bereinigen
+throttle-help = Höchstens SIZE Byte Eingabe pro Sekunde verarbeiten, z. B. 20M, um gemeinsam genutzte Laufwerke zu schonen
+jobs-help = N Dateien gleichzeitig bereinigen, jeder Worker mit eigenem ExifTool-Prozess (0: einer pro CPU)
+nice-help = Mit niedriger CPU- und Datenträgerpriorität laufen, wie nice und ionice
+sandbox-help = ExifTool einschränken: schreibt nur neben die bereinigte Datei, kein Netzwerk (erfordert das Feature sandbox)
+only-if-dirty-help = Nur Dateien mit personenbezogenen Daten neu schreiben; die übrigen unverändert in die Ausgabe kopieren
+manifest-help = SHA-256, Quelle und Richtlinie jeder Ausgabedatei in FILE schreiben (JSON)
+export-metadata-help = Vor dem Bereinigen die vollständigen Metadaten jeder Datei in einer Sidecar-Datei in DIR sichern
+export-format-help = Sidecar-Format für --export-metadata: json (wiederherstellbar) oder xmp
+unlock-readonly-help = Schreibgeschützte Dateien direkt bereinigen, indem der Schreibschutz aufgehoben und danach wiederhergestellt wird
+force-strip-help = Alle Metadaten aus Bildern mit beschädigten oder abgeschnittenen Metadaten entfernen, statt sie fehlschlagen zu lassen
+reencode-fallback-help = Wenn ein Bild nicht bereinigt werden kann, die Pixel dekodieren und eine neue Datei ohne Metadaten kodieren (bei JPEG verlustbehaftet)
+lossless-only-help = Eine Datei lieber fehlschlagen lassen als neu komprimieren; bei JPEG wird geprüft, dass die komprimierten Daten Byte für Byte erhalten bleiben
+interactive-help = Die Funde jeder Datei anzeigen und fragen, ob entfernt, behalten oder übersprungen werden soll
+tui-help = Live-Übersicht über Dateien, Funde, Durchsatz und Fehler anzeigen (p pausiert, q bricht ab)
+notify-help = Nach Abschluss des Laufs eine Desktop-Benachrichtigung mit einer Zusammenfassung anzeigen
+webhook-help = Den JSON-Laufbericht nach Abschluss per POST an URL senden
+quiet-help = Keine Ausgabe pro Datei; nur Fehler und die Zusammenfassung werden ausgegeben
+summary-format-help = Format der Zusammenfassung am Ende: text, json oder tsv
+fail-threshold-help = Mit --dry-run mit Fehler enden, wenn personenbezogene Daten ab diesem Schweregrad gefunden werden: low, medium oder high
+rebuild-metadata-help = Die Metadaten der Ausgabe neu aufbauen, nur mit den Tags, die die Datenschutzstufe behält; unbekannte Tags entfallen
+keep-size-help = Bereinigte JPEG-, PNG- und TIFF-Dateien mit Füllbytes auf ihre ursprüngliche Größe auffüllen (kosmetisch; Hashes ändern sich trotzdem)
+compact-tiff-help = Bereinigte TIFFs nur mit referenzierten Daten neu schreiben, damit keine unreferenzierten Reste entfernter Werte bleiben
+privacy-level-help = Datenschutzstufe: minimal, standard, strict, web-share oder paranoid
+keep-tag-help = Dieses EXIF-Tag behalten, auch wenn die Datenschutzstufe es entfernt, z. B. DateTimeOriginal, oder IFD0:Software für ein IFD (mehrfach möglich)
+remove-tag-help = Dieses EXIF-Tag entfernen, auch wenn die Datenschutzstufe es behält, z. B. LensModel, oder IFD1:Make für ein IFD (mehrfach möglich)
+keep-gps-help = Diese Teile der GPS-Daten behalten: position, altitude, direction, speed, time, technical (durch Kommas getrennt)
+anonymize-equipment-help = Auch Hersteller und Modell von Kamera und Objektiv entfernen (Make, Model, LensMake, LensModel, LensInfo); Belichtungsdaten bleiben erhalten
+strip-originals-help = In bearbeiteten Bildern eingebettete Originale entfernen: veraltete Vorschaubilder, Samsung-Bearbeitungsdaten, Google-Camera-Originale
+drop-motion-help = Das Video von Bewegungsfotos entfernen, statt seine GPS-Position zu bereinigen; übrig bleibt ein Standbild
+unknown-tags-help = Umgang mit Herstellertags, die keine EXIF-Spezifikation definiert: preserve, remove oder remove-above-strict
+deep-scan-help = Auch unreferenzierte Bytes und angehängte Daten nach GPS-Koordinaten, Seriennummern und von Editoren hinterlassenem XMP durchsuchen
+ignore-policy-files-help = .privacy-clean.toml-Dateien im Eingabebaum ignorieren und überall --privacy verwenden
+plugins-help = Eine Plugin-Bibliothek mit Formaten oder Richtlinien laden (mehrfach möglich; erfordert das Feature `plugins`)
+plugin-policy-help = Eine von einem Plugin registrierte Richtlinie statt --privacy verwenden
+recursive-help = Unterverzeichnisse rekursiv verarbeiten
+follow-symlinks-help = Beim Durchlaufen von Verzeichnissen symbolischen Links folgen (Zyklen werden erkannt und übersprungen)
+max-depth-help = Höchstens N Verzeichnisebenen unter das Eingabeverzeichnis absteigen
+max-files-help = Den Lauf nach N Dateien beenden
+max-total-size-help = Den Lauf nach SIZE Byte Eingabe beenden (Endungen K, M, G, T)
+sort-help = Dateien in reproduzierbarer Reihenfolge verarbeiten: name, mtime oder size
+newer-than-help = Nur Dateien, die nach WHEN geändert wurden: ein Datum (2024-05-01) oder ein Alter (7d, 12h)
+older-than-help = Nur Dateien, die vor WHEN geändert wurden: ein Datum (2024-05-01) oder ein Alter (7d, 12h)
+min-size-help = Dateien kleiner als SIZE überspringen, z. B. 100K für Vorschaubilder
+max-size-help = Dateien größer als SIZE überspringen
+analyze-input-help = Verzeichnis, ZIP-Archiv oder s3://bucket/prefix mit Bildern
+analyze-jobs-help = N Dateien gleichzeitig analysieren (0: eine pro CPU)
+analyze-summary-format-help = Format der Zusammenfassung am Ende: text, json oder tsv
+analyze-fail-threshold-help = Mit Fehler enden, wenn personenbezogene Daten ab diesem Schweregrad gefunden werden: low, medium oder high
+audit-files-help = Zu prüfende Bilder oder Verzeichnisse
+audit-json-help = Ein JSON-Array statt Text ausgeben
+audit-recursive-help = Unterverzeichnisse rekursiv prüfen
+audit-top-help = Anzahl der riskantesten Dateien, die bei Verzeichnissen aufgelistet werden
+audit-anomalies-help = Auch unbekannte oder fast zufällige Segmente und angehängte Dateien vermerken, die Daten verbergen könnten (zur Information)
+restore-input-help = Verzeichnis mit Bildern, die mit --backup direkt bereinigt wurden
+restore-recursive-help = Auch Bilder in Unterverzeichnissen wiederherstellen
+restore-dry-run-help = Anzeigen, was wiederhergestellt würde, ohne eine Datei zu ändern
+dump-files-help = Auszugebende Bilder
+dump-json-help = Ein JSON-Array statt Text ausgeben
+serve-bind-help = Adresse, auf der der Dienst lauscht
+serve-workers-help = Anzahl gleichzeitig bearbeiteter Anfragen
+serve-privacy-level-help = Standard-Datenschutzstufe, wenn eine Anfrage keine angibt
+serve-max-upload-mb-help = Größte angenommene Uploadgröße in Megabyte
+watch-config-help = TOML-Datei mit den zu überwachenden Verzeichnissen (bei SIGHUP neu gelesen)
+report-input-help = Verzeichnis der zu analysierenden Bilder
+report-recursive-help = Unterverzeichnisse rekursiv analysieren
+report-privacy-level-help = Datenschutzstufe, deren Entfernungen als Funde zählen
+report-compliance-help = Funde Kategorien personenbezogener Daten zuordnen, mit Anzahlen und den auffälligsten Dateien
+report-gps-clusters-help = GPS-Positionen aller Fotos gruppieren, um wahrscheinliche Wohn- und Arbeitsorte aufzudecken
+report-devices-help = Dateien nach Kameraseriennummern gruppieren, um zu zeigen, welche Bilder sich demselben Gerät zuordnen lassen
+report-cluster-radius-help = Fotos innerhalb dieser Entfernung von einem Clusterzentrum gehören zum Cluster
+report-format-help = Berichtsformat: json oder html
+report-output-help = Den Bericht in FILE statt auf stdout schreiben
+policy-explain-level-help = Datenschutzstufe: minimal, standard, strict, web-share oder paranoid
+apply-metadata-sidecar-help = Von --export-metadata geschriebene Sidecar-Datei (.json oder .xmp)
+apply-metadata-image-help = Bild, in das die Metadaten zurückgeschrieben werden
+apply-metadata-output-help = Das Ergebnis in FILE schreiben, statt IMAGE direkt zu ändern
+compare-original-help = Bild vor dem Bereinigen
+compare-cleaned-help = Bereinigte Kopie des Bildes
+compare-json-help = JSON statt Text ausgeben
+verify-manifest-manifest-help = Von --manifest geschriebenes Manifest
+self-test-json-help = JSON statt Text ausgeben
+
+## Datenschutzeinstellungen, vor dem Bereinigen angezeigt
+
+settings-level = Datenschutzstufe: { $level }
+settings-heading = Datenschutzeinstellungen der Stufe { $level }:
+settings-removes = • Entfernt: { $items }
+settings-preserves = • Behält: { $items }
+settings-also-keeps = • Behält außerdem: { $items }
+settings-also-removes = • Entfernt außerdem: { $items }
+settings-unknown-tags = Tags, die keine EXIF-Spezifikation definiert
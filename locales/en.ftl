@@ -0,0 +1,164 @@
+# Messages of privacy-exif-cleaner in English.
+#
+# This catalog is the reference: it defines every message the code looks up,
+# and the other files in this directory translate its ids. A message missing
+# from a translation is shown in English.
+
+## Command line
+
+app-about = Removes privacy-sensitive information from EXIF data while preserving technical metadata
+clean-about = Clean images in a directory or archive (default when no subcommand is given)
+lang-help = Language of messages, e.g. de (default: $PRIVACY_CLEANER_LANG, then the system locale)
+
+## Run summary
+
+summary-heading = Summary:
+summary-processed = Files processed: { $count }
+summary-privacy-found = Files with privacy data found: { $count }
+summary-already-clean = Files already clean (left untouched): { $count }
+summary-errors = Errors: { $count }
+summary-fields-removed = Privacy fields removed: { $count }
+summary-fields-found = Privacy fields found (none removed): { $count }
+summary-bytes-removed = Metadata removed: { $size }
+summary-bytes-removable = Metadata that would be removed: about { $size }
+summary-throughput = Processed { $size } in { $seconds }s
+summary-unsupported-skipped = Unsupported files skipped: { $count }
+summary-unsupported-copied = Unsupported files copied uncleaned: { $count }
+summary-not-selected = Files outside the selected date/size range: { $count }
+summary-stopped = Run stopped early: { $reason } (not every file was processed)
+summary-cancelled = Run cancelled (not every file was processed)
+
+## Privacy categories
+
+category-location = Location Data
+category-device-identifier = Device Identifier
+category-personal-info = Personal Information
+category-temporal = Timestamp
+category-software = Software Information
+category-metadata = Metadata
+category-other = Other
+
+## Policy explanations (`policy explain`, `policy diff`)
+
+explain-removes = { $level } removes:
+explain-preserves = { $level } preserves:
+explain-other-tags = Any other tag
+explain-same = { $from } and { $to } remove the same tags
+explain-removed-by = Removed by { $level } but not { $other }:
+
+## Command-line options
+#
+# Looked up as `<subcommand>-<option>-help`, then `<option>-help` for the
+# options of `clean`, which `analyze` and other subcommands share.
+
+log-format-help = Diagnostic log format on stderr: text or json (filter with RUST_LOG)
+color-help = Colour findings by severity: auto, always, or never (NO_COLOR turns it off)
+exiftool-help = ExifTool executable to run, e.g. a bundled copy (default: $EXIFTOOL_PATH, then a search)
+input-help = Input directory, ZIP archive, or s3://bucket/prefix containing images
+output-help = Output directory or s3://bucket/prefix (optional - will modify in-place if not specified)
+backup-help = Create backup files with .bak extension
+backup-keep-help = Numbered backups kept from later runs; the first .bak is never overwritten
+verbose-help = Show detailed information about data being removed
+dry-run-help = Show what would be removed without making changes
+stdin-help = Read one image from stdin and write the cleaned image to stdout
+bench-help = Measure files/sec and MB/sec of analysis and ExifTool removal on a synthetic corpus [default: 200 files]
+health-check-help = Check ExifTool, the temp directory, and cleaning of an embedded sample; print JSON and exit non-zero on failure
+audit-log-help = Append a tamper-evident record of every removal to FILE (JSON lines)
+audit-key-help = Sign audit log entries with this ed25519 key (32-byte seed, raw or hex)
+duplicates-help = Images whose cleaned copy already exists in the output dir: process, skip, or link
+timeout-help = Give up on a file if ExifTool takes longer than this
+quarantine-help = Copy files that fail cleaning to DIR with an error note
+require-clean-help = Never output a file unless its metadata was parsed and verified clean
+unsupported-help = Files that aren't supported images: skip, copy (verbatim, uncleaned), or fail
+preserve-hardlinks-help = Keep hard-linked inputs hard-linked in the output directory instead of cleaning each copy
+throttle-help = Process at most SIZE bytes of input per second, e.g. 20M, to spare shared disks
+jobs-help = Clean N files at once, each worker with its own ExifTool process (0: one per CPU)
+nice-help = Run at low CPU and disk priority, like nice and ionice
+sandbox-help = Confine ExifTool: writes only beside the file it cleans, no network (requires the sandbox feature)
+only-if-dirty-help = Only rewrite files with privacy data; copy the rest to the output unchanged
+manifest-help = Write the SHA-256, source, and policy of every output file to FILE (JSON)
+export-metadata-help = Before cleaning, save each file's full metadata to a sidecar in DIR
+export-format-help = Sidecar format for --export-metadata: json (restorable) or xmp
+unlock-readonly-help = Clean read-only files in place by clearing the read-only bit and restoring it afterwards
+force-strip-help = Remove all metadata from images whose metadata is corrupt or truncated instead of failing them
+reencode-fallback-help = When an image can't be cleaned, decode its pixels and encode a new file without metadata (lossy for JPEG)
+lossless-only-help = Fail a file rather than ever recompress it; JPEG output is checked to keep the compressed data byte for byte
+interactive-help = Show each file's findings and ask whether to remove, keep, or skip
+tui-help = Show a live dashboard of files, findings, throughput, and errors (p pauses, q cancels)
+notify-help = Show a desktop notification summarizing the run when it completes
+webhook-help = POST the JSON run report to URL when the run completes
+quiet-help = Suppress per-file output; only errors and the summary are printed
+summary-format-help = End-of-run summary format: text, json, or tsv
+fail-threshold-help = With --dry-run, exit with an error if privacy data at or above this severity is found: low, medium, or high
+rebuild-metadata-help = Build output metadata from scratch with only the tags the privacy level keeps, dropping unknown tags
+keep-size-help = Pad cleaned JPEG, PNG, and TIFF files with filler to their original size (cosmetic; hashes still change)
+compact-tiff-help = Rewrite cleaned TIFFs with only referenced data, so no unreferenced remnants of removed values survive
+privacy-level-help = Privacy level: minimal, standard, strict, web-share, or paranoid
+keep-tag-help = Keep this EXIF tag even if the privacy level removes it, e.g. DateTimeOriginal, or IFD0:Software for one IFD (repeatable)
+remove-tag-help = Remove this EXIF tag even if the privacy level keeps it, e.g. LensModel, or IFD1:Make for one IFD (repeatable)
+keep-gps-help = Keep these parts of the GPS data: position, altitude, direction, speed, time, technical (comma-separated)
+anonymize-equipment-help = Also remove camera and lens make and model (Make, Model, LensMake, LensModel, LensInfo); exposure settings are kept
+strip-originals-help = Remove pre-edit originals embedded in edited images: stale thumbnails, Samsung re-edit data, Google Camera originals
+drop-motion-help = Remove the video of motion photos instead of cleaning its GPS position, leaving a still image
+unknown-tags-help = What to do with vendor tags no EXIF specification defines: preserve, remove, or remove-above-strict
+deep-scan-help = Also search unreferenced bytes and trailing data for GPS coordinates, serial numbers, and XMP left by editors
+ignore-policy-files-help = Ignore .privacy-clean.toml files in the input tree and use --privacy everywhere
+plugins-help = Load a plugin library adding format handlers or policies (repeatable; needs the `plugins` feature)
+plugin-policy-help = Use a policy registered by a plugin instead of --privacy
+recursive-help = Process subdirectories recursively
+follow-symlinks-help = Follow symbolic links while walking directories (cycles are detected and skipped)
+max-depth-help = Descend at most N directory levels below the input directory
+max-files-help = Stop the run after N files
+max-total-size-help = Stop the run after SIZE bytes of input (suffixes K, M, G, T)
+sort-help = Process files in a reproducible order: name, mtime, or size
+newer-than-help = Only files modified after WHEN: a date (2024-05-01) or an age (7d, 12h)
+older-than-help = Only files modified before WHEN: a date (2024-05-01) or an age (7d, 12h)
+min-size-help = Skip files smaller than SIZE, e.g. 100K to skip thumbnails
+max-size-help = Skip files larger than SIZE
+analyze-input-help = Directory, ZIP archive, or s3://bucket/prefix containing images
+analyze-jobs-help = Analyze N files at once (0: one per CPU)
+analyze-summary-format-help = End-of-run summary format: text, json, or tsv
+analyze-fail-threshold-help = Exit with an error if privacy data at or above this severity is found: low, medium, or high
+audit-files-help = Images or directories to audit
+audit-json-help = Print a JSON array instead of text
+audit-recursive-help = Audit subdirectories recursively
+audit-top-help = Number of riskiest files to list when auditing directories
+audit-anomalies-help = Also note unknown or near-random segments and appended files that could hide data (informational)
+restore-input-help = Directory of images cleaned in place with --backup
+restore-recursive-help = Restore images in subdirectories too
+restore-dry-run-help = Show what would be restored without changing any file
+dump-files-help = Images to dump
+dump-json-help = Print a JSON array instead of text
+serve-bind-help = Address to listen on
+serve-workers-help = Number of requests handled concurrently
+serve-privacy-level-help = Default privacy level when a request doesn't specify one
+serve-max-upload-mb-help = Maximum accepted upload size in megabytes
+watch-config-help = TOML file listing the directories to watch (re-read on SIGHUP)
+report-input-help = Directory of images to analyze
+report-recursive-help = Analyze subdirectories recursively
+report-privacy-level-help = Privacy level whose removals count as findings
+report-compliance-help = Map findings to personal-data categories with counts and worst offenders
+report-gps-clusters-help = Cluster GPS positions across all photos to reveal likely home/work locations
+report-devices-help = Group files by camera serial numbers to show which images are linkable to the same device
+report-cluster-radius-help = Photos within this distance of a cluster centre belong to it
+report-format-help = Report format: json or html
+report-output-help = Write the report to FILE instead of stdout
+policy-explain-level-help = Privacy level: minimal, standard, strict, web-share, or paranoid
+apply-metadata-sidecar-help = Sidecar written by --export-metadata (.json or .xmp)
+apply-metadata-image-help = Image to restore the metadata into
+apply-metadata-output-help = Write the result to FILE instead of modifying IMAGE in place
+compare-original-help = Image before cleaning
+compare-cleaned-help = Cleaned copy of the image
+compare-json-help = Print JSON instead of text
+verify-manifest-manifest-help = Manifest written by --manifest
+self-test-json-help = Print JSON instead of text
+
+## Privacy settings, shown before cleaning
+
+settings-level = Privacy level: { $level }
+settings-heading = Privacy settings for { $level } level:
+settings-removes = • Removes: { $items }
+settings-preserves = • Preserves: { $items }
+settings-also-keeps = • Also keeps: { $items }
+settings-also-removes = • Also removes: { $items }
+settings-unknown-tags = tags no EXIF specification defines
@@ -1,85 +1,586 @@
-use std::io::Cursor;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor};
 use std::path::Path;
-use exif::{In, Reader};
-use crate::privacy::{PrivacyLevel, PrivacyPolicy};
+use chrono::{NaiveDate, NaiveDateTime};
+use clap::ValueEnum;
+use exif::{In, Reader, Tag, Value};
+use serde::Serialize;
+use tracing::debug;
+use crate::bmp;
+use crate::formats::{self, ImageFormat};
+use crate::geo::{self, GeoDisclosure};
+use crate::gif;
+use crate::headers;
+use crate::i18n::tr;
+use crate::iptc;
+use crate::jpeg;
+use crate::jxl;
+use crate::multipicture;
+use crate::originals::{self, EmbeddedOriginal};
+use crate::payload;
+use crate::pii;
+use crate::png;
+use crate::privacy::{self, Ifd, PolicyOverride, PrivacyLevel, PrivacyPolicy, UnknownTags};
+use crate::remnants;
 
 pub struct ExifAnalyzer {
     reader: Reader,
+    overrides: PolicyOverride,
+    strip_originals: bool,
+    drop_motion: bool,
+    unknown_tags: UnknownTags,
+    deep_scan: bool,
 }
 
 impl ExifAnalyzer {
     pub fn new() -> Self {
         Self {
             reader: Reader::new(),
+            overrides: PolicyOverride::default(),
+            strip_originals: false,
+            drop_motion: false,
+            unknown_tags: UnknownTags::default(),
+            deep_scan: false,
         }
     }
 
+    /// Report embedded pre-edit originals as privacy data, so they are removed
+    pub fn with_strip_originals(mut self, strip_originals: bool) -> Self {
+        self.strip_originals = strip_originals;
+        self
+    }
+
+    /// Report motion photo videos as privacy data, so they are removed
+    pub fn with_drop_motion(mut self, drop_motion: bool) -> Self {
+        self.drop_motion = drop_motion;
+        self
+    }
+
+    /// Report tags no EXIF specification defines according to `unknown_tags`
+    pub fn with_unknown_tags(mut self, unknown_tags: UnknownTags) -> Self {
+        self.unknown_tags = unknown_tags;
+        self
+    }
+
+    /// Also search the bytes outside any metadata structure for remnants of removed metadata
+    pub fn with_deep_scan(mut self, deep_scan: bool) -> Self {
+        self.deep_scan = deep_scan;
+        self
+    }
+
+    /// Report tags according to `overrides` layered on the privacy level
+    pub fn with_overrides(mut self, overrides: PolicyOverride) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
     /// Analyze what privacy-sensitive data exists in an image
+    ///
+    /// Nothing is printed: the findings are returned for the caller to report.
     pub fn analyze_privacy_data(
         &self,
         data: &[u8],
         path: &Path,
         privacy_level: &PrivacyLevel,
-        verbose: bool,
     ) -> Result<Vec<PrivacyField>, Box<dyn std::error::Error>> {
-        let mut cursor = Cursor::new(data);
-        
-        let exif = match self.reader.read_from_container(&mut cursor) {
-            Ok(exif) => exif,
-            Err(_) => return Ok(vec![]), // No EXIF data
+        let report = self.analyze_bytes(data, privacy_level)?;
+        debug!(path = %path.display(), findings = report.fields.len(), "Analyzed metadata");
+        Ok(report.fields)
+    }
+
+    /// Analyze an image held in memory, for callers with no file path (uploads, WASM)
+    pub fn analyze_bytes(&self, data: &[u8], privacy_level: &PrivacyLevel) -> Result<AnalysisReport, Box<dyn std::error::Error>> {
+        let exif = self.read_exif(data)?;
+        let mut privacy_fields = match exif {
+            Some(ref exif) => self.collect_privacy_fields(exif, privacy_level),
+            None => vec![],
         };
+        let text_findings = self.personal_data_in_text_blocks(data, exif.as_ref(), privacy_level)
+            .into_iter()
+            .chain(self.png_text_fields(data, privacy_level))
+            .chain(self.gif_comment_fields(data, privacy_level))
+            .chain(self.linked_profile_field(data))
+            .chain(self.original_fields(data, exif.as_ref(), privacy_level))
+            .chain(self.embedded_picture_fields(data, privacy_level))
+            .chain(self.motion_video_fields(data, privacy_level))
+            .chain(self.remnant_fields(data));
+        privacy_fields.extend(text_findings);
+        Ok(AnalysisReport { format: formats::sniff_image_format(data), fields: privacy_fields })
+    }
 
-        let mut privacy_fields = Vec::new();
-
-        for field in exif.fields() {
-            if !PrivacyPolicy::should_preserve_tag(field.tag, privacy_level) {
-                let privacy_field = PrivacyField {
-                    tag: field.tag,
-                    description: format!("{}: {}", 
-                        field.tag, 
-                        field.display_value().with_unit(&exif)
-                    ),
-                    category: self.categorize_privacy_field(field.tag),
-                };
+    /// Analyze an image file by reading only its metadata, never its image data
+    ///
+    /// This is the fast path for scanning large archives: JPEGs are read up to
+    /// the EXIF segment and TIFFs only at their IFD offsets.
+    pub fn analyze_file(
+        &self,
+        path: &Path,
+        privacy_level: &PrivacyLevel,
+    ) -> Result<Vec<PrivacyField>, Box<dyn std::error::Error>> {
+        match self.parse_exif_file(path)? {
+            Some(exif) => Ok(self.collect_privacy_fields(&exif, privacy_level)),
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Parse the EXIF data of an image file from its headers alone
+    ///
+    /// JPEG, TIFF, PNG, and JPEG XL are read header by header; WebP and HEIF
+    /// are read whole, as before the header-only path existed.
+    /// Returns `None` when the file has no EXIF data, and an `UnparseableMetadata`
+    /// error when it has some that is truncated or malformed.
+    pub fn parse_exif_file(&self, path: &Path) -> Result<Option<exif::Exif>, Box<dyn std::error::Error>> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        if headers::needs_full_read(file.fill_buf()?) {
+            return match self.reader.read_from_container(&mut file) {
+                Ok(exif) => Ok(Some(exif)),
+                Err(exif::Error::NotFound(_)) => Ok(None),
+                Err(e) => Err(UnparseableMetadata(e.to_string()).into()),
+            };
+        }
+        let block = match headers::read_exif_block_from(&mut file) {
+            Ok(Some(block)) => block,
+            Ok(None) => return Ok(None),
+            Err(e) => return Err(UnparseableMetadata(e.to_string()).into()),
+        };
+        match self.reader.read_raw(block) {
+            Ok(exif) => Ok(Some(exif)),
+            Err(exif::Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(UnparseableMetadata(e.to_string()).into()),
+        }
+    }
+
+    /// Parse an image's EXIF data, telling missing metadata apart from unparseable metadata
+    ///
+    /// Data in none of the supported formats has no metadata we could read, so it is `None`.
+    pub fn read_exif(&self, data: &[u8]) -> Result<Option<exif::Exif>, UnparseableMetadata> {
+        if formats::sniff_image_format(data).is_none() {
+            return Ok(None);
+        }
+
+        match self.parse_exif(data) {
+            Ok(exif) => Ok(Some(exif)),
+            Err(exif::Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(UnparseableMetadata(e.to_string())),
+        }
+    }
+
+    /// The EXIF findings of parsed metadata, borrowed from it and formatted only when displayed
+    ///
+    /// For scanners that need only tags and categories: the usual findings of
+    /// removed tags cost no allocation until their description is asked for.
+    /// Unlike `analyze_bytes`, this covers EXIF fields only, not XMP, IPTC, PNG
+    /// text, or trailers.
+    pub fn analyze_iter<'a>(
+        &'a self,
+        exif: &'a exif::Exif,
+        privacy_level: &'a PrivacyLevel,
+    ) -> impl Iterator<Item = Finding<'a>> + 'a {
+        let removes_unknown = self.unknown_tags.removes(privacy_level);
+
+        exif.fields().filter_map(move |field| {
+            let ifd = Ifd::of(field);
+            let (category, description) = if removes_unknown && privacy::is_unknown_tag(field.tag) {
+                (PrivacyCategory::Other, Description::Unknown)
+            } else if !self.overrides.should_preserve_tag_in(field.tag, ifd, privacy_level) {
+                (self.categorize_privacy_field(field.tag), Description::Value)
+            } else if let Some(privacy_field) = self.personal_data_in_text(field) {
+                (privacy_field.category, Description::Text(privacy_field.description))
+            } else if let Some(payload) = self.suspicious_payload(field) {
+                // Kept by the level, but its content gives the owner away
+                let description = format!("{}: suspicious payload ({})", field.tag, payload.reason);
+                (payload.category, Description::Text(description))
+            } else {
+                return None;
+            };
+            Some(Finding { field, exif, category, ifd, description })
+        })
+    }
+
+    fn collect_privacy_fields(
+        &self,
+        exif: &exif::Exif,
+        privacy_level: &PrivacyLevel,
+    ) -> Vec<PrivacyField> {
+        self.analyze_iter(exif, privacy_level).map(Finding::into_privacy_field).collect()
+    }
+
+    /// Emails, phone numbers, addresses, or names typed into a kept text field
+    fn personal_data_in_text(&self, field: &exif::Field) -> Option<PrivacyField> {
+        if !pii::is_textual(field.tag) || self.overrides.keep().contains(&field.tag) {
+            return None;
+        }
+        let found = pii::scan(&payload::value_text(&field.value)?).into_iter().next()?;
+        Some(PrivacyField {
+            tag: field.tag,
+            description: format!("{}: contains {} ({})", field.tag, found.kind, found.redacted),
+            category: PrivacyCategory::PersonalInfo,
+            ifd: Some(Ifd::of(field)),
+            value: Some(field.value.clone()),
+        })
+    }
+
+    /// Personal data in the text of XMP and IPTC metadata the privacy level keeps
+    ///
+    /// Findings carry `XMP_PACKET` or `IPTC_NAA` as their tag, so removing them removes the whole block.
+    fn personal_data_in_text_blocks(
+        &self,
+        data: &[u8],
+        exif: Option<&exif::Exif>,
+        privacy_level: &PrivacyLevel,
+    ) -> Vec<PrivacyField> {
+        let block = |tag| exif.and_then(|exif| exif.get_field(tag, In::PRIMARY))
+            .and_then(|field| payload::value_text(&field.value));
+        let layout = jpeg::parse_layout(data).ok();
+        let mut texts = Vec::new();
+
+        if self.overrides.should_preserve_tag(privacy::XMP_PACKET, privacy_level) {
+            let xmp = self.xmp_packet(data)
+                .map(|xmp| {
+                    [xmp.standard, xmp.extended].into_iter()
+                        .flatten()
+                        .map(|packet| String::from_utf8_lossy(&packet).into_owned())
+                        .collect()
+                })
+                .or_else(|| block(privacy::XMP_PACKET))
+                .unwrap_or_default();
+            for (property, text) in pii::xmp_text_fields(&xmp) {
+                texts.push((privacy::XMP_PACKET, format!("XMP {}", property), text));
+            }
+        }
 
-                privacy_fields.push(privacy_field);
-                
-                if verbose {
-                    println!("  Privacy data found in {}: {} ({})", 
-                        path.display(), 
-                        privacy_field.description,
-                        privacy_field.category
-                    );
-                }
+        if self.overrides.should_preserve_tag(privacy::IPTC_NAA, privacy_level) {
+            let iim = match layout {
+                Some(ref layout) => layout.segments.iter()
+                    .filter(|segment| segment.marker == jpeg::APP13)
+                    .find_map(|segment| iptc::from_photoshop(segment.payload))
+                    .map(|iim| iim.to_vec()),
+                None => exif.and_then(|exif| exif.get_field(privacy::IPTC_NAA, In::PRIMARY))
+                    .and_then(|field| match field.value {
+                        exif::Value::Byte(ref bytes) | exif::Value::Undefined(ref bytes, _) => Some(bytes.clone()),
+                        _ => None,
+                    }),
+            };
+            for caption in iim.map(|iim| iptc::captions(&iim)).unwrap_or_default() {
+                texts.push((privacy::IPTC_NAA, "IPTC caption".to_string(), caption));
             }
         }
 
-        Ok(privacy_fields)
+        texts.into_iter()
+            .filter_map(|(tag, source, text)| {
+                let found = pii::scan(&text).into_iter().next()?;
+                Some(PrivacyField {
+                    tag,
+                    description: format!("{}: contains {} ({})", source, found.kind, found.redacted),
+                    category: PrivacyCategory::PersonalInfo,
+                    ifd: None,
+                    value: None,
+                })
+            })
+            .collect()
+    }
+
+    /// Find versions of the image from before it was edited
+    pub fn embedded_originals(&self, data: &[u8]) -> Vec<EmbeddedOriginal> {
+        let exif = self.parse_exif(data).ok();
+        originals::find(data, exif.as_ref(), &self.xmp_bytes(data))
+    }
+
+    /// Embedded originals as findings, with `--strip-originals`
+    ///
+    /// A thumbnail the level removes anyway is already reported as an EXIF field.
+    fn original_fields(&self, data: &[u8], exif: Option<&exif::Exif>, privacy_level: &PrivacyLevel) -> Vec<PrivacyField> {
+        if !self.strip_originals {
+            return Vec::new();
+        }
+
+        originals::find(data, exif, &self.xmp_bytes(data)).into_iter()
+            .filter(|original| {
+                *original != EmbeddedOriginal::StaleThumbnail
+                    || self.overrides.should_preserve_tag(original.tag(), privacy_level)
+            })
+            .map(|original| PrivacyField {
+                tag: original.tag(),
+                description: original.to_string(),
+                category: PrivacyCategory::PersonalInfo,
+                ifd: None,
+                value: None,
+            })
+            .collect()
+    }
+
+    /// Findings in the pictures after the first of an MPO or multi-picture JPEG
+    fn embedded_picture_fields(&self, data: &[u8], privacy_level: &PrivacyLevel) -> Vec<PrivacyField> {
+        multipicture::embedded_images(data).into_iter()
+            .enumerate()
+            .filter_map(|(i, image)| Some((i + 2, self.read_exif(&data[image.range]).ok()??)))
+            .flat_map(|(number, exif)| {
+                self.collect_privacy_fields(&exif, privacy_level).into_iter()
+                    .map(move |field| PrivacyField { description: format!("Picture {}: {}", number, field.description), ..field })
+            })
+            .collect()
+    }
+
+    /// A motion photo's video with `--drop-motion`, or else the GPS positions recorded in it
+    fn motion_video_fields(&self, data: &[u8], privacy_level: &PrivacyLevel) -> Vec<PrivacyField> {
+        let xmp = String::from_utf8_lossy(&self.xmp_bytes(data)).into_owned();
+        let video = match multipicture::motion_video(data, &xmp) {
+            Some(video) => video,
+            None => return Vec::new(),
+        };
+
+        if self.drop_motion {
+            return vec![PrivacyField {
+                tag: privacy::MOTION_VIDEO,
+                description: format!("Motion photo video ({} KiB)", video.len() / 1024),
+                category: PrivacyCategory::PersonalInfo,
+                ifd: None,
+                value: None,
+            }];
+        }
+        if self.overrides.should_preserve_tag(exif::Tag::GPSLatitude, privacy_level) {
+            return Vec::new();
+        }
+        multipicture::video_locations(&data[video]).into_iter()
+            .map(|(_, position)| PrivacyField {
+                tag: exif::Tag::GPSLatitude,
+                description: format!("Motion photo video: GPS position {}", position),
+                category: PrivacyCategory::Location,
+                ifd: None,
+                value: None,
+            })
+            .collect()
+    }
+
+    /// Metadata left in bytes no structure references, with `--deep-scan`
+    ///
+    /// Findings carry `REMNANT` as their tag: the remover has no way to address
+    /// the bytes, so rewriting the file is what removes them.
+    fn remnant_fields(&self, data: &[u8]) -> Vec<PrivacyField> {
+        if !self.deep_scan {
+            return Vec::new();
+        }
+        let xmp = String::from_utf8_lossy(&self.xmp_bytes(data)).into_owned();
+        let known: Vec<_> = multipicture::motion_video(data, &xmp).into_iter().collect();
+
+        remnants::scan(data, &known).into_iter()
+            .map(|remnant| PrivacyField {
+                tag: privacy::REMNANT,
+                description: format!("Remnant at offset {}: {}", remnant.offset, remnant.description),
+                category: remnant.category,
+                ifd: None,
+                value: None,
+            })
+            .collect()
+    }
+
+    /// The standard and extended XMP packets, concatenated
+    pub fn xmp_bytes(&self, data: &[u8]) -> Vec<u8> {
+        self.xmp_packet(data)
+            .map(|xmp| [xmp.standard, xmp.extended].into_iter().flatten().flatten().collect())
+            .unwrap_or_default()
+    }
+
+    /// PNG text chunks the privacy level removes, or that hold personal data
+    ///
+    /// Screenshot tools record the source app, device model, and page URL here.
+    /// Findings carry `PNG_TEXT` as their tag: the text chunks are removed together.
+    fn png_text_fields(&self, data: &[u8], privacy_level: &PrivacyLevel) -> Vec<PrivacyField> {
+        let chunks = match png::parse_chunks(data) {
+            Ok(chunks) => chunks,
+            Err(_) => return Vec::new(),
+        };
+        let block_removed = !self.overrides.should_preserve_tag(privacy::PNG_TEXT, privacy_level);
+
+        png::text_chunks(&chunks).into_iter()
+            // XMP is analyzed as XMP
+            .filter(|text| text.keyword != png::XMP_KEYWORD)
+            .filter_map(|text| {
+                let (category, policy_tag) = png::text_field_policy(&text.keyword);
+                let removed = block_removed || match policy_tag {
+                    Some(tag) => !self.overrides.should_preserve_tag(tag, privacy_level),
+                    None => !PrivacyPolicy::preserves_unlisted_tags(privacy_level),
+                };
+
+                let (description, category) = if removed {
+                    let value: String = text.text.chars().take(MAX_TEXT_CHARS).collect();
+                    (format!("PNG {}: {}", text.keyword, value), category)
+                } else {
+                    let found = pii::scan(&text.text).into_iter().next()?;
+                    (
+                        format!("PNG {}: contains {} ({})", text.keyword, found.kind, found.redacted),
+                        PrivacyCategory::PersonalInfo,
+                    )
+                };
+                Some(PrivacyField { tag: privacy::PNG_TEXT, description, category, ifd: None, value: None })
+            })
+            .collect()
+    }
+
+    /// GIF comments the privacy level removes, or that hold personal data or a hidden payload
+    ///
+    /// Comments follow the level's rule for `UserComment`. Findings carry `GIF_COMMENT` as their tag.
+    fn gif_comment_fields(&self, data: &[u8], privacy_level: &PrivacyLevel) -> Vec<PrivacyField> {
+        let metadata = match gif::parse(data) {
+            Ok(metadata) => metadata,
+            Err(_) => return Vec::new(),
+        };
+        let removed = !self.overrides.should_preserve_tag(exif::Tag::UserComment, privacy_level);
+
+        metadata.comments.into_iter()
+            .filter_map(|comment| {
+                let (description, category) = if removed {
+                    let value: String = comment.chars().take(MAX_TEXT_CHARS).collect();
+                    (format!("GIF comment: {}", value), PrivacyCategory::PersonalInfo)
+                } else if let Some(found) = pii::scan(&comment).into_iter().next() {
+                    (format!("GIF comment: contains {} ({})", found.kind, found.redacted), PrivacyCategory::PersonalInfo)
+                } else {
+                    let payload = payload::inspect_text(&comment)?;
+                    (format!("GIF comment: suspicious payload ({})", payload.reason), payload.category)
+                };
+                Some(PrivacyField { tag: privacy::GIF_COMMENT, description, category, ifd: None, value: None })
+            })
+            .collect()
+    }
+
+    /// A BMP's linked color profile, whose path names a folder on the machine that saved it
+    fn linked_profile_field(&self, data: &[u8]) -> Option<PrivacyField> {
+        let (_, path) = bmp::linked_profile(data)?;
+        Some(PrivacyField {
+            tag: privacy::LINKED_PROFILE,
+            description: format!("BMP linked color profile: {}", path),
+            category: PrivacyCategory::PersonalInfo,
+            ifd: None,
+            value: None,
+        })
+    }
+
+    /// Hidden personal data in a free-form field the privacy level keeps
+    ///
+    /// Tags kept explicitly with `--keep-tag` are trusted and not inspected.
+    fn suspicious_payload(&self, field: &exif::Field) -> Option<payload::SuspiciousPayload> {
+        if !payload::is_free_form(field.tag) || self.overrides.keep().contains(&field.tag) {
+            return None;
+        }
+        payload::inspect_value(&field.value)
     }
 
     /// Check if an image contains any EXIF data at all
     pub fn has_exif_data(&self, data: &[u8]) -> bool {
+        self.parse_exif(data).is_ok()
+    }
+
+    /// Reassemble a JPEG's XMP packet, including extended XMP split across segments
+    ///
+    /// For PNG, this is the XMP text chunk; for GIF, the XMP application extension;
+    /// for JPEG XL, the `xml ` box.
+    pub fn xmp_packet(&self, data: &[u8]) -> Option<jpeg::XmpPacket> {
+        if jxl::is_jxl(data) {
+            let xmp = jxl::xmp(&jxl::parse_boxes(data).ok()?)?;
+            return Some(jpeg::XmpPacket { standard: Some(xmp), extended: None });
+        }
+        if gif::is_gif(data) {
+            let xmp = gif::parse(data).ok()?.xmp?;
+            return Some(jpeg::XmpPacket { standard: Some(xmp), extended: None });
+        }
+        if png::is_png(data) {
+            let chunks = png::parse_chunks(data).ok()?;
+            let xmp = png::text_chunks(&chunks).into_iter().find(|text| text.keyword == png::XMP_KEYWORD)?;
+            return Some(jpeg::XmpPacket { standard: Some(xmp.text.into_bytes()), extended: None });
+        }
+        let layout = jpeg::parse_layout(data).ok()?;
+        jpeg::reassemble_xmp(layout.app1_payloads())
+    }
+
+    /// Confirm an image's metadata can be parsed; having no EXIF at all counts as readable
+    pub fn check_metadata_readable(&self, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        if formats::sniff_image_format(data).is_none() {
+            return Err("Not a JPEG, TIFF, PNG, GIF, BMP, or JPEG XL image".into());
+        }
+
+        self.read_exif(data)?;
+        Ok(())
+    }
+
+    /// Parse the raw EXIF structure of an image
+    pub fn parse_exif(&self, data: &[u8]) -> Result<exif::Exif, exif::Error> {
+        // The EXIF crate only reads the first APP1 segment, so reassemble continued EXIF first
+        if let Ok(layout) = jpeg::parse_layout(data) {
+            return match jpeg::reassemble_exif(layout.app1_payloads()) {
+                Some(block) => self.reader.read_raw(block),
+                None => Err(exif::Error::NotFound("JPEG")),
+            };
+        }
+
+        if png::is_png(data) {
+            let chunks = png::parse_chunks(data).map_err(|_| exif::Error::InvalidFormat("Broken PNG chunk structure"))?;
+            return match png::exif_block(&chunks) {
+                Some(block) => self.reader.read_raw(block),
+                None => Err(exif::Error::NotFound("PNG")),
+            };
+        }
+
+        if jxl::is_jxl(data) {
+            let boxes = jxl::parse_boxes(data).map_err(|_| exif::Error::InvalidFormat("Broken JXL box structure"))?;
+            // Compressed boxes could hide anything, so they aren't taken as having no metadata
+            if jxl::has_compressed_metadata(&boxes) {
+                return Err(exif::Error::InvalidFormat("Brotli-compressed JXL metadata can't be analyzed"));
+            }
+            return match jxl::exif_block(&boxes) {
+                Some(block) => self.reader.read_raw(block),
+                None => Err(exif::Error::NotFound("JXL")),
+            };
+        }
+
+        // Neither format has EXIF; their metadata is read separately
+        if gif::is_gif(data) {
+            gif::parse(data).map_err(|_| exif::Error::InvalidFormat("Broken GIF block structure"))?;
+            return Err(exif::Error::NotFound("GIF"));
+        }
+        if bmp::is_bmp(data) {
+            return Err(exif::Error::NotFound("BMP"));
+        }
+
         let mut cursor = Cursor::new(data);
-        self.reader.read_from_container(&mut cursor).is_ok()
+        self.reader.read_from_container(&mut cursor)
+    }
+
+    /// Describe where an image's GPS data places it, using offline data only
+    pub fn location_disclosure(&self, data: &[u8]) -> Option<GeoDisclosure> {
+        let exif = self.parse_exif(data).ok()?;
+        geo::coordinates_from_exif(&exif).map(|coords| geo::describe_location(&coords))
+    }
+
+    /// Get serial numbers and other device-identifying values from parsed EXIF data
+    pub fn device_identifiers(&self, exif: &exif::Exif) -> Vec<ExifField> {
+        exif.fields()
+            .filter(|field| self.categorize_privacy_field(field.tag) == PrivacyCategory::DeviceIdentifier)
+            .map(|field| ExifField::new(field, field.display_value().to_string().trim_matches('"').trim().to_string()))
+            .filter(|field| !field.value.is_empty())
+            .collect()
     }
 
     /// Get all EXIF fields from an image (for debugging/analysis)
+    ///
+    /// An image without EXIF data has no fields; unparseable EXIF is an error.
     pub fn get_all_exif_fields(&self, data: &[u8]) -> Result<Vec<ExifField>, Box<dyn std::error::Error>> {
-        let mut cursor = Cursor::new(data);
-        let exif = self.reader.read_from_container(&mut cursor)?;
+        let exif = match self.read_exif(data)? {
+            Some(exif) => exif,
+            None => return Ok(vec![]),
+        };
 
         let fields = exif.fields()
-            .map(|field| ExifField {
-                tag: field.tag,
-                value: field.display_value().with_unit(&exif).to_string(),
-            })
+            .map(|field| ExifField::new(field, field.display_value().with_unit(&exif).to_string()))
             .collect();
 
         Ok(fields)
     }
 
     /// Categorize a privacy field for better user understanding
-    fn categorize_privacy_field(&self, tag: exif::Tag) -> PrivacyCategory {
+    /// The privacy category findings of `tag` are reported under
+    pub fn categorize_privacy_field(&self, tag: exif::Tag) -> PrivacyCategory {
         use exif::Tag;
 
         match tag {
@@ -130,20 +631,256 @@ impl Default for ExifAnalyzer {
     }
 }
 
+/// An image carries metadata that can't be parsed, so what it discloses is unknown
+///
+/// Treating this as "no metadata" would pass a file as clean that may still hold
+/// GPS coordinates, so callers have to decide explicitly what to do with it.
+#[derive(Debug, Clone)]
+pub struct UnparseableMetadata(pub String);
+
+impl std::fmt::Display for UnparseableMetadata {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Metadata could not be parsed: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnparseableMetadata {}
+
+/// An EXIF finding of `ExifAnalyzer::analyze_iter`, borrowed from the parsed metadata
+#[derive(Clone)]
+pub struct Finding<'a> {
+    field: &'a exif::Field,
+    exif: &'a exif::Exif,
+    pub category: PrivacyCategory,
+    pub ifd: Ifd,
+    description: Description,
+}
+
+/// How a finding's description is written
+#[derive(Debug, Clone)]
+enum Description {
+    /// The tag and its value, for tags the level removes
+    Value,
+    /// A tag no specification defines
+    Unknown,
+    /// Already formatted, for kept fields whose content was flagged
+    Text(String),
+}
+
+impl<'a> Finding<'a> {
+    pub fn tag(&self) -> exif::Tag {
+        self.field.tag
+    }
+
+    pub fn value(&self) -> &'a Value {
+        &self.field.value
+    }
+
+    pub fn severity(&self) -> Severity {
+        self.category.severity()
+    }
+
+    /// The finding as an owned `PrivacyField`, formatting its description
+    pub fn into_privacy_field(self) -> PrivacyField {
+        PrivacyField {
+            tag: self.field.tag,
+            description: self.to_string(),
+            category: self.category,
+            ifd: Some(self.ifd),
+            value: Some(self.field.value.clone()),
+        }
+    }
+}
+
+// `exif::Exif` has no `Debug`; it is only there to format units
+impl std::fmt::Debug for Finding<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Finding")
+            .field("field", self.field)
+            .field("category", &self.category)
+            .field("ifd", &self.ifd)
+            .field("description", &self.description)
+            .finish_non_exhaustive()
+    }
+}
+
+impl std::fmt::Display for Finding<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.description {
+            Description::Value => write!(f, "{}: {}", self.field.tag, self.field.display_value().with_unit(self.exif)),
+            Description::Unknown => write!(f, "{}: unknown tag", self.field.tag),
+            Description::Text(ref text) => f.write_str(text),
+        }
+    }
+}
+
+/// What the analysis of one image found
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisReport {
+    /// The image's format; `None` for data in none of the supported formats
+    pub format: Option<ImageFormat>,
+    pub fields: Vec<PrivacyField>,
+}
+
+impl AnalysisReport {
+    /// Whether the image already conforms to the level
+    pub fn is_clean(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// How many findings fall in each category, in category order
+    pub fn counts_by_category(&self) -> BTreeMap<PrivacyCategory, usize> {
+        let mut counts = BTreeMap::new();
+        for field in &self.fields {
+            *counts.entry(field.category).or_default() += 1;
+        }
+        counts
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PrivacyField {
     pub tag: exif::Tag,
     pub description: String,
     pub category: PrivacyCategory,
+    /// IFD the tag was read from; `None` for findings outside EXIF (XMP, PNG text, trailers)
+    pub ifd: Option<Ifd>,
+    /// The tag's stored value; `None` for findings outside EXIF
+    pub value: Option<Value>,
+}
+
+impl PrivacyField {
+    pub fn severity(&self) -> Severity {
+        self.category.severity()
+    }
+
+    /// The first component of a numeric value, e.g. `GPSAltitude` in metres
+    pub fn as_f64(&self) -> Option<f64> {
+        let value = self.value.as_ref()?;
+        let number = match value {
+            Value::Rational(values) => values.first()?.to_f64(),
+            Value::SRational(values) => values.first()?.to_f64(),
+            Value::Float(values) => f64::from(*values.first()?),
+            Value::Double(values) => *values.first()?,
+            Value::SByte(values) => f64::from(*values.first()?),
+            Value::SShort(values) => f64::from(*values.first()?),
+            Value::SLong(values) => f64::from(*values.first()?),
+            _ => f64::from(value.get_uint(0)?),
+        };
+        number.is_finite().then_some(number)
+    }
+
+    /// A GPS latitude or longitude in decimal degrees
+    ///
+    /// EXIF stores the hemisphere in a separate reference tag, so the result
+    /// is never negative; `geo::coordinates_from_fields` applies the sign.
+    pub fn decimal_degrees(&self) -> Option<f64> {
+        match self.tag {
+            Tag::GPSLatitude | Tag::GPSLongitude | Tag::GPSDestLatitude | Tag::GPSDestLongitude => {
+                geo::dms_to_degrees(self.value.as_ref()?)
+            }
+            _ => None,
+        }
+    }
+
+    /// A date-time tag (`DateTime`, `DateTimeOriginal`, `DateTimeDigitized`) as the local time it records
+    pub fn datetime(&self) -> Option<NaiveDateTime> {
+        if !matches!(self.tag, Tag::DateTime | Tag::DateTimeOriginal | Tag::DateTimeDigitized) {
+            return None;
+        }
+        let ascii = match self.value.as_ref()? {
+            Value::Ascii(values) => values.first()?,
+            _ => return None,
+        };
+        naive_datetime(&exif::DateTime::from_ascii(ascii).ok()?)
+    }
+}
+
+/// An EXIF date-time as a calendar date and wall-clock time, sub-seconds included
+pub fn naive_datetime(datetime: &exif::DateTime) -> Option<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(i32::from(datetime.year), u32::from(datetime.month), u32::from(datetime.day))?
+        .and_hms_nano_opt(
+            u32::from(datetime.hour),
+            u32::from(datetime.minute),
+            u32::from(datetime.second),
+            datetime.nanosecond.unwrap_or(0),
+        )
 }
 
 #[derive(Debug, Clone)]
 pub struct ExifField {
     pub tag: exif::Tag,
     pub value: String,
+    /// IFD the field was read from, e.g. `IFD0`, `Exif`, `GPS`, or `IFD1/Exif` for the thumbnail
+    pub location: String,
+    /// The stored value without unit conversion or lookup of enumerated values
+    pub raw: String,
+}
+
+impl ExifField {
+    fn new(field: &exif::Field, value: String) -> Self {
+        ExifField {
+            tag: field.tag,
+            value,
+            location: ifd_location(field),
+            raw: raw_value(&field.value),
+        }
+    }
+}
+
+/// Name the IFD a field lives in
+fn ifd_location(field: &exif::Field) -> String {
+    let ifd = format!("IFD{}", field.ifd_num.index());
+    match field.tag.context() {
+        exif::Context::Tiff => ifd,
+        exif::Context::Exif if field.ifd_num == In::PRIMARY => "Exif".to_string(),
+        exif::Context::Gps if field.ifd_num == In::PRIMARY => "GPS".to_string(),
+        exif::Context::Interop if field.ifd_num == In::PRIMARY => "Interop".to_string(),
+        exif::Context::Exif => format!("{}/Exif", ifd),
+        exif::Context::Gps => format!("{}/GPS", ifd),
+        exif::Context::Interop => format!("{}/Interop", ifd),
+        _ => format!("{}/Unknown", ifd),
+    }
+}
+
+/// Longest PNG text value shown in findings; some tools store whole hex dumps as text
+const MAX_TEXT_CHARS: usize = 80;
+
+/// Longest byte value shown in full; longer ones (e.g. MakerNote) are cut off
+const MAX_RAW_BYTES: usize = 64;
+
+/// Format a stored EXIF value as it appears in the file
+fn raw_value(value: &exif::Value) -> String {
+    use exif::Value;
+
+    fn join<T: ToString>(values: impl IntoIterator<Item = T>) -> String {
+        values.into_iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+    }
+
+    match value {
+        Value::Byte(bytes) | Value::Undefined(bytes, _) => {
+            let hex: String = bytes.iter().take(MAX_RAW_BYTES).map(|b| format!("{:02x}", b)).collect();
+            if bytes.len() > MAX_RAW_BYTES {
+                format!("{}... ({} bytes)", hex, bytes.len())
+            } else {
+                hex
+            }
+        }
+        Value::Ascii(strings) => join(strings.iter().map(|s| format!("{:?}", String::from_utf8_lossy(s)))),
+        Value::Short(values) => join(values),
+        Value::Long(values) => join(values),
+        Value::Rational(values) => join(values.iter().map(|r| format!("{}/{}", r.num, r.denom))),
+        Value::SByte(values) => join(values),
+        Value::SShort(values) => join(values),
+        Value::SLong(values) => join(values),
+        Value::SRational(values) => join(values.iter().map(|r| format!("{}/{}", r.num, r.denom))),
+        Value::Float(values) => join(values),
+        Value::Double(values) => join(values),
+        Value::Unknown(field_type, count, _) => format!("<unknown type {}, {} values>", field_type, count),
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum PrivacyCategory {
     Location,
     DeviceIdentifier,
@@ -168,6 +905,56 @@ impl std::fmt::Display for PrivacyCategory {
     }
 }
 
+impl PrivacyCategory {
+    /// The category's name in the user's language; `Display` stays English for reports and JSON keys
+    pub fn label(&self) -> String {
+        match self {
+            PrivacyCategory::Location => tr("category-location", &[]),
+            PrivacyCategory::DeviceIdentifier => tr("category-device-identifier", &[]),
+            PrivacyCategory::PersonalInfo => tr("category-personal-info", &[]),
+            PrivacyCategory::Temporal => tr("category-temporal", &[]),
+            PrivacyCategory::Software => tr("category-software", &[]),
+            PrivacyCategory::Metadata => tr("category-metadata", &[]),
+            PrivacyCategory::Other => tr("category-other", &[]),
+        }
+    }
+
+    /// How much a field of this category reveals about the person behind the photo
+    pub fn severity(&self) -> Severity {
+        match self {
+            PrivacyCategory::Location | PrivacyCategory::DeviceIdentifier | PrivacyCategory::PersonalInfo => Severity::High,
+            PrivacyCategory::Temporal => Severity::Medium,
+            PrivacyCategory::Software | PrivacyCategory::Metadata | PrivacyCategory::Other => Severity::Low,
+        }
+    }
+}
+
+/// Severity of a finding, ordered from least to most revealing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Not a disclosure, but worth a closer look; only audits report it
+    #[value(skip)]
+    Info,
+    /// Software and other incidental metadata
+    Low,
+    /// Timestamps
+    Medium,
+    /// Location, device identifiers, and personal information
+    High,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Low => write!(f, "low"),
+            Severity::Medium => write!(f, "medium"),
+            Severity::High => write!(f, "high"),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -198,6 +985,92 @@ mod tests {
         assert_eq!(analyzer.categorize_privacy_field(Tag::ProcessingSoftware), PrivacyCategory::Software);
     }
 
+    #[test]
+    fn test_category_severity() {
+        assert_eq!(PrivacyCategory::Location.severity(), Severity::High);
+        assert_eq!(PrivacyCategory::Temporal.severity(), Severity::Medium);
+        assert_eq!(PrivacyCategory::Software.severity(), Severity::Low);
+        assert!(Severity::High > Severity::Medium && Severity::Medium > Severity::Low);
+    }
+
+    #[test]
+    fn test_unknown_tags_follow_policy() {
+        // IFD0 with a single vendor tag 0xC7A1 no specification defines
+        let mut exif = b"Exif\0\0II*\0".to_vec();
+        exif.extend_from_slice(&8u32.to_le_bytes());
+        exif.extend_from_slice(&1u16.to_le_bytes());
+        exif.extend_from_slice(&0xC7A1u16.to_le_bytes());
+        exif.extend_from_slice(&3u16.to_le_bytes());
+        exif.extend_from_slice(&1u32.to_le_bytes());
+        exif.extend_from_slice(&5u32.to_le_bytes());
+        exif.extend_from_slice(&0u32.to_le_bytes());
+        let data = jpeg::tests::build_jpeg(&[(jpeg::APP1, &exif)], b"pixels");
+        let path = Path::new("test.jpg");
+
+        let preserved = ExifAnalyzer::new().analyze_privacy_data(&data, path, &PrivacyLevel::Standard).unwrap();
+        assert!(preserved.is_empty());
+
+        let analyzer = ExifAnalyzer::new().with_unknown_tags(UnknownTags::RemoveAboveStrict);
+        assert!(analyzer.analyze_privacy_data(&data, path, &PrivacyLevel::Standard).unwrap().is_empty());
+        let removed = analyzer.analyze_privacy_data(&data, path, &PrivacyLevel::Strict).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].tag, Tag(exif::Context::Tiff, 0xC7A1));
+        assert_eq!(removed[0].category, PrivacyCategory::Other);
+    }
+
+    #[test]
+    fn test_findings_carry_their_ifd() {
+        // `Software` in IFD0 and again in the thumbnail's IFD1
+        let mut exif = b"Exif\0\0II*\0".to_vec();
+        exif.extend_from_slice(&8u32.to_le_bytes());
+        for (value, next) in [(b"A\0\0\0", 26u32), (b"B\0\0\0", 0)] {
+            exif.extend_from_slice(&1u16.to_le_bytes());
+            exif.extend_from_slice(&0x0131u16.to_le_bytes());
+            exif.extend_from_slice(&2u16.to_le_bytes());
+            exif.extend_from_slice(&2u32.to_le_bytes());
+            exif.extend_from_slice(value);
+            exif.extend_from_slice(&next.to_le_bytes());
+        }
+        let data = jpeg::tests::build_jpeg(&[(jpeg::APP1, &exif)], b"pixels");
+        let path = Path::new("test.jpg");
+
+        let found = ExifAnalyzer::new().analyze_privacy_data(&data, path, &PrivacyLevel::Strict).unwrap();
+        let ifds: Vec<Option<Ifd>> = found.iter().map(|field| field.ifd).collect();
+        assert_eq!(ifds, vec![Some(Ifd::Ifd0), Some(Ifd::Ifd1)]);
+
+        let overrides = PolicyOverride::default().scoped(vec![(Ifd::Ifd0, Tag::Software)], vec![]).unwrap();
+        let found = ExifAnalyzer::new().with_overrides(overrides)
+            .analyze_privacy_data(&data, path, &PrivacyLevel::Strict)
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].ifd, Some(Ifd::Ifd1));
+    }
+
+    #[test]
+    fn test_raw_value_accessors() {
+        let field = |tag, value| PrivacyField {
+            tag,
+            description: String::new(),
+            category: PrivacyCategory::Other,
+            ifd: None,
+            value: Some(value),
+        };
+        let rational = |num, denom| exif::Rational { num, denom };
+
+        let latitude = field(Tag::GPSLatitude, Value::Rational(vec![rational(48, 1), rational(30, 1), rational(36, 1)]));
+        assert!((latitude.decimal_degrees().unwrap() - 48.51).abs() < 1e-9);
+        assert_eq!(latitude.as_f64(), Some(48.0));
+
+        let taken = field(Tag::DateTimeOriginal, Value::Ascii(vec![b"2024:05:17 14:03:09".to_vec()]));
+        let expected = NaiveDate::from_ymd_opt(2024, 5, 17).unwrap().and_hms_opt(14, 3, 9).unwrap();
+        assert_eq!(taken.datetime(), Some(expected));
+        assert_eq!(taken.decimal_degrees(), None);
+
+        let garbled = field(Tag::DateTime, Value::Ascii(vec![b"    :  :     :  :  ".to_vec()]));
+        assert_eq!(garbled.datetime(), None);
+        assert_eq!(field(Tag::PhotographicSensitivity, Value::Short(vec![400])).as_f64(), Some(400.0));
+    }
+
     #[test]
     fn test_has_exif_data_with_invalid_data() {
         let analyzer = ExifAnalyzer::new();
@@ -206,6 +1079,49 @@ mod tests {
         assert!(!analyzer.has_exif_data(&invalid_data));
     }
 
+    #[test]
+    fn test_check_metadata_readable() {
+        let analyzer = ExifAnalyzer::new();
+
+        // A JPEG without EXIF is readable, it just has nothing to remove
+        assert!(analyzer.check_metadata_readable(&[0xFF, 0xD8, 0xFF, 0xD9]).is_ok());
+        assert!(analyzer.check_metadata_readable(b"\x89PNG\r\n\x1a\n").is_err());
+    }
+
+    #[test]
+    fn test_analyze_bytes_needs_no_path() {
+        let exif = jpeg::tests::exif_with_artist("Ann");
+        let data = jpeg::tests::build_jpeg(&[(jpeg::APP1, &exif)], b"pixels");
+
+        let report = ExifAnalyzer::new().analyze_bytes(&data, &PrivacyLevel::Standard).unwrap();
+        assert_eq!(report.format, Some(ImageFormat::Jpeg));
+        assert!(!report.is_clean());
+        assert_eq!(report.fields[0].tag, Tag::Artist);
+        assert_eq!(report.counts_by_category().get(&PrivacyCategory::PersonalInfo), Some(&1));
+
+        let report = ExifAnalyzer::new().analyze_bytes(b"not an image", &PrivacyLevel::Standard).unwrap();
+        assert_eq!(report.format, None);
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn test_analyze_iter_matches_collected_fields() {
+        let exif = jpeg::tests::exif_with_artist("Ann");
+        let data = jpeg::tests::build_jpeg(&[(jpeg::APP1, &exif)], b"pixels");
+        let analyzer = ExifAnalyzer::new();
+        let parsed = analyzer.read_exif(&data).unwrap().unwrap();
+
+        let findings: Vec<Finding> = analyzer.analyze_iter(&parsed, &PrivacyLevel::Standard).collect();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].tag(), Tag::Artist);
+        assert_eq!(findings[0].category, PrivacyCategory::PersonalInfo);
+        assert_eq!(findings[0].ifd, Ifd::Ifd0);
+
+        let fields = analyzer.analyze_bytes(&data, &PrivacyLevel::Standard).unwrap().fields;
+        assert_eq!(findings[0].to_string(), fields[0].description);
+        assert_eq!(findings[0].clone().into_privacy_field().description, fields[0].description);
+    }
+
     #[test]
     fn test_analyze_privacy_data_no_exif() {
         let analyzer = ExifAnalyzer::new();
@@ -214,10 +1130,27 @@ mod tests {
         let result = analyzer.analyze_privacy_data(
             &no_exif_data, 
             Path::new("test.jpg"), 
-            &PrivacyLevel::Standard, 
-            false
+            &PrivacyLevel::Standard
         ).unwrap();
         
         assert!(result.is_empty());
     }
+
+    #[test]
+    fn test_analyze_privacy_data_corrupt_exif() {
+        let analyzer = ExifAnalyzer::new();
+        // An EXIF segment whose TIFF header has an invalid byte order
+        let mut corrupt = vec![0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x0C];
+        corrupt.extend_from_slice(b"Exif\0\0XX\0\0");
+        corrupt.extend_from_slice(&[0xFF, 0xD9]);
+
+        let error = analyzer.analyze_privacy_data(
+            &corrupt,
+            Path::new("test.jpg"),
+            &PrivacyLevel::Standard
+        ).unwrap_err();
+
+        assert!(error.is::<UnparseableMetadata>());
+        assert!(analyzer.check_metadata_readable(&corrupt).is_err());
+    }
 }
\ No newline at end of file
@@ -0,0 +1,217 @@
+//! Heuristics for containers that could hide data (`audit --anomalies`).
+//!
+//! Steganography tools and "copy /b photo.jpg+secret.zip" tricks put data
+//! where viewers don't look: application segments no software defines,
+//! private PNG chunks, and bytes after the end of the image. None of this
+//! discloses anything by itself, and cameras do write vendor segments, so
+//! findings are informational: a segment or chunk nobody recognizes that is
+//! unusually large or whose content is as random as encrypted or compressed
+//! data, and trailing data that isn't a known embedded picture or video.
+
+use std::ops::Range;
+use crate::formats::{self, ImageFormat};
+use crate::jpeg;
+use crate::multipicture;
+use crate::payload;
+use crate::png;
+
+/// Size from which an unrecognized segment or chunk is unusual whatever it holds
+const LARGE_UNKNOWN_LEN: usize = 16 * 1024;
+/// Shortest payload whose entropy is judged; shorter ones can't fill the byte range
+const MIN_ENTROPY_LEN: usize = 512;
+/// Bits of entropy per byte from which content looks encrypted or compressed
+const RANDOM_ENTROPY_THRESHOLD: f64 = 7.5;
+
+/// APPn segments by the identifier their payload starts with
+const KNOWN_APP_SEGMENTS: [(u8, &[u8]); 10] = [
+    (jpeg::APP0, b"JFIF\0"),
+    (jpeg::APP0, b"JFXX\0"),
+    (jpeg::APP1, jpeg::EXIF_HEADER),
+    (jpeg::APP1, jpeg::XMP_HEADER),
+    (jpeg::APP1, jpeg::XMP_EXTENSION_HEADER),
+    (jpeg::APP2, b"ICC_PROFILE\0"),
+    (jpeg::APP2, b"MPF\0"),
+    (0xEC, b"Ducky"),
+    (jpeg::APP13, b"Photoshop 3.0\0"),
+    (jpeg::APP14, b"Adobe"),
+];
+/// Chunks defined by the PNG specification and its registered extensions, and the `--keep-size` filler
+const KNOWN_CHUNKS: [&[u8; 4]; 24] = [
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"tRNS", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"cICP", b"mDCv",
+    b"cLLi", b"tEXt", b"zTXt", b"iTXt", b"bKGD", b"hIST", b"pHYs", b"sPLT", b"eXIf", b"tIME", b"acTL", b"paDd",
+];
+/// Animation chunks, which repeat per frame
+const APNG_CHUNKS: [&[u8; 4]; 2] = [b"fcTL", b"fdAT"];
+/// Signatures of files commonly appended to images, and their names
+const APPENDED_FILES: [(&[u8], &str); 5] = [
+    (b"PK\x03\x04", "a ZIP archive"),
+    (b"Rar!\x1A\x07", "a RAR archive"),
+    (b"7z\xBC\xAF\x27\x1C", "a 7-Zip archive"),
+    (b"%PDF-", "a PDF document"),
+    (b"\x1F\x8B", "gzip data"),
+];
+
+/// Something in a file that could hide data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly {
+    /// Offset in the file
+    pub offset: usize,
+    pub description: String,
+}
+
+/// Look for unusual containers in an image
+///
+/// `known` lists ranges the caller accounts for, such as a motion photo's
+/// video; trailing data inside them is not reported.
+pub fn scan(data: &[u8], known: &[Range<usize>]) -> Vec<Anomaly> {
+    match formats::sniff_image_format(data) {
+        Some(ImageFormat::Jpeg) => jpeg_anomalies(data, known),
+        Some(ImageFormat::Png) => png_anomalies(data, known),
+        _ => Vec::new(),
+    }
+}
+
+fn jpeg_anomalies(data: &[u8], known: &[Range<usize>]) -> Vec<Anomaly> {
+    let parts = match jpeg::parse_parts(data) {
+        Ok(parts) => parts,
+        Err(_) => return Vec::new(),
+    };
+    let mut anomalies = Vec::new();
+    let mut end = 2;
+    for part in &parts {
+        match part {
+            jpeg::Part::Segment(segment) => {
+                end = segment.offset + segment.encoded_len();
+                let name = match segment.marker {
+                    jpeg::APP0..=jpeg::APP15 if !is_known_app_segment(segment.marker, segment.payload) => {
+                        format!("Unknown APP{} segment", segment.marker - jpeg::APP0)
+                    }
+                    jpeg::COM => "Comment segment".to_string(),
+                    _ => continue,
+                };
+                anomalies.extend(container_anomaly(segment.offset, &name, segment.payload, segment.marker != jpeg::COM));
+            }
+            // Scans follow their SOS segment directly
+            jpeg::Part::Scan(scan) => end += scan.len(),
+        }
+    }
+
+    // Entropy-coded data escapes 0xFF bytes, so the EOI marker follows the last scan, after any fill bytes
+    let eoi = data[end..].windows(2).position(|pair| pair == [0xFF, jpeg::EOI]);
+    if let Some(eoi) = eoi {
+        let mut known = known.to_vec();
+        known.extend(multipicture::embedded_images(data).into_iter().map(|image| image.range));
+        anomalies.extend(trailer_anomaly(data, end + eoi + 2, &known));
+    }
+    anomalies
+}
+
+fn png_anomalies(data: &[u8], known: &[Range<usize>]) -> Vec<Anomaly> {
+    let chunks = match png::parse_chunks(data) {
+        Ok(chunks) => chunks,
+        Err(_) => return Vec::new(),
+    };
+    let mut anomalies = Vec::new();
+    let mut pos = png::SIGNATURE.len();
+    for chunk in &chunks {
+        if !KNOWN_CHUNKS.contains(&&chunk.kind) && !APNG_CHUNKS.contains(&&chunk.kind) {
+            let name = format!("Unknown {} chunk", String::from_utf8_lossy(&chunk.kind));
+            anomalies.extend(container_anomaly(pos, &name, chunk.data, true));
+        }
+        // Length and type, the data, then a CRC
+        pos += 12 + chunk.data.len();
+    }
+    anomalies.extend(trailer_anomaly(data, pos, known));
+    anomalies
+}
+
+fn is_known_app_segment(marker: u8, payload: &[u8]) -> bool {
+    KNOWN_APP_SEGMENTS.iter().any(|(known, identifier)| *known == marker && payload.starts_with(identifier))
+}
+
+/// A segment or chunk that is unusually large (when `large_is_unusual`) or near-random
+fn container_anomaly(offset: usize, name: &str, payload: &[u8], large_is_unusual: bool) -> Option<Anomaly> {
+    let random = looks_random(payload);
+    if !(random || (large_is_unusual && payload.len() >= LARGE_UNKNOWN_LEN)) {
+        return None;
+    }
+    let content = if random { ", as random as encrypted or compressed data" } else { "" };
+    Some(Anomaly {
+        offset,
+        description: format!("{} of {} bytes{}, which could carry hidden data", name, payload.len(), content),
+    })
+}
+
+/// Data after the end of the image at `end`, outside `known`, that is an appended file or near-random
+fn trailer_anomaly(data: &[u8], end: usize, known: &[Range<usize>]) -> Option<Anomaly> {
+    let start = known.iter()
+        .filter(|range| range.start <= end && range.end > end)
+        .map(|range| range.end)
+        .max()
+        .unwrap_or(end);
+    let trailer = data.get(start..).filter(|trailer| !trailer.is_empty())?;
+    // A trailer the caller accounts for further on is some known format's; only the gap before it is unexplained
+    let trailer = match known.iter().filter(|range| range.start > start).map(|range| range.start).min() {
+        Some(next) => &trailer[..(next - start).min(trailer.len())],
+        None => trailer,
+    };
+
+    let description = match APPENDED_FILES.iter().find(|(signature, _)| trailer.starts_with(signature)) {
+        Some((_, kind)) => format!("{} bytes after the end of the image start like {}", trailer.len(), kind),
+        None if looks_random(trailer) => format!(
+            "{} bytes after the end of the image are as random as encrypted or compressed data",
+            trailer.len()
+        ),
+        None => return None,
+    };
+    Some(Anomaly { offset: start, description })
+}
+
+fn looks_random(bytes: &[u8]) -> bool {
+    bytes.len() >= MIN_ENTROPY_LEN && payload::shannon_entropy(bytes) >= RANDOM_ENTROPY_THRESHOLD
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::tests::build_jpeg;
+
+    /// Bytes covering the whole byte range evenly, as encrypted data does
+    fn random_bytes(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 167 + i / 256) as u8).collect()
+    }
+
+    #[test]
+    fn test_flags_unknown_and_random_containers() {
+        let hidden = [b"STEG".to_vec(), random_bytes(4096)].concat();
+        let vendor = [b"Vendor".to_vec(), vec![0; 20 * 1024]].concat();
+        let mut data = build_jpeg(&[(jpeg::APP0, b"JFIF\0"), (0xE9, &hidden), (0xEB, &vendor), (0xEB, b"small")], b"pixels");
+        data.extend_from_slice(b"PK\x03\x04secret.txt");
+
+        let descriptions: Vec<String> = scan(&data, &[]).into_iter().map(|anomaly| anomaly.description).collect();
+        assert_eq!(descriptions.len(), 3);
+        assert!(descriptions[0].starts_with("Unknown APP9 segment of 4100 bytes, as random"));
+        assert!(descriptions[1].starts_with("Unknown APP11 segment of 20486 bytes, which"));
+        assert!(descriptions[2].ends_with("start like a ZIP archive"));
+    }
+
+    #[test]
+    fn test_ordinary_files_have_no_anomalies() {
+        let exif = [jpeg::EXIF_HEADER, &random_bytes(2048)[..]].concat();
+        let data = build_jpeg(&[(jpeg::APP0, b"JFIF\0"), (jpeg::APP1, &exif), (jpeg::COM, b"Shot on a tripod")], b"pixels");
+        assert!(scan(&data, &[]).is_empty());
+
+        // Trailing data the caller accounts for
+        let mut with_video = data.clone();
+        with_video.extend(random_bytes(4096));
+        assert!(!scan(&with_video, &[]).is_empty());
+        assert!(scan(&with_video, &[data.len()..with_video.len()]).is_empty());
+    }
+
+    #[test]
+    fn test_random_bytes_are_random() {
+        assert!(looks_random(&random_bytes(4096)));
+        assert!(!looks_random(&vec![7; 4096]));
+        assert!(!looks_random(&random_bytes(100)));
+    }
+}
@@ -0,0 +1,216 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+use crate::cli::UnsupportedPolicy;
+use crate::formats;
+use crate::processor::ImageProcessor;
+use crate::utils;
+use tracing::{debug, error, info, warn};
+
+/// Largest entry read into memory; the size in an entry's header is only the archive's claim
+const MAX_ENTRY_BYTES: u64 = 512 * 1024 * 1024;
+
+/// Check if a path points to a supported archive (by extension)
+pub fn is_supported_archive(path: &Path) -> bool {
+    matches!(utils::get_file_extension(path).as_deref(), Some("zip"))
+}
+
+/// Determine where the sanitized archive should be written
+///
+/// With an output directory the archive keeps its name; otherwise a
+/// `<name>.cleaned.zip` sibling is created so the original is never overwritten.
+/// An output directory holding the archive itself is refused for the same reason:
+/// creating the output would truncate the archive before it is read.
+pub fn get_archive_output_path(archive_path: &Path, output_dir: Option<&Path>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let file_name = archive_path.file_name()
+        .ok_or("Invalid archive name")?;
+
+    let output_path = if let Some(out_dir) = output_dir {
+        out_dir.join(file_name)
+    } else {
+        let stem = archive_path.file_stem()
+            .unwrap_or_default()
+            .to_string_lossy();
+        archive_path.with_file_name(format!("{}.cleaned.zip", stem))
+    };
+
+    if let (Ok(archive), Ok(output)) = (archive_path.canonicalize(), output_path.canonicalize()) {
+        if archive == output {
+            return Err(format!("Output '{}' is the input archive itself; choose another output directory", output_path.display()).into());
+        }
+    }
+    Ok(output_path)
+}
+
+/// Counters for a single archive run
+#[derive(Debug, Default)]
+pub struct ArchiveStats {
+    pub processed: u32,
+    pub privacy_data_found: u32,
+    pub errors: u32,
+    /// Entries that aren't supported images, left out under `--unsupported skip`
+    pub unsupported_skipped: u32,
+}
+
+/// Clean every supported image inside a ZIP archive without extracting to disk
+///
+/// Entries are read into memory one at a time, cleaned, and written to a new
+/// archive at `output_path`. Non-image entries follow `--unsupported` as files
+/// on disk do: left out, copied through unchanged (unless `require_clean` is
+/// set), or failing the run. In dry-run mode the archive is only analyzed and no output is written.
+pub fn process_archive(
+    processor: &ImageProcessor,
+    archive_path: &Path,
+    output_path: &Path,
+) -> Result<ArchiveStats, Box<dyn std::error::Error>> {
+    let mut archive = ZipArchive::new(File::open(archive_path)?)?;
+    let mut writer = if processor.config().dry_run {
+        None
+    } else {
+        Some(ZipWriter::new(File::create(output_path)?))
+    };
+    let mut stats = ArchiveStats::default();
+
+    for index in 0..archive.len() {
+        let mut entry = archive.by_index(index)?;
+        let entry_name = entry.name().to_string();
+        let entry_path = archive_path.join(&entry_name);
+        let options = FileOptions::default()
+            .compression_method(entry.compression())
+            .last_modified_time(entry.last_modified());
+
+        if entry.is_dir() {
+            if let Some(ref mut writer) = writer {
+                writer.add_directory(entry_name, options)?;
+            }
+            continue;
+        }
+
+        if !formats::is_supported_image(Path::new(&entry_name)) {
+            match processor.config().unsupported {
+                UnsupportedPolicy::Skip => {
+                    debug!(path = %entry_path.display(), "Skipping unsupported entry");
+                    stats.unsupported_skipped += 1;
+                }
+                UnsupportedPolicy::Fail => {
+                    return Err(format!("Unsupported entry '{}' (use --unsupported skip or copy)", entry_path.display()).into());
+                }
+                UnsupportedPolicy::Copy if processor.config().require_clean => {
+                    warn!(path = %entry_path.display(), "Unsupported entry omitted from output (--require-clean)");
+                }
+                UnsupportedPolicy::Copy => {
+                    if let Some(ref mut writer) = writer {
+                        writer.raw_copy_file(entry)?;
+                        warn!(path = %entry_path.display(), "Copied unsupported entry verbatim; its metadata was NOT cleaned");
+                    }
+                }
+            }
+            continue;
+        }
+
+        let mut data = Vec::new();
+        (&mut entry).take(MAX_ENTRY_BYTES + 1).read_to_end(&mut data)?;
+        if data.len() as u64 > MAX_ENTRY_BYTES {
+            error!(path = %entry_path.display(), "Entry is larger than {} bytes (entry omitted from output)", MAX_ENTRY_BYTES);
+            stats.errors += 1;
+            continue;
+        }
+
+        let result = match processor.process_image_data(&data, &entry_path) {
+            Ok(result) => result,
+            Err(e) => {
+                // Never pass through an image we failed to clean
+                error!(path = %entry_path.display(), "Error processing entry: {} (entry omitted from output)", e);
+                processor.quarantine_data(&data, &entry_path, &e.to_string());
+                stats.errors += 1;
+                continue;
+            }
+        };
+
+        if processor.config().verbose || processor.config().dry_run {
+            info!(path = %entry_path.display(), "Processed");
+        }
+        stats.processed += 1;
+        if result.had_privacy_data {
+            stats.privacy_data_found += 1;
+        }
+
+        if let Some(ref mut writer) = writer {
+            writer.start_file(entry_name, options)?;
+            writer.write_all(result.output(&data))?;
+        }
+    }
+
+    if let Some(mut writer) = writer {
+        writer.finish()?;
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_supported_archive() {
+        assert!(is_supported_archive(Path::new("photos.zip")));
+        assert!(is_supported_archive(Path::new("PHOTOS.ZIP")));
+
+        assert!(!is_supported_archive(Path::new("photo.jpg")));
+        assert!(!is_supported_archive(Path::new("photos")));
+    }
+
+    #[test]
+    fn test_archive_output_path_in_place() {
+        let output_path = get_archive_output_path(Path::new("/test/photos.zip"), None).unwrap();
+        assert_eq!(output_path, Path::new("/test/photos.cleaned.zip"));
+    }
+
+    #[test]
+    fn test_archive_output_path_separate_directory() {
+        let output_path = get_archive_output_path(Path::new("/test/photos.zip"), Some(Path::new("/output"))).unwrap();
+        assert_eq!(output_path, Path::new("/output/photos.zip"));
+    }
+
+    #[test]
+    fn test_archive_output_path_refuses_the_input() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let archive = temp_dir.path().join("photos.zip");
+        std::fs::write(&archive, b"PK").unwrap();
+
+        assert!(get_archive_output_path(&archive, Some(temp_dir.path())).is_err());
+        assert!(get_archive_output_path(&archive, None).is_ok());
+    }
+
+    #[test]
+    fn test_unsupported_entries_follow_the_policy() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let archive_path = temp_dir.path().join("photos.zip");
+        let mut zip = ZipWriter::new(File::create(&archive_path).unwrap());
+        zip.start_file("clean.jpg", FileOptions::default()).unwrap();
+        zip.write_all(&crate::jpeg::tests::build_jpeg(&[(crate::jpeg::APP0, b"JFIF\0")], b"pixels")).unwrap();
+        zip.start_file("clip.mov", FileOptions::default()).unwrap();
+        zip.write_all(b"not an image").unwrap();
+        zip.finish().unwrap();
+
+        let run = |unsupported: UnsupportedPolicy| {
+            let config = crate::cli::Config { unsupported, ..crate::cli::Config::default() };
+            let output_path = temp_dir.path().join("photos.cleaned.zip");
+            let stats = process_archive(&ImageProcessor::new(config), &archive_path, &output_path)?;
+            let names: Vec<String> = ZipArchive::new(File::open(&output_path)?)?.file_names().map(str::to_string).collect();
+            Ok::<_, Box<dyn std::error::Error>>((stats, names))
+        };
+
+        let (stats, names) = run(UnsupportedPolicy::Skip).unwrap();
+        assert_eq!((stats.processed, stats.unsupported_skipped), (1, 1));
+        assert_eq!(names, ["clean.jpg"]);
+        let (stats, mut names) = run(UnsupportedPolicy::Copy).unwrap();
+        names.sort();
+        assert_eq!(stats.unsupported_skipped, 0);
+        assert_eq!(names, ["clean.jpg", "clip.mov"]);
+        assert!(run(UnsupportedPolicy::Fail).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+//! Append-only audit log of metadata removals.
+//!
+//! Each line is a JSON record of one cleaned file: hashes before and after,
+//! the tags removed, and the policy used. Records are hash-chained (every
+//! entry commits to the previous entry's hash) and can additionally be signed
+//! with an ed25519 key, so edits, deletions, or reordering are detectable with
+//! `verify_log`.
+
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::analyzer::PrivacyField;
+use crate::privacy::PrivacyLevel;
+
+/// `prev_hash` of the first entry in a log
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// One line of the audit log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    pub source: String,
+    pub output: String,
+    pub sha256_before: String,
+    pub sha256_after: String,
+    pub privacy_level: String,
+    pub tags_removed: Vec<String>,
+    pub prev_hash: String,
+    pub entry_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl AuditEntry {
+    /// Hash of the entry contents, excluding `entry_hash` and `signature`
+    fn compute_hash(&self) -> Result<String, serde_json::Error> {
+        let unsigned = AuditEntry {
+            entry_hash: String::new(),
+            signature: None,
+            ..self.clone()
+        };
+        Ok(sha256_hex(serde_json::to_string(&unsigned)?.as_bytes()))
+    }
+}
+
+struct AuditState {
+    file: File,
+    last_hash: String,
+}
+
+/// Writer for the audit log, safe to share between threads
+pub struct AuditLog {
+    state: Mutex<AuditState>,
+    signing_key: Option<SigningKey>,
+}
+
+impl AuditLog {
+    /// Open (or create) a log for appending, continuing the existing hash chain
+    pub fn open<P: AsRef<Path>>(path: P, signing_key: Option<SigningKey>) -> Result<Self, Box<dyn std::error::Error>> {
+        let path = path.as_ref();
+        let last_hash = match File::open(path) {
+            Ok(file) => last_entry_hash(file)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => GENESIS_HASH.to_string(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            state: Mutex::new(AuditState { file, last_hash }),
+            signing_key,
+        })
+    }
+
+    /// Append a record for one cleaned file
+    pub fn record(
+        &self,
+        source: &Path,
+        output: &Path,
+        before: &[u8],
+        after: &[u8],
+        privacy_level: &PrivacyLevel,
+        removed: &[PrivacyField],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut state = self.state.lock().map_err(|_| "Audit log lock poisoned")?;
+
+        let mut entry = AuditEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+            source: source.display().to_string(),
+            output: output.display().to_string(),
+            sha256_before: sha256_hex(before),
+            sha256_after: sha256_hex(after),
+            privacy_level: format!("{:?}", privacy_level),
+            tags_removed: removed.iter().map(|field| field.tag.to_string()).collect(),
+            prev_hash: state.last_hash.clone(),
+            entry_hash: String::new(),
+            signature: None,
+        };
+        entry.entry_hash = entry.compute_hash()?;
+        entry.signature = self.signing_key.as_ref()
+            .map(|key| to_hex(&key.sign(entry.entry_hash.as_bytes()).to_bytes()));
+
+        let line = serde_json::to_string(&entry)?;
+        writeln!(state.file, "{}", line)?;
+        state.file.flush()?;
+        state.last_hash = entry.entry_hash;
+
+        Ok(())
+    }
+}
+
+/// Load an ed25519 signing key from a file holding a 32-byte seed, raw or hex-encoded
+pub fn load_signing_key<P: AsRef<Path>>(path: P) -> Result<SigningKey, Box<dyn std::error::Error>> {
+    let contents = std::fs::read(path)?;
+    let seed: [u8; 32] = match contents.len() {
+        32 => contents.as_slice().try_into()?,
+        _ => from_hex(String::from_utf8(contents)?.trim())
+            .ok_or("Signing key must be 32 raw bytes or 64 hex characters")?
+            .as_slice()
+            .try_into()
+            .map_err(|_| "Signing key must be 32 raw bytes or 64 hex characters")?,
+    };
+
+    Ok(SigningKey::from_bytes(&seed))
+}
+
+/// Verify the hash chain (and signatures, when a key is given) of an audit log
+///
+/// Returns the number of verified entries, or an error naming the first bad line.
+pub fn verify_log<P: AsRef<Path>>(path: P, verifying_key: Option<&VerifyingKey>) -> Result<usize, Box<dyn std::error::Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut expected_prev = GENESIS_HASH.to_string();
+    let mut count = 0;
+
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let line_number = index + 1;
+        let entry: AuditEntry = serde_json::from_str(&line)?;
+
+        if entry.prev_hash != expected_prev {
+            return Err(format!("Line {}: hash chain broken", line_number).into());
+        }
+        if entry.compute_hash()? != entry.entry_hash {
+            return Err(format!("Line {}: entry contents do not match its hash", line_number).into());
+        }
+
+        if let Some(key) = verifying_key {
+            let signature = entry.signature.as_deref()
+                .and_then(from_hex)
+                .and_then(|bytes| <[u8; 64]>::try_from(bytes.as_slice()).ok())
+                .ok_or_else(|| format!("Line {}: missing or malformed signature", line_number))?;
+            key.verify(entry.entry_hash.as_bytes(), &Signature::from_bytes(&signature))
+                .map_err(|_| format!("Line {}: invalid signature", line_number))?;
+        }
+
+        expected_prev = entry.entry_hash;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn last_entry_hash(file: File) -> Result<String, Box<dyn std::error::Error>> {
+    let mut last_line = None;
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            last_line = Some(line);
+        }
+    }
+
+    match last_line {
+        Some(line) => Ok(serde_json::from_str::<AuditEntry>(&line)?.entry_hash),
+        None => Ok(GENESIS_HASH.to_string()),
+    }
+}
+
+/// Hex-encoded SHA-256 of a byte slice
+pub fn sha256_hex(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::Tag;
+    use crate::analyzer::PrivacyCategory;
+    use tempfile::TempDir;
+
+    fn gps_field() -> PrivacyField {
+        PrivacyField {
+            tag: Tag::GPSLatitude,
+            description: "GPS Latitude: 40.7128".to_string(),
+            category: PrivacyCategory::Location,
+            ifd: None,
+            value: None,
+        }
+    }
+
+    fn write_entries(log: &AuditLog, count: usize) {
+        for i in 0..count {
+            let name = format!("photo{}.jpg", i);
+            log.record(Path::new(&name), Path::new(&name), b"before", b"after", &PrivacyLevel::Standard, &[gps_field()])
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_hash_chain_verifies_and_continues_across_reopen() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("audit.jsonl");
+
+        write_entries(&AuditLog::open(&log_path, None).unwrap(), 2);
+        write_entries(&AuditLog::open(&log_path, None).unwrap(), 1);
+
+        assert_eq!(verify_log(&log_path, None).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("audit.jsonl");
+        write_entries(&AuditLog::open(&log_path, None).unwrap(), 2);
+
+        let contents = std::fs::read_to_string(&log_path).unwrap();
+        std::fs::write(&log_path, contents.replacen("GPSLatitude", "Artist", 1)).unwrap();
+
+        assert!(verify_log(&log_path, None).is_err());
+    }
+
+    #[test]
+    fn test_signed_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("audit.jsonl");
+        let key = SigningKey::from_bytes(&[7u8; 32]);
+        let other_key = SigningKey::from_bytes(&[8u8; 32]);
+
+        write_entries(&AuditLog::open(&log_path, Some(key.clone())).unwrap(), 2);
+
+        assert_eq!(verify_log(&log_path, Some(&key.verifying_key())).unwrap(), 2);
+        assert!(verify_log(&log_path, Some(&other_key.verifying_key())).is_err());
+    }
+
+    #[test]
+    fn test_load_signing_key_hex() {
+        let temp_dir = TempDir::new().unwrap();
+        let key_path = temp_dir.path().join("audit.key");
+        std::fs::write(&key_path, format!("{}\n", "07".repeat(32))).unwrap();
+
+        let key = load_signing_key(&key_path).unwrap();
+        assert_eq!(key.to_bytes(), [7u8; 32]);
+
+        std::fs::write(&key_path, "not a key").unwrap();
+        assert!(load_signing_key(&key_path).is_err());
+    }
+
+    #[test]
+    fn test_hex_round_trip() {
+        assert_eq!(to_hex(&[0x00, 0xab, 0xff]), "00abff");
+        assert_eq!(from_hex("00abff"), Some(vec![0x00, 0xab, 0xff]));
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+    }
+}
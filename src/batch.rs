@@ -0,0 +1,616 @@
+//! Directory runs and the statistics they produce.
+//!
+//! `process_directory` walks the configured input directory, applies the
+//! selection filters and run limits, and cleans every image it finds. The CLI
+//! prints the returned `ProcessingStats`; embedders get the same numbers.
+//! `Throttle` paces runs that share a disk with other work, and `--jobs`
+//! spreads the cleaning over worker threads (see `dispatch`).
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, warn};
+use crate::analyzer::{PrivacyCategory, Severity};
+use crate::cli::{Config, UnsupportedPolicy};
+use crate::dispatch::Dispatcher;
+use crate::formats::{self, ImageFormat};
+use crate::privacy::PrivacyLevel;
+use crate::processor::{CategoryTally, ImageProcessor};
+use crate::progress::{FileOutcome, Progress, Skipped};
+use crate::walker::{self, LimitReached};
+
+/// Statistics of one run
+#[derive(Debug, Clone, Default)]
+pub struct ProcessingStats {
+    /// Images processed without error
+    pub processed: u32,
+    /// Images in which privacy data was found
+    pub privacy_data_found: u32,
+    pub errors: u32,
+    /// Images that already conformed to the policy and were left untouched
+    pub already_clean: u32,
+    pub unsupported_skipped: u32,
+    pub unsupported_copied: u32,
+    /// Files left out by the date/size selectors
+    pub not_selected: u32,
+    /// The run limit that ended the run early, if any
+    pub stopped_by: Option<LimitReached>,
+    /// Whether the run was cancelled before every file was processed
+    pub cancelled: bool,
+    /// Privacy fields found, per category
+    pub fields_by_category: BTreeMap<PrivacyCategory, usize>,
+    /// Privacy fields confirmed gone from the cleaned output, per category; empty in dry runs
+    pub removed_by_category: BTreeMap<PrivacyCategory, usize>,
+    /// Size of all images processed, in bytes
+    pub bytes_processed: u64,
+    /// Metadata bytes removed from the images; zero in dry runs
+    pub bytes_removed: u64,
+    /// Metadata bytes a dry run estimates cleaning would remove (JPEGs only)
+    pub bytes_removable: u64,
+    /// Images processed, per format
+    pub by_format: BTreeMap<ImageFormat, u32>,
+    /// Wall-clock time of the run
+    pub duration: Duration,
+}
+
+impl ProcessingStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Total privacy fields found across all categories
+    pub fn fields_found(&self) -> usize {
+        self.fields_by_category.values().sum()
+    }
+
+    /// Privacy fields found in categories of at least `severity`
+    pub fn fields_at_or_above(&self, severity: Severity) -> usize {
+        self.fields_by_category.iter()
+            .filter(|(category, _)| category.severity() >= severity)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Total privacy fields removed across all categories
+    pub fn fields_removed(&self) -> usize {
+        self.removed_by_category.values().sum()
+    }
+}
+
+/// Files collected from the walk before they are cleaned
+pub const CHUNK_SIZE: usize = 1000;
+
+/// Paces processing to a maximum input rate (`--throttle`)
+#[derive(Debug, Clone, Copy)]
+pub struct Throttle {
+    bytes_per_sec: u64,
+}
+
+impl Throttle {
+    /// The configured throttle, if any
+    pub fn new(config: &Config) -> Option<Self> {
+        config.throttle.filter(|&rate| rate > 0).map(Throttle::per_second)
+    }
+
+    /// A throttle to `bytes_per_sec`, at least one byte per second
+    pub fn per_second(bytes_per_sec: u64) -> Self {
+        Throttle { bytes_per_sec: bytes_per_sec.max(1) }
+    }
+
+    /// How much longer a file of `bytes` that took `spent` should take at the throttled rate
+    pub fn delay(&self, bytes: u64, spent: Duration) -> Duration {
+        Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64).saturating_sub(spent)
+    }
+
+    /// Sleep off the rest of a file's time at the throttled rate
+    pub fn pace(&self, bytes: u64, spent: Duration) {
+        let delay = self.delay(bytes, spent);
+        if !delay.is_zero() {
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+/// Clean every image under the configured input directory
+///
+/// Per-file failures are counted rather than returned; only `--unsupported fail`
+/// ends the run with an error. With `--jobs` the files are cleaned on worker
+/// threads, and reported as they finish.
+pub fn process_directory(
+    processor: &ImageProcessor,
+    progress: &mut dyn Progress,
+) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    let baseline = Baseline::take(processor);
+    let mut stats = ProcessingStats::new();
+    let workers = processor.config().jobs.max(1);
+    if workers == 1 {
+        walk_directory(processor, &mut stats, progress, &baseline, None, CHUNK_SIZE)?;
+    } else {
+        let (sender, results) = mpsc::channel();
+        let pool = Pool { dispatcher: Dispatcher::new(workers), results };
+        thread::scope(|scope| {
+            for worker in 0..workers {
+                let (dispatcher, sender) = (&pool.dispatcher, sender.clone());
+                scope.spawn(move || {
+                    while let Some(job) = dispatcher.next(worker) {
+                        let cleaned = clean_file(processor, &job);
+                        if sender.send((job, cleaned)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(sender);
+
+            let walked = walk_directory(processor, &mut stats, progress, &baseline, Some(&pool), CHUNK_SIZE);
+            // Idle workers wait for more work until the queues close
+            pool.dispatcher.close();
+            walked
+        })?;
+    }
+    baseline.update(processor, &mut stats);
+    Ok(stats)
+}
+
+/// Where the processor's lifetime tallies stood when a run started
+///
+/// Tallies span the processor's lifetime; only this run's share counts.
+struct Baseline {
+    started: Instant,
+    found: Vec<(PrivacyCategory, usize)>,
+    removed: Vec<(PrivacyCategory, usize)>,
+    bytes_removed: u64,
+}
+
+impl Baseline {
+    fn take(processor: &ImageProcessor) -> Self {
+        Baseline {
+            started: Instant::now(),
+            found: processor.category_tally().counts(),
+            removed: processor.removal_tally().counts(),
+            bytes_removed: processor.bytes_removed(),
+        }
+    }
+
+    /// Bring the run's field counts, bytes removed, and duration up to date
+    fn update(&self, processor: &ImageProcessor, stats: &mut ProcessingStats) {
+        stats.fields_by_category = counts_since(&processor.category_tally(), &self.found);
+        stats.removed_by_category = counts_since(&processor.removal_tally(), &self.removed);
+        let bytes_removed = processor.bytes_removed() - self.bytes_removed;
+        if processor.config().dry_run {
+            stats.bytes_removable = bytes_removed;
+        } else {
+            stats.bytes_removed = bytes_removed;
+        }
+        stats.duration = self.started.elapsed();
+    }
+}
+
+/// A file admitted to the run
+struct Job {
+    path: PathBuf,
+    size: u64,
+    /// Position in its chunk
+    order: usize,
+    /// The level its directory's policy files set, if any, resolved during the walk
+    level: Result<Option<PrivacyLevel>, String>,
+}
+
+/// What became of one file
+enum Cleaned {
+    /// A supported image, with why it was left untouched if it was
+    Image(Option<Skipped>, Option<ImageFormat>),
+    Unsupported { copied: bool },
+    Failed(String),
+    /// An unsupported file under `--unsupported fail`, which stops the run
+    Fatal(String),
+}
+
+/// Workers cleaning files in parallel (`--jobs`)
+struct Pool {
+    dispatcher: Dispatcher<Job>,
+    results: Receiver<(Job, Cleaned)>,
+}
+
+/// Walk the input directory, cleaning the admitted files a chunk at a time
+///
+/// Only one chunk of files is held at once, however large the tree, and
+/// `progress` gets the running totals after each.
+fn walk_directory(
+    processor: &ImageProcessor,
+    stats: &mut ProcessingStats,
+    progress: &mut dyn Progress,
+    baseline: &Baseline,
+    pool: Option<&Pool>,
+    chunk_size: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut limits = walker::WalkLimits::new(processor.config());
+    let mut policies = walker::DirectoryPolicies::new(processor.config());
+    let mut chunk = Vec::with_capacity(chunk_size);
+
+    for entry in walker::walk(processor.config()) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) if e.loop_ancestor().is_some() => {
+                warn!(path = ?e.path(), "Symlink cycle detected, not descending");
+                continue;
+            }
+            Err(e) => {
+                error!("Error walking directory: {}", e);
+                progress.file_done(e.path().unwrap_or(Path::new("")), 0, &FileOutcome::Failed(e.to_string()));
+                stats.errors += 1;
+                continue;
+            }
+        };
+
+        if entry.path_is_symlink() && !processor.config().follow_symlinks {
+            debug!(path = %entry.path().display(), "Skipping symlink");
+            continue;
+        }
+
+        if entry.file_type().is_file() {
+            let path = entry.path();
+
+            if entry.file_name() == walker::POLICY_FILE {
+                continue;
+            }
+
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(e) => {
+                    error!(path = %path.display(), "Error reading file metadata: {}", e);
+                    progress.file_done(path, 0, &FileOutcome::Failed(e.to_string()));
+                    stats.errors += 1;
+                    continue;
+                }
+            };
+            if !walker::is_selected(processor.config(), &metadata) {
+                debug!(path = %path.display(), "Skipping file outside the selected date/size range");
+                stats.not_selected += 1;
+                continue;
+            }
+
+            if let Err(limit) = limits.admit(metadata.len()) {
+                warn!("Stopping early: {}; remaining files were not processed", limit);
+                stats.stopped_by = Some(limit);
+                break;
+            }
+
+            let level = if processor.config().ignore_policy_files {
+                Ok(None)
+            } else {
+                policies.level_for(path).map_err(|e| e.to_string())
+            };
+            chunk.push(Job { path: path.to_path_buf(), size: metadata.len(), order: chunk.len(), level });
+
+            if chunk.len() >= chunk_size {
+                // The walk is depth-first, so directories it has left won't come up again
+                if let Some(dir) = path.parent() {
+                    policies.retain_ancestors(dir);
+                }
+                process_chunk(processor, stats, progress, pool, std::mem::take(&mut chunk))?;
+                baseline.update(processor, stats);
+                progress.checkpoint(stats);
+                if stats.cancelled {
+                    return Ok(());
+                }
+            }
+        }
+    }
+    if !chunk.is_empty() {
+        process_chunk(processor, stats, progress, pool, chunk)?;
+    }
+    Ok(())
+}
+
+/// Clean a chunk of files, on the pool's workers if there is one
+fn process_chunk(
+    processor: &ImageProcessor,
+    stats: &mut ProcessingStats,
+    progress: &mut dyn Progress,
+    pool: Option<&Pool>,
+    chunk: Vec<Job>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let Some(pool) = pool else {
+        let throttle = Throttle::new(processor.config());
+        for job in chunk {
+            if !progress.keep_going() {
+                warn!("Run cancelled; remaining files were not processed");
+                stats.cancelled = true;
+                break;
+            }
+            let file_started = Instant::now();
+            let cleaned = clean_file(processor, &job);
+            record(stats, progress, &job, cleaned)?;
+            if let Some(ref throttle) = throttle {
+                throttle.pace(job.size, file_started.elapsed());
+            }
+        }
+        return Ok(());
+    };
+
+    // With `--sort`, files are reported in walk order; otherwise as they finish
+    let ordered = processor.config().sort.is_some();
+    let mut finished = BTreeMap::new();
+    let mut next = 0;
+    let mut pending = chunk.len();
+    for job in chunk {
+        pool.dispatcher.push(job);
+    }
+    while pending > 0 {
+        let (job, cleaned) = pool.results.recv().map_err(|_| "Worker threads stopped unexpectedly")?;
+        pending -= 1;
+        finished.insert(job.order, (job, cleaned));
+
+        while let Some(&order) = finished.keys().next() {
+            if ordered && order != next {
+                break;
+            }
+            next = order + 1;
+            let Some((job, cleaned)) = finished.remove(&order) else { break };
+            if let Err(e) = record(stats, progress, &job, cleaned) {
+                pool.dispatcher.clear();
+                return Err(e);
+            }
+            if !stats.cancelled && !progress.keep_going() {
+                warn!("Run cancelled; remaining files were not processed");
+                stats.cancelled = true;
+                // Files already being cleaned still finish
+                pending -= pool.dispatcher.clear();
+            }
+        }
+    }
+    // Files finished after one that a cancellation dropped
+    for (job, cleaned) in finished.into_values() {
+        record(stats, progress, &job, cleaned)?;
+    }
+    Ok(())
+}
+
+/// Clean one file; safe to call from any worker thread
+fn clean_file(processor: &ImageProcessor, job: &Job) -> Cleaned {
+    let path = job.path.as_path();
+    // Decide by content, not extension: `photo.jpeg.txt` is still a JPEG
+    let format = formats::detect_image_format(path);
+    match format {
+        None if formats::handler_for_file(path).is_none() => match processor.process_unsupported(path) {
+            Ok(copied) => Cleaned::Unsupported { copied },
+            // `--unsupported fail` stops the whole run
+            Err(e) if processor.config().unsupported == UnsupportedPolicy::Fail => Cleaned::Fatal(e.to_string()),
+            Err(e) => {
+                error!(path = %path.display(), "Error copying unsupported file: {}", e);
+                Cleaned::Failed(e.to_string())
+            }
+        },
+        _ => match process_image(processor, &job.level, path) {
+            Ok(skipped) => {
+                if processor.config().verbose || processor.config().dry_run {
+                    match skipped {
+                        Some(Skipped::AlreadyClean) => info!(path = %path.display(), "Skipped: already clean"),
+                        _ => info!(path = %path.display(), "Processed"),
+                    }
+                }
+                Cleaned::Image(skipped, format)
+            }
+            Err(e) => {
+                error!(path = %path.display(), "Error processing file: {}", e);
+                Cleaned::Failed(e.to_string())
+            }
+        },
+    }
+}
+
+/// Count a file into the stats and report it
+fn record(
+    stats: &mut ProcessingStats,
+    progress: &mut dyn Progress,
+    job: &Job,
+    cleaned: Cleaned,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let outcome = match cleaned {
+        Cleaned::Image(skipped, format) => {
+            stats.processed += 1;
+            stats.bytes_processed += job.size;
+            if let Some(format) = format {
+                *stats.by_format.entry(format).or_default() += 1;
+            }
+            match skipped {
+                None => {
+                    stats.privacy_data_found += 1;
+                    FileOutcome::Cleaned
+                }
+                Some(reason) => {
+                    if reason == Skipped::AlreadyClean {
+                        stats.already_clean += 1;
+                    }
+                    FileOutcome::Skipped(reason)
+                }
+            }
+        }
+        Cleaned::Unsupported { copied } => {
+            if copied {
+                stats.unsupported_copied += 1;
+            } else {
+                stats.unsupported_skipped += 1;
+            }
+            FileOutcome::Unsupported
+        }
+        Cleaned::Failed(e) => {
+            stats.errors += 1;
+            FileOutcome::Failed(e)
+        }
+        Cleaned::Fatal(e) => return Err(e.into()),
+    };
+    progress.file_done(&job.path, job.size, &outcome);
+    Ok(())
+}
+
+/// Clean one image at the level its directory's policy files set, if any
+fn process_image(
+    processor: &ImageProcessor,
+    level: &Result<Option<PrivacyLevel>, String>,
+    path: &Path,
+) -> Result<Option<Skipped>, Box<dyn std::error::Error>> {
+    match level {
+        Ok(Some(level)) => {
+            debug!(path = %path.display(), "Using directory policy");
+            processor.process_image_with_level(path, level)
+        }
+        Ok(None) => processor.process_image(path),
+        Err(e) => Err(e.clone().into()),
+    }
+}
+
+/// Counts added to `tally` since `before` was taken
+fn counts_since(tally: &CategoryTally, before: &[(PrivacyCategory, usize)]) -> BTreeMap<PrivacyCategory, usize> {
+    let before: BTreeMap<_, _> = before.iter().copied().collect();
+    tally.counts().into_iter()
+        .map(|(category, count)| (category, count - before.get(&category).copied().unwrap_or(0)))
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+    use crate::jpeg::{self, tests::build_jpeg};
+    use crate::progress::NoProgress;
+
+    #[test]
+    fn test_process_directory_stats() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        fs::write(temp_dir.path().join("clean.jpg"), &image).unwrap();
+        fs::write(temp_dir.path().join("notes.txt"), b"not an image").unwrap();
+
+        let config = Config {
+            input: temp_dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let processor = ImageProcessor::new(config);
+        let stats = process_directory(&processor, &mut NoProgress).unwrap();
+
+        assert_eq!(stats.processed, 1);
+        assert_eq!(stats.privacy_data_found, 0);
+        assert_eq!(stats.already_clean, 1);
+        assert_eq!(stats.unsupported_skipped, 1);
+        assert_eq!(stats.bytes_processed, image.len() as u64);
+        assert_eq!(stats.by_format.get(&ImageFormat::Jpeg), Some(&1));
+        assert_eq!(stats.fields_found(), 0);
+        assert_eq!(stats.fields_removed(), 0);
+        assert_eq!(stats.bytes_removed, 0);
+    }
+
+    #[test]
+    fn test_backups_are_not_cleaned_again() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        let original = build_jpeg(&[(jpeg::APP1, &jpeg::tests::exif_with_artist("Ann"))], b"pixels");
+        fs::write(temp_dir.path().join("photo.jpg"), &image).unwrap();
+        fs::write(temp_dir.path().join("photo.jpg.bak"), &original).unwrap();
+        fs::write(temp_dir.path().join("photo.jpg.bak.1"), &original).unwrap();
+        fs::write(temp_dir.path().join("photo.jpg.cleaner-tmp"), &original).unwrap();
+
+        let config = Config {
+            input: temp_dir.path().to_path_buf(),
+            create_backup: true,
+            ..Config::default()
+        };
+        let processor = ImageProcessor::new(config);
+        for _ in 0..2 {
+            let stats = process_directory(&processor, &mut NoProgress).unwrap();
+            assert_eq!(stats.processed, 1);
+            assert_eq!(stats.unsupported_skipped, 0);
+        }
+
+        assert_eq!(fs::read(temp_dir.path().join("photo.jpg.bak")).unwrap(), original);
+        assert_eq!(fs::read(temp_dir.path().join("photo.jpg.bak.1")).unwrap(), original);
+        assert!(!temp_dir.path().join("photo.jpg.bak.bak").exists());
+    }
+
+    #[test]
+    fn test_parallel_run_counts_every_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        for i in 0..8 {
+            fs::write(temp_dir.path().join(format!("clean{}.jpg", i)), &image).unwrap();
+        }
+        fs::write(temp_dir.path().join("notes.txt"), b"not an image").unwrap();
+
+        let config = Config {
+            input: temp_dir.path().to_path_buf(),
+            jobs: 3,
+            ..Config::default()
+        };
+        let processor = ImageProcessor::new(config);
+        let stats = process_directory(&processor, &mut NoProgress).unwrap();
+
+        assert_eq!(stats.processed, 8);
+        assert_eq!(stats.already_clean, 8);
+        assert_eq!(stats.unsupported_skipped, 1);
+        assert_eq!(stats.errors, 0);
+        assert_eq!(stats.bytes_processed, 8 * image.len() as u64);
+    }
+
+    /// Records the processed count at each checkpoint
+    struct Checkpoints(Vec<u32>);
+
+    impl Progress for Checkpoints {
+        fn file_done(&mut self, _path: &Path, _size: u64, _outcome: &FileOutcome) {}
+
+        fn checkpoint(&mut self, stats: &ProcessingStats) {
+            self.0.push(stats.processed);
+        }
+    }
+
+    #[test]
+    fn test_walk_reports_totals_per_chunk() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        for i in 0..5 {
+            fs::write(temp_dir.path().join(format!("clean{}.jpg", i)), &image).unwrap();
+        }
+
+        let config = Config {
+            input: temp_dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let processor = ImageProcessor::new(config);
+        let baseline = Baseline::take(&processor);
+        let mut stats = ProcessingStats::new();
+        let mut progress = Checkpoints(Vec::new());
+        walk_directory(&processor, &mut stats, &mut progress, &baseline, None, 2).unwrap();
+
+        // The last, partial chunk is covered by the final stats
+        assert_eq!(progress.0, vec![2, 4]);
+        assert_eq!(stats.processed, 5);
+    }
+
+    #[test]
+    fn test_fields_at_or_above_severity() {
+        let mut stats = ProcessingStats::new();
+        stats.fields_by_category.insert(PrivacyCategory::Software, 4);
+        stats.fields_by_category.insert(PrivacyCategory::Location, 2);
+
+        assert_eq!(stats.fields_at_or_above(Severity::Low), 6);
+        assert_eq!(stats.fields_at_or_above(Severity::High), 2);
+
+        stats.fields_by_category.remove(&PrivacyCategory::Location);
+        assert_eq!(stats.fields_at_or_above(Severity::Medium), 0);
+    }
+
+    #[test]
+    fn test_throttle_delay() {
+        assert!(Throttle::new(&Config::default()).is_none());
+
+        let config = Config { throttle: Some(1_000_000), ..Config::default() };
+        let throttle = Throttle::new(&config).unwrap();
+        assert_eq!(throttle.delay(2_000_000, Duration::from_millis(500)), Duration::from_millis(1500));
+        // Files that were already slow enough aren't delayed
+        assert_eq!(throttle.delay(500_000, Duration::from_secs(1)), Duration::ZERO);
+    }
+}
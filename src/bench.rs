@@ -0,0 +1,170 @@
+//! Throughput benchmark over a synthetic corpus (`--bench`).
+//!
+//! Every file goes through two stages: in-process parsing and analysis,
+//! which decides what to remove, and ExifTool, which removes it. Only the
+//! ExifTool row is a full clean; timing them separately shows which side a
+//! regression is on. The corpus is generated in memory, so disk speed doesn't
+//! skew the numbers.
+
+use std::time::{Duration, Instant};
+use tracing::warn;
+use crate::analyzer::ExifAnalyzer;
+use crate::cli::Config;
+use crate::remover::MetadataRemover;
+
+/// Sizes of the entropy-coded data of corpus images, cycled through
+const PAYLOAD_SIZES: &[usize] = &[64 << 10, 256 << 10, 1 << 20];
+
+/// Throughput of one stage over the corpus
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchResult {
+    pub backend: &'static str,
+    pub files: usize,
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn files_per_sec(&self) -> f64 {
+        self.files as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn mb_per_sec(&self) -> f64 {
+        self.bytes as f64 / (1024.0 * 1024.0) / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// `count` JPEGs with GPS, camera, and author EXIF, of varying sizes
+pub fn synthetic_corpus(count: usize) -> Vec<Vec<u8>> {
+    (0..count).map(|i| synthetic_jpeg(PAYLOAD_SIZES[i % PAYLOAD_SIZES.len()], i as u32)).collect()
+}
+
+/// Time analysis and ExifTool removal over a corpus of `count` files
+///
+/// ExifTool is skipped, with a warning, when it isn't installed. Errors on
+/// individual files abort the run: a benchmark that silently skips files
+/// reports a throughput that was never achieved.
+pub fn run(config: &Config, count: usize) -> Result<Vec<BenchResult>, Box<dyn std::error::Error>> {
+    let corpus = synthetic_corpus(count);
+    let bytes = corpus.iter().map(|data| data.len() as u64).sum();
+    let level = &config.privacy_level;
+    let mut results = Vec::new();
+
+    let analyzer = ExifAnalyzer::new()
+        .with_overrides(config.policy_override.clone())
+        .with_unknown_tags(config.unknown_tags);
+    let start = Instant::now();
+    for data in &corpus {
+        analyzer.analyze_bytes(data, level)?;
+    }
+    results.push(BenchResult { backend: "analysis", files: count, bytes, elapsed: start.elapsed() });
+
+    let remover = MetadataRemover::new()
+        .with_timeout(Duration::from_secs(config.timeout_secs))
+        .with_overrides(config.policy_override.clone())
+        .with_unknown_tags(config.unknown_tags)
+        .with_sandbox(config.sandbox);
+    match remover.get_exiftool_version() {
+        Ok(_) => {
+            let start = Instant::now();
+            for data in &corpus {
+                remover.remove_privacy_data_from_bytes(data, level)?;
+            }
+            results.push(BenchResult { backend: "exiftool", files: count, bytes, elapsed: start.elapsed() });
+        }
+        Err(_) => warn!("ExifTool not found; skipping the exiftool backend"),
+    }
+
+    Ok(results)
+}
+
+/// A baseline JPEG with an EXIF block and `payload_len` bytes of fake entropy-coded data
+fn synthetic_jpeg(payload_len: usize, seed: u32) -> Vec<u8> {
+    let tiff = synthetic_exif();
+    let mut data = vec![0xFF, 0xD8];
+
+    data.extend_from_slice(&[0xFF, 0xE1]);
+    data.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    data.extend_from_slice(b"Exif\0\0");
+    data.extend_from_slice(&tiff);
+
+    // 1x1 grayscale frame and a scan header; the data isn't decodable, but nothing here decodes it
+    data.extend_from_slice(&[0xFF, 0xC0, 0x00, 0x0B, 0x08, 0x00, 0x01, 0x00, 0x01, 0x01, 0x01, 0x11, 0x00]);
+    data.extend_from_slice(&[0xFF, 0xDA, 0x00, 0x08, 0x01, 0x01, 0x00, 0x00, 0x3F, 0x00]);
+
+    // A linear congruential generator is enough to keep the payload from compressing away
+    let mut state = seed.wrapping_mul(2_654_435_761).wrapping_add(1);
+    data.extend((0..payload_len).map(|_| {
+        state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        // 0xFF would start a marker
+        ((state >> 24) as u8).min(0xFE)
+    }));
+
+    data.extend_from_slice(&[0xFF, 0xD9]);
+    data
+}
+
+/// Little-endian TIFF block with Make and Artist in IFD0 and a GPS latitude
+fn synthetic_exif() -> Vec<u8> {
+    fn entry(data: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32) {
+        data.extend_from_slice(&tag.to_le_bytes());
+        data.extend_from_slice(&kind.to_le_bytes());
+        data.extend_from_slice(&count.to_le_bytes());
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    const ASCII: u16 = 2;
+    const LONG: u16 = 4;
+    const RATIONAL: u16 = 5;
+
+    // IFD0 at 8 (3 entries, 42 bytes), its strings at 50 and 59, the GPS IFD
+    // at 68 (2 entries, 30 bytes), and the latitude's rationals at 98
+    let mut data = b"II*\0".to_vec();
+    data.extend_from_slice(&8u32.to_le_bytes());
+
+    data.extend_from_slice(&3u16.to_le_bytes());
+    entry(&mut data, 0x010F, ASCII, 9, 50);
+    entry(&mut data, 0x013B, ASCII, 9, 59);
+    entry(&mut data, 0x8825, LONG, 1, 68);
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(b"Benchcam\0");
+    data.extend_from_slice(b"Jane Doe\0");
+
+    data.extend_from_slice(&2u16.to_le_bytes());
+    entry(&mut data, 0x0001, ASCII, 2, u32::from_le_bytes(*b"N\0\0\0"));
+    entry(&mut data, 0x0002, RATIONAL, 3, 98);
+    data.extend_from_slice(&0u32.to_le_bytes());
+    for (numerator, denominator) in [(48u32, 1u32), (51, 1), (2412, 100)] {
+        data.extend_from_slice(&numerator.to_le_bytes());
+        data.extend_from_slice(&denominator.to_le_bytes());
+    }
+
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privacy::PrivacyLevel;
+
+    #[test]
+    fn test_synthetic_corpus_has_privacy_data() {
+        let corpus = synthetic_corpus(4);
+        assert_eq!(corpus.len(), 4);
+        assert_eq!(corpus[3].len(), corpus[0].len());
+        assert!(corpus[1].len() > PAYLOAD_SIZES[1]);
+
+        let fields = ExifAnalyzer::new()
+            .analyze_bytes(&corpus[0], &PrivacyLevel::Standard)
+            .unwrap()
+            .fields;
+        assert!(fields.iter().any(|field| field.tag == exif::Tag::GPSLatitude));
+        assert!(fields.iter().any(|field| field.tag == exif::Tag::Artist));
+    }
+
+    #[test]
+    fn test_throughput() {
+        let result = BenchResult { backend: "analysis", files: 10, bytes: 20 << 20, elapsed: Duration::from_secs(2) };
+        assert_eq!(result.files_per_sec(), 5.0);
+        assert_eq!(result.mb_per_sec(), 10.0);
+    }
+}
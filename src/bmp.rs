@@ -0,0 +1,123 @@
+//! BMP headers.
+//!
+//! BMPs have no EXIF or XMP. The one thing that gives anything away is a
+//! BITMAPV5HEADER's linked color profile: a path on the machine that saved the
+//! file, often including the user name. ExifTool can't write BMP, so the path
+//! is blanked here, in place.
+
+use std::ops::Range;
+
+/// Size of the file header before the DIB header
+const FILE_HEADER_LEN: usize = 14;
+/// Size of a BITMAPV5HEADER, the only header with a profile
+const V5_HEADER_LEN: u32 = 124;
+/// Offsets into the V5 header of the color space type and the profile's offset and size
+const CS_TYPE: usize = 56;
+const PROFILE_DATA: usize = 112;
+const PROFILE_SIZE: usize = 116;
+/// `bV5CSType` values: the profile is a file name, or the image is plain sRGB
+const PROFILE_LINKED: u32 = u32::from_be_bytes(*b"LINK");
+const LCS_SRGB: u32 = u32::from_be_bytes(*b"sRGB");
+/// DIB header sizes in use, from BITMAPCOREHEADER to BITMAPV5HEADER
+const DIB_HEADER_LENS: &[u32] = &[12, 40, 52, 56, 64, 108, 124];
+
+/// Check for the `BM` signature followed by a known DIB header size
+pub fn is_bmp(data: &[u8]) -> bool {
+    data.starts_with(b"BM") && read_u32(data, FILE_HEADER_LEN).map_or(false, |len| DIB_HEADER_LENS.contains(&len))
+}
+
+/// The path of a linked color profile, and where it is stored
+pub fn linked_profile(data: &[u8]) -> Option<(Range<usize>, String)> {
+    if !is_bmp(data) || read_u32(data, FILE_HEADER_LEN)? != V5_HEADER_LEN {
+        return None;
+    }
+    if read_u32(data, FILE_HEADER_LEN + CS_TYPE)? != PROFILE_LINKED {
+        return None;
+    }
+
+    // The profile offset counts from the start of the DIB header
+    let start = FILE_HEADER_LEN + read_u32(data, FILE_HEADER_LEN + PROFILE_DATA)? as usize;
+    let end = start.checked_add(read_u32(data, FILE_HEADER_LEN + PROFILE_SIZE)? as usize)?;
+    let path = data.get(start..end)?;
+    // Windows-1252, NUL-terminated; the path is only shown, so Latin-1 is close enough
+    let path: String = path.iter().take_while(|&&byte| byte != 0).map(|&byte| byte as char).collect();
+    Some((start..end, path))
+}
+
+/// Replace a linked color profile with the sRGB color space and blank its path
+///
+/// Returns whether there was a linked profile. The file keeps its size and layout.
+pub fn remove_linked_profile(data: &mut [u8]) -> bool {
+    let (range, _) = match linked_profile(data) {
+        Some(profile) => profile,
+        None => return false,
+    };
+    data[range].fill(0);
+    let header = FILE_HEADER_LEN;
+    data[header + CS_TYPE..header + CS_TYPE + 4].copy_from_slice(&LCS_SRGB.to_le_bytes());
+    data[header + PROFILE_DATA..header + PROFILE_SIZE + 4].fill(0);
+    true
+}
+
+fn read_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    /// A 1x1 BMP with a V5 header linking to `profile`
+    fn build_bmp(profile: &[u8]) -> Vec<u8> {
+        let mut header = vec![0u8; V5_HEADER_LEN as usize];
+        header[0..4].copy_from_slice(&V5_HEADER_LEN.to_le_bytes());
+        header[4..8].copy_from_slice(&1u32.to_le_bytes());
+        header[8..12].copy_from_slice(&1u32.to_le_bytes());
+        header[12..14].copy_from_slice(&1u16.to_le_bytes());
+        header[14..16].copy_from_slice(&24u16.to_le_bytes());
+        header[CS_TYPE..CS_TYPE + 4].copy_from_slice(&PROFILE_LINKED.to_le_bytes());
+        // Pixels (one padded row) come first, then the profile
+        header[PROFILE_DATA..PROFILE_DATA + 4].copy_from_slice(&(V5_HEADER_LEN + 4).to_le_bytes());
+        header[PROFILE_SIZE..PROFILE_SIZE + 4].copy_from_slice(&(profile.len() as u32).to_le_bytes());
+
+        let pixels_at = (FILE_HEADER_LEN + header.len()) as u32;
+        let mut data = b"BM".to_vec();
+        data.extend_from_slice(&(pixels_at + 4 + profile.len() as u32).to_le_bytes());
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&pixels_at.to_le_bytes());
+        data.extend_from_slice(&header);
+        data.extend_from_slice(&[0, 0, 255, 0]);
+        data.extend_from_slice(profile);
+        data
+    }
+
+    #[test]
+    fn test_linked_profile_is_removed() {
+        let mut data = build_bmp(b"C:\\Users\\jdoe\\Profiles\\monitor.icm\0");
+        assert_eq!(linked_profile(&data).unwrap().1, "C:\\Users\\jdoe\\Profiles\\monitor.icm");
+
+        let len = data.len();
+        assert!(remove_linked_profile(&mut data));
+        assert_eq!(data.len(), len);
+        assert!(linked_profile(&data).is_none());
+        assert!(!data.windows(4).any(|window| window == b"jdoe"));
+        assert!(is_bmp(&data));
+    }
+
+    proptest! {
+        #[test]
+        fn removal_keeps_pixels_and_is_idempotent(path in proptest::collection::vec(1u8.., 1..260)) {
+            let original = build_bmp(&path);
+            let mut once = original.clone();
+            prop_assert!(remove_linked_profile(&mut once));
+            prop_assert_eq!(once.len(), original.len());
+            let pixels = FILE_HEADER_LEN + V5_HEADER_LEN as usize..FILE_HEADER_LEN + V5_HEADER_LEN as usize + 4;
+            prop_assert_eq!(&once[pixels.clone()], &original[pixels]);
+
+            let mut twice = once.clone();
+            prop_assert!(!remove_linked_profile(&mut twice));
+            prop_assert_eq!(twice, once);
+        }
+    }
+}
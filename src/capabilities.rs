@@ -0,0 +1,307 @@
+//! Finding the ExifTool to run and probing what it supports.
+//!
+//! The executable is `--exiftool` or `$EXIFTOOL_PATH` when set, else one
+//! bundled next to this program, else the first found on `PATH` or, on
+//! Windows, in the usual install locations.
+//!
+//! ExifTool skips options and tag names it doesn't know with at most a
+//! warning on stderr, so an old install would silently leave data behind.
+//! The version and the writable tag names are probed once per process; the
+//! remover leaves out what isn't supported and each gap is logged once.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Mutex, OnceLock};
+use tracing::{debug, info, warn};
+
+/// Environment variable naming the ExifTool executable
+pub const EXIFTOOL_PATH_VAR: &str = "EXIFTOOL_PATH";
+
+static EXIFTOOL: OnceLock<PathBuf> = OnceLock::new();
+
+/// Use the ExifTool at `path` instead of searching for one; must be called before ExifTool is first run
+pub fn set_exiftool_path(path: impl Into<PathBuf>) -> Result<(), String> {
+    let path = path.into();
+    if !path.is_file() {
+        return Err(format!("ExifTool not found at {}", path.display()));
+    }
+    EXIFTOOL.set(path).map_err(|_| "The ExifTool path is already in use".to_string())
+}
+
+/// The ExifTool executable, found on first use
+pub fn exiftool_path() -> &'static Path {
+    EXIFTOOL.get_or_init(|| {
+        let path = locate();
+        debug!(path = %path.display(), "ExifTool executable");
+        if is_pausing_build(&path) {
+            warn!(
+                "{} waits for a key press when it finishes; rename it to exiftool.exe, keeping the exiftool_files folder beside it",
+                path.display()
+            );
+        }
+        path
+    })
+}
+
+/// A command running ExifTool
+pub fn command() -> Command {
+    Command::new(exiftool_path())
+}
+
+/// Whether `path` is the Windows build named to pause before exiting, as downloaded
+pub fn is_pausing_build(path: &Path) -> bool {
+    path.file_name().map_or(false, |name| name.to_string_lossy().contains("(-k)"))
+}
+
+fn locate() -> PathBuf {
+    if let Some(path) = std::env::var_os(EXIFTOOL_PATH_VAR).filter(|path| !path.is_empty()) {
+        return PathBuf::from(path);
+    }
+
+    let bundled = std::env::current_exe().ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        .map(|dir| vec![dir.join("exiftool"), dir.join("exiftool").join("exiftool")])
+        .unwrap_or_default();
+    let on_path = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    candidates(&bundled, &on_path, &install_dirs())
+        .into_iter()
+        .find(|candidate| candidate.is_file())
+        // Leave it to the OS search and its "not found" error
+        .unwrap_or_else(|| PathBuf::from("exiftool"))
+}
+
+/// Where ExifTool installers put it on Windows
+fn install_dirs() -> Vec<PathBuf> {
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+    let mut dirs: Vec<PathBuf> = ["ProgramFiles", "ProgramFiles(x86)"].iter()
+        .filter_map(std::env::var_os)
+        .map(|dir| PathBuf::from(dir).join("ExifTool"))
+        .collect();
+    if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+        dirs.push(PathBuf::from(local).join("Programs").join("ExifTool"));
+    }
+    if let Some(windows) = std::env::var_os("SystemRoot") {
+        dirs.push(PathBuf::from(windows));
+    }
+    dirs
+}
+
+/// Executables to try in order: bundled paths, then each directory under each install name
+///
+/// The pausing `exiftool(-k).exe` is tried last in every directory, so a renamed copy wins.
+fn candidates(bundled: &[PathBuf], path_dirs: &[PathBuf], install_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let names: &[&str] = if cfg!(windows) { &["exiftool.exe", "exiftool(-k).exe"] } else { &["exiftool"] };
+    let suffix = if cfg!(windows) { ".exe" } else { "" };
+
+    let bundled = bundled.iter().map(|path| {
+        let mut path = path.clone().into_os_string();
+        path.push(suffix);
+        PathBuf::from(path)
+    });
+    let searched = path_dirs.iter().chain(install_dirs)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)));
+    bundled.chain(searched).collect()
+}
+
+/// An ExifTool release, e.g. 12.76
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ExifToolVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl ExifToolVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        ExifToolVersion { major, minor }
+    }
+
+    /// Parse the output of `exiftool -ver`
+    pub fn parse(version: &str) -> Option<Self> {
+        let (major, minor) = version.trim().split_once('.')?;
+        // Development releases carry a suffix, e.g. 12.77-dev
+        let minor: String = minor.chars().take_while(char::is_ascii_digit).collect();
+        Some(ExifToolVersion::new(major.parse().ok()?, minor.parse().ok()?))
+    }
+}
+
+impl std::fmt::Display for ExifToolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
+
+/// Options the remover passes only when ExifTool understands them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    /// `-charset filename=utf8`, for non-ASCII file names on Windows
+    FileNameCharset,
+    /// `-api WindowsLongPath=1`, for paths over 260 characters on Windows
+    WindowsLongPath,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 2] = [Feature::FileNameCharset, Feature::WindowsLongPath];
+
+    /// The first release with the feature
+    pub fn minimum_version(&self) -> ExifToolVersion {
+        match self {
+            Feature::FileNameCharset => ExifToolVersion::new(9, 79),
+            Feature::WindowsLongPath => ExifToolVersion::new(12, 66),
+        }
+    }
+
+    /// Whether the remover would use the feature on this platform
+    fn applies(&self) -> bool {
+        cfg!(windows)
+    }
+
+    /// What goes wrong without the feature
+    fn degradation(&self) -> &'static str {
+        match self {
+            Feature::FileNameCharset => "files with non-ASCII names may fail to open",
+            Feature::WindowsLongPath => "files with paths over 260 characters will fail",
+        }
+    }
+}
+
+/// What the installed ExifTool supports
+#[derive(Debug, Clone)]
+pub struct Capabilities {
+    pub version: ExifToolVersion,
+    /// Lowercased names of writable tags; `None` when they couldn't be listed
+    writable: Option<HashSet<String>>,
+}
+
+impl Capabilities {
+    /// Run ExifTool to find its version and writable tags
+    pub fn probe() -> Result<Self, Box<dyn std::error::Error>> {
+        let output = command().arg("-ver").output()?;
+        if !output.status.success() {
+            return Err("ExifTool found but returned error".into());
+        }
+        let version = String::from_utf8_lossy(&output.stdout);
+        let version = ExifToolVersion::parse(&version)
+            .ok_or_else(|| format!("Unrecognized ExifTool version '{}'", version.trim()))?;
+
+        let writable = command().arg("-listw").output().ok()
+            .filter(|output| output.status.success())
+            .map(|output| parse_tag_list(&String::from_utf8_lossy(&output.stdout)))
+            .filter(|tags| !tags.is_empty());
+
+        Ok(Capabilities { version, writable })
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.version >= feature.minimum_version()
+    }
+
+    /// Whether ExifTool can write a tag name as the remover emits it, with or without a group prefix
+    pub fn can_write(&self, name: &str) -> bool {
+        let name = name.rsplit(':').next().unwrap_or(name).to_ascii_lowercase();
+        self.writable.as_ref().map_or(true, |writable| writable.contains(&name))
+    }
+}
+
+/// The tag names of `exiftool -listw` output: a heading line, then names separated by whitespace
+fn parse_tag_list(listing: &str) -> HashSet<String> {
+    listing.lines()
+        .skip(1)
+        .flat_map(str::split_whitespace)
+        .map(str::to_ascii_lowercase)
+        .collect()
+}
+
+/// The installed ExifTool's capabilities, probed on first use; `None` when it can't be run
+///
+/// The first call logs the version and any feature this platform needs that it lacks.
+pub fn installed() -> Option<&'static Capabilities> {
+    static INSTALLED: OnceLock<Option<Capabilities>> = OnceLock::new();
+    INSTALLED.get_or_init(|| match Capabilities::probe() {
+        Ok(capabilities) => {
+            info!(version = %capabilities.version, "Using ExifTool");
+            for feature in Feature::ALL.iter().filter(|feature| feature.applies() && !capabilities.supports(**feature)) {
+                warn!(
+                    "ExifTool {} is older than {}, so {}; upgrade ExifTool",
+                    capabilities.version, feature.minimum_version(), feature.degradation()
+                );
+            }
+            Some(capabilities)
+        }
+        Err(_) => None,
+    }).as_ref()
+}
+
+/// Whether the installed ExifTool supports `feature`; assumed when it can't be probed
+pub fn supports(feature: Feature) -> bool {
+    installed().map_or(true, |capabilities| capabilities.supports(feature))
+}
+
+/// Whether the installed ExifTool can write the tag `name`, warning once per name when it can't
+pub fn can_write(name: &str) -> bool {
+    static WARNED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+    let capabilities = match installed() {
+        Some(capabilities) => capabilities,
+        None => return true,
+    };
+    if capabilities.can_write(name) {
+        return true;
+    }
+    if let Ok(mut warned) = WARNED.lock() {
+        if warned.get_or_insert_with(HashSet::new).insert(name.to_string()) {
+            warn!("ExifTool {} doesn't know the tag {}; it will not be removed. Upgrade ExifTool", capabilities.version, name);
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_parsing_and_features() {
+        assert_eq!(ExifToolVersion::parse("12.76\n"), Some(ExifToolVersion::new(12, 76)));
+        assert_eq!(ExifToolVersion::parse("12.77-dev"), Some(ExifToolVersion::new(12, 77)));
+        assert_eq!(ExifToolVersion::parse("garbage"), None);
+        assert_eq!(ExifToolVersion::new(9, 5).to_string(), "9.05");
+
+        let old = Capabilities { version: ExifToolVersion::new(11, 88), writable: None };
+        assert!(old.supports(Feature::FileNameCharset));
+        assert!(!old.supports(Feature::WindowsLongPath));
+    }
+
+    #[test]
+    fn test_bundled_executable_is_tried_first() {
+        let bundled = [PathBuf::from("app").join("exiftool")];
+        let found = candidates(&bundled, &[PathBuf::from("bin")], &[]);
+
+        let first = found[0].to_string_lossy().into_owned();
+        assert!(first.starts_with(&*PathBuf::from("app").join("exiftool").to_string_lossy()));
+        assert!(found[1].starts_with("bin"));
+        assert!(!is_pausing_build(&found[1]));
+        if cfg!(windows) {
+            assert!(is_pausing_build(found.last().unwrap()));
+        }
+    }
+
+    #[test]
+    fn test_writable_tags() {
+        let listing = "Writable tags:\n  Artist GPSLatitude\n  SerialNumber\n";
+        let capabilities = Capabilities { version: ExifToolVersion::new(12, 0), writable: Some(parse_tag_list(listing)) };
+
+        assert!(capabilities.can_write("GPSLatitude"));
+        assert!(capabilities.can_write("IFD0:Artist"));
+        assert!(!capabilities.can_write("OffsetTimeOriginal"));
+        assert!(!capabilities.can_write("Writable"));
+
+        let unlisted = Capabilities { writable: None, ..capabilities };
+        assert!(unlisted.can_write("OffsetTimeOriginal"));
+    }
+}
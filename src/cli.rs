@@ -1,106 +1,1249 @@
-use clap::{Arg, Command, ValueEnum};
-use crate::privacy::PrivacyLevel;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use clap::{Arg, ArgGroup, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use fluent_bundle::FluentValue;
+use crate::analyzer::Severity;
+use crate::cloud;
+use crate::i18n::{self, tr};
+use crate::privacy::{self, GpsGroup, Ifd, PolicyOverride, PrivacyLevel, PrivacyPolicy, UnknownTags};
+use crate::utils;
 
 #[derive(Debug, Clone)]
 pub struct Config {
-    pub input_dir: String,
-    pub output_dir: Option<String>,
+    /// Directory (or ZIP archive, PDF, or `s3://` URI) to clean
+    pub input: PathBuf,
+    /// Where cleaned files go; `None` cleans in place
+    pub output: Option<PathBuf>,
     pub recursive: bool,
     pub create_backup: bool,
+    /// Numbered backups (`.bak.1`, `.bak.2`, ...) kept beside the original `.bak`
+    pub backup_keep: usize,
     pub privacy_level: PrivacyLevel,
     pub verbose: bool,
     pub dry_run: bool,
+    /// Read a single image from stdin and write the cleaned image to stdout
+    pub use_stdin: bool,
+    /// Append a hash-chained record of every removal to this file
+    pub audit_log: Option<PathBuf>,
+    /// ed25519 key file used to sign audit log entries
+    pub audit_key: Option<PathBuf>,
+    /// What to do when a cleaned copy of an image already exists in the output directory
+    pub duplicates: DuplicatePolicy,
+    /// Kill ExifTool and fail the file if it runs longer than this many seconds
+    pub timeout_secs: u64,
+    /// Copy files that fail analysis or removal here, with a note explaining the error
+    pub quarantine: Option<PathBuf>,
+    /// Refuse to output any file whose metadata can't be parsed or isn't fully removed
+    pub require_clean: bool,
+    /// What to do with files that aren't supported images
+    pub unsupported: UnsupportedPolicy,
+    /// Follow symbolic links while walking the input directory
+    pub follow_symlinks: bool,
+    /// Hard-link the cleaned copies of hard-linked inputs to each other in the output directory
+    pub preserve_hardlinks: bool,
+    /// Deepest directory level to descend into when recursing
+    pub max_depth: Option<usize>,
+    /// Stop the run after this many files
+    pub max_files: Option<u64>,
+    /// Stop the run once this many bytes of input have been seen
+    pub max_total_size: Option<u64>,
+    /// Pace the run to at most this many bytes of input per second
+    pub throttle: Option<u64>,
+    /// Clean this many files at once
+    pub jobs: usize,
+    /// Process each directory's entries in this order rather than the filesystem's
+    pub sort: Option<SortOrder>,
+    /// Run at lower CPU and disk priority
+    pub nice: bool,
+    /// Confine ExifTool to the files it works on
+    pub sandbox: bool,
+    /// Only process files modified after this time
+    pub newer_than: Option<SystemTime>,
+    /// Only process files modified before this time
+    pub older_than: Option<SystemTime>,
+    /// Skip files smaller than this many bytes
+    pub min_size: Option<u64>,
+    /// Skip files larger than this many bytes
+    pub max_size: Option<u64>,
+    /// Copy files without privacy data to the output directory byte-for-byte instead of leaving them out
+    pub only_if_dirty: bool,
+    /// Pad cleaned files with filler back to their original size (cosmetic)
+    pub keep_size: bool,
+    /// Rewrite cleaned TIFFs with only the data their IFDs reference
+    pub compact_tiff: bool,
+    /// Also search bytes outside any metadata structure for remnants of removed metadata
+    pub deep_scan: bool,
+    /// Write output metadata from scratch with only the tags the level keeps
+    pub rebuild_metadata: bool,
+    /// Write a JSON manifest with the SHA-256 of every output file here
+    pub manifest: Option<PathBuf>,
+    /// Save each file's full metadata to a sidecar in this directory before cleaning it
+    pub export_metadata: Option<PathBuf>,
+    pub export_format: ExportFormat,
+    /// Temporarily make read-only files writable for in-place cleaning, then restore their permissions
+    pub unlock_readonly: bool,
+    /// Remove all metadata from images whose metadata can't be parsed instead of failing them
+    pub force_strip: bool,
+    /// Re-encode an image from its pixels when its metadata can't be cleaned
+    pub reencode_fallback: bool,
+    /// Fail rather than output a JPEG whose compressed image data changed
+    pub lossless_only: bool,
+    /// Ask before changing each file with findings
+    pub interactive: bool,
+    /// Show a live terminal dashboard instead of log lines
+    pub tui: bool,
+    /// Show a desktop notification when the run completes
+    pub notify: bool,
+    /// POST the JSON run report to this URL when the run completes
+    pub webhook: Option<String>,
+    /// Only log errors, nothing per file
+    pub quiet: bool,
+    /// How the end-of-run summary is printed
+    pub summary_format: SummaryFormat,
+    /// Fail a dry run that finds privacy data at or above this severity
+    pub fail_threshold: Option<Severity>,
+    /// Tags kept or removed regardless of the privacy level
+    pub policy_override: PolicyOverride,
+    /// Don't read `.privacy-clean.toml` files in the input tree
+    pub ignore_policy_files: bool,
+    /// Remove pre-edit originals embedded in edited images
+    pub strip_originals: bool,
+    /// Remove the video of motion photos, leaving a still image
+    pub drop_motion: bool,
+    /// What happens to tags no EXIF specification defines
+    pub unknown_tags: UnknownTags,
+    /// Plugin libraries to load before processing
+    pub plugins: Vec<PathBuf>,
+    /// A policy registered by a plugin, used instead of the privacy level
+    pub plugin_policy: Option<String>,
+    /// Benchmark this many synthetic files instead of processing input
+    pub bench: Option<usize>,
+    /// Run the install self-checks and print a JSON health report instead of cleaning
+    pub health_check: bool,
+}
+
+/// Handling of images whose cleaned copy already exists in the output directory
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum DuplicatePolicy {
+    /// Clean every image regardless of existing output
+    Process,
+    /// Leave existing cleaned copies alone and skip the image
+    Skip,
+    /// Hard-link the existing cleaned copy under the new name
+    Link,
+}
+
+/// Handling of files the cleaner can't process (HEIC, videos, ...)
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum UnsupportedPolicy {
+    /// Leave them out of the output and count them in the summary
+    Skip,
+    /// Copy them to the output directory unchanged, with a warning
+    Copy,
+    /// Stop the run at the first unsupported file
+    Fail,
+}
+
+/// Order in which a directory's entries are processed (`--sort`)
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum SortOrder {
+    /// By file name
+    Name,
+    /// Oldest modification time first
+    Mtime,
+    /// Smallest first
+    Size,
+}
+
+/// Options for the `serve` subcommand
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    pub bind: String,
+    pub workers: usize,
+    pub default_privacy_level: PrivacyLevel,
+    pub max_upload_bytes: u64,
+}
+
+/// Which library-wide report to produce
+#[derive(Clone, Debug, PartialEq)]
+pub enum ReportKind {
+    /// Findings mapped to personal-data categories
+    Compliance,
+    /// Recurring GPS locations across the library (likely home/work)
+    GpsClusters,
+    /// Files linkable to the same device through serial numbers
+    Devices,
+}
+
+/// Output format for reports
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum ReportFormat {
+    Json,
+    Html,
+}
+
+/// Options for the `report` subcommand
+#[derive(Debug, Clone)]
+pub struct ReportOptions {
+    pub input_dir: PathBuf,
+    pub recursive: bool,
+    pub privacy_level: PrivacyLevel,
+    pub kind: ReportKind,
+    pub format: ReportFormat,
+    /// Maximum distance from a cluster centre, in metres
+    pub cluster_radius_m: f64,
+    /// Write the report here instead of stdout
+    pub output_file: Option<PathBuf>,
+}
+
+/// Options for the `dump` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct DumpOptions {
+    /// Images to dump
+    #[arg(value_name = "FILE", required = true)]
+    pub files: Vec<PathBuf>,
+    /// Print a JSON array instead of text
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Options for the `apply-metadata` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct ApplyMetadataOptions {
+    /// Sidecar written by --export-metadata (.json or .xmp)
+    #[arg(long, value_name = "FILE")]
+    pub sidecar: PathBuf,
+    /// Image to restore the metadata into
+    #[arg(value_name = "IMAGE")]
+    pub image: PathBuf,
+    /// Write the result to FILE instead of modifying IMAGE in place
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+/// Options for the `audit` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct AuditOptions {
+    /// Images or directories to audit
+    #[arg(value_name = "FILE", required = true)]
+    pub files: Vec<PathBuf>,
+    /// Print a JSON array instead of text
+    #[arg(long)]
+    pub json: bool,
+    /// Audit subdirectories recursively
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Number of riskiest files to list when auditing directories
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub top: usize,
+    /// Also note unknown or near-random segments and appended files that could hide data (informational)
+    #[arg(long)]
+    pub anomalies: bool,
+}
+
+/// Options for the `restore` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct RestoreOptions {
+    /// Directory of images cleaned in place with --backup
+    #[arg(short, long, value_name = "DIR")]
+    pub input: PathBuf,
+    /// Restore images in subdirectories too
+    #[arg(short, long)]
+    pub recursive: bool,
+    /// Show what would be restored without changing any file
+    #[arg(short = 'n', long)]
+    pub dry_run: bool,
+}
+
+/// Options for the `self-test` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct SelfTestOptions {
+    /// Print JSON instead of text
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Options for the `watch` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct WatchOptions {
+    /// TOML file listing the directories to watch (re-read on SIGHUP)
+    #[arg(short, long, value_name = "FILE")]
+    pub config: PathBuf,
+}
+
+/// Options for the `compare` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct CompareOptions {
+    /// Image before cleaning
+    #[arg(value_name = "ORIGINAL")]
+    pub original: PathBuf,
+    /// Cleaned copy of the image
+    #[arg(value_name = "CLEANED")]
+    pub cleaned: PathBuf,
+    /// Print JSON instead of text
+    #[arg(long)]
+    pub json: bool,
+}
+
+/// Options for the `verify-manifest` subcommand
+#[derive(Debug, Clone, Args)]
+pub struct VerifyManifestOptions {
+    /// Manifest written by --manifest
+    #[arg(value_name = "MANIFEST")]
+    pub manifest: PathBuf,
+}
+
+/// What the `policy` subcommand should show
+#[derive(Debug, Clone)]
+pub enum PolicyCommand {
+    /// Every tag a level removes and preserves
+    Explain(PrivacyLevel),
+    /// Tags whose handling differs between two levels
+    Diff(PrivacyLevel, PrivacyLevel),
+}
+
+/// Format of the end-of-run summary on stdout
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum SummaryFormat {
+    /// Human-readable lines
+    Text,
+    /// A single JSON object
+    Json,
+    /// One `key<TAB>value` line per statistic
+    Tsv,
+}
+
+/// Format of `--export-metadata` sidecars
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    /// ExifTool JSON with binary values base64-encoded, restorable tag for tag
+    Json,
+    /// XMP, readable by photo managers; tags without an XMP equivalent are lost
+    Xmp,
+}
+
+impl ExportFormat {
+    /// Extension of sidecar files, without the dot
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Xmp => "xmp",
+        }
+    }
+}
+
+/// Format of diagnostic log output on stderr
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines
+    Text,
+    /// One JSON object per event, for log aggregators
+    Json,
+}
+
+/// When to colour terminal output (`--color`)
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum ColorChoice {
+    /// When writing to a terminal and `NO_COLOR` isn't set
+    Auto,
+    /// Even when piped, unless `NO_COLOR` is set
+    Always,
+    Never,
+}
+
+/// Parsed command line: the action plus global options
+#[derive(Debug, Clone)]
+pub struct CliArgs {
+    pub command: CliCommand,
+    pub log_format: LogFormat,
+    pub color: ColorChoice,
+    /// The ExifTool executable to run instead of searching for one
+    pub exiftool: Option<PathBuf>,
+}
+
+/// What the user asked the binary to do
+#[derive(Debug, Clone)]
+pub enum CliCommand {
+    /// Clean images in a directory or archive (the default; `analyze` is a verbose dry run of it)
+    Clean(Box<Config>),
+    /// Run the HTTP sanitization service
+    Serve(ServeOptions),
+    /// Analyze a directory and produce a report without modifying anything
+    Report(ReportOptions),
+    /// Print every metadata field of individual images
+    Dump(DumpOptions),
+    /// Show which tags privacy levels remove
+    Policy(PolicyCommand),
+    /// Write an exported metadata sidecar back into an image
+    ApplyMetadata(ApplyMetadataOptions),
+    /// Diff the metadata of an image and its cleaned copy
+    Compare(CompareOptions),
+    /// Check the files listed in an output manifest against their hashes
+    VerifyManifest(VerifyManifestOptions),
+    /// Report what images received from others disclose, without cleaning them
+    Audit(AuditOptions),
+    /// Copy originals back from the backups of an in-place clean
+    Restore(RestoreOptions),
+    /// Watch directories and clean new images as a long-running service
+    Watch(WatchOptions),
+    /// Clean an embedded sample at every privacy level and verify the result
+    SelfTest(SelfTestOptions),
+}
+
+/// Parse the command line into a `CliCommand`
+///
+/// Running without a subcommand behaves exactly like `clean`, so existing
+/// invocations such as `privacy-exif-cleaner -i photos/` keep working.
+pub fn parse_args() -> Result<CliArgs, Box<dyn std::error::Error>> {
+    // The help text is translated, so the language is needed before clap sees the arguments
+    i18n::init(lang_argument().as_deref())?;
+    try_parse_from(std::env::args_os()).map_err(|e| match e.downcast::<clap::Error>() {
+        // Usage errors, --help, and --version print themselves and exit as usual
+        Ok(clap_error) => clap_error.exit(),
+        Err(e) => e,
+    })
+}
+
+/// Parse `args`, program name first, returning usage errors instead of exiting
+pub fn try_parse_from<I, T>(args: I) -> Result<CliArgs, Box<dyn std::error::Error>>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone,
+{
+    let cli = Cli::from_arg_matches(&command().try_get_matches_from(args)?)?;
+
+    let command = match cli.command {
+        None => CliCommand::Clean(Box::new(cli.clean.into_config()?)),
+        Some(Commands::Clean(args)) => CliCommand::Clean(Box::new(args.into_config()?)),
+        Some(Commands::Analyze(args)) => CliCommand::Clean(Box::new(args.into_config()?)),
+        Some(Commands::Audit(options)) => CliCommand::Audit(options),
+        Some(Commands::Restore(options)) => CliCommand::Restore(options),
+        Some(Commands::Dump(options)) => CliCommand::Dump(options),
+        Some(Commands::Serve(args)) => CliCommand::Serve(args.into()),
+        Some(Commands::Watch(options)) => CliCommand::Watch(options),
+        Some(Commands::Report(args)) => CliCommand::Report(args.into()),
+        Some(Commands::Policy(command)) => CliCommand::Policy(command.into()),
+        Some(Commands::ApplyMetadata(options)) => CliCommand::ApplyMetadata(options),
+        Some(Commands::Compare(options)) => CliCommand::Compare(options),
+        Some(Commands::VerifyManifest(options)) => CliCommand::VerifyManifest(options),
+        Some(Commands::SelfTest(options)) => CliCommand::SelfTest(options),
+    };
+    let GlobalArgs { log_format, color, exiftool } = cli.global;
+
+    Ok(CliArgs { command, log_format, color, exiftool })
+}
+
+/// The full command line definition, with help text in the chosen language
+pub fn command() -> clap::Command {
+    translate_help(Cli::command(), "")
+        .about(tr("app-about", &[]))
+        .mut_subcommand("clean", |clean| clean.about(tr("clean-about", &[])))
+        // Read ahead of clap by `lang_argument`; declared so help lists it and clap accepts it
+        .arg(
+            Arg::new("lang")
+                .long("lang")
+                .value_name("LANG")
+                .global(true)
+                .help(tr("lang-help", &[])),
+        )
+}
+
+/// Replace the help of each argument, in `command` and its subcommands, with its message
+///
+/// Arguments without a message keep the help from their doc comment.
+fn translate_help(mut command: clap::Command, path: &str) -> clap::Command {
+    let args: Vec<String> = command.get_arguments().map(|arg| arg.get_id().to_string()).collect();
+    for arg in args {
+        if let Some(help) = help_ids(path, &arg).iter().find_map(|id| i18n::try_tr(id, &[])) {
+            command = command.mut_arg(&arg, |arg| arg.help(help));
+        }
+    }
+    let subcommands: Vec<String> = command.get_subcommands().map(|sub| sub.get_name().to_string()).collect();
+    for name in subcommands {
+        let sub_path = if path.is_empty() { name.clone() } else { format!("{}-{}", path, name) };
+        command = command.mut_subcommand(&name, |sub| translate_help(sub, &sub_path));
+    }
+    command
+}
+
+/// Message ids for the help of argument `arg` of the subcommand at `path`, in lookup order
+///
+/// `report --input` is `report-input-help`. Options of `clean` are
+/// `<option>-help`, which subcommands sharing them (`analyze --privacy`) fall back to.
+fn help_ids(path: &str, arg: &str) -> Vec<String> {
+    let arg = arg.replace('_', "-");
+    let mut ids = vec![format!("{}-help", arg)];
+    if !path.is_empty() {
+        ids.insert(0, format!("{}-{}-help", path, arg));
+    }
+    ids
+}
+
+/// The `--lang` argument, read ahead of clap
+fn lang_argument() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        }
+        if arg == "--lang" {
+            return args.next();
+        }
+        if let Some(lang) = arg.strip_prefix("--lang=") {
+            return Some(lang.to_string());
+        }
+    }
+    None
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "privacy-exif-cleaner", version = "1.0")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+    // Without a subcommand the arguments are those of `clean`
+    #[command(flatten)]
+    clean: CleanArgs,
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+/// Options that apply whatever the subcommand
+#[derive(Args, Debug)]
+struct GlobalArgs {
+    /// Diagnostic log format on stderr: text or json (filter with RUST_LOG)
+    #[arg(long, value_enum, default_value = "text", global = true)]
+    log_format: LogFormat,
+    /// Colour findings by severity: auto, always, or never (NO_COLOR turns it off)
+    #[arg(long, value_name = "WHEN", value_enum, default_value = "auto", global = true)]
+    color: ColorChoice,
+    /// ExifTool executable to run, e.g. a bundled copy (default: $EXIFTOOL_PATH, then a search)
+    #[arg(long, value_name = "PATH", global = true)]
+    exiftool: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    // The about text is translated; see `command`
+    Clean(Box<CleanArgs>),
+    /// List the privacy data in images without changing any file (a verbose dry run of clean)
+    Analyze(AnalyzeArgs),
+    /// Report what received images disclose (EXIF, XMP, IPTC, thumbnails, trailers, file names) without cleaning
+    Audit(AuditOptions),
+    /// Copy originals back over images cleaned in place with --backup
+    Restore(RestoreOptions),
+    /// Print every metadata field of images with raw values and IFD location
+    Dump(DumpOptions),
+    /// Start an HTTP service that sanitizes uploaded images
+    Serve(ServeArgs),
+    /// Watch the directories in a config file and clean new images as they arrive (reloads on SIGHUP)
+    #[command(alias = "daemon")]
+    Watch(WatchOptions),
+    /// Analyze a directory and write a report without modifying any files
+    Report(ReportArgs),
+    /// Show exactly which tags each privacy level removes and preserves
+    #[command(subcommand)]
+    Policy(PolicyCommands),
+    /// Write a sidecar saved by --export-metadata back into a (cleaned) image
+    ApplyMetadata(ApplyMetadataOptions),
+    /// List which metadata fields a cleaned copy removed, changed, or preserved
+    Compare(CompareOptions),
+    /// Check that every file listed in a --manifest file still has its recorded SHA-256
+    VerifyManifest(VerifyManifestOptions),
+    /// Clean an embedded sample image at every privacy level and verify that the data is gone
+    SelfTest(SelfTestOptions),
+}
+
+/// Options of `clean`, which are also those of running without a subcommand
+#[derive(Args, Debug)]
+struct CleanArgs {
+    /// Input directory, ZIP archive, or s3://bucket/prefix containing images
+    #[arg(short, long, value_name = "DIR", required_unless_present_any = ["stdin", "bench", "health_check"])]
+    input: Option<PathBuf>,
+    /// Output directory or s3://bucket/prefix (optional - will modify in-place if not specified)
+    #[arg(short, long, value_name = "DIR")]
+    output: Option<PathBuf>,
+    /// Create backup files with .bak extension
+    #[arg(short, long)]
+    backup: bool,
+    /// Numbered backups kept from later runs; the first .bak is never overwritten
+    #[arg(long, value_name = "N", default_value_t = 3, requires = "backup")]
+    backup_keep: usize,
+    /// Show detailed information about data being removed
+    #[arg(short, long)]
+    verbose: bool,
+    /// Show what would be removed without making changes
+    #[arg(short = 'n', long)]
+    dry_run: bool,
+    /// Read one image from stdin and write the cleaned image to stdout
+    #[arg(long, conflicts_with_all = ["input", "output", "recursive", "backup", "quarantine"])]
+    stdin: bool,
+    /// Measure files/sec and MB/sec of analysis and ExifTool removal on a synthetic corpus [default: 200 files]
+    #[arg(long, value_name = "FILES", num_args = 0..=1, default_missing_value = "200")]
+    #[arg(conflicts_with_all = ["input", "output", "stdin", "dry_run"])]
+    bench: Option<usize>,
+    /// Check ExifTool, the temp directory, and cleaning of an embedded sample; print JSON and exit non-zero on failure
+    #[arg(long, conflicts_with_all = ["input", "output", "stdin", "bench"])]
+    health_check: bool,
+    /// Append a tamper-evident record of every removal to FILE (JSON lines)
+    #[arg(long, value_name = "FILE")]
+    audit_log: Option<PathBuf>,
+    /// Sign audit log entries with this ed25519 key (32-byte seed, raw or hex)
+    #[arg(long, value_name = "FILE", requires = "audit_log")]
+    audit_key: Option<PathBuf>,
+    /// Images whose cleaned copy already exists in the output dir: process, skip, or link
+    #[arg(long, value_enum, default_value = "process", requires = "output")]
+    duplicates: DuplicatePolicy,
+    /// Give up on a file if ExifTool takes longer than this
+    #[arg(long, value_name = "SECONDS", default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+    /// Copy files that fail cleaning to DIR with an error note
+    #[arg(long, value_name = "DIR")]
+    quarantine: Option<PathBuf>,
+    /// Never output a file unless its metadata was parsed and verified clean
+    #[arg(long)]
+    require_clean: bool,
+    /// Files that aren't supported images: skip, copy (verbatim, uncleaned), or fail
+    #[arg(long, value_enum, default_value = "skip")]
+    unsupported: UnsupportedPolicy,
+    /// Keep hard-linked inputs hard-linked in the output directory instead of cleaning each copy
+    #[arg(long, requires = "output")]
+    preserve_hardlinks: bool,
+    /// Process at most SIZE bytes of input per second, e.g. 20M, to spare shared disks
+    #[arg(long, value_name = "SIZE", value_parser = utils::parse_size)]
+    throttle: Option<u64>,
+    /// Clean N files at once, each worker with its own ExifTool process (0: one per CPU)
+    #[arg(short, long, value_name = "N", conflicts_with_all = ["throttle", "interactive", "stdin"])]
+    jobs: Option<usize>,
+    /// Run at low CPU and disk priority, like nice and ionice
+    #[arg(long)]
+    nice: bool,
+    /// Confine ExifTool: writes only beside the file it cleans, no network (requires the sandbox feature)
+    #[arg(long)]
+    sandbox: bool,
+    /// Only rewrite files with privacy data; copy the rest to the output unchanged
+    #[arg(long, requires = "output")]
+    only_if_dirty: bool,
+    /// Write the SHA-256, source, and policy of every output file to FILE (JSON)
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+    /// Before cleaning, save each file's full metadata to a sidecar in DIR
+    #[arg(long, value_name = "DIR", conflicts_with = "stdin")]
+    export_metadata: Option<PathBuf>,
+    /// Sidecar format for --export-metadata: json (restorable) or xmp
+    #[arg(long, value_name = "FMT", value_enum, default_value = "json", requires = "export_metadata")]
+    export_format: ExportFormat,
+    /// Clean read-only files in place by clearing the read-only bit and restoring it afterwards
+    #[arg(long, conflicts_with = "output")]
+    unlock_readonly: bool,
+    /// Remove all metadata from images whose metadata is corrupt or truncated instead of failing them
+    #[arg(long)]
+    force_strip: bool,
+    /// When an image can't be cleaned, decode its pixels and encode a new file without metadata (lossy for JPEG)
+    #[arg(long)]
+    reencode_fallback: bool,
+    /// Fail a file rather than ever recompress it; JPEG output is checked to keep the compressed data byte for byte
+    #[arg(long, conflicts_with = "reencode_fallback")]
+    lossless_only: bool,
+    /// Show each file's findings and ask whether to remove, keep, or skip
+    #[arg(long, conflicts_with = "stdin")]
+    interactive: bool,
+    /// Show a live dashboard of files, findings, throughput, and errors (p pauses, q cancels)
+    #[arg(long, conflicts_with_all = ["stdin", "interactive"])]
+    tui: bool,
+    /// Show a desktop notification summarizing the run when it completes
+    #[arg(long)]
+    notify: bool,
+    /// POST the JSON run report to URL when the run completes
+    #[arg(long, value_name = "URL")]
+    webhook: Option<String>,
+    /// Suppress per-file output; only errors and the summary are printed
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+    /// End-of-run summary format: text, json, or tsv
+    #[arg(long, value_enum, default_value = "text")]
+    summary_format: SummaryFormat,
+    /// With --dry-run, exit with an error if privacy data at or above this severity is found: low, medium, or high
+    #[arg(long, value_name = "SEVERITY", value_enum, requires = "dry_run")]
+    fail_threshold: Option<Severity>,
+    /// Build output metadata from scratch with only the tags the privacy level keeps, dropping unknown tags
+    #[arg(long, requires = "output")]
+    rebuild_metadata: bool,
+    /// Pad cleaned JPEG, PNG, and TIFF files with filler to their original size (cosmetic; hashes still change)
+    #[arg(long)]
+    keep_size: bool,
+    /// Rewrite cleaned TIFFs with only referenced data, so no unreferenced remnants of removed values survive
+    #[arg(long)]
+    compact_tiff: bool,
+    #[command(flatten)]
+    policy: PolicyArgs,
+    #[command(flatten)]
+    walk: WalkArgs,
+}
+
+/// Options of `analyze`: the input, what counts as a finding, and how to summarize
+#[derive(Args, Debug)]
+struct AnalyzeArgs {
+    /// Directory, ZIP archive, or s3://bucket/prefix containing images
+    #[arg(short, long, value_name = "DIR")]
+    input: PathBuf,
+    /// Analyze N files at once (0: one per CPU)
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+    /// End-of-run summary format: text, json, or tsv
+    #[arg(long, value_enum, default_value = "text")]
+    summary_format: SummaryFormat,
+    /// Exit with an error if privacy data at or above this severity is found: low, medium, or high
+    #[arg(long, value_name = "SEVERITY", value_enum)]
+    fail_threshold: Option<Severity>,
+    #[command(flatten)]
+    policy: PolicyArgs,
+    #[command(flatten)]
+    walk: WalkArgs,
+}
+
+/// What counts as privacy data, shared by `clean` and `analyze`
+#[derive(Args, Debug)]
+struct PolicyArgs {
+    /// Privacy level: minimal, standard, strict, web-share, or paranoid
+    #[arg(short = 'p', long = "privacy", value_enum, default_value = "standard")]
+    privacy_level: PrivacyLevel,
+    /// Keep this EXIF tag even if the privacy level removes it, e.g. DateTimeOriginal, or IFD0:Software for one IFD (repeatable)
+    #[arg(long, value_name = "TAG", value_parser = privacy::parse_scoped_tag)]
+    keep_tag: Vec<(Option<Ifd>, exif::Tag)>,
+    /// Remove this EXIF tag even if the privacy level keeps it, e.g. LensModel, or IFD1:Make for one IFD (repeatable)
+    #[arg(long, value_name = "TAG", value_parser = privacy::parse_scoped_tag)]
+    remove_tag: Vec<(Option<Ifd>, exif::Tag)>,
+    /// Keep these parts of the GPS data: position, altitude, direction, speed, time, technical (comma-separated)
+    #[arg(long, value_name = "GROUP", value_enum, value_delimiter = ',')]
+    keep_gps: Vec<GpsGroup>,
+    /// Also remove camera and lens make and model (Make, Model, LensMake, LensModel, LensInfo); exposure settings are kept
+    #[arg(long)]
+    anonymize_equipment: bool,
+    /// Remove pre-edit originals embedded in edited images: stale thumbnails, Samsung re-edit data, Google Camera originals
+    #[arg(long)]
+    strip_originals: bool,
+    /// Remove the video of motion photos instead of cleaning its GPS position, leaving a still image
+    #[arg(long)]
+    drop_motion: bool,
+    /// What to do with vendor tags no EXIF specification defines: preserve, remove, or remove-above-strict
+    #[arg(long, value_name = "POLICY", value_enum, default_value = "preserve")]
+    unknown_tags: UnknownTags,
+    /// Also search unreferenced bytes and trailing data for GPS coordinates, serial numbers, and XMP left by editors
+    #[arg(long)]
+    deep_scan: bool,
+    /// Ignore .privacy-clean.toml files in the input tree and use --privacy everywhere
+    #[arg(long)]
+    ignore_policy_files: bool,
+    /// Load a plugin library adding format handlers or policies (repeatable; needs the `plugins` feature)
+    #[arg(long = "plugin", value_name = "PATH")]
+    plugins: Vec<PathBuf>,
+    /// Use a policy registered by a plugin instead of --privacy
+    #[arg(long, value_name = "NAME", requires = "plugins")]
+    plugin_policy: Option<String>,
+}
+
+/// Which files a run visits, shared by `clean` and `analyze`
+#[derive(Args, Debug)]
+struct WalkArgs {
+    /// Process subdirectories recursively
+    #[arg(short, long)]
+    recursive: bool,
+    /// Follow symbolic links while walking directories (cycles are detected and skipped)
+    #[arg(long)]
+    follow_symlinks: bool,
+    /// Descend at most N directory levels below the input directory
+    #[arg(long, value_name = "N", requires = "recursive")]
+    max_depth: Option<usize>,
+    /// Stop the run after N files
+    #[arg(long, value_name = "N")]
+    max_files: Option<u64>,
+    /// Stop the run after SIZE bytes of input (suffixes K, M, G, T)
+    #[arg(long, value_name = "SIZE", value_parser = utils::parse_size)]
+    max_total_size: Option<u64>,
+    /// Process files in a reproducible order: name, mtime, or size
+    #[arg(long, value_name = "ORDER", value_enum)]
+    sort: Option<SortOrder>,
+    /// Only files modified after WHEN: a date (2024-05-01) or an age (7d, 12h)
+    #[arg(long, value_name = "WHEN", value_parser = utils::parse_time_bound)]
+    newer_than: Option<SystemTime>,
+    /// Only files modified before WHEN: a date (2024-05-01) or an age (7d, 12h)
+    #[arg(long, value_name = "WHEN", value_parser = utils::parse_time_bound)]
+    older_than: Option<SystemTime>,
+    /// Skip files smaller than SIZE, e.g. 100K to skip thumbnails
+    #[arg(long, value_name = "SIZE", value_parser = utils::parse_size)]
+    min_size: Option<u64>,
+    /// Skip files larger than SIZE
+    #[arg(long, value_name = "SIZE", value_parser = utils::parse_size)]
+    max_size: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+struct ServeArgs {
+    /// Address to listen on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    bind: String,
+    /// Number of requests handled concurrently
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    workers: usize,
+    /// Default privacy level when a request doesn't specify one
+    #[arg(short = 'p', long = "privacy", value_enum, default_value = "standard")]
+    privacy_level: PrivacyLevel,
+    /// Maximum accepted upload size in megabytes
+    #[arg(long, value_name = "MB", default_value_t = 50)]
+    max_upload_mb: u64,
+}
+
+#[derive(Args, Debug)]
+#[command(group(ArgGroup::new("kind").args(["compliance", "gps_clusters", "devices"]).required(true)))]
+struct ReportArgs {
+    /// Directory of images to analyze
+    #[arg(short, long, value_name = "DIR")]
+    input: PathBuf,
+    /// Analyze subdirectories recursively
+    #[arg(short, long)]
+    recursive: bool,
+    /// Privacy level whose removals count as findings
+    #[arg(short = 'p', long = "privacy", value_enum, default_value = "standard")]
+    privacy_level: PrivacyLevel,
+    /// Map findings to personal-data categories with counts and worst offenders
+    #[arg(long)]
+    compliance: bool,
+    /// Cluster GPS positions across all photos to reveal likely home/work locations
+    #[arg(long)]
+    gps_clusters: bool,
+    /// Group files by camera serial numbers to show which images are linkable to the same device
+    #[arg(long)]
+    devices: bool,
+    /// Photos within this distance of a cluster centre belong to it
+    #[arg(long, value_name = "METERS", default_value_t = 250.0)]
+    cluster_radius: f64,
+    /// Report format: json or html
+    #[arg(long, value_enum, default_value = "json")]
+    format: ReportFormat,
+    /// Write the report to FILE instead of stdout
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum PolicyCommands {
+    /// List every tag a privacy level removes and preserves
+    Explain {
+        /// Privacy level: minimal, standard, strict, web-share, or paranoid
+        #[arg(long, value_enum, default_value = "standard")]
+        level: PrivacyLevel,
+    },
+    /// List the tags two privacy levels handle differently
+    Diff {
+        #[arg(value_name = "FROM", value_enum)]
+        from: PrivacyLevel,
+        #[arg(value_name = "TO", value_enum)]
+        to: PrivacyLevel,
+    },
+}
+
+impl CleanArgs {
+    fn into_config(self) -> Result<Config, Box<dyn std::error::Error>> {
+        if self.require_clean && self.unsupported == UnsupportedPolicy::Copy {
+            return Err("--unsupported copy cannot be combined with --require-clean".into());
+        }
+        if let Some(ref url) = self.webhook {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(format!("--webhook must be an http:// or https:// URL, got '{}'", url).into());
+            }
+        }
+
+        let mut config = Config {
+            input: self.input.unwrap_or_else(|| PathBuf::from("-")),
+            output: self.output,
+            create_backup: self.backup,
+            backup_keep: self.backup_keep,
+            verbose: self.verbose,
+            dry_run: self.dry_run,
+            use_stdin: self.stdin,
+            audit_log: self.audit_log,
+            audit_key: self.audit_key,
+            duplicates: self.duplicates,
+            timeout_secs: self.timeout,
+            quarantine: self.quarantine,
+            require_clean: self.require_clean,
+            unsupported: self.unsupported,
+            preserve_hardlinks: self.preserve_hardlinks,
+            throttle: self.throttle,
+            jobs: jobs(self.jobs),
+            nice: self.nice,
+            sandbox: self.sandbox,
+            only_if_dirty: self.only_if_dirty,
+            keep_size: self.keep_size,
+            compact_tiff: self.compact_tiff,
+            rebuild_metadata: self.rebuild_metadata,
+            manifest: self.manifest,
+            export_metadata: self.export_metadata,
+            export_format: self.export_format,
+            unlock_readonly: self.unlock_readonly,
+            force_strip: self.force_strip,
+            reencode_fallback: self.reencode_fallback,
+            lossless_only: self.lossless_only,
+            interactive: self.interactive,
+            tui: self.tui,
+            notify: self.notify,
+            webhook: self.webhook,
+            quiet: self.quiet,
+            summary_format: self.summary_format,
+            fail_threshold: self.fail_threshold,
+            bench: self.bench,
+            health_check: self.health_check,
+            ..Config::default()
+        };
+        self.policy.apply(&mut config)?;
+        self.walk.apply(&mut config);
+        Ok(config)
+    }
+}
+
+impl AnalyzeArgs {
+    fn into_config(self) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config = Config {
+            input: self.input,
+            verbose: true,
+            dry_run: true,
+            jobs: jobs(self.jobs),
+            summary_format: self.summary_format,
+            fail_threshold: self.fail_threshold,
+            ..Config::default()
+        };
+        self.policy.apply(&mut config)?;
+        self.walk.apply(&mut config);
+        Ok(config)
+    }
+}
+
+impl PolicyArgs {
+    fn apply(self, config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+        let tags = |scoped: &[(Option<Ifd>, exif::Tag)]| -> Vec<exif::Tag> {
+            scoped.iter().filter(|(ifd, _)| ifd.is_none()).map(|(_, tag)| *tag).collect()
+        };
+        let tags_in = |scoped: &[(Option<Ifd>, exif::Tag)]| -> Vec<(Ifd, exif::Tag)> {
+            scoped.iter().filter_map(|(ifd, tag)| Some(((*ifd)?, *tag))).collect()
+        };
+        let mut keep = tags(&self.keep_tag);
+        keep.extend(self.keep_gps.iter().flat_map(|group| group.tags()));
+        let mut remove = tags(&self.remove_tag);
+        if self.anonymize_equipment {
+            // An explicit --keep-tag still wins for the individual tag
+            remove.extend(PrivacyPolicy::get_equipment_tags().into_iter().filter(|tag| !keep.contains(tag)));
+        }
+
+        config.policy_override = PolicyOverride::new(keep, remove)?
+            .scoped(tags_in(&self.keep_tag), tags_in(&self.remove_tag))?;
+        config.privacy_level = self.privacy_level;
+        config.strip_originals = self.strip_originals;
+        config.drop_motion = self.drop_motion;
+        config.unknown_tags = self.unknown_tags;
+        config.deep_scan = self.deep_scan;
+        config.ignore_policy_files = self.ignore_policy_files;
+        config.plugins = self.plugins;
+        config.plugin_policy = self.plugin_policy;
+        Ok(())
+    }
+}
+
+impl WalkArgs {
+    fn apply(self, config: &mut Config) {
+        config.recursive = self.recursive;
+        config.follow_symlinks = self.follow_symlinks;
+        config.max_depth = self.max_depth;
+        config.max_files = self.max_files;
+        config.max_total_size = self.max_total_size;
+        config.sort = self.sort;
+        config.newer_than = self.newer_than;
+        config.older_than = self.older_than;
+        config.min_size = self.min_size;
+        config.max_size = self.max_size;
+    }
+}
+
+/// `--jobs`, where 0 means one per CPU and leaving it out means one
+fn jobs(jobs: Option<usize>) -> usize {
+    match jobs {
+        Some(0) => std::thread::available_parallelism().map_or(1, |cpus| cpus.get()),
+        jobs => jobs.unwrap_or(1),
+    }
+}
+
+impl From<ServeArgs> for ServeOptions {
+    fn from(args: ServeArgs) -> Self {
+        ServeOptions {
+            bind: args.bind,
+            workers: args.workers,
+            default_privacy_level: args.privacy_level,
+            max_upload_bytes: args.max_upload_mb * 1024 * 1024,
+        }
+    }
+}
+
+impl From<ReportArgs> for ReportOptions {
+    fn from(args: ReportArgs) -> Self {
+        ReportOptions {
+            input_dir: args.input,
+            recursive: args.recursive,
+            privacy_level: args.privacy_level,
+            kind: if args.gps_clusters {
+                ReportKind::GpsClusters
+            } else if args.devices {
+                ReportKind::Devices
+            } else {
+                ReportKind::Compliance
+            },
+            format: args.format,
+            cluster_radius_m: args.cluster_radius,
+            output_file: args.output,
+        }
+    }
+}
+
+impl From<PolicyCommands> for PolicyCommand {
+    fn from(command: PolicyCommands) -> Self {
+        match command {
+            PolicyCommands::Explain { level } => PolicyCommand::Explain(level),
+            PolicyCommands::Diff { from, to } => PolicyCommand::Diff(from, to),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::with_privacy_level(PrivacyLevel::Standard)
+    }
+}
+
+/// Everything `Config::validate` found wrong with a configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigErrors(pub Vec<String>);
+
+impl std::fmt::Display for ConfigErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.0 {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigErrors {}
+
+/// Whether `path` is an `s3://` URI rather than a local path
+pub fn is_s3(path: &Path) -> bool {
+    path.to_str().map_or(false, cloud::is_s3_uri)
+}
+
+/// Why output can't be written to `dir`, which need not exist yet
+fn unwritable(dir: &Path) -> Option<String> {
+    let existing = dir.ancestors()
+        .map(|ancestor| if ancestor.as_os_str().is_empty() { Path::new(".") } else { ancestor })
+        .find(|ancestor| ancestor.exists())?;
+    if !existing.is_dir() {
+        return Some(format!("is inside '{}', which is not a directory", existing.display()));
+    }
+    // Permission bits don't tell the whole story (ACLs, read-only mounts), so try it
+    let probe = existing.join(".privacy-cleaner-probe");
+    match std::fs::OpenOptions::new().write(true).create_new(true).open(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            None
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => None,
+        Err(e) => Some(format!("is not writable ({})", e)),
+    }
 }
 
 impl Config {
-    pub fn from_args() -> Result<Self, Box<dyn std::error::Error>> {
-        let matches = Command::new("privacy-exif-cleaner")
-            .version("1.0")
-            .about("Removes privacy-sensitive information from EXIF data while preserving technical metadata")
-            .arg(
-                Arg::new("input")
-                    .short('i')
-                    .long("input")
-                    .value_name("DIR")
-                    .help("Input directory containing images")
-                    .required(true),
-            )
-            .arg(
-                Arg::new("output")
-                    .short('o')
-                    .long("output")
-                    .value_name("DIR")
-                    .help("Output directory (optional - will modify in-place if not specified)"),
-            )
-            .arg(
-                Arg::new("recursive")
-                    .short('r')
-                    .long("recursive")
-                    .help("Process subdirectories recursively")
-                    .action(clap::ArgAction::SetTrue),
-            )
-            .arg(
-                Arg::new("backup")
-                    .short('b')
-                    .long("backup")
-                    .help("Create backup files with .bak extension")
-                    .action(clap::ArgAction::SetTrue),
-            )
-            .arg(
-                Arg::new("privacy_level")
-                    .short('p')
-                    .long("privacy")
-                    .value_enum::<PrivacyLevel>()
-                    .default_value("standard")
-                    .help("Privacy level: minimal, standard, strict, or paranoid"),
-            )
-            .arg(
-                Arg::new("verbose")
-                    .short('v')
-                    .long("verbose")
-                    .help("Show detailed information about data being removed")
-                    .action(clap::ArgAction::SetTrue),
-            )
-            .arg(
-                Arg::new("dry_run")
-                    .short('n')
-                    .long("dry-run")
-                    .help("Show what would be removed without making changes")
-                    .action(clap::ArgAction::SetTrue),
-            )
-            .get_matches();
-
-        Ok(Config {
-            input_dir: matches.get_one::<String>("input").unwrap().clone(),
-            output_dir: matches.get_one::<String>("output").cloned(),
-            recursive: matches.get_flag("recursive"),
-            create_backup: matches.get_flag("backup"),
-            privacy_level: matches.get_one::<PrivacyLevel>("privacy_level").unwrap().clone(),
-            verbose: matches.get_flag("verbose"),
-            dry_run: matches.get_flag("dry_run"),
-        })
+    /// Create a configuration with default settings for a given privacy level
+    pub fn with_privacy_level(privacy_level: PrivacyLevel) -> Self {
+        Config {
+            input: PathBuf::from("."),
+            output: None,
+            recursive: false,
+            create_backup: false,
+            backup_keep: 3,
+            privacy_level,
+            verbose: false,
+            dry_run: false,
+            use_stdin: false,
+            audit_log: None,
+            audit_key: None,
+            duplicates: DuplicatePolicy::Process,
+            timeout_secs: 60,
+            quarantine: None,
+            require_clean: false,
+            unsupported: UnsupportedPolicy::Skip,
+            follow_symlinks: false,
+            preserve_hardlinks: false,
+            max_depth: None,
+            max_files: None,
+            max_total_size: None,
+            throttle: None,
+            jobs: 1,
+            sort: None,
+            nice: false,
+            sandbox: false,
+            newer_than: None,
+            older_than: None,
+            min_size: None,
+            max_size: None,
+            only_if_dirty: false,
+            keep_size: false,
+            compact_tiff: false,
+            deep_scan: false,
+            rebuild_metadata: false,
+            manifest: None,
+            export_metadata: None,
+            export_format: ExportFormat::Json,
+            unlock_readonly: false,
+            force_strip: false,
+            reencode_fallback: false,
+            lossless_only: false,
+            interactive: false,
+            tui: false,
+            notify: false,
+            webhook: None,
+            quiet: false,
+            summary_format: SummaryFormat::Text,
+            fail_threshold: None,
+            policy_override: PolicyOverride::default(),
+            ignore_policy_files: false,
+            strip_originals: false,
+            drop_motion: false,
+            unknown_tags: UnknownTags::Preserve,
+            plugins: Vec::new(),
+            plugin_policy: None,
+            bench: None,
+            health_check: false,
+        }
     }
 
-    pub fn print_privacy_explanation(&self) {
-        println!("\nPrivacy settings for {:?} level:", self.privacy_level);
-        match self.privacy_level {
-            PrivacyLevel::Minimal => {
-                println!("• Removes: GPS coordinates, location data");
-                println!("• Preserves: All camera settings, timestamps, device info");
+    /// Check the configuration before a run, reporting every problem at once
+    ///
+    /// Catches what would otherwise fail part way through a run: a missing
+    /// input, an output directory that can't be written, `--backup` with
+    /// `--output` (originals are never modified then), and zero workers.
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        let mut problems = Vec::new();
+
+        let needs_input = !(self.use_stdin || self.bench.is_some() || self.health_check);
+        if needs_input && !is_s3(&self.input) && !self.input.exists() {
+            problems.push(format!("Input path '{}' does not exist", self.input.display()));
+        }
+        if let Some(ref output) = self.output {
+            if !self.dry_run && !is_s3(output) {
+                if let Some(problem) = unwritable(output) {
+                    problems.push(format!("Output directory '{}' {}", output.display(), problem));
+                }
             }
-            PrivacyLevel::Standard => {
-                println!("• Removes: GPS data, camera serial numbers, unique device IDs");
-                println!("• Preserves: Camera model, settings, timestamps, non-identifying technical data");
+            if self.create_backup {
+                problems.push("--backup has no effect with --output, which never modifies the originals".to_string());
             }
-            PrivacyLevel::Strict => {
-                println!("• Removes: GPS, device IDs, timestamps, user comments, software info");
-                println!("• Preserves: Camera settings (ISO, aperture, etc.), color profiles");
+        }
+        if self.jobs == 0 {
+            problems.push("At least one job is needed".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigErrors(problems))
+        }
+    }
+
+    /// The input as a string, as the old `input_dir` field held it
+    #[deprecated(note = "use the `input` field, a `PathBuf`; non-UTF-8 paths are shown lossily here")]
+    pub fn input_dir(&self) -> String {
+        self.input.display().to_string()
+    }
+
+    /// The output as a string, as the old `output_dir` field held it
+    #[deprecated(note = "use the `output` field, a `PathBuf`; non-UTF-8 paths are shown lossily here")]
+    pub fn output_dir(&self) -> Option<String> {
+        self.output.as_ref().map(|output| output.display().to_string())
+    }
+
+    pub fn print_privacy_explanation(&self) {
+        let list = |items: Vec<String>| FluentValue::from(items.join(", "));
+        println!("\n{}", tr("settings-heading", &[("level", format!("{:?}", self.privacy_level).into())]));
+        // Generated from the policy's tag lists so the promise can't drift from the behavior
+        println!("{}", tr("settings-removes", &[("items", list(PrivacyPolicy::get_removed_description(&self.privacy_level)))]));
+        println!("{}", tr("settings-preserves", &[("items", list(PrivacyPolicy::get_preserved_description(&self.privacy_level)))]));
+        let names = |tags: &[exif::Tag]| tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+        if !self.policy_override.keep().is_empty() {
+            println!("{}", tr("settings-also-keeps", &[("items", list(names(self.policy_override.keep())))]));
+        }
+        if !self.policy_override.remove().is_empty() {
+            println!("{}", tr("settings-also-removes", &[("items", list(names(self.policy_override.remove())))]));
+        }
+        if self.unknown_tags.removes(&self.privacy_level) && PrivacyPolicy::preserves_unlisted_tags(&self.privacy_level) {
+            println!("{}", tr("settings-also-removes", &[("items", list(vec![tr("settings-unknown-tags", &[])]))]));
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_line_definition() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn test_subcommands_parse() {
+        // No subcommand is still `clean`
+        let args = try_parse_from(["privacy-exif-cleaner", "-i", "photos", "-p", "strict", "-r"]).unwrap();
+        let CliCommand::Clean(config) = args.command else { panic!("expected clean") };
+        assert_eq!(config.input, PathBuf::from("photos"));
+        assert_eq!(config.privacy_level, PrivacyLevel::Strict);
+        assert!(config.recursive && !config.dry_run);
+
+        let args = try_parse_from(["privacy-exif-cleaner", "analyze", "-i", "photos", "--keep-tag", "Make"]).unwrap();
+        let CliCommand::Clean(config) = args.command else { panic!("expected analyze to clean") };
+        assert!(config.dry_run && config.verbose);
+        assert_eq!(config.policy_override.keep(), [exif::Tag::Make]);
+
+        let args = try_parse_from(["privacy-exif-cleaner", "restore", "-i", "photos", "-n"]).unwrap();
+        assert!(matches!(args.command, CliCommand::Restore(ref options) if options.dry_run));
+        // `daemon` keeps working as an alias of `watch`
+        let args = try_parse_from(["privacy-exif-cleaner", "daemon", "-c", "watch.toml"]).unwrap();
+        assert!(matches!(args.command, CliCommand::Watch(ref options) if options.config.as_os_str() == "watch.toml"));
+        let args = try_parse_from(["privacy-exif-cleaner", "verify-manifest", "publish.json"]).unwrap();
+        assert!(matches!(args.command, CliCommand::VerifyManifest(ref options) if options.manifest.as_os_str() == "publish.json"));
+
+        // Options belong to their subcommand
+        assert!(try_parse_from(["privacy-exif-cleaner", "audit", "--backup", "a.jpg"]).is_err());
+        assert!(try_parse_from(["privacy-exif-cleaner", "-i", "photos", "dump", "a.jpg"]).is_err());
+    }
+
+    #[test]
+    fn test_every_option_has_a_message() {
+        fn untranslated(command: &clap::Command, path: &str, missing: &mut Vec<String>) {
+            for arg in command.get_arguments().filter(|arg| arg.get_help().is_some()) {
+                let ids = help_ids(path, arg.get_id().as_str());
+                if !ids.iter().any(|id| i18n::try_tr(id, &[]).is_some()) {
+                    missing.push(ids[0].clone());
+                }
             }
-            PrivacyLevel::Paranoid => {
-                println!("• Removes: All metadata except essential technical camera settings");
-                println!("• Preserves: Only ISO, aperture, focal length, exposure time");
+            for sub in command.get_subcommands() {
+                let sub_path = if path.is_empty() { sub.get_name().to_string() } else { format!("{}-{}", path, sub.get_name()) };
+                untranslated(sub, &sub_path, missing);
             }
         }
-        println!();
+
+        let mut missing = Vec::new();
+        untranslated(&Cli::command(), "", &mut missing);
+        assert!(missing.is_empty(), "Options without help in en.ftl: {:?}", missing);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,282 @@
+//! S3-compatible object storage as an input source and output sink.
+//!
+//! Location parsing is always available so the CLI can recognise `s3://` URIs;
+//! the actual bucket I/O requires the `cloud` feature.
+
+use std::path::{Component, Path, PathBuf};
+use crate::formats;
+
+/// A bucket plus key prefix parsed from an `s3://bucket/prefix` URI
+#[derive(Debug, Clone, PartialEq)]
+pub struct S3Location {
+    pub bucket: String,
+    pub prefix: String,
+}
+
+impl S3Location {
+    /// Parse an `s3://bucket/prefix` URI; returns `None` for anything else
+    pub fn parse(uri: &str) -> Option<Self> {
+        let rest = uri.strip_prefix("s3://")?;
+        let (bucket, prefix) = match rest.split_once('/') {
+            Some((bucket, prefix)) => (bucket, prefix),
+            None => (rest, ""),
+        };
+
+        if bucket.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            bucket: bucket.to_string(),
+            prefix: prefix.to_string(),
+        })
+    }
+
+    /// Parse a CLI path argument that holds an `s3://` URI
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::parse(path.to_str()?)
+    }
+
+    /// Strip this location's prefix from a key under it
+    ///
+    /// The prefix is a directory: `photos` covers `photos/a.jpg` but not
+    /// `photos2/a.jpg`, although S3 lists both for it. Keys outside give `None`.
+    pub fn relative_key<'a>(&self, key: &'a str) -> Option<&'a str> {
+        let prefix = self.prefix.trim_end_matches('/');
+        let rest = key.strip_prefix(prefix)?;
+        if !prefix.is_empty() && !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        Some(rest.trim_start_matches('/'))
+    }
+
+    /// A key under this location as a relative file path, for writing into a directory
+    ///
+    /// Keys are arbitrary strings; one with a `..`, root, or drive component
+    /// would land outside the directory and gives `None`, as does an empty one.
+    pub fn relative_path(&self, key: &str) -> Option<PathBuf> {
+        let relative = Path::new(self.relative_key(key)?);
+        let contained = relative.components().all(|component| matches!(component, Component::Normal(_)));
+        (contained && !relative.as_os_str().is_empty()).then(|| relative.to_path_buf())
+    }
+
+    /// Map a key under `source` to the equivalent key under this location
+    pub fn rebase_key(&self, source: &S3Location, key: &str) -> Option<String> {
+        let relative = source.relative_key(key)?;
+        if self.prefix.is_empty() {
+            Some(relative.to_string())
+        } else {
+            Some(format!("{}/{}", self.prefix.trim_end_matches('/'), relative))
+        }
+    }
+}
+
+/// Check if a CLI path argument refers to object storage
+pub fn is_s3_uri(path: &str) -> bool {
+    S3Location::parse(path).is_some()
+}
+
+/// Check if an object key looks like a supported image
+pub fn is_supported_key(key: &str) -> bool {
+    !key.ends_with('/') && formats::is_supported_image(Path::new(key))
+}
+
+#[cfg(feature = "cloud")]
+pub use self::storage::{process_bucket, CloudStats};
+
+#[cfg(feature = "cloud")]
+mod storage {
+    use std::path::{Path, PathBuf};
+    use s3::bucket::Bucket;
+    use s3::creds::Credentials;
+    use s3::region::Region;
+    use super::{is_supported_key, S3Location};
+    use crate::processor::ImageProcessor;
+    use tracing::{error, info};
+
+    /// Counters for a single bucket run
+    #[derive(Debug, Default)]
+    pub struct CloudStats {
+        pub processed: u32,
+        pub privacy_data_found: u32,
+        pub errors: u32,
+    }
+
+    /// Where cleaned objects are written
+    enum Sink {
+        Bucket(Box<Bucket>, S3Location),
+        Directory(PathBuf),
+    }
+
+    /// Open a bucket using credentials and region from the environment
+    ///
+    /// `AWS_REGION` selects the region; setting `S3_ENDPOINT` targets an
+    /// S3-compatible service (MinIO, R2, ...) with path-style addressing.
+    fn open_bucket(location: &S3Location) -> Result<Box<Bucket>, Box<dyn std::error::Error>> {
+        let credentials = Credentials::default()?;
+        let region_name = std::env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+
+        let bucket = match std::env::var("S3_ENDPOINT") {
+            Ok(endpoint) => {
+                let region = Region::Custom { region: region_name, endpoint };
+                Bucket::new(&location.bucket, region, credentials)?.with_path_style()
+            }
+            Err(_) => Bucket::new(&location.bucket, region_name.parse()?, credentials)?,
+        };
+
+        Ok(bucket)
+    }
+
+    /// Clean every supported image under an S3 prefix
+    ///
+    /// `output` may be another `s3://` URI or a local directory. Without an
+    /// output the objects are overwritten in place, mirroring local in-place runs.
+    pub fn process_bucket(
+        processor: &ImageProcessor,
+        input: &S3Location,
+        output: Option<&Path>,
+    ) -> Result<CloudStats, Box<dyn std::error::Error>> {
+        let source = open_bucket(input)?;
+        let sink = match output {
+            Some(out) => match S3Location::from_path(out) {
+                Some(location) => Sink::Bucket(open_bucket(&location)?, location),
+                None => Sink::Directory(out.to_path_buf()),
+            },
+            None => Sink::Bucket(open_bucket(input)?, input.clone()),
+        };
+
+        let mut stats = CloudStats::default();
+
+        for page in source.list(input.prefix.clone(), None)? {
+            for object in page.contents {
+                if !is_supported_key(&object.key) || input.relative_key(&object.key).is_none() {
+                    continue;
+                }
+
+                match process_object(processor, &source, input, &sink, &object.key) {
+                    Ok(had_privacy_data) => {
+                        if processor.config().verbose || processor.config().dry_run {
+                            info!(bucket = %input.bucket, key = %object.key, "Processed");
+                        }
+                        stats.processed += 1;
+                        if had_privacy_data {
+                            stats.privacy_data_found += 1;
+                        }
+                    }
+                    Err(e) => {
+                        error!(bucket = %input.bucket, key = %object.key, "Error processing object: {}", e);
+                        stats.errors += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(stats)
+    }
+
+    fn process_object(
+        processor: &ImageProcessor,
+        source: &Bucket,
+        input: &S3Location,
+        sink: &Sink,
+        key: &str,
+    ) -> Result<bool, Box<dyn std::error::Error>> {
+        let response = source.get_object(key)?;
+        if response.status_code() != 200 {
+            return Err(format!("GET returned HTTP {}", response.status_code()).into());
+        }
+        let data = response.bytes();
+
+        let result = match processor.process_image_data(data, Path::new(key)) {
+            Ok(result) => result,
+            Err(e) => {
+                processor.quarantine_data(data, Path::new(key), &e.to_string());
+                return Err(e);
+            }
+        };
+        if processor.config().dry_run {
+            return Ok(result.had_privacy_data);
+        }
+
+        let in_place = matches!(sink, Sink::Bucket(_, location) if location == input);
+        if in_place && result.cleaned.is_none() {
+            // Nothing to rewrite
+            return Ok(false);
+        }
+
+        match sink {
+            Sink::Bucket(bucket, location) => {
+                if in_place && processor.config().create_backup {
+                    bucket.put_object(format!("{}.bak", key), data)?;
+                }
+                let target_key = location.rebase_key(input, key).ok_or("Key outside the input prefix")?;
+                bucket.put_object(target_key, result.output(data))?;
+            }
+            Sink::Directory(dir) => {
+                let target = dir.join(input.relative_path(key).ok_or("Key outside the input prefix or the output directory")?);
+                if let Some(parent) = target.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(target, result.output(data))?;
+            }
+        }
+
+        Ok(result.had_privacy_data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_s3_location() {
+        assert_eq!(
+            S3Location::parse("s3://photos/2024/trip"),
+            Some(S3Location { bucket: "photos".to_string(), prefix: "2024/trip".to_string() })
+        );
+        assert_eq!(
+            S3Location::parse("s3://photos"),
+            Some(S3Location { bucket: "photos".to_string(), prefix: "".to_string() })
+        );
+
+        assert_eq!(S3Location::parse("s3://"), None);
+        assert_eq!(S3Location::parse("/local/photos"), None);
+    }
+
+    #[test]
+    fn test_rebase_key() {
+        let source = S3Location::parse("s3://raw/uploads").unwrap();
+        let dest = S3Location::parse("s3://clean/public/").unwrap();
+        let root = S3Location::parse("s3://clean").unwrap();
+
+        assert_eq!(dest.rebase_key(&source, "uploads/a/photo.jpg").as_deref(), Some("public/a/photo.jpg"));
+        assert_eq!(root.rebase_key(&source, "uploads/photo.jpg").as_deref(), Some("photo.jpg"));
+        assert_eq!(source.relative_key("uploads/a/photo.jpg"), Some("a/photo.jpg"));
+        assert_eq!(root.relative_key("a/photo.jpg"), Some("a/photo.jpg"));
+        // A listing of `uploads` also returns its sibling `uploads2`
+        assert_eq!(source.relative_key("uploads2/photo.jpg"), None);
+        assert_eq!(dest.rebase_key(&source, "uploads2/photo.jpg"), None);
+    }
+
+    #[test]
+    fn test_relative_path_stays_inside_the_directory() {
+        let source = S3Location::parse("s3://raw/uploads").unwrap();
+        assert_eq!(source.relative_path("uploads/a/photo.jpg"), Some(PathBuf::from("a/photo.jpg")));
+        assert_eq!(source.relative_path("uploads/../../home/u/x.jpg"), None);
+        assert_eq!(source.relative_path("uploads/a/../../x.jpg"), None);
+        assert_eq!(source.relative_path("uploads/./x.jpg"), None);
+        assert_eq!(source.relative_path("uploads"), None);
+
+        let root = S3Location::parse("s3://raw").unwrap();
+        assert_eq!(root.relative_path("/etc/x.jpg"), Some(PathBuf::from("etc/x.jpg")));
+        assert_eq!(root.relative_path("../x.jpg"), None);
+    }
+
+    #[test]
+    fn test_is_supported_key() {
+        assert!(is_supported_key("uploads/photo.JPG"));
+        assert!(!is_supported_key("uploads/notes.txt"));
+        assert!(!is_supported_key("uploads/folder.jpg/"));
+    }
+}
@@ -0,0 +1,228 @@
+//! Field-level comparison of an image before and after cleaning.
+//!
+//! Backs the `compare` subcommand, for spot-auditing that an image "cleaned"
+//! by some other tool really lost its metadata. EXIF fields are matched by
+//! IFD and tag number and compared by stored value; XMP packets count as one
+//! field each.
+
+use std::path::Path;
+use serde::Serialize;
+use crate::dump::{self, DumpedField, MetadataDump};
+use crate::privacy;
+
+/// What happened to a field between the original and the cleaned image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldChange {
+    /// Only in the original
+    Removed,
+    /// In both, with a different stored value
+    Changed,
+    /// In both, unchanged
+    Preserved,
+    /// Only in the cleaned image
+    Added,
+}
+
+impl std::fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FieldChange::Removed => write!(f, "removed"),
+            FieldChange::Changed => write!(f, "changed"),
+            FieldChange::Preserved => write!(f, "preserved"),
+            FieldChange::Added => write!(f, "added"),
+        }
+    }
+}
+
+/// One field of the comparison
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldDiff {
+    /// IFD the field was read from, or `XMP`
+    pub ifd: String,
+    pub tag: String,
+    pub tag_id: u16,
+    pub change: FieldChange,
+    /// Interpreted value in the original, if it has the field
+    pub original: Option<String>,
+    /// Interpreted value in the cleaned image, if it has the field
+    pub cleaned: Option<String>,
+}
+
+/// Every field of two images, matched up
+#[derive(Debug, Clone, Serialize)]
+pub struct Comparison {
+    pub original: String,
+    pub cleaned: String,
+    pub fields: Vec<FieldDiff>,
+}
+
+/// Compare the metadata of an original image file with its cleaned copy
+pub fn compare_files(original: &Path, cleaned: &Path) -> Result<Comparison, Box<dyn std::error::Error>> {
+    Ok(compare_dumps(&dump::dump_file(original)?, &dump::dump_file(cleaned)?))
+}
+
+/// Compare two metadata dumps field by field
+pub fn compare_dumps(original: &MetadataDump, cleaned: &MetadataDump) -> Comparison {
+    let before = comparable_fields(original);
+    let after = comparable_fields(cleaned);
+    let mut matched = vec![false; after.len()];
+    let mut fields = Vec::new();
+
+    for field in &before {
+        // Match each field at most once, so a duplicated tag isn't counted as preserved twice
+        let counterpart = (0..after.len())
+            .find(|&i| !matched[i] && after[i].ifd == field.ifd && after[i].tag_id == field.tag_id && after[i].tag == field.tag);
+        let change = match counterpart {
+            Some(i) => {
+                matched[i] = true;
+                if after[i].raw == field.raw { FieldChange::Preserved } else { FieldChange::Changed }
+            }
+            None => FieldChange::Removed,
+        };
+        fields.push(FieldDiff {
+            ifd: field.ifd.clone(),
+            tag: field.tag.clone(),
+            tag_id: field.tag_id,
+            change,
+            original: Some(field.value.clone()),
+            cleaned: counterpart.map(|i| after[i].value.clone()),
+        });
+    }
+
+    for (field, _) in after.iter().zip(&matched).filter(|(_, matched)| !**matched) {
+        fields.push(FieldDiff {
+            ifd: field.ifd.clone(),
+            tag: field.tag.clone(),
+            tag_id: field.tag_id,
+            change: FieldChange::Added,
+            original: None,
+            cleaned: Some(field.value.clone()),
+        });
+    }
+
+    Comparison {
+        original: original.path.clone(),
+        cleaned: cleaned.path.clone(),
+        fields,
+    }
+}
+
+/// The EXIF fields of a dump, with its XMP packets as fields of their own
+fn comparable_fields(dump: &MetadataDump) -> Vec<DumpedField> {
+    let xmp = [("XMP packet", &dump.xmp), ("Extended XMP", &dump.xmp_extended)];
+    let packets = xmp.into_iter().filter_map(|(tag, packet)| {
+        packet.as_ref().map(|packet| DumpedField {
+            ifd: "XMP".to_string(),
+            tag: tag.to_string(),
+            tag_id: privacy::XMP_PACKET.number(),
+            value: format!("{} bytes", packet.len()),
+            raw: packet.clone(),
+        })
+    });
+    dump.fields.iter().cloned().chain(packets).collect()
+}
+
+impl Comparison {
+    /// Number of fields with the given change
+    pub fn count(&self, change: FieldChange) -> usize {
+        self.fields.iter().filter(|field| field.change == change).count()
+    }
+
+    /// Render the comparison as aligned, human-readable text
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} -> {}\n", self.original, self.cleaned);
+
+        for field in &self.fields {
+            let value = match (&field.original, &field.cleaned) {
+                (Some(original), Some(cleaned)) if field.change == FieldChange::Changed => {
+                    format!("{} -> {}", original, cleaned)
+                }
+                (Some(value), _) | (None, Some(value)) => value.clone(),
+                (None, None) => String::new(),
+            };
+            text.push_str(&format!(
+                "  {:<10} {:<12} {:<32} {}\n",
+                field.change,
+                field.ifd,
+                format!("{} (0x{:04X})", field.tag, field.tag_id),
+                value
+            ));
+        }
+
+        text.push_str(&format!(
+            "  {} removed, {} changed, {} preserved, {} added\n",
+            self.count(FieldChange::Removed),
+            self.count(FieldChange::Changed),
+            self.count(FieldChange::Preserved),
+            self.count(FieldChange::Added)
+        ));
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(ifd: &str, tag: &str, tag_id: u16, raw: &str) -> DumpedField {
+        DumpedField {
+            ifd: ifd.to_string(),
+            tag: tag.to_string(),
+            tag_id,
+            value: raw.to_string(),
+            raw: raw.to_string(),
+        }
+    }
+
+    fn dump(path: &str, fields: Vec<DumpedField>, xmp: Option<&str>) -> MetadataDump {
+        MetadataDump {
+            path: path.to_string(),
+            fields,
+            xmp: xmp.map(str::to_string),
+            xmp_extended: None,
+        }
+    }
+
+    #[test]
+    fn test_fields_are_classified() {
+        let original = dump("original.jpg", vec![
+            field("IFD0", "Make", 0x010F, "Canon"),
+            field("IFD0", "Orientation", 0x0112, "6"),
+            field("IFD0", "Artist", 0x013B, "Jane Doe"),
+            field("GPS", "GPSLatitude", 0x0002, "48 deg"),
+        ], Some("<x:xmpmeta/>"));
+        let cleaned = dump("cleaned.jpg", vec![
+            field("IFD0", "Make", 0x010F, "Canon"),
+            field("IFD0", "Orientation", 0x0112, "1"),
+            field("IFD0", "Software", 0x0131, "Editor"),
+        ], None);
+
+        let comparison = compare_dumps(&original, &cleaned);
+        let changes: Vec<(&str, FieldChange)> = comparison.fields.iter()
+            .map(|field| (field.tag.as_str(), field.change))
+            .collect();
+        assert_eq!(changes, vec![
+            ("Make", FieldChange::Preserved),
+            ("Orientation", FieldChange::Changed),
+            ("Artist", FieldChange::Removed),
+            ("GPSLatitude", FieldChange::Removed),
+            ("XMP packet", FieldChange::Removed),
+            ("Software", FieldChange::Added),
+        ]);
+
+        let text = comparison.to_text();
+        assert!(text.contains("6 -> 1"));
+        assert!(text.contains("3 removed, 1 changed, 1 preserved, 1 added"));
+    }
+
+    #[test]
+    fn test_same_tag_in_different_ifds_is_not_matched() {
+        let original = dump("original.jpg", vec![field("IFD1", "Software", 0x0131, "Editor")], None);
+        let cleaned = dump("cleaned.jpg", vec![field("IFD0", "Software", 0x0131, "Editor")], None);
+
+        let comparison = compare_dumps(&original, &cleaned);
+        assert_eq!(comparison.count(FieldChange::Removed), 1);
+        assert_eq!(comparison.count(FieldChange::Added), 1);
+    }
+}
@@ -0,0 +1,531 @@
+//! Long-running service mode for the `watch` subcommand (alias `daemon`).
+//!
+//! Watches the directories listed in a TOML config file and cleans each image
+//! once it has stopped changing, e.g. uploads landing on a server. SIGHUP
+//! reloads the config file; SIGTERM and SIGINT stop after the current file.
+//! Events are printed to stdout as JSON lines. Under systemd (`Type=notify`),
+//! readiness, reloads, status, and watchdog pings go to `$NOTIFY_SOCKET`.
+//! Requires the `daemon` feature and a Unix system.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use tracing::{debug, error, info, warn};
+use crate::batch::Throttle;
+use crate::cli::{Config, WatchOptions};
+use crate::formats;
+use crate::privacy::PrivacyLevel;
+use crate::processor::ImageProcessor;
+use crate::progress::Skipped;
+use crate::sandbox;
+use crate::utils;
+use crate::walker;
+
+/// How often sleeping checks for signals
+const TICK: Duration = Duration::from_millis(200);
+
+/// The daemon config file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DaemonConfig {
+    /// Seconds between scans of the watched directories
+    #[serde(default = "default_poll_interval")]
+    pub poll_interval: u64,
+    /// Seconds a file must keep its size and modification time before it is cleaned
+    #[serde(default = "default_settle")]
+    pub settle: u64,
+    /// Most input to clean per second across all directories, e.g. "20M"
+    pub throttle: Option<String>,
+    /// Run at low CPU and disk priority; takes effect at startup only
+    #[serde(default)]
+    pub nice: bool,
+    /// Confine ExifTool to the watched directories (requires the sandbox feature)
+    #[serde(default)]
+    pub sandbox: bool,
+    #[serde(rename = "watch", default)]
+    pub watches: Vec<WatchConfig>,
+}
+
+/// One watched directory
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WatchConfig {
+    pub input: PathBuf,
+    /// Where cleaned copies are written; images are cleaned in place when unset
+    pub output: Option<PathBuf>,
+    #[serde(default = "default_privacy_level")]
+    pub privacy_level: String,
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+fn default_poll_interval() -> u64 {
+    5
+}
+
+fn default_settle() -> u64 {
+    2
+}
+
+fn default_privacy_level() -> String {
+    "standard".to_string()
+}
+
+impl DaemonConfig {
+    /// Read and check a daemon config file
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let config: DaemonConfig = toml::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        config.validate().map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.watches.is_empty() {
+            return Err("No [[watch]] directories configured".to_string());
+        }
+        if self.poll_interval == 0 {
+            return Err("poll_interval must be at least 1 second".to_string());
+        }
+        self.throttle_rate()?;
+        for watch in &self.watches {
+            watch.clean_config()?;
+            // Cleaned copies inside a recursively watched input would be picked up again
+            if let Some(ref output) = watch.output {
+                if watch.recursive && output.starts_with(&watch.input) {
+                    return Err(format!("Output {} is inside recursively watched {}", output.display(), watch.input.display()));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The throttle in bytes per second
+    fn throttle_rate(&self) -> Result<Option<u64>, String> {
+        self.throttle.as_deref()
+            .map(|rate| utils::parse_size(rate).map_err(|e| format!("Invalid throttle: {}", e)))
+            .transpose()
+    }
+}
+
+impl WatchConfig {
+    /// The cleaning config for this directory
+    ///
+    /// Policy files are ignored: whoever uploads into a watched directory
+    /// could otherwise weaken its cleaning.
+    fn clean_config(&self) -> Result<Config, String> {
+        let level = PrivacyLevel::from_str(&self.privacy_level, true)
+            .map_err(|_| format!("Unknown privacy level '{}'", self.privacy_level))?;
+        let mut config = Config::with_privacy_level(level);
+        config.input = self.input.clone();
+        config.output = self.output.clone();
+        config.recursive = self.recursive;
+        config.ignore_policy_files = true;
+        Ok(config)
+    }
+}
+
+/// Counters of one watched directory, as reported in status events
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WatchStatus {
+    pub input: String,
+    /// Images that had privacy data removed
+    pub cleaned: u64,
+    /// Images that already conformed to the policy
+    pub already_clean: u64,
+    pub errors: u64,
+    pub last_error: Option<String>,
+}
+
+/// One line of the daemon's stdout
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    Started { watches: usize },
+    Cleaned { input: &'a str, path: String, already_clean: bool },
+    Failed { input: &'a str, path: String, error: String },
+    Reloaded { watches: usize },
+    ReloadFailed { error: String },
+    Status { watches: Vec<&'a WatchStatus> },
+    Stopped,
+}
+
+fn emit(event: &Event) {
+    match serde_json::to_string(event) {
+        Ok(line) => {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "{}", line);
+            let _ = stdout.flush();
+        }
+        Err(e) => error!("Could not serialize event: {}", e),
+    }
+}
+
+/// Size and modification time, to tell when a file has stopped changing
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Signature {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Signature {
+    fn of(metadata: &fs::Metadata) -> Self {
+        Signature { len: metadata.len(), modified: metadata.modified().ok() }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum FileState {
+    /// Last seen with this signature, unchanged since the instant
+    Changing(Signature, Instant),
+    /// Handled with this signature
+    Done(Signature),
+}
+
+/// Files seen in a watched directory and whether they are ready to clean
+#[derive(Debug, Default)]
+struct FileTracker {
+    files: HashMap<PathBuf, FileState>,
+}
+
+impl FileTracker {
+    /// Whether `path` has kept `signature` for `settle` and wasn't already handled with it
+    fn ready(&mut self, path: &Path, signature: Signature, now: Instant, settle: Duration) -> bool {
+        match self.files.get(path) {
+            Some(FileState::Done(done)) if *done == signature => false,
+            Some(FileState::Changing(seen, since)) if *seen == signature => now.duration_since(*since) >= settle,
+            _ => {
+                self.files.insert(path.to_path_buf(), FileState::Changing(signature, now));
+                settle.is_zero()
+            }
+        }
+    }
+
+    fn done(&mut self, path: &Path, signature: Signature) {
+        self.files.insert(path.to_path_buf(), FileState::Done(signature));
+    }
+
+    /// Forget files that are gone
+    fn retain(&mut self, present: &HashSet<PathBuf>) {
+        self.files.retain(|path, _| present.contains(path));
+    }
+}
+
+/// A watched directory and its cleaning state
+struct Watcher {
+    processor: ImageProcessor,
+    tracker: FileTracker,
+    status: WatchStatus,
+}
+
+impl Watcher {
+    fn new(watch: &WatchConfig, sandbox: bool) -> Result<Self, Box<dyn std::error::Error>> {
+        if !watch.input.is_dir() {
+            return Err(format!("Watched directory {} does not exist", watch.input.display()).into());
+        }
+        if let Some(ref output) = watch.output {
+            fs::create_dir_all(output)?;
+        }
+        let mut config = watch.clean_config()?;
+        config.sandbox = sandbox;
+        Ok(Watcher {
+            status: WatchStatus { input: config.input.display().to_string(), ..WatchStatus::default() },
+            processor: ImageProcessor::new(config),
+            tracker: FileTracker::default(),
+        })
+    }
+
+    /// Clean the images that are ready; returns how many files were handled
+    fn scan(&mut self, settle: Duration, throttle: Option<Throttle>, notifier: &mut Notifier, stop: &AtomicBool) -> usize {
+        let now = Instant::now();
+        let mut present = HashSet::new();
+        let mut handled = 0;
+
+        for entry in walker::walk(self.processor.config()) {
+            let entry = match entry {
+                Ok(entry) if entry.file_type().is_file() => entry,
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Error walking {}: {}", self.status.input, e);
+                    continue;
+                }
+            };
+            let path = entry.path();
+            let signature = match entry.metadata() {
+                Ok(metadata) => Signature::of(&metadata),
+                Err(e) => {
+                    debug!(path = %path.display(), "Cannot read file metadata: {}", e);
+                    continue;
+                }
+            };
+            present.insert(path.to_path_buf());
+            if !self.tracker.ready(path, signature, now, settle) {
+                continue;
+            }
+
+            if formats::detect_image_format(path).is_some() || formats::handler_for_file(path).is_some() {
+                let started = Instant::now();
+                self.clean(path);
+                handled += 1;
+                if let Some(throttle) = throttle {
+                    throttle.pace(signature.len, started.elapsed());
+                }
+            }
+            // Cleaning in place changes the file; remember it as it is now
+            let signature = fs::metadata(path).map(|metadata| Signature::of(&metadata)).unwrap_or(signature);
+            self.tracker.done(path, signature);
+
+            notifier.keepalive();
+            if stop.load(Ordering::Relaxed) {
+                return handled;
+            }
+        }
+
+        self.tracker.retain(&present);
+        handled
+    }
+
+    fn clean(&mut self, path: &Path) {
+        match self.processor.process_image(path) {
+            Ok(skipped) => {
+                let already_clean = skipped == Some(Skipped::AlreadyClean);
+                if already_clean {
+                    self.status.already_clean += 1;
+                } else {
+                    self.status.cleaned += 1;
+                }
+                emit(&Event::Cleaned { input: &self.status.input, path: path.display().to_string(), already_clean });
+            }
+            Err(e) => {
+                error!(path = %path.display(), "Error processing file: {}", e);
+                self.status.errors += 1;
+                self.status.last_error = Some(format!("{}: {}", path.display(), e));
+                emit(&Event::Failed { input: &self.status.input, path: path.display().to_string(), error: e.to_string() });
+            }
+        }
+    }
+}
+
+fn build_watchers(config: &DaemonConfig) -> Result<Vec<Watcher>, Box<dyn std::error::Error>> {
+    if config.sandbox {
+        sandbox::check().map_err(|e| format!("Cannot sandbox ExifTool: {}", e))?;
+    }
+    config.watches.iter().map(|watch| Watcher::new(watch, config.sandbox)).collect()
+}
+
+/// Messages to the service manager, when started by one with `Type=notify`
+struct Notifier {
+    watchdog: Option<Duration>,
+    last_ping: Instant,
+}
+
+impl Notifier {
+    fn from_env() -> Self {
+        // Ping at half the configured timeout, as sd_watchdog_enabled(3) recommends
+        let watchdog = std::env::var("WATCHDOG_USEC").ok()
+            .and_then(|usec| usec.parse::<u64>().ok())
+            .filter(|&usec| usec > 0)
+            .map(|usec| Duration::from_micros(usec / 2));
+        Notifier { watchdog, last_ping: Instant::now() }
+    }
+
+    /// Ping the watchdog if it's due
+    fn keepalive(&mut self) {
+        if let Some(interval) = self.watchdog {
+            if self.last_ping.elapsed() >= interval {
+                sd_notify("WATCHDOG=1");
+                self.last_ping = Instant::now();
+            }
+        }
+    }
+
+    fn status(&self, watchers: &[Watcher]) {
+        let cleaned: u64 = watchers.iter().map(|watcher| watcher.status.cleaned).sum();
+        let errors: u64 = watchers.iter().map(|watcher| watcher.status.errors).sum();
+        sd_notify(&format!("STATUS=Watching {} directories: {} images cleaned, {} errors", watchers.len(), cleaned, errors));
+    }
+}
+
+/// Send a state to `$NOTIFY_SOCKET`; a no-op outside systemd, failures are only logged
+fn sd_notify(state: &str) {
+    use std::os::unix::net::UnixDatagram;
+
+    let socket_path = match std::env::var_os("NOTIFY_SOCKET") {
+        Some(path) => path,
+        None => return,
+    };
+    let result = UnixDatagram::unbound().and_then(|socket| {
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::linux::net::SocketAddrExt;
+            use std::os::unix::ffi::OsStrExt;
+            use std::os::unix::net::SocketAddr;
+
+            // A leading '@' names a socket in the abstract namespace
+            if let Some(name) = socket_path.as_bytes().strip_prefix(b"@") {
+                return socket.send_to_addr(state.as_bytes(), &SocketAddr::from_abstract_name(name)?);
+            }
+        }
+        socket.send_to(state.as_bytes(), &socket_path)
+    });
+    if let Err(e) = result {
+        warn!("Could not notify service manager: {}", e);
+    }
+}
+
+fn emit_status(watchers: &[Watcher], notifier: &Notifier) {
+    emit(&Event::Status { watches: watchers.iter().map(|watcher| &watcher.status).collect() });
+    notifier.status(watchers);
+}
+
+/// Run until SIGTERM or SIGINT, cleaning the directories in `options.config`
+pub fn run(options: &WatchOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let reload = Arc::new(AtomicBool::new(false));
+    let stop = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(SIGHUP, Arc::clone(&reload))?;
+    signal_hook::flag::register(SIGTERM, Arc::clone(&stop))?;
+    signal_hook::flag::register(SIGINT, Arc::clone(&stop))?;
+
+    let config_path = options.config.as_path();
+    let mut config = DaemonConfig::load(config_path)?;
+    let mut watchers = build_watchers(&config)?;
+    let mut notifier = Notifier::from_env();
+    if config.nice {
+        if let Err(e) = utils::lower_priority() {
+            warn!("Could not lower process priority: {}", e);
+        }
+    }
+
+    info!(config = %config_path.display(), watches = watchers.len(), "Daemon started");
+    emit(&Event::Started { watches: watchers.len() });
+    sd_notify("READY=1");
+    emit_status(&watchers, &notifier);
+
+    while !stop.load(Ordering::Relaxed) {
+        if reload.swap(false, Ordering::Relaxed) {
+            sd_notify("RELOADING=1");
+            match DaemonConfig::load(config_path).and_then(|new_config| Ok((build_watchers(&new_config)?, new_config))) {
+                Ok((mut new_watchers, new_config)) => {
+                    // Directories still watched keep their counters and don't clean files again
+                    for watcher in &mut new_watchers {
+                        if let Some(old) = watchers.iter_mut().find(|old| old.status.input == watcher.status.input) {
+                            watcher.tracker = std::mem::take(&mut old.tracker);
+                            watcher.status = old.status.clone();
+                        }
+                    }
+                    watchers = new_watchers;
+                    config = new_config;
+                    info!(watches = watchers.len(), "Config reloaded");
+                    emit(&Event::Reloaded { watches: watchers.len() });
+                }
+                Err(e) => {
+                    error!("Config reload failed, keeping the previous config: {}", e);
+                    emit(&Event::ReloadFailed { error: e.to_string() });
+                }
+            }
+            sd_notify("READY=1");
+            emit_status(&watchers, &notifier);
+        }
+
+        let settle = Duration::from_secs(config.settle);
+        let throttle = config.throttle_rate()?.map(Throttle::per_second);
+        let mut handled = 0;
+        for watcher in &mut watchers {
+            handled += watcher.scan(settle, throttle, &mut notifier, &stop);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+        }
+        if handled > 0 {
+            emit_status(&watchers, &notifier);
+        }
+
+        let next_scan = Instant::now() + Duration::from_secs(config.poll_interval);
+        while Instant::now() < next_scan && !stop.load(Ordering::Relaxed) && !reload.load(Ordering::Relaxed) {
+            notifier.keepalive();
+            std::thread::sleep(TICK);
+        }
+    }
+
+    sd_notify("STOPPING=1");
+    info!("Daemon stopped");
+    emit(&Event::Stopped);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_parses_watches() {
+        let config: DaemonConfig = toml::from_str(
+            r#"
+            poll_interval = 10
+
+            [[watch]]
+            input = "/srv/uploads"
+            output = "/srv/published"
+            privacy_level = "strict"
+            recursive = true
+
+            [[watch]]
+            input = "/srv/avatars"
+            "#,
+        ).unwrap();
+
+        assert_eq!(config.poll_interval, 10);
+        assert_eq!(config.settle, 2);
+        assert!(config.validate().is_ok());
+        let clean_config = config.watches[0].clean_config().unwrap();
+        assert_eq!(clean_config.privacy_level, PrivacyLevel::Strict);
+        assert_eq!(clean_config.output.as_deref(), Some(Path::new("/srv/published")));
+        assert!(clean_config.ignore_policy_files);
+        assert_eq!(config.watches[1].clean_config().unwrap().output, None);
+    }
+
+    #[test]
+    fn test_config_rejects_output_inside_recursive_input() {
+        let config: DaemonConfig = toml::from_str(
+            r#"
+            [[watch]]
+            input = "/srv/uploads"
+            output = "/srv/uploads/clean"
+            recursive = true
+            "#,
+        ).unwrap();
+        assert!(config.validate().is_err());
+
+        let config: DaemonConfig = toml::from_str("[[watch]]\ninput = \"/srv\"\nprivacy_level = \"bogus\"").unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_file_is_ready_once_settled() {
+        let mut tracker = FileTracker::default();
+        let path = Path::new("upload.jpg");
+        let settle = Duration::from_secs(2);
+        let start = Instant::now();
+        let partial = Signature { len: 100, modified: None };
+        let complete = Signature { len: 200, modified: None };
+
+        assert!(!tracker.ready(path, partial, start, settle));
+        // Still growing: the settle time starts over
+        assert!(!tracker.ready(path, complete, start + Duration::from_secs(3), settle));
+        assert!(!tracker.ready(path, complete, start + Duration::from_secs(4), settle));
+        assert!(tracker.ready(path, complete, start + Duration::from_secs(5), settle));
+
+        tracker.done(path, complete);
+        assert!(!tracker.ready(path, complete, start + Duration::from_secs(60), settle));
+
+        tracker.retain(&HashSet::new());
+        assert!(tracker.files.is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Duplicate detection by image content.
+//!
+//! The content hash covers only the image data, not the metadata, so an
+//! original and its cleaned copy hash identically. Indexing the output
+//! directory lets a run skip images whose cleaned copy already exists.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+use crate::formats;
+use crate::jpeg;
+use crate::utils;
+
+/// Hash the image data of a file, ignoring all metadata segments
+///
+/// Returns `None` for formats where image data can't be separated from
+/// metadata yet (currently everything but JPEG).
+pub fn content_hash(data: &[u8]) -> Option<String> {
+    let layout = jpeg::parse_layout(data).ok()?;
+    let mut hasher = Sha256::new();
+
+    for segment in layout.segments.iter().filter(|segment| !segment.is_metadata()) {
+        hasher.update([segment.marker]);
+        hasher.update(segment.payload);
+    }
+    hasher.update(&data[layout.scan_offset..]);
+
+    Some(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Content hashes of images already present in an output directory
+#[derive(Debug, Default)]
+pub struct DuplicateIndex {
+    entries: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl DuplicateIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Index every supported image under `dir`
+    pub fn scan(dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let index = Self::new();
+        if !dir.is_dir() {
+            return Ok(index);
+        }
+
+        for entry in WalkDir::new(dir) {
+            let entry = entry?;
+            if !entry.file_type().is_file() || formats::detect_image_format(entry.path()).is_none() {
+                continue;
+            }
+            if let Some(hash) = content_hash(&utils::map_file(entry.path())?) {
+                index.insert(hash, entry.into_path());
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Find an indexed file with the given content hash
+    pub fn find(&self, hash: &str) -> Option<PathBuf> {
+        self.entries.lock().ok()?.get(hash).cloned()
+    }
+
+    /// Record a file; the first path indexed for a hash wins
+    pub fn insert(&self, hash: String, path: PathBuf) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.entry(hash).or_insert(path);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|entries| entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::tests::build_jpeg;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_content_hash_ignores_metadata() {
+        let original = build_jpeg(&[(jpeg::APP1, b"Exif\0\0GPS data"), (0xDB, b"qtable")], b"pixels");
+        let cleaned = build_jpeg(&[(0xDB, b"qtable")], b"pixels");
+        let different = build_jpeg(&[(0xDB, b"qtable")], b"other pixels");
+
+        assert_eq!(content_hash(&original), content_hash(&cleaned));
+        assert_ne!(content_hash(&original), content_hash(&different));
+        assert_eq!(content_hash(b"not an image"), None);
+    }
+
+    #[test]
+    fn test_duplicate_index_scan() {
+        let temp_dir = TempDir::new().unwrap();
+        let image = build_jpeg(&[(0xDB, b"qtable")], b"pixels");
+        std::fs::write(temp_dir.path().join("clean.jpg"), &image).unwrap();
+        std::fs::write(temp_dir.path().join("notes.txt"), b"text").unwrap();
+
+        let index = DuplicateIndex::scan(temp_dir.path()).unwrap();
+        assert_eq!(index.len(), 1);
+
+        let hash = content_hash(&image).unwrap();
+        assert_eq!(index.find(&hash), Some(temp_dir.path().join("clean.jpg")));
+    }
+}
@@ -0,0 +1,155 @@
+//! Work-stealing queues for parallel runs (`--jobs`).
+//!
+//! Each worker thread has its own queue, filled in turn as the directory walk
+//! finds files. A worker takes from the front of its own queue and, once it
+//! runs dry, steals from the back of another's, so one slow file (a huge TIFF,
+//! an ExifTool timeout) doesn't leave the files queued behind it waiting while
+//! other workers sit idle.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex};
+
+/// Work shared out among a fixed number of workers
+pub struct Dispatcher<T> {
+    queues: Vec<Mutex<VecDeque<T>>>,
+    /// Whether no more work will be pushed
+    closed: Mutex<bool>,
+    available: Condvar,
+    next: AtomicUsize,
+}
+
+impl<T> Dispatcher<T> {
+    /// Queues for `workers` workers, at least one
+    pub fn new(workers: usize) -> Self {
+        Dispatcher {
+            queues: (0..workers.max(1)).map(|_| Mutex::new(VecDeque::new())).collect(),
+            closed: Mutex::new(false),
+            available: Condvar::new(),
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.queues.len()
+    }
+
+    /// Queue an item for the next worker in turn
+    pub fn push(&self, item: T) {
+        let worker = self.next.fetch_add(1, Ordering::Relaxed) % self.queues.len();
+        if let Ok(mut queue) = self.queues[worker].lock() {
+            queue.push_back(item);
+        }
+        // Taken so a worker between finding the queues empty and waiting can't miss the wakeup
+        let _closed = self.closed.lock();
+        self.available.notify_one();
+    }
+
+    /// Let workers finish once the queues are empty
+    pub fn close(&self) {
+        if let Ok(mut closed) = self.closed.lock() {
+            *closed = true;
+        }
+        self.available.notify_all();
+    }
+
+    /// Drop all queued work, for runs that stop early, returning how many items were dropped
+    pub fn clear(&self) -> usize {
+        self.queues.iter()
+            .filter_map(|queue| queue.lock().ok())
+            .map(|mut queue| queue.drain(..).count())
+            .sum()
+    }
+
+    /// The next item for `worker`, waiting for one; `None` once closed and drained
+    pub fn next(&self, worker: usize) -> Option<T> {
+        loop {
+            if let Some(item) = self.take(worker) {
+                return Some(item);
+            }
+            let closed = self.closed.lock().ok()?;
+            // Work pushed since the queues were checked holds the lock until it notifies
+            if let Some(item) = self.take(worker) {
+                return Some(item);
+            }
+            if *closed {
+                return None;
+            }
+            drop(self.available.wait(closed).ok()?);
+        }
+    }
+
+    /// The front of `worker`'s own queue, or else the back of another's
+    fn take(&self, worker: usize) -> Option<T> {
+        let count = self.queues.len();
+        let own = worker % count;
+        if let Some(item) = self.queues[own].lock().ok()?.pop_front() {
+            return Some(item);
+        }
+        (1..count).find_map(|offset| self.queues[(own + offset) % count].lock().ok()?.pop_back())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn test_workers_take_their_own_work_first() {
+        let dispatcher = Dispatcher::new(2);
+        for item in 0..6 {
+            dispatcher.push(item);
+        }
+        dispatcher.close();
+
+        // Worker 0 was given 0, 2, and 4
+        assert_eq!(dispatcher.next(0), Some(0));
+        assert_eq!(dispatcher.next(0), Some(2));
+        assert_eq!(dispatcher.next(0), Some(4));
+        // Then steals from the back of worker 1's queue
+        assert_eq!(dispatcher.next(0), Some(5));
+        assert_eq!(dispatcher.next(1), Some(1));
+        assert_eq!(dispatcher.next(1), Some(3));
+        assert_eq!(dispatcher.next(1), None);
+        assert_eq!(dispatcher.next(0), None);
+    }
+
+    #[test]
+    fn test_waiting_workers_finish_every_item() {
+        let dispatcher = Dispatcher::new(4);
+        let (sender, receiver) = mpsc::channel();
+        thread::scope(|scope| {
+            for worker in 0..dispatcher.workers() {
+                let (dispatcher, sender) = (&dispatcher, sender.clone());
+                scope.spawn(move || {
+                    while let Some(item) = dispatcher.next(worker) {
+                        sender.send(item).unwrap();
+                    }
+                });
+            }
+            for item in 0..100 {
+                dispatcher.push(item);
+            }
+            dispatcher.close();
+        });
+        drop(sender);
+
+        let mut done: Vec<i32> = receiver.iter().collect();
+        done.sort();
+        assert_eq!(done, (0..100).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_clear_drops_queued_work() {
+        let dispatcher = Dispatcher::new(2);
+        dispatcher.push("a");
+        dispatcher.push("b");
+        dispatcher.push("c");
+        assert_eq!(dispatcher.clear(), 3);
+        dispatcher.close();
+        assert_eq!(dispatcher.next(0), None);
+        assert_eq!(dispatcher.next(1), None);
+    }
+}
@@ -0,0 +1,141 @@
+//! Full metadata listing for single images.
+//!
+//! Backs the `dump` subcommand: every EXIF field with its IFD, interpreted
+//! value, and stored value, plus the XMP packet when there is one. Meant for
+//! deciding what a policy should keep, not for scanning many files.
+
+use std::path::Path;
+use serde::Serialize;
+use crate::analyzer::ExifAnalyzer;
+use crate::utils;
+
+/// One metadata field as stored in the file
+#[derive(Debug, Clone, Serialize)]
+pub struct DumpedField {
+    /// IFD the field was read from
+    pub ifd: String,
+    pub tag: String,
+    pub tag_id: u16,
+    /// Value with units and enumerations resolved
+    pub value: String,
+    /// Value as stored, before interpretation
+    pub raw: String,
+}
+
+/// Everything the cleaner can read from one image's metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct MetadataDump {
+    pub path: String,
+    pub fields: Vec<DumpedField>,
+    /// Main XMP packet, if the image has one
+    pub xmp: Option<String>,
+    /// Extended XMP reassembled from its segments
+    pub xmp_extended: Option<String>,
+}
+
+/// Read every metadata field of an image file
+pub fn dump_file(path: &Path) -> Result<MetadataDump, Box<dyn std::error::Error>> {
+    let data = utils::map_file(path)?;
+    dump_data(&path.display().to_string(), &data)
+}
+
+/// Read every metadata field of an in-memory image
+pub fn dump_data(path: &str, data: &[u8]) -> Result<MetadataDump, Box<dyn std::error::Error>> {
+    let analyzer = ExifAnalyzer::new();
+
+    let fields = analyzer.get_all_exif_fields(data)?
+        .into_iter()
+        .map(|field| DumpedField {
+            ifd: field.location,
+            tag: field.tag.to_string(),
+            tag_id: field.tag.number(),
+            value: field.value,
+            raw: field.raw,
+        })
+        .collect();
+
+    let xmp = analyzer.xmp_packet(data).unwrap_or_default();
+    let to_text = |packet: Option<Vec<u8>>| packet.map(|p| String::from_utf8_lossy(&p).into_owned());
+
+    Ok(MetadataDump {
+        path: path.to_string(),
+        fields,
+        xmp: to_text(xmp.standard),
+        xmp_extended: to_text(xmp.extended),
+    })
+}
+
+impl MetadataDump {
+    /// Render the dump as aligned, human-readable text
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{}\n", self.path);
+
+        if self.fields.is_empty() {
+            text.push_str("  (no EXIF data)\n");
+        }
+        for field in &self.fields {
+            text.push_str(&format!(
+                "  {:<12} {:<32} {}\n  {:<12} {:<32} raw: {}\n",
+                field.ifd,
+                format!("{} (0x{:04X})", field.tag, field.tag_id),
+                field.value,
+                "",
+                "",
+                field.raw
+            ));
+        }
+
+        if let Some(ref xmp) = self.xmp {
+            text.push_str(&format!("  XMP packet ({} bytes):\n{}\n", xmp.len(), xmp));
+        }
+        if let Some(ref xmp) = self.xmp_extended {
+            text.push_str(&format!("  Extended XMP ({} bytes):\n{}\n", xmp.len(), xmp));
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::{self, tests::build_jpeg};
+
+    /// EXIF APP1 payload holding a single IFD0 `Make` entry
+    fn exif_with_make(make: &[u8; 6]) -> Vec<u8> {
+        let mut payload = b"Exif\0\0II*\0".to_vec();
+        payload.extend_from_slice(&8u32.to_le_bytes());
+        payload.extend_from_slice(&1u16.to_le_bytes());
+        payload.extend_from_slice(&0x010Fu16.to_le_bytes());
+        payload.extend_from_slice(&2u16.to_le_bytes());
+        payload.extend_from_slice(&6u32.to_le_bytes());
+        payload.extend_from_slice(&26u32.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+        payload.extend_from_slice(make);
+        payload
+    }
+
+    #[test]
+    fn test_dump_lists_fields_with_ifd_and_raw_value() {
+        let exif = exif_with_make(b"Canon\0");
+        let data = build_jpeg(&[(jpeg::APP1, &exif), (jpeg::APP1, b"http://ns.adobe.com/xap/1.0/\0<x:xmpmeta/>")], b"pixels");
+
+        let dump = dump_data("photo.jpg", &data).unwrap();
+
+        assert_eq!(dump.fields.len(), 1);
+        assert_eq!(dump.fields[0].ifd, "IFD0");
+        assert_eq!(dump.fields[0].tag_id, 0x010F);
+        assert_eq!(dump.fields[0].raw, "\"Canon\"");
+        assert_eq!(dump.xmp.as_deref(), Some("<x:xmpmeta/>"));
+        assert!(dump.to_text().contains("Make (0x010F)"));
+    }
+
+    #[test]
+    fn test_dump_without_exif() {
+        let data = build_jpeg(&[(0xDB, b"qtable")], b"pixels");
+        let dump = dump_data("plain.jpg", &data).unwrap();
+
+        assert!(dump.fields.is_empty());
+        assert!(dump.to_text().contains("(no EXIF data)"));
+    }
+}
@@ -0,0 +1,113 @@
+//! Audit views of the privacy levels.
+//!
+//! Backs `policy explain` and `policy diff`. Both are computed from
+//! `PrivacyPolicy`'s tag lists rather than written by hand, so they describe
+//! exactly what the analyzer reports for each level.
+
+use exif::Tag;
+use crate::i18n::tr;
+use crate::privacy::{PrivacyLevel, PrivacyPolicy};
+
+/// List every tag `level` removes and preserves, by group
+pub fn explain(level: &PrivacyLevel) -> String {
+    let mut removed = String::new();
+    let mut preserved = String::new();
+
+    for (group, tags) in PrivacyPolicy::tag_groups() {
+        let (kept, dropped): (Vec<Tag>, Vec<Tag>) = tags.into_iter()
+            .partition(|tag| PrivacyPolicy::should_preserve_tag(*tag, level));
+        push_group(&mut removed, group, &dropped);
+        push_group(&mut preserved, group, &kept);
+    }
+
+    let unlisted = format!("  {}\n", tr("explain-other-tags", &[]));
+    if PrivacyPolicy::preserves_unlisted_tags(level) {
+        preserved.push_str(&unlisted);
+    } else {
+        removed.push_str(&unlisted);
+    }
+
+    let level = format!("{:?}", level);
+    format!(
+        "{}\n{}{}\n{}",
+        tr("explain-removes", &[("level", level.as_str().into())]),
+        removed,
+        tr("explain-preserves", &[("level", level.as_str().into())]),
+        preserved
+    )
+}
+
+/// List the tags `from` and `to` handle differently
+pub fn diff(from: &PrivacyLevel, to: &PrivacyLevel) -> String {
+    let mut only_to = String::new();
+    let mut only_from = String::new();
+
+    for (group, tags) in PrivacyPolicy::tag_groups() {
+        let removed_by = |level| -> Vec<Tag> {
+            tags.iter().copied().filter(|tag| !PrivacyPolicy::should_preserve_tag(*tag, level)).collect()
+        };
+        let (by_from, by_to) = (removed_by(from), removed_by(to));
+        push_group(&mut only_to, group, &by_to.iter().copied().filter(|tag| !by_from.contains(tag)).collect::<Vec<_>>());
+        push_group(&mut only_from, group, &by_from.iter().copied().filter(|tag| !by_to.contains(tag)).collect::<Vec<_>>());
+    }
+
+    let unlisted = format!("  {}\n", tr("explain-other-tags", &[]));
+    match (PrivacyPolicy::preserves_unlisted_tags(from), PrivacyPolicy::preserves_unlisted_tags(to)) {
+        (true, false) => only_to.push_str(&unlisted),
+        (false, true) => only_from.push_str(&unlisted),
+        _ => {}
+    }
+
+    let (from, to) = (format!("{:?}", from), format!("{:?}", to));
+    if only_to.is_empty() && only_from.is_empty() {
+        return format!("{}\n", tr("explain-same", &[("from", from.as_str().into()), ("to", to.as_str().into())]));
+    }
+
+    let removed_by = |level: &str, other: &str| tr("explain-removed-by", &[("level", level.into()), ("other", other.into())]);
+    let mut text = String::new();
+    if !only_to.is_empty() {
+        text.push_str(&format!("{}\n{}", removed_by(&to, &from), only_to));
+    }
+    if !only_from.is_empty() {
+        text.push_str(&format!("{}\n{}", removed_by(&from, &to), only_from));
+    }
+    text
+}
+
+fn push_group(text: &mut String, group: &str, tags: &[Tag]) {
+    if tags.is_empty() {
+        return;
+    }
+    let names: Vec<String> = tags.iter().map(|tag| tag.to_string()).collect();
+    text.push_str(&format!("  {}: {}\n", group, names.join(", ")));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_explain_lists_removed_and_preserved_tags() {
+        let text = explain(&PrivacyLevel::Minimal);
+        let (removes, preserves) = text.split_once("Minimal preserves:").unwrap();
+
+        assert!(removes.contains("GPSLatitude"));
+        assert!(preserves.contains("DateTimeOriginal"));
+        assert!(preserves.contains("Any other tag"));
+    }
+
+    #[test]
+    fn test_diff_lists_only_differences() {
+        let text = diff(&PrivacyLevel::Minimal, &PrivacyLevel::Strict);
+
+        assert!(text.contains("Removed by Strict but not Minimal"));
+        assert!(text.contains("Artist"));
+        assert!(!text.contains("GPSLatitude"));
+        assert!(!text.contains("Removed by Minimal"));
+    }
+
+    #[test]
+    fn test_diff_of_same_level() {
+        assert_eq!(diff(&PrivacyLevel::Strict, &PrivacyLevel::Strict), "Strict and Strict remove the same tags\n");
+    }
+}
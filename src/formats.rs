@@ -0,0 +1,426 @@
+//! Registry of the file formats the cleaner handles.
+//!
+//! Files on disk are identified by content (`detect_image_format`),
+//! but archive entries and object keys only have a name. The registry maps
+//! their extensions to a format and what can be done with it, and new
+//! formats can be added at runtime without touching the built-in list.
+//!
+//! Formats the built-in analyzer and remover don't understand (proprietary
+//! RAW, medical imaging, ...) are added as a `FormatHandler`: once registered,
+//! files it detects are analyzed, cleaned, and verified by it instead.
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+use crate::analyzer::PrivacyField;
+use crate::bmp;
+use crate::privacy::PrivacyLevel;
+use crate::utils;
+
+/// Bytes read to identify a format: enough for a BMP's file header and DIB header size
+const MAGIC_LEN: u64 = 18;
+
+/// Image formats the cleaner can process, identified by content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ImageFormat {
+    Jpeg,
+    Tiff,
+    Png,
+    Gif,
+    Bmp,
+    Jxl,
+}
+
+impl std::fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ImageFormat::Jpeg => write!(f, "JPEG"),
+            ImageFormat::Tiff => write!(f, "TIFF"),
+            ImageFormat::Png => write!(f, "PNG"),
+            ImageFormat::Gif => write!(f, "GIF"),
+            ImageFormat::Bmp => write!(f, "BMP"),
+            ImageFormat::Jxl => write!(f, "JPEG XL"),
+        }
+    }
+}
+
+impl ImageFormat {
+    /// Every built-in format
+    pub const ALL: &'static [ImageFormat] = &[
+        ImageFormat::Jpeg,
+        ImageFormat::Tiff,
+        ImageFormat::Png,
+        ImageFormat::Gif,
+        ImageFormat::Bmp,
+        ImageFormat::Jxl,
+    ];
+
+    /// File extensions conventionally used for the format
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            ImageFormat::Jpeg => &["jpg", "jpeg", "mpo"],
+            ImageFormat::Tiff => &["tif", "tiff"],
+            ImageFormat::Png => &["png"],
+            ImageFormat::Gif => &["gif"],
+            ImageFormat::Bmp => &["bmp", "dib"],
+            ImageFormat::Jxl => &["jxl"],
+        }
+    }
+}
+
+/// Identify an image format from its leading magic bytes
+pub fn sniff_image_format(data: &[u8]) -> Option<ImageFormat> {
+    match data {
+        [0xFF, 0xD8, 0xFF, ..] => Some(ImageFormat::Jpeg),
+        [b'I', b'I', 0x2A, 0x00, ..] | [b'M', b'M', 0x00, 0x2A, ..] => Some(ImageFormat::Tiff),
+        [0x89, b'P', b'N', b'G', ..] => Some(ImageFormat::Png),
+        [b'G', b'I', b'F', b'8', ..] => Some(ImageFormat::Gif),
+        [0xFF, 0x0A, ..] | [0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', ..] => Some(ImageFormat::Jxl),
+        // "BM" alone is too common a start for text, so the header size is checked too
+        [b'B', b'M', ..] if bmp::is_bmp(data) => Some(ImageFormat::Bmp),
+        _ => None,
+    }
+}
+
+/// Identify the format of an image file from its content, whatever its extension
+pub fn detect_image_format(path: &Path) -> Option<ImageFormat> {
+    use std::io::Read;
+
+    let mut magic = Vec::new();
+    std::fs::File::open(utils::long_path(path)).ok()?.take(MAGIC_LEN).read_to_end(&mut magic).ok()?;
+    sniff_image_format(&magic)
+}
+
+/// Check whether a file's extension is one conventionally used for its format
+pub fn has_matching_extension(path: &Path, format: ImageFormat) -> bool {
+    utils::get_file_extension(path).map_or(false, |ext| format.extensions().contains(&ext.as_str()))
+}
+
+/// Check if a file can be cleaned, by its extension (see `FormatRegistry`)
+///
+/// Only for names without local content (archive entries, object keys); for
+/// files on disk use `detect_image_format`, which looks at the content.
+pub fn is_supported_image(path: &Path) -> bool {
+    capabilities(path).clean
+}
+
+/// Check if a path points to a PDF (by extension)
+pub fn is_pdf(path: &Path) -> bool {
+    matches!(utils::get_file_extension(path).as_deref(), Some("pdf"))
+}
+
+/// What the cleaner can do with a format
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Metadata can be read and reported
+    pub analyze: bool,
+    /// Metadata can be removed
+    pub clean: bool,
+    /// Cleaned output can be re-analyzed to confirm nothing is left
+    pub verify: bool,
+}
+
+impl Capabilities {
+    /// Analyze, clean, and verify
+    pub const FULL: Capabilities = Capabilities { analyze: true, clean: true, verify: true };
+    /// Report only; files are never modified
+    pub const ANALYZE_ONLY: Capabilities = Capabilities { analyze: true, clean: false, verify: false };
+}
+
+/// Support for a format outside the built-in ones
+///
+/// Handlers see the whole file and return the complete cleaned file. They
+/// are tried newest first, before the built-in formats, so a handler can also
+/// take over a format the cleaner already knows.
+pub trait FormatHandler: Send + Sync {
+    /// Name shown in logs and errors
+    fn name(&self) -> &str;
+
+    /// Lowercase extensions, without the dot, for names without content (archive entries, object keys)
+    fn extensions(&self) -> &[&str];
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::FULL
+    }
+
+    /// Whether `data` is in this handler's format
+    fn detect(&self, data: &[u8]) -> bool;
+
+    /// The privacy-sensitive fields `level` would remove
+    ///
+    /// Return an `analyzer::UnparseableMetadata` error for metadata that can't
+    /// be read, so it is never treated as clean.
+    fn analyze(&self, data: &[u8], level: &PrivacyLevel) -> Result<Vec<PrivacyField>, Box<dyn std::error::Error>>;
+
+    /// The file with the fields `level` covers removed
+    fn clean(&self, data: &[u8], level: &PrivacyLevel) -> Result<Vec<u8>, Box<dyn std::error::Error>>;
+
+    /// The file with all metadata removed, for `--force-strip`
+    fn strip(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.clean(data, &PrivacyLevel::Paranoid)
+    }
+
+    /// Fail if anything `level` covers survived cleaning
+    fn verify(&self, cleaned: &[u8], level: &PrivacyLevel) -> Result<(), Box<dyn std::error::Error>> {
+        let remaining = self.analyze(cleaned, level)?;
+        if !remaining.is_empty() {
+            return Err(format!("{} privacy-sensitive fields remain after cleaning", remaining.len()).into());
+        }
+        Ok(())
+    }
+}
+
+/// A format known by extension
+#[derive(Debug, Clone, PartialEq)]
+pub struct FormatEntry {
+    pub name: String,
+    /// Lowercase extensions, without the dot
+    pub extensions: Vec<String>,
+    pub capabilities: Capabilities,
+}
+
+impl FormatEntry {
+    pub fn new(name: &str, extensions: &[&str], capabilities: Capabilities) -> Self {
+        Self {
+            name: name.to_string(),
+            extensions: extensions.iter().map(|ext| ext.to_lowercase()).collect(),
+            capabilities,
+        }
+    }
+}
+
+/// Formats by extension and handlers by content; later registrations take precedence
+#[derive(Clone)]
+pub struct FormatRegistry {
+    entries: Vec<FormatEntry>,
+    handlers: Vec<Arc<dyn FormatHandler>>,
+}
+
+impl Default for FormatRegistry {
+    /// The built-in image formats, all fully supported
+    fn default() -> Self {
+        let entries = ImageFormat::ALL.iter()
+            .map(|format| FormatEntry::new(&format.to_string(), format.extensions(), Capabilities::FULL))
+            .collect();
+        Self { entries, handlers: Vec::new() }
+    }
+}
+
+impl FormatRegistry {
+    /// A registry with no formats at all
+    pub fn empty() -> Self {
+        Self { entries: Vec::new(), handlers: Vec::new() }
+    }
+
+    /// Add a format, overriding earlier entries for the same extensions
+    pub fn register(&mut self, entry: FormatEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Add a handler, along with an entry for its extensions
+    pub fn register_handler(&mut self, handler: Arc<dyn FormatHandler>) {
+        self.register(FormatEntry::new(handler.name(), handler.extensions(), handler.capabilities()));
+        self.handlers.push(handler);
+    }
+
+    /// The entry for a path's extension, if any
+    pub fn lookup(&self, path: &Path) -> Option<&FormatEntry> {
+        let ext = utils::get_file_extension(path)?;
+        self.entries.iter().rev().find(|entry| entry.extensions.contains(&ext))
+    }
+
+    /// What can be done with a path, by its extension; nothing for unknown extensions
+    pub fn capabilities(&self, path: &Path) -> Capabilities {
+        self.lookup(path).map(|entry| entry.capabilities).unwrap_or_default()
+    }
+
+    /// Whether files with this path's extension can be cleaned
+    pub fn is_supported(&self, path: &Path) -> bool {
+        self.capabilities(path).clean
+    }
+
+    /// The newest handler that detects `data`
+    pub fn handler_for(&self, data: &[u8]) -> Option<Arc<dyn FormatHandler>> {
+        self.handlers.iter().rev().find(|handler| handler.detect(data)).cloned()
+    }
+
+    pub fn entries(&self) -> &[FormatEntry] {
+        &self.entries
+    }
+}
+
+fn global() -> &'static RwLock<FormatRegistry> {
+    static REGISTRY: OnceLock<RwLock<FormatRegistry>> = OnceLock::new();
+    REGISTRY.get_or_init(|| RwLock::new(FormatRegistry::default()))
+}
+
+/// Register a format with the process-wide registry
+pub fn register(entry: FormatEntry) {
+    if let Ok(mut registry) = global().write() {
+        registry.register(entry);
+    }
+}
+
+/// Register a handler with the process-wide registry
+pub fn register_handler(handler: Arc<dyn FormatHandler>) {
+    if let Ok(mut registry) = global().write() {
+        registry.register_handler(handler);
+    }
+}
+
+/// What the process-wide registry says can be done with a path
+pub fn capabilities(path: &Path) -> Capabilities {
+    global().read().map(|registry| registry.capabilities(path)).unwrap_or_default()
+}
+
+/// The registered handler that detects `data`, if any
+pub fn handler_for(data: &[u8]) -> Option<Arc<dyn FormatHandler>> {
+    global().read().ok()?.handler_for(data)
+}
+
+/// The registered handler that detects a file's content, if any
+///
+/// The file is only opened when handlers are registered.
+pub fn handler_for_file(path: &Path) -> Option<Arc<dyn FormatHandler>> {
+    if global().read().ok()?.handlers.is_empty() {
+        return None;
+    }
+    handler_for(&utils::map_file(path).ok()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::Tag;
+    use crate::analyzer::PrivacyCategory;
+    use tempfile::TempDir;
+
+    /// A made-up format: `RAWX`, then `name=value` lines, of which `owner` is private
+    struct RawxHandler;
+
+    impl FormatHandler for RawxHandler {
+        fn name(&self) -> &str {
+            "RAWX"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["rawx"]
+        }
+
+        fn detect(&self, data: &[u8]) -> bool {
+            data.starts_with(b"RAWX")
+        }
+
+        fn analyze(&self, data: &[u8], _level: &PrivacyLevel) -> Result<Vec<PrivacyField>, Box<dyn std::error::Error>> {
+            Ok(String::from_utf8_lossy(data).lines()
+                .filter_map(|line| line.strip_prefix("owner="))
+                .map(|owner| PrivacyField {
+                    tag: Tag::Artist,
+                    description: format!("Owner: {}", owner),
+                    category: PrivacyCategory::PersonalInfo,
+                    ifd: None,
+                    value: None,
+                })
+                .collect())
+        }
+
+        fn clean(&self, data: &[u8], _level: &PrivacyLevel) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+            Ok(String::from_utf8_lossy(data).lines()
+                .filter(|line| !line.starts_with("owner="))
+                .collect::<Vec<_>>()
+                .join("\n")
+                .into_bytes())
+        }
+    }
+
+    #[test]
+    fn test_builtin_formats() {
+        let registry = FormatRegistry::default();
+
+        assert_eq!(registry.lookup(Path::new("photo.JPG")).unwrap().name, "JPEG");
+        assert_eq!(registry.capabilities(Path::new("scan.tif")), Capabilities::FULL);
+        assert!(registry.is_supported(Path::new("image.jxl")));
+        assert!(!registry.is_supported(Path::new("clip.mov")));
+        assert!(!registry.is_supported(Path::new("README")));
+    }
+
+    #[test]
+    fn test_registered_entry_takes_precedence() {
+        let mut registry = FormatRegistry::default();
+        registry.register(FormatEntry::new("HEIF", &["HEIC", "heif"], Capabilities::ANALYZE_ONLY));
+        registry.register(FormatEntry::new("Camera RAW", &["dng", "tif"], Capabilities::ANALYZE_ONLY));
+
+        assert_eq!(registry.capabilities(Path::new("IMG_0001.heic")), Capabilities::ANALYZE_ONLY);
+        assert!(!registry.is_supported(Path::new("IMG_0001.heic")));
+        assert_eq!(registry.lookup(Path::new("scan.tif")).unwrap().name, "Camera RAW");
+        assert!(!FormatRegistry::empty().is_supported(Path::new("photo.jpg")));
+    }
+
+    #[test]
+    fn test_handler_detects_analyzes_and_cleans() {
+        let mut registry = FormatRegistry::default();
+        registry.register_handler(Arc::new(RawxHandler));
+        let data = b"RAWX\nowner=Jane Doe\niso=200";
+
+        assert!(registry.is_supported(Path::new("frame.RAWX")));
+        assert!(registry.handler_for(b"\xFF\xD8\xFF").is_none());
+        let handler = registry.handler_for(data).unwrap();
+        assert_eq!(handler.analyze(data, &PrivacyLevel::Standard).unwrap()[0].description, "Owner: Jane Doe");
+
+        let cleaned = handler.clean(data, &PrivacyLevel::Standard).unwrap();
+        assert_eq!(cleaned, b"RAWX\niso=200");
+        assert!(handler.verify(&cleaned, &PrivacyLevel::Standard).is_ok());
+        assert!(handler.verify(data, &PrivacyLevel::Standard).is_err());
+    }
+
+    #[test]
+    fn test_is_supported_image() {
+        assert!(is_supported_image(Path::new("test.jpg")));
+        assert!(is_supported_image(Path::new("test.jpeg")));
+        assert!(is_supported_image(Path::new("test.JPEG")));
+        assert!(is_supported_image(Path::new("test.tiff")));
+        assert!(is_supported_image(Path::new("photo.TIF")));
+        assert!(is_supported_image(Path::new("test.png")));
+        assert!(is_supported_image(Path::new("test.gif")));
+        
+        assert!(!is_supported_image(Path::new("test.heic")));
+        assert!(!is_supported_image(Path::new("test.txt")));
+        assert!(!is_supported_image(Path::new("test")));
+    }
+
+    #[test]
+    fn test_is_pdf() {
+        assert!(is_pdf(Path::new("ALBUM.PDF")));
+        assert!(!is_pdf(Path::new("album.pdf.zip")));
+    }
+
+    #[test]
+    fn test_sniff_image_format() {
+        assert_eq!(sniff_image_format(&[0xFF, 0xD8, 0xFF, 0xE1, 0x00]), Some(ImageFormat::Jpeg));
+        assert_eq!(sniff_image_format(b"II*\0\x08\0\0\0"), Some(ImageFormat::Tiff));
+        assert_eq!(sniff_image_format(b"MM\0*\0\0\0\x08"), Some(ImageFormat::Tiff));
+        assert_eq!(sniff_image_format(b"\x89PNG\r\n\x1a\n"), Some(ImageFormat::Png));
+        assert_eq!(sniff_image_format(b"GIF89a\x01\0"), Some(ImageFormat::Gif));
+        assert_eq!(sniff_image_format(b"\0\0\0\x0CJXL \r\n\x87\n"), Some(ImageFormat::Jxl));
+        assert_eq!(sniff_image_format(b"BM\x3a\0\0\0\0\0\0\0\x36\0\0\0\x28\0\0\0"), Some(ImageFormat::Bmp));
+        assert_eq!(sniff_image_format(b"BMW 320i service notes"), None);
+        assert_eq!(sniff_image_format(&[0xFF, 0xD8]), None);
+    }
+
+    #[test]
+    fn test_detect_image_format_ignores_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let disguised = temp_dir.path().join("photo.jpeg.txt");
+        let mislabeled = temp_dir.path().join("scan.jpg");
+        let not_image = temp_dir.path().join("notes.jpg");
+        std::fs::write(&disguised, [0xFF, 0xD8, 0xFF, 0xE0, 0x00]).unwrap();
+        std::fs::write(&mislabeled, b"II*\0\x08\0\0\0").unwrap();
+        std::fs::write(&not_image, b"just text").unwrap();
+
+        assert_eq!(detect_image_format(&disguised), Some(ImageFormat::Jpeg));
+        assert_eq!(detect_image_format(&mislabeled), Some(ImageFormat::Tiff));
+        assert_eq!(detect_image_format(&not_image), None);
+
+        assert!(!has_matching_extension(&mislabeled, ImageFormat::Tiff));
+        assert!(has_matching_extension(Path::new("a.TIF"), ImageFormat::Tiff));
+    }
+}
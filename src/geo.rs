@@ -0,0 +1,278 @@
+//! Offline geo-disclosure summaries.
+//!
+//! Turns GPS coordinates into a coarse, human-readable place ("Berlin,
+//! Germany") using a small bundled gazetteer, so users can see what a photo
+//! actually reveals. No network calls are ever made.
+
+use std::sync::OnceLock;
+use exif::{Exif, In, Tag, Value};
+use crate::analyzer::PrivacyField;
+
+/// Places closer than this are reported as being "in" the place
+const IN_PLACE_RADIUS_KM: f64 = 40.0;
+/// Places closer than this are reported as being "near" the place
+const NEAR_PLACE_RADIUS_KM: f64 = 300.0;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+static PLACES_CSV: &str = include_str!("data/places.csv");
+
+/// Decimal-degree coordinates decoded from EXIF GPS tags
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub altitude: Option<f64>,
+}
+
+impl Coordinates {
+    /// Great-circle distance to another point in kilometres
+    pub fn distance_km(&self, latitude: f64, longitude: f64) -> f64 {
+        let (lat1, lat2) = (self.latitude.to_radians(), latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+/// A gazetteer entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Place {
+    pub name: String,
+    pub country: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// What a photo's GPS data reveals, in human terms
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeoDisclosure {
+    /// Within a city's immediate area
+    In(Place),
+    /// Within a few hundred kilometres of a listed city
+    Near { place: Place, distance_km: f64 },
+    /// Far from anything in the gazetteer
+    Remote(Coordinates),
+}
+
+impl std::fmt::Display for GeoDisclosure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GeoDisclosure::In(place) => write!(f, "in {}, {}", place.name, place.country),
+            GeoDisclosure::Near { place, distance_km } => {
+                write!(f, "about {:.0} km from {}, {}", distance_km, place.name, place.country)
+            }
+            GeoDisclosure::Remote(coords) => {
+                write!(f, "at {:.3}, {:.3} (no nearby city in offline data)", coords.latitude, coords.longitude)
+            }
+        }
+    }
+}
+
+/// Read GPS coordinates from parsed EXIF data
+///
+/// `None` without the hemisphere references, as for `coordinates_from_fields`.
+pub fn coordinates_from_exif(exif: &Exif) -> Option<Coordinates> {
+    let latitude = read_dms(exif, Tag::GPSLatitude)? * hemisphere(exif, Tag::GPSLatitudeRef, b'N', b'S')?;
+    let longitude = read_dms(exif, Tag::GPSLongitude)? * hemisphere(exif, Tag::GPSLongitudeRef, b'E', b'W')?;
+
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return None;
+    }
+
+    let altitude = exif.get_field(Tag::GPSAltitude, In::PRIMARY)
+        .and_then(|field| match field.value {
+            Value::Rational(ref values) => values.first().map(|r| r.to_f64()),
+            _ => None,
+        })
+        .map(|altitude| {
+            // GPSAltitudeRef 1 means below sea level
+            let below = exif.get_field(Tag::GPSAltitudeRef, In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+                == Some(1);
+            if below { -altitude } else { altitude }
+        });
+
+    Some(Coordinates { latitude, longitude, altitude })
+}
+
+/// Read GPS coordinates from analysis findings, e.g. to place a batch's photos on a map
+///
+/// `None` unless the findings include the latitude and longitude and their
+/// hemispheres: without the reference, the sign is a guess.
+pub fn coordinates_from_fields(fields: &[PrivacyField]) -> Option<Coordinates> {
+    let find = |tag: Tag| fields.iter().find(|field| field.tag == tag);
+    let hemisphere = |tag: Tag, positive: u8, negative: u8| {
+        let letter = find(tag).and_then(|field| match field.value {
+            Some(Value::Ascii(ref values)) => values.first().and_then(|v| v.first()).copied(),
+            _ => None,
+        })?;
+        match letter.to_ascii_uppercase() {
+            c if c == positive => Some(1.0),
+            c if c == negative => Some(-1.0),
+            _ => None,
+        }
+    };
+
+    let latitude = find(Tag::GPSLatitude)?.decimal_degrees()? * hemisphere(Tag::GPSLatitudeRef, b'N', b'S')?;
+    let longitude = find(Tag::GPSLongitude)?.decimal_degrees()? * hemisphere(Tag::GPSLongitudeRef, b'E', b'W')?;
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return None;
+    }
+
+    let altitude = find(Tag::GPSAltitude).and_then(PrivacyField::as_f64).map(|altitude| {
+        let below = find(Tag::GPSAltitudeRef).and_then(PrivacyField::as_f64) == Some(1.0);
+        if below { -altitude } else { altitude }
+    });
+
+    Some(Coordinates { latitude, longitude, altitude })
+}
+
+/// Describe where coordinates are using the bundled gazetteer
+pub fn describe_location(coords: &Coordinates) -> GeoDisclosure {
+    let nearest = places().iter()
+        .map(|place| (place, coords.distance_km(place.latitude, place.longitude)))
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    match nearest {
+        Some((place, distance)) if distance <= IN_PLACE_RADIUS_KM => GeoDisclosure::In(place.clone()),
+        Some((place, distance)) if distance <= NEAR_PLACE_RADIUS_KM => GeoDisclosure::Near {
+            place: place.clone(),
+            distance_km: distance,
+        },
+        _ => GeoDisclosure::Remote(*coords),
+    }
+}
+
+/// Read a degrees/minutes/seconds tag as decimal degrees
+fn read_dms(exif: &Exif, tag: Tag) -> Option<f64> {
+    dms_to_degrees(&exif.get_field(tag, In::PRIMARY)?.value)
+}
+
+/// Convert degrees/minutes/seconds rationals to decimal degrees
+pub fn dms_to_degrees(value: &Value) -> Option<f64> {
+    match value {
+        Value::Rational(values) if !values.is_empty() => {
+            let component = |i: usize| values.get(i).map(|r| r.to_f64()).unwrap_or(0.0);
+            let degrees = component(0) + component(1) / 60.0 + component(2) / 3600.0;
+            degrees.is_finite().then_some(degrees)
+        }
+        _ => None,
+    }
+}
+
+/// 1.0 or -1.0 for the hemisphere letter in a reference tag; `None` when it is missing or neither
+fn hemisphere(exif: &Exif, tag: Tag, positive: u8, negative: u8) -> Option<f64> {
+    let letter = exif.get_field(tag, In::PRIMARY).and_then(|field| match field.value {
+        Value::Ascii(ref values) => values.first().and_then(|v| v.first()).copied(),
+        _ => None,
+    })?;
+    match letter.to_ascii_uppercase() {
+        c if c == positive => Some(1.0),
+        c if c == negative => Some(-1.0),
+        _ => None,
+    }
+}
+
+fn places() -> &'static [Place] {
+    static PLACES: OnceLock<Vec<Place>> = OnceLock::new();
+    PLACES.get_or_init(|| parse_places(PLACES_CSV))
+}
+
+fn parse_places(csv: &str) -> Vec<Place> {
+    csv.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split(',');
+            Some(Place {
+                name: parts.next()?.to_string(),
+                country: parts.next()?.to_string(),
+                latitude: parts.next()?.parse().ok()?,
+                longitude: parts.next()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn coords(latitude: f64, longitude: f64) -> Coordinates {
+        Coordinates { latitude, longitude, altitude: None }
+    }
+
+    #[test]
+    fn test_bundled_places_parse() {
+        assert!(places().len() > 100);
+        assert!(places().iter().any(|p| p.name == "Berlin" && p.country == "Germany"));
+    }
+
+    #[test]
+    fn test_describe_location_in_city() {
+        // Brandenburg Gate
+        let disclosure = describe_location(&coords(52.5163, 13.3777));
+        assert_eq!(disclosure.to_string(), "in Berlin, Germany");
+    }
+
+    #[test]
+    fn test_describe_location_near_city() {
+        // Leipzig is ~150 km from Berlin and not in the gazetteer
+        match describe_location(&coords(51.340, 12.375)) {
+            GeoDisclosure::Near { place, distance_km } => {
+                assert!(distance_km > IN_PLACE_RADIUS_KM);
+                assert_eq!(place.country, "Germany");
+            }
+            other => panic!("Expected Near, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_describe_location_remote() {
+        // Middle of the South Pacific
+        assert!(matches!(describe_location(&coords(-40.0, -130.0)), GeoDisclosure::Remote(_)));
+    }
+
+    #[test]
+    fn test_distance_km() {
+        // Paris to London is roughly 344 km
+        let paris = coords(48.857, 2.352);
+        let distance = paris.distance_km(51.507, -0.128);
+        assert!((distance - 344.0).abs() < 5.0, "distance was {}", distance);
+    }
+
+    #[test]
+    fn test_coordinates_from_fields() {
+        use crate::analyzer::PrivacyCategory;
+        let field = |tag, value| PrivacyField {
+            tag,
+            description: String::new(),
+            category: PrivacyCategory::Location,
+            ifd: None,
+            value: Some(value),
+        };
+        let degrees = |d| Value::Rational(vec![exif::Rational { num: d, denom: 1 }]);
+        let fields = vec![
+            field(Tag::GPSLatitudeRef, Value::Ascii(vec![b"S".to_vec()])),
+            field(Tag::GPSLatitude, degrees(33)),
+            field(Tag::GPSLongitude, degrees(151)),
+            field(Tag::GPSAltitude, degrees(12)),
+            field(Tag::GPSLongitudeRef, Value::Ascii(vec![b"E".to_vec()])),
+        ];
+
+        let found = coordinates_from_fields(&fields).unwrap();
+        assert_eq!(found, Coordinates { latitude: -33.0, longitude: 151.0, altitude: Some(12.0) });
+        assert_eq!(coordinates_from_fields(&fields[..2]), None);
+        // Without a hemisphere the sign is unknown
+        assert_eq!(coordinates_from_fields(&fields[..4]), None);
+    }
+
+    #[test]
+    fn test_parse_places_skips_bad_lines() {
+        let parsed = parse_places("# comment\nA,B,1.0,2.0\nbroken line\nC,D,x,3\n");
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].name, "A");
+    }
+}
@@ -0,0 +1,141 @@
+//! Minimal GIF block parser.
+//!
+//! GIFs carry little metadata: comment extensions, and XMP in an application
+//! extension. Blocks are walked up to the trailer; image data is skipped
+//! without decoding.
+
+/// Identifier and authentication code of the XMP application extension
+const XMP_APPLICATION: &[u8] = b"XMP DataXMP";
+/// Length of the "magic trailer" after raw XMP: 0x01, 0xFF down to 0x00, then the terminator
+const XMP_MAGIC_TRAILER_LEN: usize = 258;
+/// Introduces an extension block
+const EXTENSION: u8 = 0x21;
+/// Introduces an image
+const IMAGE_DESCRIPTOR: u8 = 0x2C;
+/// Ends the file
+const TRAILER: u8 = 0x3B;
+const COMMENT_LABEL: u8 = 0xFE;
+const APPLICATION_LABEL: u8 = 0xFF;
+
+/// Metadata found in a GIF
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GifMetadata {
+    pub comments: Vec<String>,
+    pub xmp: Option<Vec<u8>>,
+}
+
+/// Check for the GIF87a or GIF89a signature
+pub fn is_gif(data: &[u8]) -> bool {
+    data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a")
+}
+
+/// Walk a GIF's blocks and collect its comments and XMP
+pub fn parse(data: &[u8]) -> Result<GifMetadata, Box<dyn std::error::Error>> {
+    if !is_gif(data) {
+        return Err("Not a GIF file (missing signature)".into());
+    }
+
+    // Header and logical screen descriptor, then the global color table
+    let flags = *data.get(10).ok_or("Unexpected end of GIF data")?;
+    let mut pos = 13 + color_table_len(flags);
+    let mut metadata = GifMetadata::default();
+
+    loop {
+        match *data.get(pos).ok_or("GIF ends before its trailer")? {
+            EXTENSION => {
+                let label = *data.get(pos + 1).ok_or("Unexpected end of GIF data")?;
+                let body = pos + 2;
+                let xmp_start = body + 1 + XMP_APPLICATION.len();
+                if label == APPLICATION_LABEL && data.get(body + 1..xmp_start) == Some(XMP_APPLICATION) {
+                    // XMP is stored raw rather than in sub-blocks, followed by the magic trailer
+                    if let Some(packet) = xmp_packet(&data[xmp_start..]) {
+                        let end = xmp_start + packet.len();
+                        if data.get(end..end + 3) == Some(&[0x01, 0xFF, 0xFE][..]) {
+                            metadata.xmp = Some(packet);
+                            pos = end + XMP_MAGIC_TRAILER_LEN;
+                            continue;
+                        }
+                    }
+                }
+
+                let (end, content) = sub_blocks(data, body)?;
+                if label == COMMENT_LABEL {
+                    metadata.comments.push(String::from_utf8_lossy(&content).into_owned());
+                }
+                pos = end;
+            }
+            IMAGE_DESCRIPTOR => {
+                let flags = *data.get(pos + 9).ok_or("Unexpected end of GIF data")?;
+                // Descriptor, local color table, and LZW code size, then the image data
+                pos += 10 + color_table_len(flags) + 1;
+                pos = sub_blocks(data, pos)?.0;
+            }
+            TRAILER => return Ok(metadata),
+            other => return Err(format!("Unexpected GIF block 0x{:02X} at offset {}", other, pos).into()),
+        }
+    }
+}
+
+fn color_table_len(flags: u8) -> usize {
+    if flags & 0x80 != 0 { 3 << ((flags & 0x07) + 1) } else { 0 }
+}
+
+/// Read length-prefixed sub-blocks from `pos`, returning the offset after the terminator and their data
+fn sub_blocks(data: &[u8], mut pos: usize) -> Result<(usize, Vec<u8>), Box<dyn std::error::Error>> {
+    let mut content = Vec::new();
+    loop {
+        let len = *data.get(pos).ok_or("GIF sub-block runs past the end of the file")? as usize;
+        pos += 1;
+        if len == 0 {
+            return Ok((pos, content));
+        }
+        content.extend_from_slice(data.get(pos..pos + len).ok_or("GIF sub-block runs past the end of the file")?);
+        pos += len;
+    }
+}
+
+/// The XMP packet at the start of the raw application data, without the magic trailer
+fn xmp_packet(raw: &[u8]) -> Option<Vec<u8>> {
+    let end_marker = b"<?xpacket end=";
+    let start = raw.windows(end_marker.len()).position(|window| window == end_marker)?;
+    let close = raw[start..].windows(2).position(|window| window == b"?>")?;
+    Some(raw[..start + close + 2].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1x1 GIF with the given blocks inserted before the image
+    fn build_gif(blocks: &[u8]) -> Vec<u8> {
+        let mut data = b"GIF89a\x01\x00\x01\x00\x80\x00\x00".to_vec();
+        data.extend_from_slice(&[0, 0, 0, 255, 255, 255]);
+        data.extend_from_slice(blocks);
+        data.extend_from_slice(b"\x2C\0\0\0\0\x01\0\x01\0\0\x02\x02\x44\x01\0");
+        data.push(TRAILER);
+        data
+    }
+
+    #[test]
+    fn test_comments_and_xmp() {
+        let mut blocks = b"\x21\xFE\x05hello\x06 world\0".to_vec();
+        blocks.extend_from_slice(b"\x21\xFF\x0BXMP DataXMP");
+        blocks.extend_from_slice(br#"<?xpacket begin=""?><x:xmpmeta/><?xpacket end="w"?>"#);
+        // Magic trailer: bytes 0x01, 0xFF down to 0x00, then the terminator
+        blocks.push(0x01);
+        blocks.extend((0..=0xFFu8).rev());
+        blocks.push(0x00);
+
+        let metadata = parse(&build_gif(&blocks)).unwrap();
+        assert_eq!(metadata.comments, vec!["hello world".to_string()]);
+        assert_eq!(metadata.xmp.unwrap(), br#"<?xpacket begin=""?><x:xmpmeta/><?xpacket end="w"?>"#.to_vec());
+    }
+
+    #[test]
+    fn test_truncated_gif_is_an_error() {
+        let mut data = build_gif(b"\x21\xFE\x05hello\0");
+        data.truncate(20);
+        assert!(parse(&data).is_err());
+        assert_eq!(parse(&build_gif(&[])).unwrap(), GifMetadata::default());
+    }
+}
@@ -0,0 +1,82 @@
+//! GPS position decoding for library consumers.
+//!
+//! EXIF stores latitude and longitude as degree/minute/second rationals, with
+//! the hemisphere in separate reference tags. Archiving tools that want to
+//! record where a photo was taken before cleaning it can read the position
+//! here instead of reparsing the display strings of analysis findings.
+
+use std::path::Path;
+use crate::analyzer::ExifAnalyzer;
+use crate::geo;
+
+/// An image's GPS position as decimal latitude, longitude, and altitude in metres
+///
+/// Southern latitudes, western longitudes, and altitudes below sea level are
+/// negative. `None` when the image has no valid position or its metadata can't
+/// be parsed.
+pub fn extract_coordinates(data: &[u8]) -> Option<(f64, f64, Option<f64>)> {
+    let exif = ExifAnalyzer::new().read_exif(data).ok()??;
+    let coords = geo::coordinates_from_exif(&exif)?;
+    Some((coords.latitude, coords.longitude, coords.altitude))
+}
+
+/// `extract_coordinates` for an image file, reading only its metadata
+pub fn extract_coordinates_from_file(path: &Path) -> Option<(f64, f64, Option<f64>)> {
+    let exif = ExifAnalyzer::new().parse_exif_file(path).ok()??;
+    let coords = geo::coordinates_from_exif(&exif)?;
+    Some((coords.latitude, coords.longitude, coords.altitude))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::{self, tests::build_jpeg};
+
+    /// Little-endian EXIF whose GPS IFD holds 48°30'36" N, 2°15'0" W
+    fn exif_with_position() -> Vec<u8> {
+        let entry = |payload: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: [u8; 4]| {
+            payload.extend_from_slice(&tag.to_le_bytes());
+            payload.extend_from_slice(&kind.to_le_bytes());
+            payload.extend_from_slice(&count.to_le_bytes());
+            payload.extend_from_slice(&value);
+        };
+
+        let mut payload = b"Exif\0\0II*\0".to_vec();
+        payload.extend_from_slice(&8u32.to_le_bytes());
+        payload.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut payload, 0x8825, 4, 1, 26u32.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+
+        payload.extend_from_slice(&4u16.to_le_bytes());
+        entry(&mut payload, 0x0001, 2, 2, *b"N\0\0\0");
+        entry(&mut payload, 0x0002, 5, 3, 80u32.to_le_bytes());
+        entry(&mut payload, 0x0003, 2, 2, *b"W\0\0\0");
+        entry(&mut payload, 0x0004, 5, 3, 104u32.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+
+        for value in [48u32, 30, 36, 2, 15, 0] {
+            payload.extend_from_slice(&value.to_le_bytes());
+            payload.extend_from_slice(&1u32.to_le_bytes());
+        }
+        payload
+    }
+
+    #[test]
+    fn test_extract_coordinates() {
+        let data = build_jpeg(&[(jpeg::APP1, &exif_with_position())], b"pixels");
+
+        let (latitude, longitude, altitude) = extract_coordinates(&data).unwrap();
+        assert!((latitude - 48.51).abs() < 1e-9);
+        assert!((longitude + 2.25).abs() < 1e-9);
+        assert_eq!(altitude, None);
+
+        let without_gps = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        assert_eq!(extract_coordinates(&without_gps), None);
+
+        // Without a valid hemisphere the sign would be a guess
+        let mut unreferenced = exif_with_position();
+        let at = unreferenced.windows(4).position(|window| window == b"W\0\0\0").unwrap();
+        unreferenced[at] = b'?';
+        assert_eq!(extract_coordinates(&build_jpeg(&[(jpeg::APP1, &unreferenced)], b"pixels")), None);
+    }
+}
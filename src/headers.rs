@@ -0,0 +1,456 @@
+//! Header-only metadata reads.
+//!
+//! Pulls the EXIF block out of an image by seeking through its structure on
+//! disk, never touching pixel data. For JPEG this is the APP1 segment; for
+//! TIFF the IFDs are scattered through the file, so they are collected and
+//! repacked into a compact TIFF blob that the EXIF parser can read as-is. PNG
+//! chunks are skipped over until the `eXIf` chunk. Other containers the EXIF
+//! parser knows (WebP, HEIF) are left to a full read; see `needs_full_read`.
+
+use std::io::{Read, Seek, SeekFrom};
+use crate::jpeg;
+use crate::png;
+
+/// Upper bound on metadata bytes read from one file, guarding against corrupt offsets
+const MAX_METADATA_BYTES: u64 = 32 * 1024 * 1024;
+/// Upper bound on entries in a single IFD
+const MAX_IFD_ENTRIES: u16 = 1024;
+/// IFD0 and the thumbnail IFD1 are the only top-level IFDs the analyzer looks at
+const MAX_TOP_LEVEL_IFDS: usize = 2;
+
+/// Tags whose value is the offset of a nested IFD
+const EXIF_IFD_POINTER: u16 = 0x8769;
+const GPS_IFD_POINTER: u16 = 0x8825;
+const INTEROP_IFD_POINTER: u16 = 0xA005;
+
+/// Read the EXIF block of an image without reading the image data
+///
+/// Returns TIFF-structured bytes suitable for `exif::Reader::read_raw`, or
+/// `None` when there is no EXIF block or the data isn't a JPEG, TIFF, PNG, or JPEG XL container.
+pub fn read_exif_block_from<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut magic = [0u8; 4];
+    if reader.read_exact(&mut magic).is_err() {
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::Start(0))?;
+
+    match magic {
+        [0xFF, jpeg::SOI, ..] => read_jpeg_exif(reader),
+        [b'I', b'I', 0x2A, 0x00] | [b'M', b'M', 0x00, 0x2A] => read_tiff_ifds(reader).map(Some),
+        [0x89, b'P', b'N', b'G'] => read_png_exif(reader),
+        [0, 0, 0, 0x0C] => read_jxl_exif(reader),
+        _ => Ok(None),
+    }
+}
+
+/// Whether data starting with `magic` is a container only a full read finds EXIF in
+///
+/// WebP and HEIF/AVIF have no header reader here; `read_exif_block_from`
+/// returns `None` for them, which must not be taken as having no EXIF.
+pub fn needs_full_read(magic: &[u8]) -> bool {
+    let webp = magic.len() >= 12 && &magic[..4] == b"RIFF" && &magic[8..12] == b"WEBP";
+    let heif = magic.get(4..8) == Some(b"ftyp".as_slice());
+    webp || heif
+}
+
+/// Seek from chunk header to chunk header until the `eXIf` chunk, IEND, or the end of the data
+fn read_png_exif<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    reader.seek(SeekFrom::Start(png::SIGNATURE.len() as u64))?;
+
+    loop {
+        let mut header = [0u8; 8];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let length = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+
+        match &header[4..] {
+            b"eXIf" => {
+                if length > MAX_METADATA_BYTES {
+                    return Err("PNG metadata exceeds size limit".into());
+                }
+                let mut block = vec![0u8; length as usize];
+                reader.read_exact(&mut block)?;
+                return Ok(Some(block));
+            }
+            b"IEND" => return Ok(None),
+            // Skip the data and its CRC
+            _ => reader.seek(SeekFrom::Current(length as i64 + 4))?,
+        };
+    }
+}
+
+/// Seek from box header to box header until the `Exif` box
+///
+/// A Brotli-compressed (`brob`) `Exif` box is an error, so it is never taken as no EXIF.
+fn read_jxl_exif<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    reader.seek(SeekFrom::Start(0))?;
+
+    loop {
+        let mut header = [0u8; 8];
+        match reader.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let (header_len, length) = match u32::from_be_bytes([header[0], header[1], header[2], header[3]]) {
+            // The last box, usually the codestream
+            0 => return Ok(None),
+            1 => {
+                let mut large = [0u8; 8];
+                reader.read_exact(&mut large)?;
+                (16, u64::from_be_bytes(large))
+            }
+            length => (8, length as u64),
+        };
+        let body = length.checked_sub(header_len).ok_or("Invalid JXL box size")?;
+
+        if &header[4..] == b"Exif" {
+            if body > MAX_METADATA_BYTES {
+                return Err("JXL metadata exceeds size limit".into());
+            }
+            let mut block = vec![0u8; body as usize];
+            reader.read_exact(&mut block)?;
+            let offset = block.get(..4).map_or(0, |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize);
+            return Ok(block.get(4 + offset..).map(<[u8]>::to_vec));
+        }
+        if &header[4..] == b"brob" && body >= 4 {
+            let mut kind = [0u8; 4];
+            reader.read_exact(&mut kind)?;
+            if &kind == b"Exif" {
+                return Err("Brotli-compressed JXL metadata can't be analyzed".into());
+            }
+            reader.seek(SeekFrom::Current(body as i64 - 4))?;
+            continue;
+        }
+        reader.seek(SeekFrom::Current(body as i64))?;
+    }
+}
+
+/// Walk JPEG marker segments up to the start of scan, returning the reassembled EXIF block
+fn read_jpeg_exif<R: Read + Seek>(reader: &mut R) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    reader.seek(SeekFrom::Start(2))?;
+
+    // EXIF may be continued across several APP1 segments
+    let mut exif_payloads = Vec::new();
+    let mut bytes_read = 0u64;
+
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        if byte[0] != 0xFF {
+            return Err("Expected JPEG marker".into());
+        }
+        // Skip fill bytes before the marker code
+        while byte[0] == 0xFF {
+            reader.read_exact(&mut byte)?;
+        }
+        let marker = byte[0];
+
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            continue;
+        }
+        if marker == jpeg::SOS || marker == jpeg::EOI {
+            return Ok(jpeg::reassemble_exif(exif_payloads.iter().map(Vec::as_slice)));
+        }
+
+        let mut length = [0u8; 2];
+        reader.read_exact(&mut length)?;
+        let length = u16::from_be_bytes(length) as usize;
+        if length < 2 {
+            return Err("Invalid JPEG segment length".into());
+        }
+
+        if marker == jpeg::APP1 {
+            bytes_read += length as u64;
+            if bytes_read > MAX_METADATA_BYTES {
+                return Err("JPEG metadata exceeds size limit".into());
+            }
+
+            let mut payload = vec![0u8; length - 2];
+            reader.read_exact(&mut payload)?;
+            if payload.starts_with(jpeg::EXIF_HEADER) {
+                exif_payloads.push(payload);
+            }
+        } else {
+            reader.seek(SeekFrom::Current(length as i64 - 2))?;
+        }
+    }
+}
+
+struct IfdEntry {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// Value bytes in the file's byte order; up to 4 bytes are stored inline
+    value: Vec<u8>,
+    sub_ifd: Option<Ifd>,
+}
+
+struct Ifd {
+    entries: Vec<IfdEntry>,
+}
+
+/// Reads TIFF IFDs by offset, tracking how many bytes have been pulled in
+struct TiffReader<'a, R> {
+    inner: &'a mut R,
+    little_endian: bool,
+    bytes_read: u64,
+}
+
+impl<R: Read + Seek> TiffReader<'_, R> {
+    fn read_at(&mut self, offset: u64, len: usize) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.bytes_read += len as u64;
+        if self.bytes_read > MAX_METADATA_BYTES {
+            return Err("TIFF metadata exceeds size limit".into());
+        }
+
+        self.inner.seek(SeekFrom::Start(offset))?;
+        let mut buf = vec![0u8; len];
+        self.inner.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) }
+    }
+
+    /// Read one IFD and the IFDs it points to; returns the IFD and its next-IFD offset
+    fn read_ifd(&mut self, offset: u32, depth: usize) -> Result<(Ifd, u32), Box<dyn std::error::Error>> {
+        let count_bytes = self.read_at(offset as u64, 2)?;
+        let count = self.u16(&count_bytes);
+        if count > MAX_IFD_ENTRIES {
+            return Err(format!("Implausible IFD entry count {} at offset {}", count, offset).into());
+        }
+
+        let raw = self.read_at(offset as u64 + 2, count as usize * 12 + 4)?;
+        let mut entries = Vec::with_capacity(count as usize);
+
+        for chunk in raw[..count as usize * 12].chunks_exact(12) {
+            let tag = self.u16(&chunk[0..2]);
+            let field_type = self.u16(&chunk[2..4]);
+            let count = self.u32(&chunk[4..8]);
+            let inline = &chunk[8..12];
+
+            // Skip entries of unknown type; their size can't be determined
+            let Some(size) = type_size(field_type).map(|size| size as u64 * count as u64) else {
+                continue;
+            };
+
+            let is_pointer = matches!(tag, EXIF_IFD_POINTER | GPS_IFD_POINTER | INTEROP_IFD_POINTER);
+            let sub_ifd = if is_pointer && depth < 2 {
+                Some(self.read_ifd(self.u32(inline), depth + 1)?.0)
+            } else {
+                None
+            };
+
+            let value = if size <= 4 {
+                inline[..size as usize].to_vec()
+            } else {
+                self.read_at(self.u32(inline) as u64, size as usize)?
+            };
+
+            entries.push(IfdEntry { tag, field_type, count, value, sub_ifd });
+        }
+
+        let next = self.u32(&raw[count as usize * 12..]);
+        Ok((Ifd { entries }, next))
+    }
+}
+
+/// Collect the IFDs of a TIFF and repack them into a compact TIFF blob
+fn read_tiff_ifds<R: Read + Seek>(reader: &mut R) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut tiff = TiffReader { inner: reader, little_endian: true, bytes_read: 0 };
+    let header = tiff.read_at(0, 8)?;
+    tiff.little_endian = header[0] == b'I';
+
+    let mut ifds = Vec::new();
+    let mut offset = tiff.u32(&header[4..8]);
+    while offset != 0 && ifds.len() < MAX_TOP_LEVEL_IFDS {
+        let (ifd, next) = tiff.read_ifd(offset, 0)?;
+        ifds.push(ifd);
+        offset = next;
+    }
+
+    let mut out = header[..4].to_vec();
+    out.extend_from_slice(&encode_u32(8, tiff.little_endian));
+
+    let mut next_field = None;
+    for ifd in &ifds {
+        let start = write_ifd(&mut out, ifd, tiff.little_endian);
+        if let Some(pos) = next_field {
+            out[pos..pos + 4].copy_from_slice(&encode_u32(start, tiff.little_endian));
+        }
+        next_field = Some(start as usize + 2 + ifd.entries.len() * 12);
+    }
+
+    Ok(out)
+}
+
+/// Append an IFD (and its out-of-line values and nested IFDs), returning its offset
+fn write_ifd(out: &mut Vec<u8>, ifd: &Ifd, little_endian: bool) -> u32 {
+    // TIFF offsets must be word-aligned
+    if out.len() % 2 != 0 {
+        out.push(0);
+    }
+    let start = out.len();
+    out.extend_from_slice(&encode_u16(ifd.entries.len() as u16, little_endian));
+    out.resize(start + 2 + ifd.entries.len() * 12 + 4, 0);
+
+    for (index, entry) in ifd.entries.iter().enumerate() {
+        let value_field = match entry.sub_ifd {
+            Some(ref sub_ifd) => encode_u32(write_ifd(out, sub_ifd, little_endian), little_endian),
+            None if entry.value.len() <= 4 => {
+                let mut inline = [0u8; 4];
+                inline[..entry.value.len()].copy_from_slice(&entry.value);
+                inline
+            }
+            None => {
+                if out.len() % 2 != 0 {
+                    out.push(0);
+                }
+                let value_offset = out.len() as u32;
+                out.extend_from_slice(&entry.value);
+                encode_u32(value_offset, little_endian)
+            }
+        };
+
+        let pos = start + 2 + index * 12;
+        out[pos..pos + 2].copy_from_slice(&encode_u16(entry.tag, little_endian));
+        out[pos + 2..pos + 4].copy_from_slice(&encode_u16(entry.field_type, little_endian));
+        out[pos + 4..pos + 8].copy_from_slice(&encode_u32(entry.count, little_endian));
+        out[pos + 8..pos + 12].copy_from_slice(&value_field);
+    }
+
+    start as u32
+}
+
+/// Size in bytes of one value of a TIFF field type
+pub(crate) fn type_size(field_type: u16) -> Option<u32> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),  // BYTE, ASCII, SBYTE, UNDEFINED
+        3 | 8 => Some(2),          // SHORT, SSHORT
+        4 | 9 | 11 | 13 => Some(4), // LONG, SLONG, FLOAT, IFD
+        5 | 10 | 12 => Some(8),    // RATIONAL, SRATIONAL, DOUBLE
+        _ => None,
+    }
+}
+
+pub(crate) fn encode_u16(value: u16, little_endian: bool) -> [u8; 2] {
+    if little_endian { value.to_le_bytes() } else { value.to_be_bytes() }
+}
+
+pub(crate) fn encode_u32(value: u32, little_endian: bool) -> [u8; 4] {
+    if little_endian { value.to_le_bytes() } else { value.to_be_bytes() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use exif::{In, Reader, Tag};
+    use crate::jpeg::tests::build_jpeg;
+    use crate::jxl::tests::build_jxl;
+
+    /// Little-endian TIFF with pixel data first and the IFDs at the end, as libtiff writes them
+    fn build_tiff() -> Vec<u8> {
+        let pixels = vec![0xAB; 4096];
+        let ifd0 = 8 + pixels.len() as u32;
+        // IFD0: 2 entries; Exif IFD: 1 entry; each IFD is 2 + 12n + 4 bytes
+        let exif_ifd = ifd0 + 2 + 2 * 12 + 4;
+        let artist = exif_ifd + 2 + 12 + 4;
+        let serial = artist + 6;
+
+        let mut data = b"II*\0".to_vec();
+        data.extend_from_slice(&ifd0.to_le_bytes());
+        data.extend_from_slice(&pixels);
+
+        let entry = |data: &mut Vec<u8>, tag: u16, field_type: u16, count: u32, value: u32| {
+            data.extend_from_slice(&tag.to_le_bytes());
+            data.extend_from_slice(&field_type.to_le_bytes());
+            data.extend_from_slice(&count.to_le_bytes());
+            data.extend_from_slice(&value.to_le_bytes());
+        };
+
+        data.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut data, 0x013B, 2, 6, artist);
+        entry(&mut data, EXIF_IFD_POINTER, 4, 1, exif_ifd);
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        data.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut data, 0xA431, 2, 7, serial);
+        data.extend_from_slice(&0u32.to_le_bytes());
+
+        data.extend_from_slice(b"Alice\0");
+        data.extend_from_slice(b"SN-1234\0");
+        data
+    }
+
+    fn ascii_value(exif: &exif::Exif, tag: Tag) -> String {
+        exif.get_field(tag, In::PRIMARY).unwrap().display_value().to_string()
+    }
+
+    #[test]
+    fn test_tiff_ifds_are_repacked() {
+        let data = build_tiff();
+        let block = read_exif_block_from(&mut Cursor::new(&data)).unwrap().unwrap();
+
+        assert!(block.len() < 100, "repacked block should not include pixel data");
+        let exif = Reader::new().read_raw(block).unwrap();
+        assert_eq!(ascii_value(&exif, Tag::Artist), "\"Alice\"");
+        assert_eq!(ascii_value(&exif, Tag::BodySerialNumber), "\"SN-1234\"");
+    }
+
+    #[test]
+    fn test_jxl_exif_box_is_extracted() {
+        let data = build_jxl(&[(b"ftyp", b"jxl \0\0\0\0jxl "), (b"Exif", b"\0\0\0\0II*\0"), (b"jxlc", &[0xFF, 0x0A])]);
+        let block = read_exif_block_from(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(block.as_deref(), Some(&b"II*\0"[..]));
+
+        let compressed = build_jxl(&[(b"brob", b"Exif\x1b\x00"), (b"jxlc", &[0xFF, 0x0A])]);
+        assert!(read_exif_block_from(&mut Cursor::new(&compressed)).is_err());
+    }
+
+    #[test]
+    fn test_jpeg_app1_is_extracted() {
+        let data = build_jpeg(&[(jpeg::APP0, b"JFIF\0"), (jpeg::APP1, b"Exif\0\0II*\0")], b"pixels");
+        let block = read_exif_block_from(&mut Cursor::new(&data)).unwrap();
+        assert_eq!(block.as_deref(), Some(&b"II*\0"[..]));
+
+        let split = build_jpeg(&[(jpeg::APP1, b"Exif\0\0II*\0ab"), (jpeg::APP1, b"Exif\0\0cd")], b"pixels");
+        let block = read_exif_block_from(&mut Cursor::new(&split)).unwrap();
+        assert_eq!(block.as_deref(), Some(&b"II*\0abcd"[..]));
+
+        let no_exif = build_jpeg(&[(0xDB, b"qtable")], b"pixels");
+        assert_eq!(read_exif_block_from(&mut Cursor::new(&no_exif)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_formats_have_no_block() {
+        assert_eq!(read_exif_block_from(&mut Cursor::new(b"\x89PNG\r\n")).unwrap(), None);
+        assert_eq!(read_exif_block_from(&mut Cursor::new(b"")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_corrupt_tiff_offsets_are_rejected() {
+        let mut data = build_tiff();
+        data[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(read_exif_block_from(&mut Cursor::new(&data)).is_err());
+    }
+
+    #[test]
+    fn test_webp_and_heif_need_a_full_read() {
+        assert!(needs_full_read(b"RIFF\x24\0\0\0WEBPVP8X"));
+        assert!(needs_full_read(b"\0\0\0\x18ftypheic"));
+        assert!(!needs_full_read(b"\xFF\xD8\xFF\xE1\0\x10Exif\0\0"));
+        assert!(!needs_full_read(b"RIFF"));
+    }
+}
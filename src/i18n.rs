@@ -0,0 +1,214 @@
+//! Translated user-facing text (`--lang`).
+//!
+//! Messages are Fluent files under `locales/`, one per language, compiled into
+//! the binary. `locales/en.ftl` is the reference catalog: code looks messages
+//! up by id with `tr`, and `message_ids` extracts those ids from source so the
+//! tests can check that English defines every one, that every translation is
+//! complete, and that no translation defines ids the code doesn't use. Option
+//! help is looked up by ids built from the option names (see `cli::command`).
+//! A message missing from a translation would be shown in English. Reports,
+//! JSON keys, and log lines stay English so scripts and bug reports read the
+//! same everywhere.
+
+use std::sync::OnceLock;
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource, FluentValue};
+use unic_langid::LanguageIdentifier;
+
+/// Environment variable selecting the language, ahead of the locale variables
+pub const LANG_VAR: &str = "PRIVACY_CLEANER_LANG";
+/// Variables consulted for the language, in order, after `--lang`
+const LANG_VARS: [&str; 4] = [LANG_VAR, "LC_ALL", "LC_MESSAGES", "LANG"];
+/// Languages with a catalog, and its Fluent source; the first is the fallback
+const CATALOGS: [(&str, &str); 2] = [
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// The messages of one language, backed by English
+pub struct Catalog {
+    language: &'static str,
+    bundle: FluentBundle<FluentResource>,
+}
+
+impl Catalog {
+    /// The catalog of `language`, one of `languages()`; anything else gets English
+    pub fn new(language: &str) -> Self {
+        let (language, source) = CATALOGS.iter().copied()
+            .find(|(tag, _)| *tag == language)
+            .unwrap_or(CATALOGS[0]);
+        let locale: LanguageIdentifier = language.parse().unwrap_or_default();
+        let mut bundle = FluentBundle::new_concurrent(vec![locale]);
+        // Terminals show the Unicode isolation marks around arguments as garbage
+        bundle.set_use_isolating(false);
+        let _ = bundle.add_resource(parse(source));
+        if language != CATALOGS[0].0 {
+            // English fills in what the translation lacks; its duplicates are rejected
+            let _ = bundle.add_resource(parse(CATALOGS[0].1));
+        }
+        Catalog { language, bundle }
+    }
+
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// Format message `id` with `args`, or return the id if no catalog defines it
+    pub fn message(&self, id: &str, args: &[(&str, FluentValue)]) -> String {
+        self.try_message(id, args).unwrap_or_else(|| id.to_string())
+    }
+
+    /// Format message `id` with `args`, or `None` if no catalog defines it
+    pub fn try_message(&self, id: &str, args: &[(&str, FluentValue)]) -> Option<String> {
+        let pattern = self.bundle.get_message(id)?.value()?;
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+        let mut errors = Vec::new();
+        Some(self.bundle.format_pattern(pattern, Some(&fluent_args), &mut errors).into_owned())
+    }
+}
+
+/// Choose the language of all later messages: `lang`, else the environment
+///
+/// An explicit `lang` without a catalog is an error; an unknown locale in the
+/// environment (`C`, `POSIX`, a language not yet translated) just means English.
+/// Only the first call takes effect.
+pub fn init(lang: Option<&str>) -> Result<(), String> {
+    let language = match lang {
+        Some(lang) => resolve(lang).ok_or_else(|| {
+            format!("No translation for language '{}' (available: {})", lang, languages().join(", "))
+        })?,
+        None => LANG_VARS.iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| resolve(&value))
+            .unwrap_or(CATALOGS[0].0),
+    };
+    let _ = CATALOG.set(Catalog::new(language));
+    Ok(())
+}
+
+/// Message `id` in the chosen language (English before `init`)
+pub fn tr(id: &str, args: &[(&str, FluentValue)]) -> String {
+    catalog().message(id, args)
+}
+
+/// Message `id` in the chosen language, or `None` for ids built at runtime that no catalog defines
+pub fn try_tr(id: &str, args: &[(&str, FluentValue)]) -> Option<String> {
+    catalog().try_message(id, args)
+}
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::new(CATALOGS[0].0))
+}
+
+/// Languages with a catalog
+pub fn languages() -> Vec<&'static str> {
+    CATALOGS.iter().map(|(tag, _)| *tag).collect()
+}
+
+/// The catalog for a language tag or locale such as `de`, `de-AT`, or `de_DE.UTF-8`
+pub fn resolve(requested: &str) -> Option<&'static str> {
+    let primary = requested.split(['_', '-', '.', '@']).next()?.to_ascii_lowercase();
+    CATALOGS.iter().map(|(tag, _)| *tag).find(|tag| *tag == primary)
+}
+
+/// Message ids looked up in Rust source, in order of appearance
+///
+/// Finds literal ids passed to `tr`; this is what keeps the catalogs and the
+/// code in step.
+pub fn message_ids(source: &str) -> Vec<String> {
+    let mut ids = Vec::new();
+    for (pos, _) in source.match_indices("tr(\"") {
+        // Not the tail of a longer name such as `attr("`
+        if source[..pos].chars().next_back().map_or(false, |c| c.is_alphanumeric() || c == '_') {
+            continue;
+        }
+        let rest = &source[pos + 4..];
+        if let Some(end) = rest.find('"') {
+            ids.push(rest[..end].to_string());
+        }
+    }
+    ids
+}
+
+/// Message ids a Fluent catalog defines
+pub fn defined_ids(catalog: &str) -> Vec<&str> {
+    catalog.lines()
+        .filter(|line| line.starts_with(|c: char| c.is_ascii_lowercase()))
+        .filter_map(|line| line.split_once(" ="))
+        .map(|(id, _)| id)
+        .collect()
+}
+
+fn parse(source: &str) -> FluentResource {
+    // A malformed entry is skipped; the rest of the catalog still works
+    FluentResource::try_new(source.to_string()).unwrap_or_else(|(resource, _)| resource)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    /// Message ids used anywhere under `src/`
+    fn used_ids() -> Vec<String> {
+        let mut ids = Vec::new();
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        for entry in walkdir::WalkDir::new(src).into_iter().filter_map(Result::ok) {
+            if entry.path().extension().map_or(false, |ext| ext == "rs") {
+                ids.extend(message_ids(&fs::read_to_string(entry.path()).unwrap()));
+            }
+        }
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    #[test]
+    fn test_catalogs_match_the_code() {
+        let used = used_ids();
+        assert!(!used.is_empty());
+        let english = defined_ids(CATALOGS[0].1);
+        let undefined: Vec<&String> = used.iter().filter(|id| !english.contains(&id.as_str())).collect();
+        assert!(undefined.is_empty(), "Messages missing from en.ftl: {:?}", undefined);
+
+        for (language, catalog) in &CATALOGS[1..] {
+            let defined = defined_ids(catalog);
+            let unused: Vec<&&str> = defined.iter().filter(|id| !english.contains(id)).collect();
+            assert!(unused.is_empty(), "{}.ftl defines messages en.ftl doesn't: {:?}", language, unused);
+            let untranslated: Vec<&&str> = english.iter().filter(|id| !defined.contains(id)).collect();
+            assert!(untranslated.is_empty(), "{}.ftl lacks translations for {:?}", language, untranslated);
+        }
+    }
+
+    #[test]
+    fn test_messages_are_formatted() {
+        let english = Catalog::new("en");
+        assert_eq!(english.message("summary-processed", &[("count", 3.into())]), "Files processed: 3");
+        let german = Catalog::new("de");
+        assert_eq!(german.message("summary-processed", &[("count", 3.into())]), "Verarbeitete Dateien: 3");
+        assert_eq!(german.message("no-such-message", &[]), "no-such-message");
+        assert_eq!(Catalog::new("xx").language(), "en");
+    }
+
+    #[test]
+    fn test_resolve_locales() {
+        assert_eq!(resolve("de"), Some("de"));
+        assert_eq!(resolve("de_DE.UTF-8"), Some("de"));
+        assert_eq!(resolve("DE-at"), Some("de"));
+        assert_eq!(resolve("C"), None);
+        assert_eq!(resolve("pt-BR"), None);
+    }
+
+    #[test]
+    fn test_message_ids() {
+        let source = r#"println!("{}", tr("summary-errors", &[])); let x = attr("nope"); i18n::tr("explain-same", &args);"#;
+        assert_eq!(message_ids(source), ["summary-errors", "explain-same"]);
+    }
+}
@@ -0,0 +1,78 @@
+//! Just enough IPTC-IIM parsing to read captions.
+//!
+//! JPEGs carry IPTC in an APP13 `Photoshop 3.0` segment as image resource
+//! 0x0404; TIFFs store the same IIM stream in their IPTC-NAA tag.
+
+/// Identifier that starts a Photoshop APP13 payload
+pub const PHOTOSHOP_HEADER: &[u8] = b"Photoshop 3.0\0";
+/// Photoshop image resource holding the IPTC-IIM stream
+const IPTC_RESOURCE: u16 = 0x0404;
+/// IIM dataset of the caption/abstract (record 2, dataset 120)
+const CAPTION: (u8, u8) = (2, 120);
+
+/// The IPTC-IIM stream in a Photoshop APP13 payload, if it has one
+pub fn from_photoshop(payload: &[u8]) -> Option<&[u8]> {
+    let mut data = payload.strip_prefix(PHOTOSHOP_HEADER)?;
+
+    while data.len() >= 12 && data.starts_with(b"8BIM") {
+        let id = u16::from_be_bytes([data[4], data[5]]);
+        // Pascal-string name, padded to an even length including its length byte
+        let name_len = (data[6] as usize + 2) & !1;
+        let size_at = 6 + name_len;
+        let size_bytes = data.get(size_at..size_at + 4)?;
+        let size = u32::from_be_bytes([size_bytes[0], size_bytes[1], size_bytes[2], size_bytes[3]]) as usize;
+        let start = size_at + 4;
+        let resource = data.get(start..start + size)?;
+
+        if id == IPTC_RESOURCE {
+            return Some(resource);
+        }
+        data = data.get(start + ((size + 1) & !1)..)?;
+    }
+    None
+}
+
+/// Captions in an IPTC-IIM stream
+pub fn captions(iim: &[u8]) -> Vec<String> {
+    let mut captions = Vec::new();
+    let mut pos = 0;
+
+    // Each dataset: 0x1C, record, dataset, 16-bit length, value; extended lengths aren't used for text
+    while pos + 5 <= iim.len() && iim[pos] == 0x1C {
+        let (record, dataset) = (iim[pos + 1], iim[pos + 2]);
+        let len = u16::from_be_bytes([iim[pos + 3], iim[pos + 4]]) as usize;
+        if len & 0x8000 != 0 {
+            break;
+        }
+        let Some(value) = iim.get(pos + 5..pos + 5 + len) else { break };
+
+        if (record, dataset) == CAPTION {
+            captions.push(String::from_utf8_lossy(value).into_owned());
+        }
+        pos += 5 + len;
+    }
+    captions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_caption_from_photoshop_segment() {
+        let caption = b"Call 555-867-5309";
+        let mut iim = vec![0x1C, 2, 0, 0, 2, 0, 4];
+        iim.extend_from_slice(&[0x1C, 2, 120, 0, caption.len() as u8]);
+        iim.extend_from_slice(caption);
+
+        let mut payload = PHOTOSHOP_HEADER.to_vec();
+        payload.extend_from_slice(b"8BIM");
+        payload.extend_from_slice(&IPTC_RESOURCE.to_be_bytes());
+        payload.extend_from_slice(&[0, 0]);
+        payload.extend_from_slice(&(iim.len() as u32).to_be_bytes());
+        payload.extend_from_slice(&iim);
+
+        let iim = from_photoshop(&payload).unwrap();
+        assert_eq!(captions(iim), vec!["Call 555-867-5309".to_string()]);
+    }
+}
@@ -0,0 +1,488 @@
+//! Minimal JPEG marker-segment parser and rewriter.
+//!
+//! Only the file structure is parsed: the sequence of marker segments and,
+//! where a whole file is walked, the extent of each scan's entropy-coded data.
+//! That data is never decoded, so rewriting a file keeps its pixels bit for bit.
+
+/// Start of image
+pub const SOI: u8 = 0xD8;
+/// End of image
+pub const EOI: u8 = 0xD9;
+/// Start of scan
+pub const SOS: u8 = 0xDA;
+/// First application segment (APP0)
+pub const APP0: u8 = 0xE0;
+/// APP1 carries EXIF and XMP
+pub const APP1: u8 = 0xE1;
+/// APP2 carries ICC profiles and the MPF index of multi-picture files
+pub const APP2: u8 = 0xE2;
+/// APP13 carries Photoshop image resources, including IPTC
+pub const APP13: u8 = 0xED;
+/// APP14 carries Adobe's colour transform flag, which decoders need
+pub const APP14: u8 = 0xEE;
+/// Last application segment (APP15)
+pub const APP15: u8 = 0xEF;
+/// Comment
+pub const COM: u8 = 0xFE;
+
+/// Length of a JFIF APP0 payload up to its thumbnail: identifier, version,
+/// density, and the thumbnail's width and height (the last two bytes)
+const JFIF_HEADER_LEN: usize = 14;
+
+/// Identifier that starts an EXIF APP1 payload
+pub const EXIF_HEADER: &[u8] = b"Exif\0\0";
+/// Identifier that starts the main XMP packet's APP1 payload
+pub const XMP_HEADER: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+/// Identifier of extended XMP chunks, followed by a 32-byte GUID, the full length, and the chunk offset
+pub const XMP_EXTENSION_HEADER: &[u8] = b"http://ns.adobe.com/xmp/extension/\0";
+
+/// One marker segment in the JPEG header
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment<'a> {
+    pub marker: u8,
+    /// Offset of the 0xFF byte that starts the marker
+    pub offset: usize,
+    /// Segment contents after the two length bytes
+    pub payload: &'a [u8],
+}
+
+impl Segment<'_> {
+    /// APPn and COM segments carry metadata rather than image data
+    pub fn is_metadata(&self) -> bool {
+        (APP0..=APP15).contains(&self.marker) || self.marker == COM
+    }
+
+    /// Total size of the segment in the file, including marker and length bytes
+    pub fn encoded_len(&self) -> usize {
+        self.payload.len() + 4
+    }
+
+    /// The Adobe segment, whose colour transform flag changes how the pixels decode
+    pub fn affects_decoding(&self) -> bool {
+        self.marker == APP14 && self.payload.starts_with(b"Adobe")
+    }
+
+    /// Metadata kept when stripping: JFIF, the ICC profile, and the Adobe segment
+    fn kept_when_stripping(&self) -> bool {
+        match self.marker {
+            APP0 => self.payload.starts_with(b"JFIF\0"),
+            APP2 => self.payload.starts_with(b"ICC_PROFILE\0"),
+            _ => self.affects_decoding(),
+        }
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(&[0xFF, self.marker]);
+        out.extend_from_slice(&((self.payload.len() + 2) as u16).to_be_bytes());
+        out.extend_from_slice(self.payload);
+    }
+}
+
+/// A piece of a JPEG after SOI, in file order
+#[derive(Debug, Clone, PartialEq)]
+pub enum Part<'a> {
+    Segment(Segment<'a>),
+    /// Entropy-coded data following an SOS segment, restart markers included
+    Scan(&'a [u8]),
+}
+
+/// Header segments plus where the compressed scan data begins
+#[derive(Debug, Clone)]
+pub struct JpegLayout<'a> {
+    pub segments: Vec<Segment<'a>>,
+    /// Offset of the first byte after the SOS segment header
+    pub scan_offset: usize,
+}
+
+impl<'a> JpegLayout<'a> {
+    /// Payloads of the APP1 segments, in file order
+    pub fn app1_payloads(&self) -> impl Iterator<Item = &'a [u8]> + '_ {
+        self.segments.iter().filter(|s| s.marker == APP1).map(|s| s.payload)
+    }
+
+    /// Width and height from the start-of-frame segment
+    pub fn frame_size(&self) -> Option<(u16, u16)> {
+        // SOF0-SOF15, except DHT (C4), JPG (C8), and DAC (CC), which share the range
+        let sof = self.segments.iter()
+            .find(|s| (0xC0..=0xCF).contains(&s.marker) && ![0xC4, 0xC8, 0xCC].contains(&s.marker))?;
+        let height = u16::from_be_bytes([*sof.payload.get(1)?, *sof.payload.get(2)?]);
+        let width = u16::from_be_bytes([*sof.payload.get(3)?, *sof.payload.get(4)?]);
+        Some((width, height))
+    }
+}
+
+/// A JPEG's XMP: the main packet, plus the extended packet split across further segments
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct XmpPacket {
+    pub standard: Option<Vec<u8>>,
+    pub extended: Option<Vec<u8>>,
+}
+
+/// Reassemble the EXIF block from a JPEG's APP1 payloads
+///
+/// EXIF too large for one 64 KiB segment is continued in further `Exif\0\0`
+/// segments, which are appended to the first. A later segment that starts a
+/// TIFF header of its own is a separate block and is not merged.
+pub fn reassemble_exif<'a, I: IntoIterator<Item = &'a [u8]>>(app1_payloads: I) -> Option<Vec<u8>> {
+    let mut block: Option<Vec<u8>> = None;
+
+    for tiff in app1_payloads.into_iter().filter_map(|payload| payload.strip_prefix(EXIF_HEADER)) {
+        if let Some(existing) = block.as_mut() {
+            if !is_tiff_header(tiff) {
+                existing.extend_from_slice(tiff);
+            }
+        } else {
+            block = Some(tiff.to_vec());
+        }
+    }
+
+    block
+}
+
+/// Reassemble a JPEG's XMP from its APP1 payloads
+///
+/// Extended XMP chunks carry their offset into the extended packet and may be
+/// stored in any order; chunks from a second GUID or leaving a gap are dropped.
+pub fn reassemble_xmp<'a, I: IntoIterator<Item = &'a [u8]>>(app1_payloads: I) -> Option<XmpPacket> {
+    let mut xmp = XmpPacket::default();
+    let mut guid: Option<&[u8]> = None;
+    let mut chunks = Vec::new();
+
+    for payload in app1_payloads {
+        if let Some(packet) = payload.strip_prefix(XMP_HEADER) {
+            xmp.standard.get_or_insert_with(|| packet.to_vec());
+        } else if let Some(chunk) = payload.strip_prefix(XMP_EXTENSION_HEADER) {
+            if chunk.len() < 40 || *guid.get_or_insert(&chunk[..32]) != &chunk[..32] {
+                continue;
+            }
+            let offset = u32::from_be_bytes([chunk[36], chunk[37], chunk[38], chunk[39]]) as usize;
+            chunks.push((offset, &chunk[40..]));
+        }
+    }
+
+    chunks.sort_by_key(|(offset, _)| *offset);
+    for (offset, data) in chunks {
+        let extended = xmp.extended.get_or_insert_with(Vec::new);
+        if offset == extended.len() {
+            extended.extend_from_slice(data);
+        }
+    }
+
+    if xmp.standard.is_none() && xmp.extended.is_none() {
+        return None;
+    }
+    Some(xmp)
+}
+
+fn is_tiff_header(data: &[u8]) -> bool {
+    data.starts_with(b"II*\0") || data.starts_with(b"MM\0*")
+}
+
+/// Check for the JPEG SOI marker
+pub fn is_jpeg(data: &[u8]) -> bool {
+    data.len() >= 2 && data[0] == 0xFF && data[1] == SOI
+}
+
+/// Parse the marker segments of a JPEG up to and including the first SOS
+pub fn parse_layout(data: &[u8]) -> Result<JpegLayout<'_>, Box<dyn std::error::Error>> {
+    if !is_jpeg(data) {
+        return Err("Not a JPEG file (missing SOI marker)".into());
+    }
+
+    let mut segments = Vec::new();
+    let mut pos = 2;
+
+    loop {
+        if pos >= data.len() {
+            return Err("Unexpected end of JPEG header".into());
+        }
+        if data[pos] != 0xFF {
+            return Err(format!("Expected JPEG marker at offset {}", pos).into());
+        }
+
+        let offset = pos;
+        // Markers may be preceded by any number of 0xFF fill bytes
+        while pos < data.len() && data[pos] == 0xFF {
+            pos += 1;
+        }
+        let marker = *data.get(pos).ok_or("Unexpected end of JPEG header")?;
+        pos += 1;
+
+        // Standalone markers carry no length
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            continue;
+        }
+        if marker == EOI {
+            return Err("JPEG ends before start of scan".into());
+        }
+
+        let length_bytes = data.get(pos..pos + 2).ok_or("Truncated JPEG segment length")?;
+        let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        if length < 2 {
+            return Err(format!("Invalid JPEG segment length at offset {}", offset).into());
+        }
+
+        let payload = data.get(pos + 2..pos + length)
+            .ok_or_else(|| format!("Truncated JPEG segment at offset {}", offset))?;
+        segments.push(Segment { marker, offset, payload });
+        pos += length;
+
+        if marker == SOS {
+            return Ok(JpegLayout { segments, scan_offset: pos });
+        }
+    }
+}
+
+/// Parse a whole JPEG into its segments and scans up to EOI
+///
+/// Bytes after EOI are ignored. A file cut off inside its last scan is
+/// accepted, since decoders show what is there.
+pub fn parse_parts(data: &[u8]) -> Result<Vec<Part<'_>>, Box<dyn std::error::Error>> {
+    if !is_jpeg(data) {
+        return Err("Not a JPEG file (missing SOI marker)".into());
+    }
+
+    let mut parts = Vec::new();
+    let mut pos = 2;
+
+    loop {
+        if pos >= data.len() {
+            return match parts.last() {
+                Some(Part::Scan(_)) => Ok(parts),
+                _ => Err("Unexpected end of JPEG data".into()),
+            };
+        }
+        if data[pos] != 0xFF {
+            return Err(format!("Expected JPEG marker at offset {}", pos).into());
+        }
+
+        let offset = pos;
+        while pos < data.len() && data[pos] == 0xFF {
+            pos += 1;
+        }
+        let marker = *data.get(pos).ok_or("Unexpected end of JPEG data")?;
+        pos += 1;
+
+        if marker == 0x01 || (0xD0..=0xD7).contains(&marker) {
+            continue;
+        }
+        if marker == EOI {
+            return Ok(parts);
+        }
+
+        let length_bytes = data.get(pos..pos + 2).ok_or("Truncated JPEG segment length")?;
+        let length = u16::from_be_bytes([length_bytes[0], length_bytes[1]]) as usize;
+        if length < 2 {
+            return Err(format!("Invalid JPEG segment length at offset {}", offset).into());
+        }
+        let payload = data.get(pos + 2..pos + length)
+            .ok_or_else(|| format!("Truncated JPEG segment at offset {}", offset))?;
+        parts.push(Part::Segment(Segment { marker, offset, payload }));
+        pos += length;
+
+        if marker == SOS {
+            let end = scan_end(data, pos);
+            parts.push(Part::Scan(&data[pos..end]));
+            pos = end;
+        }
+    }
+}
+
+/// Offset of the marker ending the entropy-coded data that starts at `start`, or the end of the data
+fn scan_end(data: &[u8], start: usize) -> usize {
+    let mut pos = start;
+    while pos + 1 < data.len() {
+        if data[pos] == 0xFF {
+            match data[pos + 1] {
+                // An escaped 0xFF data byte, or a restart marker inside the scan
+                0x00 | 0xD0..=0xD7 => pos += 2,
+                // Fill byte before a marker
+                0xFF => pos += 1,
+                _ => return pos,
+            }
+        } else {
+            pos += 1;
+        }
+    }
+    data.len()
+}
+
+/// Rewrite a JPEG without its metadata, copying the compressed image data byte for byte
+///
+/// APPn and COM segments are dropped wherever they appear, except JFIF, the
+/// ICC profile, and the Adobe segment, which decoding and display depend on.
+/// JFIF keeps only its header: the thumbnail it can carry is dropped too.
+/// Data after EOI, such as appended pictures or a motion photo's video, is dropped too.
+pub fn strip_metadata(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut stripped = Vec::with_capacity(data.len());
+    stripped.extend_from_slice(&[0xFF, SOI]);
+    for part in parse_parts(data)? {
+        match part {
+            Part::Segment(segment) if segment.is_metadata() && !segment.kept_when_stripping() => {}
+            Part::Segment(segment) if segment.marker == APP0 && segment.payload.len() > JFIF_HEADER_LEN => {
+                let mut header = segment.payload[..JFIF_HEADER_LEN].to_vec();
+                header[JFIF_HEADER_LEN - 2..].fill(0);
+                Segment { payload: &header, ..segment }.write_to(&mut stripped);
+            }
+            Part::Segment(segment) => segment.write_to(&mut stripped),
+            Part::Scan(scan) => stripped.extend_from_slice(scan),
+        }
+    }
+    stripped.extend_from_slice(&[0xFF, EOI]);
+    Ok(stripped)
+}
+
+/// The parts of a JPEG that decide its pixels: (marker, payload), with no marker for scan data
+type ImageData<'a> = Vec<(Option<u8>, &'a [u8])>;
+
+/// Whether two JPEGs hold the same compressed image: the same tables, frame, and scans, byte for byte
+///
+/// Metadata segments are ignored, except the Adobe segment, which changes how the pixels decode.
+pub fn same_image_data(a: &[u8], b: &[u8]) -> Result<bool, Box<dyn std::error::Error>> {
+    fn image_data(data: &[u8]) -> Result<ImageData<'_>, Box<dyn std::error::Error>> {
+        Ok(parse_parts(data)?.into_iter()
+            .filter_map(|part| match part {
+                Part::Segment(segment) if segment.is_metadata() && !segment.affects_decoding() => None,
+                Part::Segment(segment) => Some((Some(segment.marker), segment.payload)),
+                Part::Scan(scan) => Some((None, scan)),
+            })
+            .collect())
+    }
+    Ok(image_data(a)? == image_data(b)?)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Build a minimal structurally valid JPEG from (marker, payload) pairs
+    pub(crate) fn build_jpeg(segments: &[(u8, &[u8])], scan_data: &[u8]) -> Vec<u8> {
+        let mut data = vec![0xFF, SOI];
+        for (marker, payload) in segments {
+            data.extend_from_slice(&[0xFF, *marker]);
+            data.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+            data.extend_from_slice(payload);
+        }
+        data.extend_from_slice(&[0xFF, SOS, 0x00, 0x03, 0x01]);
+        data.extend_from_slice(scan_data);
+        data.extend_from_slice(&[0xFF, EOI]);
+        data
+    }
+
+    /// A little-endian EXIF APP1 payload whose IFD0 holds only `Artist`
+    ///
+    /// Values of up to four bytes, NUL included, are stored in the entry; longer ones follow the IFD.
+    pub(crate) fn exif_with_artist(artist: &str) -> Vec<u8> {
+        let value = [artist.as_bytes(), b"\0"].concat();
+        let mut payload = b"Exif\0\0II*\0".to_vec();
+        payload.extend_from_slice(&8u32.to_le_bytes());
+        payload.extend_from_slice(&1u16.to_le_bytes());
+        payload.extend_from_slice(&0x013Bu16.to_le_bytes());
+        payload.extend_from_slice(&2u16.to_le_bytes());
+        payload.extend_from_slice(&(value.len() as u32).to_le_bytes());
+        if value.len() <= 4 {
+            payload.extend_from_slice(&value);
+            payload.resize(payload.len() + 4 - value.len(), 0);
+            payload.extend_from_slice(&0u32.to_le_bytes());
+        } else {
+            // Header, entry count, one entry, and the next-IFD offset come first
+            payload.extend_from_slice(&26u32.to_le_bytes());
+            payload.extend_from_slice(&0u32.to_le_bytes());
+            payload.extend_from_slice(&value);
+        }
+        payload
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        let data = build_jpeg(&[(APP1, b"Exif\0\0abc"), (0xDB, b"qtable"), (COM, b"hi")], b"\x12\x34");
+        let layout = parse_layout(&data).unwrap();
+
+        let markers: Vec<u8> = layout.segments.iter().map(|s| s.marker).collect();
+        assert_eq!(markers, vec![APP1, 0xDB, COM, SOS]);
+        assert_eq!(layout.segments[0].payload, b"Exif\0\0abc");
+        assert!(layout.segments[0].is_metadata());
+        assert!(!layout.segments[1].is_metadata());
+        assert_eq!(&data[layout.scan_offset..layout.scan_offset + 2], b"\x12\x34");
+    }
+
+    #[test]
+    fn test_multi_segment_exif_is_reassembled() {
+        let data = build_jpeg(
+            &[(APP1, b"Exif\0\0II*\0first"), (APP1, b"http://ns.adobe.com/xap/1.0/\0<x/>"), (APP1, b"Exif\0\0-second")],
+            b"pixels",
+        );
+        let layout = parse_layout(&data).unwrap();
+        assert_eq!(reassemble_exif(layout.app1_payloads()).unwrap(), b"II*\0first-second");
+
+        // A second complete TIFF block is not glued onto the first
+        let duplicate = build_jpeg(&[(APP1, b"Exif\0\0II*\0one"), (APP1, b"Exif\0\0MM\0*two")], b"pixels");
+        let layout = parse_layout(&duplicate).unwrap();
+        assert_eq!(reassemble_exif(layout.app1_payloads()).unwrap(), b"II*\0one");
+    }
+
+    #[test]
+    fn test_extended_xmp_is_reassembled_in_offset_order() {
+        let chunk = |offset: u32, data: &[u8]| {
+            let mut payload = XMP_EXTENSION_HEADER.to_vec();
+            payload.extend_from_slice(&[b'G'; 32]);
+            payload.extend_from_slice(&6u32.to_be_bytes());
+            payload.extend_from_slice(&offset.to_be_bytes());
+            payload.extend_from_slice(data);
+            payload
+        };
+        let (second, first) = (chunk(3, b"def"), chunk(0, b"abc"));
+        let payloads: Vec<&[u8]> = vec![b"http://ns.adobe.com/xap/1.0/\0<main/>", &second, &first];
+
+        let xmp = reassemble_xmp(payloads).unwrap();
+        assert_eq!(xmp.standard.as_deref(), Some(&b"<main/>"[..]));
+        assert_eq!(xmp.extended.as_deref(), Some(&b"abcdef"[..]));
+
+        assert_eq!(reassemble_xmp(vec![&b"Exif\0\0II*\0"[..]]), None);
+    }
+
+    #[test]
+    fn test_parse_layout_rejects_bad_input() {
+        assert!(parse_layout(b"not a jpeg").is_err());
+        assert!(parse_layout(&[0xFF, SOI, 0xFF, APP1, 0x00, 0x10, 0x01]).is_err());
+        assert!(parse_layout(&[0xFF, SOI, 0xFF, EOI]).is_err());
+    }
+
+    #[test]
+    fn test_strip_metadata_keeps_image_data() {
+        let mut data = build_jpeg(
+            &[(APP0, b"JFIF\0"), (APP1, b"Exif\0\0II*\0"), (0xDB, b"qtable"), (APP14, b"Adobe\0\x64"), (COM, b"hi")],
+            b"\x12\xFF\x00\x34\xFF\xD0\x56",
+        );
+        // A second scan of a progressive JPEG, with a comment before it and trailing data after EOI
+        let eoi = data.len() - 2;
+        data.splice(eoi..eoi, [0xFF, COM, 0x00, 0x04, b'n', b'o', 0xFF, SOS, 0x00, 0x03, 0x02, 0x78, 0x9A]);
+        data.extend_from_slice(b"appended video");
+
+        let stripped = strip_metadata(&data).unwrap();
+        let markers: Vec<Option<u8>> = parse_parts(&stripped).unwrap().iter()
+            .map(|part| match part {
+                Part::Segment(segment) => Some(segment.marker),
+                Part::Scan(_) => None,
+            })
+            .collect();
+        assert_eq!(markers, vec![Some(APP0), Some(0xDB), Some(APP14), Some(SOS), None, Some(SOS), None]);
+        assert!(stripped.ends_with(&[0x78, 0x9A, 0xFF, EOI]));
+        assert!(same_image_data(&data, &stripped).unwrap());
+
+        let mut recompressed = stripped.clone();
+        let scan = recompressed.len() - 3;
+        recompressed[scan] ^= 1;
+        assert!(!same_image_data(&data, &recompressed).unwrap());
+    }
+
+    #[test]
+    fn test_strip_metadata_drops_jfif_thumbnail() {
+        // JFIF 1.02, no units, 1x1 density, and a 1x1 RGB thumbnail
+        let jfif = b"JFIF\0\x01\x02\0\0\x01\0\x01\x01\x01\xAA\xBB\xCC";
+        let stripped = strip_metadata(&build_jpeg(&[(APP0, jfif)], b"\x12")).unwrap();
+
+        let layout = parse_layout(&stripped).unwrap();
+        assert_eq!(layout.segments[0].payload, b"JFIF\0\x01\x02\0\0\x01\0\x01\0\0");
+    }
+}
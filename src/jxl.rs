@@ -0,0 +1,128 @@
+//! Minimal JPEG XL container parser.
+//!
+//! A JXL file is either a bare codestream, which has no room for metadata,
+//! or an ISOBMFF-style container whose `Exif` and `xml ` boxes hold EXIF and
+//! XMP next to the codestream boxes. Metadata boxes may also be stored
+//! Brotli-compressed inside `brob` boxes, which are recognized but not read.
+
+/// Signature box every JXL container starts with
+pub const CONTAINER_SIGNATURE: &[u8] = b"\0\0\0\x0CJXL \r\n\x87\n";
+/// Start of a bare JXL codestream
+pub const CODESTREAM_SIGNATURE: &[u8] = &[0xFF, 0x0A];
+
+/// One box of a JXL container
+#[derive(Debug, Clone, PartialEq)]
+pub struct JxlBox<'a> {
+    pub kind: [u8; 4],
+    pub data: &'a [u8],
+}
+
+impl JxlBox<'_> {
+    /// The type of the box a `brob` box holds compressed
+    pub fn compressed_kind(&self) -> Option<&[u8]> {
+        if &self.kind == b"brob" { self.data.get(..4) } else { None }
+    }
+}
+
+/// Check for a JXL container or bare codestream
+pub fn is_jxl(data: &[u8]) -> bool {
+    data.starts_with(CONTAINER_SIGNATURE) || data.starts_with(CODESTREAM_SIGNATURE)
+}
+
+/// Parse the boxes of a JXL container; a bare codestream has none
+pub fn parse_boxes(data: &[u8]) -> Result<Vec<JxlBox<'_>>, Box<dyn std::error::Error>> {
+    if data.starts_with(CODESTREAM_SIGNATURE) {
+        return Ok(Vec::new());
+    }
+    if !data.starts_with(CONTAINER_SIGNATURE) {
+        return Err("Not a JPEG XL file (missing signature)".into());
+    }
+
+    let mut boxes = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let header = data.get(pos..pos + 8).ok_or("Truncated JXL box header")?;
+        let size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as u64;
+        let kind = [header[4], header[5], header[6], header[7]];
+
+        let (header_len, size) = match size {
+            // Runs to the end of the file
+            0 => (8, (data.len() - pos) as u64),
+            1 => {
+                let large = data.get(pos + 8..pos + 16).ok_or("Truncated JXL box header")?;
+                (16, u64::from_be_bytes(large.try_into()?))
+            }
+            size => (8, size),
+        };
+        let end = usize::try_from(size).ok()
+            .and_then(|size| pos.checked_add(size))
+            .filter(|&end| size >= header_len && end <= data.len())
+            .ok_or("JXL box runs past the end of the file")?;
+
+        boxes.push(JxlBox { kind, data: &data[pos + header_len as usize..end] });
+        pos = end;
+    }
+    Ok(boxes)
+}
+
+/// The TIFF-structured EXIF block of the `Exif` box
+///
+/// The box starts with a 4-byte offset to the TIFF header, normally zero.
+pub fn exif_block(boxes: &[JxlBox<'_>]) -> Option<Vec<u8>> {
+    let exif = boxes.iter().find(|b| &b.kind == b"Exif")?;
+    let offset = u32::from_be_bytes(exif.data.get(..4)?.try_into().ok()?) as usize;
+    exif.data.get(4usize.checked_add(offset)?..).map(<[u8]>::to_vec)
+}
+
+/// The XMP packet of the `xml ` box
+pub fn xmp(boxes: &[JxlBox<'_>]) -> Option<Vec<u8>> {
+    boxes.iter().find(|b| &b.kind == b"xml ").map(|b| b.data.to_vec())
+}
+
+/// Whether any metadata box is Brotli-compressed, and so can't be analyzed
+pub fn has_compressed_metadata(boxes: &[JxlBox<'_>]) -> bool {
+    boxes.iter().any(|b| matches!(b.compressed_kind(), Some(b"Exif") | Some(b"xml ")))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Assemble a JXL container from `(type, data)` boxes after the signature
+    pub(crate) fn build_jxl(boxes: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut data = CONTAINER_SIGNATURE.to_vec();
+        for (kind, box_data) in boxes {
+            data.extend_from_slice(&((box_data.len() + 8) as u32).to_be_bytes());
+            data.extend_from_slice(*kind);
+            data.extend_from_slice(box_data);
+        }
+        data
+    }
+
+    #[test]
+    fn test_exif_and_xmp_boxes() {
+        let data = build_jxl(&[
+            (b"ftyp", b"jxl \0\0\0\0jxl "),
+            (b"Exif", b"\0\0\0\0II*\0\x08\0\0\0"),
+            (b"xml ", b"<x:xmpmeta/>"),
+            (b"jxlc", &[0xFF, 0x0A, 0x00]),
+        ]);
+        let boxes = parse_boxes(&data).unwrap();
+
+        assert_eq!(boxes.len(), 5);
+        assert_eq!(exif_block(&boxes).unwrap(), b"II*\0\x08\0\0\0");
+        assert_eq!(xmp(&boxes).unwrap(), b"<x:xmpmeta/>");
+        assert!(!has_compressed_metadata(&boxes));
+        assert!(parse_boxes(&[0xFF, 0x0A, 0x12]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_compressed_and_truncated_boxes() {
+        let data = build_jxl(&[(b"brob", b"Exif\x1b\x00")]);
+        assert!(has_compressed_metadata(&parse_boxes(&data).unwrap()));
+
+        let mut truncated = build_jxl(&[(b"xml ", b"<x:xmpmeta/>")]);
+        truncated.truncate(truncated.len() - 4);
+        assert!(parse_boxes(&truncated).is_err());
+    }
+}
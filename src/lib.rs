@@ -5,57 +5,166 @@
 //! both as a command-line tool and as a library in other Rust projects.
 
 pub mod analyzer;
+pub mod anomaly;
+pub mod archive;
+pub mod audit;
+pub mod batch;
+pub mod bench;
+pub mod bmp;
+pub mod capabilities;
 pub mod cli;
+pub mod cloud;
+pub mod compare;
+#[cfg(all(unix, feature = "daemon"))]
+pub mod daemon;
+pub mod dedup;
+pub mod dispatch;
+pub mod dump;
+pub mod explain;
+pub mod formats;
+pub mod geo;
+pub mod gif;
+pub mod gps;
+pub mod headers;
+pub mod i18n;
+pub mod iptc;
+pub mod jpeg;
+pub mod jxl;
+pub mod manifest;
+pub mod multipicture;
+#[cfg(feature = "notify")]
+pub mod notify;
+pub mod options;
+pub mod originals;
+pub mod padding;
+pub mod payload;
+#[cfg(feature = "pdf")]
+pub mod pdf;
+pub mod pii;
+#[cfg(feature = "plugins")]
+pub mod plugin;
+pub mod png;
 pub mod privacy;
 pub mod processor;
+pub mod progress;
+#[cfg(feature = "reencode")]
+pub mod reencode;
+pub mod remnants;
 pub mod remover;
+pub mod report;
+pub mod restore;
+pub mod review;
+pub mod sandbox;
+pub mod savings;
+pub mod selftest;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod stayopen;
+pub mod style;
+#[cfg(feature = "testkit")]
+pub mod testkit;
+pub mod tiff;
+pub mod timeline;
+pub mod triage;
+#[cfg(feature = "tui")]
+pub mod tui;
 pub mod utils;
+pub mod walker;
+#[cfg(feature = "webhook")]
+pub mod webhook;
+
+use std::sync::Arc;
 
 // Re-export main types for easier use
-pub use analyzer::{ExifAnalyzer, PrivacyField, PrivacyCategory};
-pub use cli::Config;
-pub use privacy::{PrivacyLevel, PrivacyPolicy};
-pub use processor::ImageProcessor;
+pub use analyzer::{AnalysisReport, ExifAnalyzer, Finding, PrivacyField, PrivacyCategory, Severity};
+pub use batch::ProcessingStats;
+pub use cli::{Config, ConfigErrors};
+pub use options::ProcessorOptions;
+pub use privacy::{GpsGroup, PolicyOverride, PolicySpec, PrivacyLevel, PrivacyPolicy};
+pub use processor::{ImageProcessor, ProcessedImage};
+pub use progress::{FileFindings, FindingsReporter};
 pub use remover::MetadataRemover;
 
 /// Main library interface for processing images
+///
+/// Clones are cheap and share one processor, including its configuration,
+/// audit log, and running tallies, so a server can hand a clone to each
+/// worker thread.
+#[derive(Clone)]
 pub struct PrivacyExifCleaner {
-    processor: ImageProcessor,
+    processor: Arc<ImageProcessor>,
 }
 
 impl PrivacyExifCleaner {
-    /// Create a new instance with the given configuration
-    pub fn new(config: Config) -> Self {
+    /// Create a new instance with the given configuration, owned or already shared
+    pub fn new(config: impl Into<Arc<Config>>) -> Self {
         Self {
-            processor: ImageProcessor::new(config),
+            processor: Arc::new(ImageProcessor::new(config)),
         }
     }
 
+    /// Like `new`, but first checks the configuration with `Config::validate`
+    pub fn try_new(config: impl Into<Arc<Config>>) -> Result<Self, ConfigErrors> {
+        let config = config.into();
+        config.validate()?;
+        Ok(Self::new(config))
+    }
+
+    /// Create a new instance from library options, leaving the command-line settings at their defaults
+    pub fn with_options(options: ProcessorOptions) -> Self {
+        Self::new(Config::from(options))
+    }
+
     /// Create a new instance with default settings for a given privacy level
     pub fn with_privacy_level(privacy_level: PrivacyLevel) -> Self {
-        let config = Config {
-            input_dir: ".".to_string(),
-            output_dir: None,
-            recursive: false,
-            create_backup: false,
-            privacy_level,
-            verbose: false,
-            dry_run: false,
-        };
-        
-        Self::new(config)
+        Self::new(Config::with_privacy_level(privacy_level))
+    }
+
+    /// Hand each processed file's findings to `reporter`; nothing is printed otherwise
+    ///
+    /// Clones made before this call keep their own processor, without the reporter.
+    pub fn with_findings_reporter(self, reporter: Box<dyn FindingsReporter>) -> Self {
+        let processor = Arc::try_unwrap(self.processor)
+            .unwrap_or_else(|shared| ImageProcessor::new(shared.shared_config()));
+        Self {
+            processor: Arc::new(processor.with_findings_reporter(reporter)),
+        }
     }
 
-    /// Process a single image file
+    /// Process a single image file, returning whether privacy data was found
     pub fn process_image<P: AsRef<std::path::Path>>(&self, path: P) -> Result<bool, Box<dyn std::error::Error>> {
-        self.processor.process_image(path.as_ref())
+        Ok(self.processor.process_image(path.as_ref())?.is_none())
+    }
+
+    /// Clean every image under the configured input directory, returning the run's statistics
+    pub fn process_directory(&self) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+        batch::process_directory(&self.processor, &mut progress::NoProgress)
+    }
+
+    /// Like `process_directory`, reporting each finished file to `progress`
+    pub fn process_directory_with_progress(&self, progress: &mut dyn progress::Progress) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+        batch::process_directory(&self.processor, progress)
+    }
+
+    /// Process an image held in memory, returning the cleaned bytes if anything was removed
+    pub fn process_image_data(&self, data: &[u8]) -> Result<ProcessedImage, Box<dyn std::error::Error>> {
+        self.processor.process_image_data(data, std::path::Path::new("<memory>"))
+    }
+
+    /// Clean every supported image inside a ZIP archive, writing a sanitized copy to `output_path`
+    pub fn process_archive<P: AsRef<std::path::Path>, Q: AsRef<std::path::Path>>(&self, archive_path: P, output_path: Q) -> Result<archive::ArchiveStats, Box<dyn std::error::Error>> {
+        archive::process_archive(&self.processor, archive_path.as_ref(), output_path.as_ref())
     }
 
     /// Analyze what privacy data exists in an image without removing it
     pub fn analyze_image<P: AsRef<std::path::Path>>(&self, path: P) -> Result<Vec<PrivacyField>, Box<dyn std::error::Error>> {
-        let file_data = std::fs::read(path.as_ref())?;
         let analyzer = ExifAnalyzer::new();
-        analyzer.analyze_privacy_data(&file_data, path.as_ref(), &self.processor.config().privacy_level, false)
+        analyzer.analyze_file(path.as_ref(), &self.processor.config().privacy_level)
+    }
+
+    /// Analyze an image held in memory without removing anything
+    pub fn analyze_image_data(&self, data: &[u8]) -> Result<AnalysisReport, Box<dyn std::error::Error>> {
+        self.processor.analyze_bytes(data)
     }
 
     /// Get the current configuration
@@ -99,11 +208,18 @@ pub mod convenience {
         Ok(privacy_fields.iter().any(|field| field.category == PrivacyCategory::Location))
     }
 
+    /// Describe where an image's GPS data places it (e.g. "in Berlin, Germany"), offline
+    pub fn describe_gps_location<P: AsRef<Path>>(image_path: P) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let analyzer = ExifAnalyzer::new();
+        let coords = analyzer.parse_exif_file(image_path.as_ref())?
+            .and_then(|exif| geo::coordinates_from_exif(&exif));
+        Ok(coords.map(|coords| geo::describe_location(&coords).to_string()))
+    }
+
     /// Check if an image has any EXIF data at all
     pub fn has_exif_data<P: AsRef<Path>>(image_path: P) -> Result<bool, Box<dyn std::error::Error>> {
-        let file_data = std::fs::read(image_path)?;
         let analyzer = ExifAnalyzer::new();
-        Ok(analyzer.has_exif_data(&file_data))
+        Ok(analyzer.parse_exif_file(image_path.as_ref())?.is_some())
     }
 
     /// Get a summary of privacy categories found in an image
@@ -190,19 +306,61 @@ mod tests {
     #[test]
     fn test_privacy_exif_cleaner_creation() {
         let config = Config {
-            input_dir: ".".to_string(),
-            output_dir: None,
-            recursive: false,
-            create_backup: false,
             privacy_level: PrivacyLevel::Standard,
-            verbose: false,
-            dry_run: false,
+            ..Config::default()
         };
 
         let cleaner = PrivacyExifCleaner::new(config);
         assert_eq!(cleaner.config().privacy_level, PrivacyLevel::Standard);
     }
 
+    #[test]
+    fn test_cleaner_is_shared_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ExifAnalyzer>();
+        assert_send_sync::<ImageProcessor>();
+        assert_send_sync::<PrivacyExifCleaner>();
+
+        let cleaner = PrivacyExifCleaner::with_privacy_level(PrivacyLevel::Strict);
+        let workers: Vec<_> = (0..4)
+            .map(|_| {
+                let cleaner = cleaner.clone();
+                std::thread::spawn(move || cleaner.analyze_image_data(b"not an image").unwrap().is_clean())
+            })
+            .collect();
+        for worker in workers {
+            assert!(worker.join().unwrap());
+        }
+        // Clones share the configuration instead of copying it
+        assert!(std::ptr::eq(cleaner.config(), cleaner.clone().config()));
+    }
+
+    #[test]
+    fn test_config_problems_are_reported_together() {
+        let temp_dir = TempDir::new().unwrap();
+        let blocker = temp_dir.path().join("file");
+        fs::write(&blocker, b"").unwrap();
+
+        let config = Config {
+            input: temp_dir.path().join("missing"),
+            output: Some(blocker.join("out")),
+            create_backup: true,
+            jobs: 0,
+            ..Config::default()
+        };
+        let errors = PrivacyExifCleaner::try_new(config).err().unwrap();
+        assert_eq!(errors.0.len(), 4, "{}", errors);
+        assert!(errors.to_string().starts_with("Invalid configuration:"));
+
+        let config = Config {
+            input: temp_dir.path().to_path_buf(),
+            output: Some(temp_dir.path().join("new/out")),
+            ..Config::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(PrivacyExifCleaner::try_new(config).is_ok());
+    }
+
     #[test]
     fn test_privacy_exif_cleaner_with_privacy_level() {
         let cleaner = PrivacyExifCleaner::with_privacy_level(PrivacyLevel::Paranoid);
@@ -228,6 +386,8 @@ mod tests {
                 tag: Tag::GPSLatitude,
                 description: "GPS Latitude: 40.7128".to_string(),
                 category: PrivacyCategory::Location,
+                ifd: None,
+                value: None,
             }
         ];
         
@@ -259,4 +419,4 @@ mod tests {
         let _ = analyze_privacy_data(&fake_image, PrivacyLevel::Standard);
         let _ = get_privacy_summary(&fake_image, PrivacyLevel::Minimal);
     }
-}
\ No newline at end of file
+}
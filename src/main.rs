@@ -1,104 +1,838 @@
+mod anomaly;
+mod archive;
+mod audit;
+mod batch;
+mod bench;
+mod bmp;
+mod capabilities;
 mod cli;
+mod cloud;
+mod compare;
+#[cfg(all(unix, feature = "daemon"))]
+mod daemon;
+mod dedup;
+mod dispatch;
+mod dump;
+mod explain;
+mod formats;
+mod geo;
+mod gif;
+mod headers;
+mod i18n;
+mod iptc;
+mod jpeg;
+mod jxl;
+mod manifest;
+mod multipicture;
+#[cfg(feature = "notify")]
+mod notify;
+mod originals;
+mod padding;
+mod payload;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod pii;
+#[cfg(feature = "plugins")]
+mod plugin;
+mod png;
 mod privacy;
 mod processor;
+mod progress;
 mod analyzer;
+#[cfg(feature = "reencode")]
+mod reencode;
+mod remnants;
 mod remover;
+mod report;
+mod restore;
+mod review;
+mod sandbox;
+mod savings;
+mod selftest;
+#[cfg(feature = "server")]
+mod server;
+mod stayopen;
+mod style;
+mod tiff;
+mod triage;
+#[cfg(feature = "tui")]
+mod tui;
 mod utils;
+mod walker;
+#[cfg(feature = "webhook")]
+mod webhook;
 
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
 use std::path::Path;
-use walkdir::WalkDir;
-use cli::Config;
+use std::time::Instant;
+use batch::ProcessingStats;
+use cli::{ApplyMetadataOptions, AuditOptions, CliCommand, CompareOptions, Config, WatchOptions, SelfTestOptions, DumpOptions, DuplicatePolicy, LogFormat, PolicyCommand, SummaryFormat, ReportFormat, ReportKind, ReportOptions, RestoreOptions, ServeOptions, VerifyManifestOptions};
+use tracing_subscriber::EnvFilter;
+use analyzer::PrivacyCategory;
+use i18n::tr;
 use processor::ImageProcessor;
+use progress::{FileFindings, FindingsReporter, LogFindings};
+use remover::MetadataRemover;
+use tracing::{error, info, warn};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let config = Config::from_args()?;
-    
-    // Validate input directory
-    if !Path::new(&config.input_dir).is_dir() {
-        eprintln!("Error: Input path '{}' is not a directory", config.input_dir);
+    let args = cli::parse_args()?;
+    // The dashboard owns the terminal; log lines would tear through it
+    let tui = matches!(args.command, CliCommand::Clean(ref config) if shows_dashboard(config));
+    let quiet = matches!(args.command, CliCommand::Clean(ref config) if config.quiet);
+    style::init(&args.color);
+    if !tui {
+        init_logging(&args.log_format, quiet);
+    }
+    if let Some(ref path) = args.exiftool {
+        capabilities::set_exiftool_path(path)?;
+    }
+
+    match args.command {
+        CliCommand::Clean(config) => run_clean(*config),
+        CliCommand::Serve(options) => run_serve(&options),
+        CliCommand::Report(options) => run_report(&options),
+        CliCommand::Dump(options) => run_dump(&options),
+        CliCommand::Policy(command) => run_policy(&command),
+        CliCommand::ApplyMetadata(options) => run_apply_metadata(&options),
+        CliCommand::Compare(options) => run_compare(&options),
+        CliCommand::VerifyManifest(options) => run_verify_manifest(&options),
+        CliCommand::Audit(options) => run_audit(&options),
+        CliCommand::Restore(options) => run_restore(&options),
+        CliCommand::Watch(options) => run_daemon(&options),
+        CliCommand::SelfTest(options) => run_self_test(&options),
+    }
+}
+
+/// Whether a clean with `config` runs the `--tui` dashboard
+///
+/// Only directory runs have one; archive, PDF, bucket, and stdin runs log as usual.
+fn shows_dashboard(config: &Config) -> bool {
+    let input = config.input.as_path();
+    let single_file = input.is_file() && (archive::is_supported_archive(input) || formats::is_pdf(input));
+    cfg!(feature = "tui")
+        && config.tui
+        && !config.health_check
+        && config.bench.is_none()
+        && !config.use_stdin
+        && !cli::is_s3(input)
+        && !single_file
+}
+
+/// Send diagnostics to stderr so stdout stays free for reports and piped image data
+///
+/// `quiet` lowers the default level to errors; `RUST_LOG` still takes precedence.
+fn init_logging(log_format: &LogFormat, quiet: bool) {
+    let default_level = if quiet { "error" } else { "info" };
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(default_level));
+    // Escape codes would end up inside JSON strings
+    if *log_format == LogFormat::Json {
+        style::set(style::Stream::Stderr, false);
+    }
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(style::enabled(style::Stream::Stderr))
+        .with_target(false);
+
+    match log_format {
+        LogFormat::Text => builder.without_time().init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+fn run_clean(mut config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    config.validate()?;
+    load_plugins(&mut config)?;
+    // Probe ExifTool up front so warnings about an old release come before per-file output
+    capabilities::installed();
+    if config.sandbox {
+        sandbox::check().map_err(|e| format!("Cannot sandbox ExifTool: {}", e))?;
+    }
+
+    if config.health_check {
+        return run_health_check(&config);
+    }
+    if let Some(count) = config.bench {
+        let results = bench::run(&config, count)?;
+        print_bench_results(&results);
+        return Ok(());
+    }
+    if config.use_stdin {
+        return run_stdin(config);
+    }
+    if config.nice {
+        if let Err(e) = utils::lower_priority() {
+            warn!("Could not lower process priority: {}", e);
+        }
+    }
+
+    let audit_log = open_audit_log(&config)?;
+
+    let input_path = config.input.as_path();
+    let is_archive = input_path.is_file() && archive::is_supported_archive(input_path);
+    let is_cloud = cli::is_s3(input_path);
+    let is_pdf = input_path.is_file() && formats::is_pdf(input_path);
+
+    if is_cloud && !cfg!(feature = "cloud") {
+        error!("S3 input requires building with `--features cloud`");
+        std::process::exit(1);
+    }
+    if is_pdf && !cfg!(feature = "pdf") {
+        error!("PDF input requires building with `--features pdf`");
+        std::process::exit(1);
+    }
+    if config.reencode_fallback && !cfg!(feature = "reencode") {
+        error!("--reencode-fallback requires building with `--features reencode`");
+        std::process::exit(1);
+    }
+
+    // Validate input directory (or archive)
+    if !is_cloud && !input_path.is_dir() && !is_archive && !is_pdf {
+        error!("Input path '{}' is not a directory, ZIP archive, or PDF", input_path.display());
         std::process::exit(1);
     }
 
     // Create output directory if specified
-    if let Some(ref out_dir) = config.output_dir {
-        std::fs::create_dir_all(out_dir)?;
+    if let Some(ref out_dir) = config.output {
+        if !cli::is_s3(out_dir) {
+            std::fs::create_dir_all(out_dir)?;
+        }
+    }
+
+    if let Some(ref export_dir) = config.export_metadata {
+        if is_cloud || is_archive || is_pdf {
+            warn!("--export-metadata only exports image files on disk; archive, PDF, and bucket contents are not exported");
+        } else if !config.dry_run {
+            std::fs::create_dir_all(export_dir)?;
+        }
     }
 
     if config.dry_run {
-        println!("DRY RUN MODE - No files will be modified");
+        info!("DRY RUN MODE - No files will be modified");
     }
 
-    println!("Privacy level: {:?}", config.privacy_level);
+    println!("{}", tr("settings-level", &[("level", format!("{:?}", config.privacy_level).into())]));
     config.print_privacy_explanation();
 
-    let processor = ImageProcessor::new(config);
-    let stats = run_processing(&processor)?;
+    let duplicate_index = match config.output {
+        Some(ref out_dir) if config.duplicates != DuplicatePolicy::Process && !is_cloud => {
+            let index = dedup::DuplicateIndex::scan(out_dir)?;
+            info!(indexed = index.len(), "Indexed existing images in output directory");
+            Some(index)
+        }
+        _ => None,
+    };
+
+    let mut processor = ImageProcessor::new(config);
+    if processor.config().verbose {
+        processor = processor.with_findings_reporter(Box::new(LogFindings));
+    }
+    if let Some(audit_log) = audit_log {
+        processor = processor.with_audit_log(audit_log);
+    }
+    if let Some(duplicate_index) = duplicate_index {
+        processor = processor.with_duplicate_index(duplicate_index);
+    }
+    if processor.config().manifest.is_some() && !processor.config().dry_run {
+        let manifest = manifest::Manifest::new(processor.config().privacy_level.clone());
+        processor = processor.with_manifest(manifest);
+    }
+    if processor.config().interactive {
+        if is_cloud || is_archive || is_pdf {
+            warn!("--interactive only applies to directories and single files; cleaning without asking");
+        } else {
+            processor = processor.with_review(review::InteractiveReview::stdin());
+        }
+    }
+
+    let stats = if is_cloud {
+        run_cloud_processing(&processor)?
+    } else if is_archive {
+        run_archive_processing(&processor)?
+    } else if is_pdf {
+        run_pdf_processing(&processor)?
+    } else if processor.config().tui {
+        run_with_dashboard(&processor)?
+    } else {
+        batch::process_directory(&processor, &mut progress::LogProgress)?
+    };
+
+    if let (Some(manifest), Some(manifest_path)) = (processor.manifest(), &processor.config().manifest) {
+        manifest.write(manifest_path)?;
+        info!(path = %manifest_path.display(), files = manifest.len(), "Output manifest written");
+    }
+
+    print_summary(&stats, &processor.config().summary_format);
+
+    if processor.config().notify {
+        notify_completion(&stats, processor.config().dry_run);
+    }
+    if let Some(ref url) = processor.config().webhook {
+        post_webhook(url, &stats);
+    }
+
+    if let Some(threshold) = processor.config().fail_threshold {
+        let blocking = stats.fields_at_or_above(threshold);
+        if blocking > 0 {
+            return Err(format!("{} privacy fields of {} severity or higher found", blocking, threshold).into());
+        }
+    }
+    Ok(())
+}
+
+/// Load `--plugin` libraries, and switch to the `--plugin-policy` they registered
+#[cfg(feature = "plugins")]
+fn load_plugins(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut loaded = plugin::load_plugins(&config.plugins)?;
+    if let Some(ref name) = config.plugin_policy {
+        let spec = loaded.policies.remove(name).ok_or_else(|| {
+            let known: Vec<&str> = loaded.policies.keys().map(String::as_str).collect();
+            format!("No plugin registers policy '{}' (registered: {})", name, known.join(", "))
+        })?;
+        config.privacy_level = privacy::PrivacyLevel::Custom(spec);
+    }
+    Ok(())
+}
 
-    print_summary(&stats);
+#[cfg(not(feature = "plugins"))]
+fn load_plugins(config: &mut Config) -> Result<(), Box<dyn std::error::Error>> {
+    if !config.plugins.is_empty() {
+        return Err("--plugin requires building with `--features plugins`".into());
+    }
     Ok(())
 }
 
-fn run_processing(processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
-    let mut stats = ProcessingStats::new();
+/// Summarize the finished run in a desktop notification; failures are only logged
+#[cfg(feature = "notify")]
+fn notify_completion(stats: &ProcessingStats, dry_run: bool) {
+    let summary = if stats.errors > 0 {
+        format!("Privacy cleaning finished with {} errors", stats.errors)
+    } else {
+        "Privacy cleaning finished".to_string()
+    };
+    // Dry runs remove nothing, so report what was found
+    let (fields, action) = match dry_run {
+        true => (stats.fields_found(), "would be removed"),
+        false => (stats.fields_removed(), "removed"),
+    };
+    let body = format!(
+        "{} files processed, {} with privacy data, {} fields {}",
+        stats.processed,
+        stats.privacy_data_found,
+        fields,
+        action,
+    );
+
+    if let Err(e) = notify::send(&summary, &body) {
+        warn!("Could not show desktop notification: {}", e);
+    }
+}
+
+#[cfg(not(feature = "notify"))]
+fn notify_completion(_stats: &ProcessingStats, _dry_run: bool) {
+    warn!("--notify requires building with `--features notify`");
+}
+
+/// POST the run report, as printed by `--summary-format json`; failures are only logged
+#[cfg(feature = "webhook")]
+fn post_webhook(url: &str, stats: &ProcessingStats) {
+    match webhook::post(url, &summary_json(stats)) {
+        Ok(()) => info!(url, "Run report posted"),
+        Err(e) => warn!(url, "Could not post run report: {}", e),
+    }
+}
 
-    let walker = if processor.config().recursive {
-        WalkDir::new(&processor.config().input_dir)
+#[cfg(not(feature = "webhook"))]
+fn post_webhook(_url: &str, _stats: &ProcessingStats) {
+    warn!("--webhook requires building with `--features webhook`");
+}
+
+/// Open the audit log requested in the config, if any (never in dry-run mode)
+fn open_audit_log(config: &Config) -> Result<Option<audit::AuditLog>, Box<dyn std::error::Error>> {
+    let log_path = match config.audit_log {
+        Some(ref path) if !config.dry_run => path,
+        _ => return Ok(None),
+    };
+
+    let signing_key = match config.audit_key {
+        Some(ref key_path) => Some(audit::load_signing_key(key_path)?),
+        None => None,
+    };
+
+    Ok(Some(audit::AuditLog::open(log_path, signing_key)?))
+}
+
+/// Print the self-test report; a level that leaves privacy data behind fails the run
+fn run_self_test(options: &SelfTestOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let report = selftest::self_test()?;
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
     } else {
-        WalkDir::new(&processor.config().input_dir).max_depth(1)
+        print!("{}", report.to_text());
+    }
+
+    if !report.passed {
+        return Err("Self-test failed: this install does not remove all privacy data".into());
+    }
+    Ok(())
+}
+
+/// Print the health report; unhealthy installs fail, exiting with status 1 for readiness probes
+fn run_health_check(config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let report = selftest::health_check(&config.privacy_level);
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    if !report.healthy {
+        return Err("Health check failed".into());
+    }
+    Ok(())
+}
+
+/// Print `--bench` results as an aligned table
+fn print_bench_results(results: &[bench::BenchResult]) {
+    println!("{:<10} {:>7} {:>10} {:>9} {:>10} {:>9}", "Stage", "Files", "MB", "Seconds", "Files/s", "MB/s");
+    for result in results {
+        println!(
+            "{:<10} {:>7} {:>10.1} {:>9.2} {:>10.1} {:>9.1}",
+            result.backend,
+            result.files,
+            result.bytes as f64 / (1024.0 * 1024.0),
+            result.elapsed.as_secs_f64(),
+            result.files_per_sec(),
+            result.mb_per_sec(),
+        );
+    }
+}
+
+/// Clean a single image from stdin to stdout
+///
+/// stdout carries only image bytes, so all reporting goes to stderr.
+fn run_stdin(config: Config) -> Result<(), Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    std::io::stdin().read_to_end(&mut data)?;
+
+    let display_path = Path::new("<stdin>");
+    let verbose = config.verbose;
+    let dry_run = config.dry_run;
+    let audit_log = open_audit_log(&config)?;
+    let mut processor = ImageProcessor::new(Config { verbose: false, dry_run: false, ..config });
+    if let Some(audit_log) = audit_log {
+        processor = processor.with_audit_log(audit_log);
+    }
+
+    if verbose || dry_run {
+        let report = processor.analyze_bytes(&data)?;
+        let level = &processor.config().privacy_level;
+        LogFindings.report(&FileFindings { path: display_path, level, fields: &report.fields });
+    }
+
+    if dry_run {
+        info!("DRY RUN MODE - No output written");
+        return Ok(());
+    }
+
+    let result = processor.process_image_data(&data, display_path)?;
+    let mut stdout = std::io::stdout().lock();
+    stdout.write_all(result.output(&data))?;
+    stdout.flush()?;
+
+    Ok(())
+}
+
+fn run_report(options: &ReportOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let input_path = options.input_dir.as_path();
+    if !input_path.is_dir() {
+        error!("Input path '{}' is not a directory", input_path.display());
+        std::process::exit(1);
+    }
+
+    let rendered = match options.kind {
+        ReportKind::Compliance => {
+            let report = report::scan_directory(input_path, options.recursive, &options.privacy_level)?;
+            match options.format {
+                ReportFormat::Json => report.to_json()?,
+                ReportFormat::Html => report.to_html(),
+            }
+        }
+        ReportKind::GpsClusters => {
+            let report = report::scan_gps_clusters(input_path, options.recursive, options.cluster_radius_m)?;
+            match options.format {
+                ReportFormat::Json => report.to_json()?,
+                ReportFormat::Html => report.to_html(),
+            }
+        }
+        ReportKind::Devices => {
+            let report = report::scan_devices(input_path, options.recursive)?;
+            match options.format {
+                ReportFormat::Json => report.to_json()?,
+                ReportFormat::Html => report.to_html(),
+            }
+        }
     };
 
-    for entry in walker {
-        let entry = match entry {
-            Ok(entry) => entry,
+    match options.output_file {
+        Some(ref path) => {
+            std::fs::write(path, rendered)?;
+            info!(output = %path.display(), "Report written");
+        }
+        None => println!("{}", rendered),
+    }
+
+    Ok(())
+}
+
+fn run_policy(command: &PolicyCommand) -> Result<(), Box<dyn std::error::Error>> {
+    match command {
+        PolicyCommand::Explain(level) => print!("{}", explain::explain(level)),
+        PolicyCommand::Diff(from, to) => print!("{}", explain::diff(from, to)),
+    }
+    Ok(())
+}
+
+fn run_dump(options: &DumpOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mut dumps = Vec::new();
+    let mut failed = 0;
+
+    for file in &options.files {
+        match dump::dump_file(file) {
+            Ok(dump) => dumps.push(dump),
             Err(e) => {
-                eprintln!("Error walking directory: {}", e);
-                stats.errors += 1;
-                continue;
+                error!(path = %file.display(), "Cannot read metadata: {}", e);
+                failed += 1;
             }
-        };
-
-        if entry.file_type().is_file() {
-            let path = entry.path();
-            
-            if utils::is_supported_image(path) {
-                match processor.process_image(path) {
-                    Ok(had_privacy_data) => {
-                        if processor.config().verbose || processor.config().dry_run {
-                            println!("Processed: {}", path.display());
-                        }
-                        stats.processed += 1;
-                        if had_privacy_data {
-                            stats.privacy_data_found += 1;
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Error processing {}: {}", path.display(), e);
-                        stats.errors += 1;
+        }
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&dumps)?);
+    } else {
+        for dump in &dumps {
+            print!("{}", dump.to_text());
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} file(s) could not be dumped", failed).into());
+    }
+    Ok(())
+}
+
+fn run_apply_metadata(options: &ApplyMetadataOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let image = options.image.as_path();
+    let output = options.output.as_deref().unwrap_or(image);
+
+    MetadataRemover::new().apply_metadata(&options.sidecar, image, output)
+        .map_err(|e| format!("Failed to apply '{}' to '{}': {}", options.sidecar.display(), image.display(), e))?;
+    info!(sidecar = %options.sidecar.display(), output = %output.display(), "Applied metadata");
+    Ok(())
+}
+
+fn run_restore(options: &RestoreOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if !options.input.is_dir() {
+        return Err(format!("Input path '{}' is not a directory", options.input.display()).into());
+    }
+
+    let stats = restore::restore(options);
+    info!(restored = stats.restored, failed = stats.failed, dry_run = options.dry_run, "Restore complete");
+
+    if stats.failed > 0 {
+        return Err(format!("{} file(s) could not be restored", stats.failed).into());
+    }
+    Ok(())
+}
+
+fn run_compare(options: &CompareOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let comparison = compare::compare_files(&options.original, &options.cleaned)?;
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&comparison)?);
+    } else {
+        print!("{}", comparison.to_text());
+    }
+    Ok(())
+}
+
+fn run_verify_manifest(options: &VerifyManifestOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let mismatched = manifest::verify_manifest(&options.manifest)?;
+    if mismatched.is_empty() {
+        println!("All files match {}", options.manifest.display());
+        return Ok(());
+    }
+
+    for path in &mismatched {
+        println!("missing or changed: {}", path.display());
+    }
+    Err(format!("{} file(s) don't match the manifest", mismatched.len()).into())
+}
+
+fn run_audit(options: &AuditOptions) -> Result<(), Box<dyn std::error::Error>> {
+    if options.files.iter().any(|file| file.is_dir()) {
+        return run_batch_audit(options);
+    }
+
+    let mut audits = Vec::new();
+    let mut failed = 0;
+
+    for file in &options.files {
+        match triage::audit_file(file, options.anomalies) {
+            Ok(audit) => audits.push(audit),
+            Err(e) => {
+                error!(path = %file.display(), "Cannot audit: {}", e);
+                failed += 1;
+            }
+        }
+    }
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&audits)?);
+    } else {
+        for audit in &audits {
+            print!("{}", audit.to_text());
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} file(s) could not be audited", failed).into());
+    }
+    Ok(())
+}
+
+/// Audit directories as one batch, ranking the riskiest files
+fn run_batch_audit(options: &AuditOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let batch = triage::audit_paths(&options.files, options.recursive, options.top, options.anomalies)?;
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&batch)?);
+    } else {
+        print!("{}", batch.to_text());
+    }
+
+    if !batch.failed.is_empty() {
+        return Err(format!("{} file(s) could not be audited", batch.failed.len()).into());
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, feature = "daemon"))]
+fn run_daemon(options: &WatchOptions) -> Result<(), Box<dyn std::error::Error>> {
+    daemon::run(options)
+}
+
+#[cfg(not(all(unix, feature = "daemon")))]
+fn run_daemon(_options: &WatchOptions) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The watch subcommand requires a Unix system and building with `--features daemon`".into())
+}
+
+#[cfg(feature = "server")]
+fn run_serve(options: &ServeOptions) -> Result<(), Box<dyn std::error::Error>> {
+    server::serve(options)
+}
+
+#[cfg(not(feature = "server"))]
+fn run_serve(_options: &ServeOptions) -> Result<(), Box<dyn std::error::Error>> {
+    Err("The serve subcommand requires building with `--features server`".into())
+}
+
+#[cfg(feature = "tui")]
+fn run_with_dashboard(processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    let mut dashboard = tui::Dashboard::start(processor.category_tally())?;
+    let stats = batch::process_directory(processor, &mut dashboard);
+
+    for error in dashboard.finish()? {
+        eprintln!("Error: {}", error);
+    }
+    stats
+}
+
+#[cfg(not(feature = "tui"))]
+fn run_with_dashboard(_processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    Err("--tui requires building with `--features tui`".into())
+}
+
+fn run_archive_processing(processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    let archive_path = processor.config().input.as_path();
+    let output_path = archive::get_archive_output_path(
+        archive_path,
+        processor.config().output.as_deref(),
+    )?;
+
+    let started = Instant::now();
+    let archive_stats = archive::process_archive(processor, archive_path, &output_path)?;
+
+    if !processor.config().dry_run {
+        info!(output = %output_path.display(), "Sanitized archive written");
+        if let Some(manifest) = processor.manifest() {
+            manifest.record(archive_path, &output_path, manifest::OutputAction::Cleaned)?;
+        }
+    }
+
+    Ok(ProcessingStats {
+        processed: archive_stats.processed,
+        privacy_data_found: archive_stats.privacy_data_found,
+        errors: archive_stats.errors,
+        unsupported_skipped: archive_stats.unsupported_skipped,
+        fields_by_category: processor.category_tally().counts().into_iter().collect(),
+        removed_by_category: processor.removal_tally().counts().into_iter().collect(),
+        duration: started.elapsed(),
+        ..ProcessingStats::new()
+    })
+}
+
+#[cfg(feature = "pdf")]
+fn run_pdf_processing(processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    let pdf_path = processor.config().input.as_path();
+    let output_path = pdf::get_pdf_output_path(pdf_path, processor.config().output.as_deref())?;
+
+    let started = Instant::now();
+    let pdf_stats = pdf::process_pdf(processor, pdf_path, &output_path)?;
+
+    if !processor.config().dry_run {
+        info!(output = %output_path.display(), "Sanitized PDF written");
+        if let Some(manifest) = processor.manifest() {
+            manifest.record(pdf_path, &output_path, manifest::OutputAction::Cleaned)?;
+        }
+    }
+
+    Ok(ProcessingStats {
+        processed: pdf_stats.processed,
+        privacy_data_found: pdf_stats.privacy_data_found,
+        errors: pdf_stats.errors,
+        fields_by_category: processor.category_tally().counts().into_iter().collect(),
+        removed_by_category: processor.removal_tally().counts().into_iter().collect(),
+        duration: started.elapsed(),
+        ..ProcessingStats::new()
+    })
+}
+
+#[cfg(not(feature = "pdf"))]
+fn run_pdf_processing(_processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    Err("PDF input requires building with `--features pdf`".into())
+}
+
+#[cfg(feature = "cloud")]
+fn run_cloud_processing(processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    let input = cloud::S3Location::from_path(&processor.config().input).ok_or("Invalid S3 input URI")?;
+    let output = processor.config().output.as_deref();
+
+    let started = Instant::now();
+    let cloud_stats = cloud::process_bucket(processor, &input, output)?;
+
+    Ok(ProcessingStats {
+        processed: cloud_stats.processed,
+        privacy_data_found: cloud_stats.privacy_data_found,
+        errors: cloud_stats.errors,
+        fields_by_category: processor.category_tally().counts().into_iter().collect(),
+        removed_by_category: processor.removal_tally().counts().into_iter().collect(),
+        duration: started.elapsed(),
+        ..ProcessingStats::new()
+    })
+}
+
+#[cfg(not(feature = "cloud"))]
+fn run_cloud_processing(_processor: &ImageProcessor) -> Result<ProcessingStats, Box<dyn std::error::Error>> {
+    Err("S3 input requires building with `--features cloud`".into())
+}
+
+fn print_summary(stats: &ProcessingStats, format: &SummaryFormat) {
+    match format {
+        SummaryFormat::Text => print_text_summary(stats),
+        SummaryFormat::Json => println!("{}", summary_json(stats)),
+        SummaryFormat::Tsv => {
+            if let serde_json::Value::Object(fields) = summary_json(stats) {
+                for (key, value) in fields {
+                    match value {
+                        serde_json::Value::String(text) => println!("{}\t{}", key, text),
+                        serde_json::Value::Null => println!("{}\t", key),
+                        other => println!("{}\t{}", key, other),
                     }
                 }
             }
         }
     }
-
-    Ok(stats)
 }
 
-fn print_summary(stats: &ProcessingStats) {
-    println!("\nSummary:");
-    println!("Files processed: {}", stats.processed);
-    println!("Files with privacy data found: {}", stats.privacy_data_found);
-    println!("Errors: {}", stats.errors);
+/// Summary statistics under stable keys, for wrapper scripts
+fn summary_json(stats: &ProcessingStats) -> serde_json::Value {
+    serde_json::json!({
+        "processed": stats.processed,
+        "privacy_data_found": stats.privacy_data_found,
+        "already_clean": stats.already_clean,
+        "errors": stats.errors,
+        "unsupported_skipped": stats.unsupported_skipped,
+        "unsupported_copied": stats.unsupported_copied,
+        "not_selected": stats.not_selected,
+        "stopped_by": stats.stopped_by.as_ref().map(|limit| limit.to_string()),
+        "cancelled": stats.cancelled,
+        "fields_found": stats.fields_found(),
+        "fields_by_category": stats.fields_by_category.iter()
+            .map(|(category, count)| (category.to_string(), serde_json::json!(count)))
+            .collect::<serde_json::Map<_, _>>(),
+        "fields_removed": stats.fields_removed(),
+        "removed_by_category": stats.removed_by_category.iter()
+            .map(|(category, count)| (category.to_string(), serde_json::json!(count)))
+            .collect::<serde_json::Map<_, _>>(),
+        "bytes_processed": stats.bytes_processed,
+        "bytes_removed": stats.bytes_removed,
+        "bytes_removable": stats.bytes_removable,
+        "by_format": stats.by_format.iter()
+            .map(|(format, count)| (format.to_string(), serde_json::json!(count)))
+            .collect::<serde_json::Map<_, _>>(),
+        "duration_secs": stats.duration.as_secs_f64(),
+    })
 }
 
-#[derive(Default)]
-struct ProcessingStats {
-    processed: u32,
-    privacy_data_found: u32,
-    errors: u32,
+fn print_text_summary(stats: &ProcessingStats) {
+    println!("\n{}", style::bold(&tr("summary-heading", &[]), style::Stream::Stdout));
+    println!("{}", tr("summary-processed", &[("count", stats.processed.into())]));
+    println!("{}", tr("summary-privacy-found", &[("count", stats.privacy_data_found.into())]));
+    if stats.already_clean > 0 {
+        println!("{}", tr("summary-already-clean", &[("count", stats.already_clean.into())]));
+    }
+    let errors = tr("summary-errors", &[("count", stats.errors.into())]);
+    if stats.errors > 0 {
+        println!("{}", style::error(&errors, style::Stream::Stdout));
+    } else {
+        println!("{}", errors);
+    }
+    // Dry runs remove nothing, so fall back to what was found
+    if stats.fields_removed() > 0 {
+        println!("{}", tr("summary-fields-removed", &[("count", stats.fields_removed().into())]));
+        print_category_counts(&stats.removed_by_category);
+    } else if stats.fields_found() > 0 {
+        println!("{}", tr("summary-fields-found", &[("count", stats.fields_found().into())]));
+        print_category_counts(&stats.fields_by_category);
+    }
+    if stats.bytes_removed > 0 {
+        println!("{}", tr("summary-bytes-removed", &[("size", utils::format_file_size(stats.bytes_removed).into())]));
+    } else if stats.bytes_removable > 0 {
+        println!("{}", tr("summary-bytes-removable", &[("size", utils::format_file_size(stats.bytes_removable).into())]));
+    }
+    println!("{}", tr("summary-throughput", &[
+        ("size", utils::format_file_size(stats.bytes_processed).into()),
+        ("seconds", format!("{:.1}", stats.duration.as_secs_f64()).into()),
+    ]));
+    if stats.unsupported_skipped > 0 {
+        println!("{}", tr("summary-unsupported-skipped", &[("count", stats.unsupported_skipped.into())]));
+    }
+    if stats.unsupported_copied > 0 {
+        println!("{}", tr("summary-unsupported-copied", &[("count", stats.unsupported_copied.into())]));
+    }
+    if stats.not_selected > 0 {
+        println!("{}", tr("summary-not-selected", &[("count", stats.not_selected.into())]));
+    }
+    if let Some(ref limit) = stats.stopped_by {
+        println!("{}", tr("summary-stopped", &[("reason", limit.to_string().into())]));
+    }
+    if stats.cancelled {
+        println!("{}", tr("summary-cancelled", &[]));
+    }
 }
 
-impl ProcessingStats {
-    fn new() -> Self {
-        Self::default()
+fn print_category_counts(counts: &BTreeMap<PrivacyCategory, usize>) {
+    // Categories that identify a person stand out
+    for (category, count) in counts {
+        let line = format!("{}: {}", category.label(), count);
+        println!("  {}", style::severity(&line, category.severity(), style::Stream::Stdout));
     }
-}
\ No newline at end of file
+}
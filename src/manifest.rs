@@ -0,0 +1,147 @@
+//! Integrity manifest of a run's outputs.
+//!
+//! Lists every file a run wrote with its SHA-256, source path, and the policy
+//! used, so whoever receives the sanitized set can check that nothing was
+//! altered or added after cleaning.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::audit::sha256_hex;
+use crate::privacy::PrivacyLevel;
+use crate::utils;
+
+/// How an output file came to be
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputAction {
+    /// Privacy data was removed
+    Cleaned,
+    /// Nothing needed removing; copied byte-for-byte
+    Unchanged,
+    /// Hard-linked to another output with the same content
+    Linked,
+    /// Not a supported image; copied without cleaning
+    Uncleaned,
+    /// Privacy data was found but kept on interactive review
+    Kept,
+}
+
+/// One output file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub output: String,
+    pub source: String,
+    pub sha256: String,
+    pub action: OutputAction,
+}
+
+/// The manifest file as written to disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestDocument {
+    pub generated_at: u64,
+    pub privacy_level: String,
+    pub files: Vec<ManifestEntry>,
+}
+
+/// Collects output hashes during a run, safe to share between threads
+pub struct Manifest {
+    privacy_level: PrivacyLevel,
+    entries: Mutex<Vec<ManifestEntry>>,
+}
+
+impl Manifest {
+    pub fn new(privacy_level: PrivacyLevel) -> Self {
+        Self {
+            privacy_level,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Hash an output file and add it to the manifest
+    pub fn record(&self, source: &Path, output: &Path, action: OutputAction) -> Result<(), Box<dyn std::error::Error>> {
+        let sha256 = sha256_hex(&utils::map_file(output)?);
+        let entry = ManifestEntry {
+            output: output.display().to_string(),
+            source: source.display().to_string(),
+            sha256,
+            action,
+        };
+
+        self.entries.lock().map_err(|_| "Manifest lock poisoned")?.push(entry);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().map(|entries| entries.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Snapshot of the manifest, sorted by output path
+    pub fn document(&self) -> ManifestDocument {
+        let mut files = self.entries.lock().map(|entries| entries.clone()).unwrap_or_default();
+        files.sort_by(|a, b| a.output.cmp(&b.output));
+
+        ManifestDocument {
+            generated_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            privacy_level: format!("{:?}", self.privacy_level),
+            files,
+        }
+    }
+
+    /// Write the manifest as JSON
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(path, serde_json::to_string_pretty(&self.document())?)?;
+        Ok(())
+    }
+}
+
+/// Check every file listed in a manifest, returning those that are missing or changed
+pub fn verify_manifest<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>, Box<dyn std::error::Error>> {
+    let document: ManifestDocument = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+    let mismatched = document.files.into_iter()
+        .filter(|entry| match utils::map_file(Path::new(&entry.output)) {
+            Ok(data) => sha256_hex(&data) != entry.sha256,
+            Err(_) => true,
+        })
+        .map(|entry| PathBuf::from(entry.output))
+        .collect();
+
+    Ok(mismatched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_manifest_round_trip_detects_tampering() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_a = temp_dir.path().join("a.jpg");
+        let output_b = temp_dir.path().join("b.jpg");
+        std::fs::write(&output_a, b"cleaned a").unwrap();
+        std::fs::write(&output_b, b"cleaned b").unwrap();
+
+        let manifest = Manifest::new(PrivacyLevel::Standard);
+        manifest.record(Path::new("in/b.jpg"), &output_b, OutputAction::Unchanged).unwrap();
+        manifest.record(Path::new("in/a.jpg"), &output_a, OutputAction::Cleaned).unwrap();
+
+        let document = manifest.document();
+        assert_eq!(document.privacy_level, "Standard");
+        assert_eq!(document.files[0].source, "in/a.jpg");
+        assert_eq!(document.files[0].sha256, sha256_hex(b"cleaned a"));
+
+        let manifest_path = temp_dir.path().join("manifest.json");
+        manifest.write(&manifest_path).unwrap();
+        assert!(verify_manifest(&manifest_path).unwrap().is_empty());
+
+        std::fs::write(&output_b, b"tampered").unwrap();
+        assert_eq!(verify_manifest(&manifest_path).unwrap(), vec![output_b]);
+    }
+}
@@ -0,0 +1,295 @@
+//! Multi-picture JPEGs: MPO files and motion photos.
+//!
+//! MPO files (and phones' preview and depth companions) list further JPEGs
+//! after the first in an APP2 `MPF` index; Google and Samsung motion photos
+//! append an MP4 after the image. Every embedded picture carries metadata of
+//! its own and the video can hold a GPS position, so ExifTool cleaning the
+//! first picture is not enough. This module finds the parts and cleans them.
+
+use std::ops::Range;
+use std::sync::OnceLock;
+use regex::Regex;
+use crate::jpeg;
+
+/// Identifier that starts an MPF APP2 payload
+const MPF_HEADER: &[u8] = b"MPF\0";
+/// MPF tag listing the images in the file
+const MP_ENTRY: u16 = 0xB002;
+/// Size of one MP entry: attributes, size, offset, and two dependent image entries
+const MP_ENTRY_LEN: usize = 16;
+/// Samsung trailer entry holding the motion photo video
+const SAMSUNG_MOTION: &[u8] = b"MotionPhoto_Data";
+/// Start of the directory at the end of a Samsung trailer
+const SAMSUNG_DIRECTORY: &[u8] = b"SEFH";
+/// MP4 box type of the QuickTime GPS position
+const QUICKTIME_LOCATION: &[u8; 4] = b"\xA9xyz";
+/// Boxes that only contain other boxes on the way to the location
+const CONTAINER_BOXES: &[&[u8; 4]] = &[b"moov", b"trak", b"mdia", b"minf", b"udta", b"meta"];
+
+/// A picture after the first one, as listed in the MPF index
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddedImage {
+    /// Bytes of the picture within the file
+    pub range: Range<usize>,
+    /// Offset of the picture's MP entry, whose size and offset fields point at it
+    entry: usize,
+    little_endian: bool,
+}
+
+/// Pictures listed in the MPF index after the first, in file order
+pub fn embedded_images(data: &[u8]) -> Vec<EmbeddedImage> {
+    parse_mpf(data).map(|(_, images)| images).unwrap_or_default()
+}
+
+/// Offset of the TIFF header that MPF offsets count from, and the pictures after the first
+fn parse_mpf(data: &[u8]) -> Option<(usize, Vec<EmbeddedImage>)> {
+    let layout = jpeg::parse_layout(data).ok()?;
+    let segment = layout.segments.iter().find(|s| s.marker == jpeg::APP2 && s.payload.starts_with(MPF_HEADER))?;
+    // Marker and length bytes, then the identifier
+    let base = segment.offset + 4 + MPF_HEADER.len();
+    let tiff = data.get(base..)?;
+
+    let little_endian = match tiff.get(..4)? {
+        b"II*\0" => true,
+        b"MM\0*" => false,
+        _ => return None,
+    };
+    let read_u16 = |at: usize| {
+        let bytes = [*tiff.get(at)?, *tiff.get(at + 1)?];
+        Some(if little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) })
+    };
+    let read_u32 = |at: usize| {
+        let bytes = [*tiff.get(at)?, *tiff.get(at + 1)?, *tiff.get(at + 2)?, *tiff.get(at + 3)?];
+        Some(if little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) } as usize)
+    };
+
+    let ifd = read_u32(4)?;
+    let entry_count = read_u16(ifd)? as usize;
+    let (count, entries) = (0..entry_count)
+        .map(|i| ifd + 2 + i * 12)
+        .find(|&entry| read_u16(entry) == Some(MP_ENTRY))
+        .and_then(|entry| Some((read_u32(entry + 4)?, read_u32(entry + 8)?)))?;
+
+    let mut images: Vec<EmbeddedImage> = (1..count / MP_ENTRY_LEN)
+        .filter_map(|i| {
+            let entry = entries + i * MP_ENTRY_LEN;
+            let size = read_u32(entry + 4)?;
+            let start = base.checked_add(read_u32(entry + 8)?)?;
+            let end = start.checked_add(size)?;
+            // An offset of zero means the image isn't in this file
+            if start == base || end > data.len() {
+                return None;
+            }
+            Some(EmbeddedImage { range: start..end, entry: base + entry, little_endian })
+        })
+        .collect();
+    images.sort_by_key(|image| image.range.start);
+    Some((base, images))
+}
+
+/// Where a motion photo's video is, from the original file and its XMP
+pub fn motion_video(data: &[u8], xmp: &str) -> Option<Range<usize>> {
+    static ITEM: OnceLock<Regex> = OnceLock::new();
+    static MICRO_VIDEO: OnceLock<Regex> = OnceLock::new();
+
+    // Google: the last item of the container directory, counted from the end of the file
+    let item = ITEM.get_or_init(|| Regex::new(r#"<Container:Item\b[^>]*>"#).unwrap());
+    let length = item.find_iter(xmp)
+        .map(|element| element.as_str())
+        .find(|element| element.contains(r#"Item:Semantic="MotionPhoto""#))
+        .and_then(|element| attribute(element, "Item:Length"))
+        .or_else(|| {
+            // Older motion photos give the offset from the end directly
+            let micro_video = MICRO_VIDEO.get_or_init(|| Regex::new(r#"GCamera:MicroVideoOffset="(\d+)""#).unwrap());
+            micro_video.captures(xmp)?[1].parse().ok()
+        });
+    if let Some(length) = length.filter(|&length| length > 0 && length <= data.len()) {
+        return Some(data.len() - length..data.len());
+    }
+
+    // Samsung: an entry in the trailer, which ends in the trailer's directory
+    let start = find(data, SAMSUNG_MOTION)? + SAMSUNG_MOTION.len();
+    let end = start + find(&data[start..], SAMSUNG_DIRECTORY)?;
+    Some(start..end)
+}
+
+fn attribute(element: &str, name: &str) -> Option<usize> {
+    let value = element.split(&format!("{}=\"", name)).nth(1)?;
+    value.split('"').next()?.parse().ok()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+/// GPS positions in an MP4's QuickTime metadata, with the boxes they are stored in
+pub fn video_locations(video: &[u8]) -> Vec<(Range<usize>, String)> {
+    let mut found = Vec::new();
+    collect_locations(video, 0..video.len(), &mut found);
+    found
+}
+
+fn collect_locations(data: &[u8], range: Range<usize>, found: &mut Vec<(Range<usize>, String)>) {
+    let mut pos = range.start;
+    while pos + 8 <= range.end {
+        let size = u32::from_be_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]]) as usize;
+        let kind: [u8; 4] = [data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]];
+        let (header, size) = match size {
+            0 => (8, range.end - pos),
+            1 => match data.get(pos + 8..pos + 16) {
+                Some(large) => (16, u64::from_be_bytes(large.try_into().unwrap()) as usize),
+                None => return,
+            },
+            size => (8, size),
+        };
+        // Sizes come from the file: a huge one must neither overflow nor reach past the data
+        let end = match pos.checked_add(size) {
+            Some(end) if size >= header && end <= range.end && end <= data.len() => end,
+            _ => return,
+        };
+
+        let payload = pos + header..end;
+        if &kind == QUICKTIME_LOCATION {
+            // 16-bit length and language, then the ISO 6709 string
+            let text = data.get(payload.start + 4..payload.end).unwrap_or_default();
+            found.push((pos..end, String::from_utf8_lossy(text).trim_end_matches('\0').to_string()));
+        } else if CONTAINER_BOXES.contains(&&kind) {
+            // ISO `meta` boxes have version and flags before their children; QuickTime ones don't
+            let skip = if &kind == b"meta" && data.get(payload.start + 4..payload.start + 8) != Some(&b"hdlr"[..]) { 4 } else { 0 };
+            collect_locations(data, payload.start + skip..payload.end, found);
+        }
+        pos = end;
+    }
+}
+
+/// Clean the embedded pictures and the motion video of an image ExifTool has cleaned
+///
+/// ExifTool only rewrites the first picture's metadata. Each picture in the
+/// MPF index is run through `clean_image` and the index is updated to the new
+/// sizes; the video's GPS boxes are turned into `free` boxes of the same size
+/// when `scrub_video_gps` is set. `original` and `xmp` are the input before
+/// cleaning, whose XMP locates the video. Returns `None` when nothing changed.
+pub fn clean(
+    original: &[u8],
+    xmp: &str,
+    cleaned: &[u8],
+    scrub_video_gps: bool,
+    mut clean_image: impl FnMut(&[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>>,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    let mut output = cleaned.to_vec();
+    let mut changed = false;
+
+    // ExifTool keeps trailers byte for byte, so the video sits as far from the end as before
+    if let Some(video) = motion_video(original, xmp).filter(|_| scrub_video_gps) {
+        let (from_end, after) = (original.len() - video.start, original.len() - video.end);
+        if let Some(start) = output.len().checked_sub(from_end) {
+            let end = output.len() - after;
+            for (location, _) in video_locations(&output[start..end]) {
+                output[start + location.start + 4..start + location.start + 8].copy_from_slice(b"free");
+                changed = true;
+            }
+        }
+    }
+
+    let (base, images) = match parse_mpf(&output) {
+        Some((base, images)) if !images.is_empty() => (base, images),
+        _ => return Ok(if changed { Some(output) } else { None }),
+    };
+
+    let mut rebuilt = output[..images[0].range.start].to_vec();
+    let mut previous_end = images[0].range.start;
+    for image in &images {
+        // Keep anything between the pictures as it is
+        rebuilt.extend_from_slice(&output[previous_end..image.range.start]);
+        let offset = rebuilt.len() - base;
+        let picture = clean_image(&output[image.range.clone()])?;
+
+        let encode = |value: usize| {
+            let value = u32::try_from(value).map_err(|_| "Multi-picture file too large")?;
+            Ok::<_, Box<dyn std::error::Error>>(if image.little_endian { value.to_le_bytes() } else { value.to_be_bytes() })
+        };
+        rebuilt[image.entry + 4..image.entry + 8].copy_from_slice(&encode(picture.len())?);
+        rebuilt[image.entry + 8..image.entry + 12].copy_from_slice(&encode(offset)?);
+
+        rebuilt.extend_from_slice(&picture);
+        previous_end = image.range.end;
+    }
+    rebuilt.extend_from_slice(&output[previous_end..]);
+
+    Ok(Some(rebuilt))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::tests::build_jpeg;
+
+    /// A JPEG whose MPF index lists `second` as its second picture, appended after it
+    fn build_mpo(second: &[u8]) -> Vec<u8> {
+        // Index IFD with one MPEntry entry, then the entries themselves
+        let mut mpf = MPF_HEADER.to_vec();
+        mpf.extend_from_slice(b"II*\0");
+        mpf.extend_from_slice(&8u32.to_le_bytes());
+        mpf.extend_from_slice(&1u16.to_le_bytes());
+        mpf.extend_from_slice(&MP_ENTRY.to_le_bytes());
+        mpf.extend_from_slice(&7u16.to_le_bytes());
+        mpf.extend_from_slice(&32u32.to_le_bytes());
+        mpf.extend_from_slice(&26u32.to_le_bytes());
+        mpf.extend_from_slice(&[0; 4]);
+        mpf.extend_from_slice(&[0; 32]);
+
+        let mut data = build_jpeg(&[(jpeg::APP2, &mpf)], b"first");
+        let base = 2 + 4 + MPF_HEADER.len();
+        let second_at = data.len();
+        let entry = base + 26 + 16;
+        data[entry + 4..entry + 8].copy_from_slice(&(second.len() as u32).to_le_bytes());
+        data[entry + 8..entry + 12].copy_from_slice(&((second_at - base) as u32).to_le_bytes());
+        data.extend_from_slice(second);
+        data
+    }
+
+    #[test]
+    fn test_embedded_images_are_cleaned_and_reindexed() {
+        let second = build_jpeg(&[(jpeg::APP1, b"Exif\0\0secret metadata")], b"second");
+        let data = build_mpo(&second);
+
+        let images = embedded_images(&data);
+        assert_eq!(images.len(), 1);
+        assert_eq!(&data[images[0].range.clone()], &second[..]);
+
+        let stripped = build_jpeg(&[], b"second");
+        let cleaned = clean(&data, "", &data, true, |_| Ok(stripped.clone())).unwrap().unwrap();
+        let images = embedded_images(&cleaned);
+        assert_eq!(&cleaned[images[0].range.clone()], &stripped[..]);
+    }
+
+    #[test]
+    fn test_motion_video_gps_is_scrubbed() {
+        let mut xyz = vec![0, 0, 0, 26, 0xA9, b'x', b'y', b'z', 0, 14, 0x15, 0xC7];
+        xyz.extend_from_slice(b"+48.85+002.29/");
+        let mut video = vec![0, 0, 0, 8 + 8 + 26, b'm', b'o', b'o', b'v', 0, 0, 0, 8 + 26, b'u', b'd', b't', b'a'];
+        video.extend_from_slice(&xyz);
+
+        let mut data = build_jpeg(&[], b"pixels");
+        data.extend_from_slice(&video);
+        let xmp = format!(r#"<Container:Item Item:Mime="video/mp4" Item:Semantic="MotionPhoto" Item:Length="{}"/>"#, video.len());
+
+        let range = motion_video(&data, &xmp).unwrap();
+        assert_eq!(video_locations(&data[range]), vec![(16..42, "+48.85+002.29/".to_string())]);
+
+        let cleaned = clean(&data, &xmp, &data, true, |image| Ok(image.to_vec())).unwrap().unwrap();
+        assert_eq!(cleaned.len(), data.len());
+        assert!(video_locations(&cleaned[data.len() - video.len()..]).is_empty());
+    }
+
+    #[test]
+    fn test_oversized_boxes_are_ignored() {
+        // A 64-bit box size that would overflow the position
+        let mut video = vec![0, 0, 0, 1, b'm', b'o', b'o', b'v'];
+        video.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert!(video_locations(&video).is_empty());
+
+        let video = [0xFF, 0xFF, 0xFF, 0xFF, b'u', b'd', b't', b'a', 0, 0];
+        assert!(video_locations(&video).is_empty());
+    }
+}
@@ -0,0 +1,13 @@
+//! Desktop notification when a run completes (`--notify`).
+
+use notify_rust::Notification;
+
+/// Show a native desktop notification
+pub fn send(summary: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    Notification::new()
+        .appname("privacy-exif-cleaner")
+        .summary(summary)
+        .body(body)
+        .show()?;
+    Ok(())
+}
@@ -0,0 +1,172 @@
+//! Processing options for library users.
+//!
+//! `Config` is shaped around the command line: it carries the binary's
+//! output modes, dashboards, and notifications alongside the settings that
+//! decide how images are cleaned. `ProcessorOptions` holds only the latter,
+//! as plain Rust types, and converts into a `Config` with the rest left at
+//! their defaults. Parsed arguments convert too, when they ask for a clean.
+
+use std::path::PathBuf;
+use std::time::Duration;
+use crate::cli::{CliArgs, CliCommand, Config, ConfigErrors};
+use crate::privacy::{PolicyOverride, PrivacyLevel, UnknownTags};
+
+/// How to clean images, without any command-line concerns
+#[derive(Debug, Clone)]
+pub struct ProcessorOptions {
+    /// Directory (or ZIP archive, PDF, or `s3://` URI) to clean
+    pub input: PathBuf,
+    /// Where cleaned files go; `None` cleans in place
+    pub output: Option<PathBuf>,
+    pub recursive: bool,
+    pub privacy_level: PrivacyLevel,
+    /// Tags kept or removed on top of the privacy level
+    pub overrides: PolicyOverride,
+    pub unknown_tags: UnknownTags,
+    /// Report what would be removed without changing any file
+    pub dry_run: bool,
+    /// Keep a `.bak` copy of each original cleaned in place
+    pub backup: bool,
+    /// Numbered backups kept from later runs
+    pub backup_keep: usize,
+    /// Remove edited images' embedded originals
+    pub strip_originals: bool,
+    /// Remove motion photos' videos
+    pub drop_motion: bool,
+    /// Search the bytes outside any metadata structure too
+    pub deep_scan: bool,
+    /// Remove all metadata from files whose metadata can't be parsed
+    pub force_strip: bool,
+    pub follow_symlinks: bool,
+    pub preserve_hardlinks: bool,
+    pub max_depth: Option<usize>,
+    /// Worker threads for directory runs
+    pub jobs: usize,
+    /// How long ExifTool may take per file
+    pub timeout: Duration,
+    /// Run ExifTool without network access or writes outside the file
+    pub sandbox: bool,
+}
+
+impl ProcessorOptions {
+    /// Default options for cleaning `input`
+    pub fn new(input: impl Into<PathBuf>) -> Self {
+        Self { input: input.into(), ..Self::default() }
+    }
+
+    /// Check the options before a run, reporting every problem at once
+    pub fn validate(&self) -> Result<(), ConfigErrors> {
+        Config::from(self.clone()).validate()
+    }
+}
+
+impl Default for ProcessorOptions {
+    fn default() -> Self {
+        Self::from(&Config::default())
+    }
+}
+
+impl From<&Config> for ProcessorOptions {
+    fn from(config: &Config) -> Self {
+        Self {
+            input: config.input.clone(),
+            output: config.output.clone(),
+            recursive: config.recursive,
+            privacy_level: config.privacy_level.clone(),
+            overrides: config.policy_override.clone(),
+            unknown_tags: config.unknown_tags,
+            dry_run: config.dry_run,
+            backup: config.create_backup,
+            backup_keep: config.backup_keep,
+            strip_originals: config.strip_originals,
+            drop_motion: config.drop_motion,
+            deep_scan: config.deep_scan,
+            force_strip: config.force_strip,
+            follow_symlinks: config.follow_symlinks,
+            preserve_hardlinks: config.preserve_hardlinks,
+            max_depth: config.max_depth,
+            jobs: config.jobs,
+            timeout: Duration::from_secs(config.timeout_secs),
+            sandbox: config.sandbox,
+        }
+    }
+}
+
+/// The processing options of a `clean` run; other commands clean nothing and have none
+impl TryFrom<CliArgs> for ProcessorOptions {
+    type Error = Box<dyn std::error::Error>;
+
+    fn try_from(args: CliArgs) -> Result<Self, Self::Error> {
+        match args.command {
+            CliCommand::Clean(ref config) => Ok(Self::from(&**config)),
+            _ => Err("Only the clean command has processing options".into()),
+        }
+    }
+}
+
+impl From<ProcessorOptions> for Config {
+    fn from(options: ProcessorOptions) -> Self {
+        Config {
+            input: options.input,
+            output: options.output,
+            recursive: options.recursive,
+            policy_override: options.overrides,
+            unknown_tags: options.unknown_tags,
+            dry_run: options.dry_run,
+            create_backup: options.backup,
+            backup_keep: options.backup_keep,
+            strip_originals: options.strip_originals,
+            drop_motion: options.drop_motion,
+            deep_scan: options.deep_scan,
+            force_strip: options.force_strip,
+            follow_symlinks: options.follow_symlinks,
+            preserve_hardlinks: options.preserve_hardlinks,
+            max_depth: options.max_depth,
+            jobs: options.jobs,
+            // ExifTool's timeout is whole seconds; round up so a short one isn't zero
+            timeout_secs: options.timeout.as_secs() + u64::from(options.timeout.subsec_nanos() > 0),
+            sandbox: options.sandbox,
+            ..Config::with_privacy_level(options.privacy_level)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli;
+
+    #[test]
+    fn test_options_round_trip_through_config() {
+        let options = ProcessorOptions {
+            output: Some(PathBuf::from("cleaned")),
+            privacy_level: PrivacyLevel::Strict,
+            jobs: 4,
+            timeout: Duration::from_millis(2500),
+            ..ProcessorOptions::new("photos")
+        };
+
+        let config = Config::from(options.clone());
+        assert_eq!(config.input, PathBuf::from("photos"));
+        assert_eq!(config.privacy_level, PrivacyLevel::Strict);
+        assert_eq!(config.timeout_secs, 3);
+        // Settings only the binary uses keep their defaults
+        assert!(!config.tui && !config.quiet && config.webhook.is_none());
+
+        let back = ProcessorOptions::from(&config);
+        assert_eq!(back.output, options.output);
+        assert_eq!(back.jobs, 4);
+        assert_eq!(ProcessorOptions::default().jobs, Config::default().jobs);
+    }
+
+    #[test]
+    fn test_options_from_parsed_arguments() {
+        let args = cli::try_parse_from(["privacy-exif-cleaner", "-i", "photos", "-p", "strict"]).unwrap();
+        let options = ProcessorOptions::try_from(args).unwrap();
+        assert_eq!(options.input, PathBuf::from("photos"));
+        assert_eq!(options.privacy_level, PrivacyLevel::Strict);
+
+        let args = cli::try_parse_from(["privacy-exif-cleaner", "restore", "-i", "photos"]).unwrap();
+        assert!(ProcessorOptions::try_from(args).is_err());
+    }
+}
@@ -0,0 +1,129 @@
+//! Pre-edit originals embedded in edited images.
+//!
+//! Cropping a photo before sharing it only helps if the uncropped version
+//! goes too. Some files keep it anyway: a thumbnail left over from before
+//! the crop, Samsung Gallery's re-edit trailer, or the original image Google
+//! Camera stores in XMP. These are found here and can be removed with
+//! `--strip-originals`.
+
+use exif::{In, Tag};
+use crate::jpeg;
+use crate::privacy;
+
+/// Samsung trailers end with this signature
+const SAMSUNG_TRAILER_END: &[u8] = b"SEFT";
+/// Samsung trailer entry holding the data to undo Gallery edits, including the original
+const SAMSUNG_RE_EDIT: &[u8] = b"PhotoEditor_Re_Edit_Data";
+/// XMP property holding Google Camera's original image, base64-encoded
+const GOOGLE_ORIGINAL: &[u8] = b"GImage:Data";
+/// Standard DCF thumbnail size; cameras letterbox other aspect ratios into it
+const DCF_THUMBNAIL: (u16, u16) = (160, 120);
+/// Relative aspect ratio difference above which the thumbnail can't show the same crop
+const ASPECT_TOLERANCE: f64 = 0.02;
+
+/// Where an edited image keeps a version from before the edit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddedOriginal {
+    /// EXIF thumbnail whose shape doesn't match the image, as after a crop
+    StaleThumbnail,
+    /// Samsung Gallery re-edit data in the trailer after the image
+    SamsungReEdit,
+    /// Original image in Google Camera's `GImage` XMP namespace
+    GoogleOriginal,
+}
+
+impl EmbeddedOriginal {
+    /// The tag findings of this kind are reported under
+    ///
+    /// The thumbnail's own IFD1 pointer removes the whole thumbnail; the others
+    /// stand for the trailer and the XMP namespace they live in.
+    pub fn tag(&self) -> Tag {
+        match self {
+            EmbeddedOriginal::StaleThumbnail => Tag::JPEGInterchangeFormat,
+            EmbeddedOriginal::SamsungReEdit => privacy::EDIT_TRAILER,
+            EmbeddedOriginal::GoogleOriginal => privacy::XMP_ORIGINAL,
+        }
+    }
+}
+
+impl std::fmt::Display for EmbeddedOriginal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EmbeddedOriginal::StaleThumbnail => write!(f, "EXIF thumbnail differs in shape from the image and may show it uncropped"),
+            EmbeddedOriginal::SamsungReEdit => write!(f, "Samsung re-edit data holds the image before editing"),
+            EmbeddedOriginal::GoogleOriginal => write!(f, "Google Camera XMP holds the original image"),
+        }
+    }
+}
+
+/// Find the pre-edit versions an image carries
+///
+/// `xmp` is the image's XMP, standard and extended packets concatenated.
+pub fn find(data: &[u8], exif: Option<&exif::Exif>, xmp: &[u8]) -> Vec<EmbeddedOriginal> {
+    let mut found = Vec::new();
+
+    if exif.map_or(false, |exif| has_stale_thumbnail(data, exif)) {
+        found.push(EmbeddedOriginal::StaleThumbnail);
+    }
+    if data.ends_with(SAMSUNG_TRAILER_END) && contains(data, SAMSUNG_RE_EDIT) {
+        found.push(EmbeddedOriginal::SamsungReEdit);
+    }
+    if contains(xmp, GOOGLE_ORIGINAL) {
+        found.push(EmbeddedOriginal::GoogleOriginal);
+    }
+    found
+}
+
+/// Whether the EXIF thumbnail has a different aspect ratio than the JPEG it belongs to
+fn has_stale_thumbnail(data: &[u8], exif: &exif::Exif) -> bool {
+    let thumbnail_size = || {
+        let offset = exif.get_field(Tag::JPEGInterchangeFormat, In::THUMBNAIL)?.value.get_uint(0)? as usize;
+        let len = exif.get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)?.value.get_uint(0)? as usize;
+        let thumbnail = exif.buf().get(offset..offset.checked_add(len)?)?;
+        jpeg::parse_layout(thumbnail).ok()?.frame_size()
+    };
+    let image_size = || jpeg::parse_layout(data).ok()?.frame_size();
+
+    match (thumbnail_size(), image_size()) {
+        (Some(thumbnail), _) if thumbnail == DCF_THUMBNAIL => false,
+        (Some(thumbnail), Some(image)) => aspect_ratios_differ(thumbnail, image),
+        _ => false,
+    }
+}
+
+fn aspect_ratios_differ((w1, h1): (u16, u16), (w2, h2): (u16, u16)) -> bool {
+    if h1 == 0 || h2 == 0 {
+        return false;
+    }
+    let (r1, r2) = (w1 as f64 / h1 as f64, w2 as f64 / h2 as f64);
+    (r1 - r2).abs() / r2 > ASPECT_TOLERANCE
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::tests::build_jpeg;
+
+    #[test]
+    fn test_samsung_and_google_originals() {
+        let mut data = build_jpeg(&[(jpeg::APP1, b"http://ns.adobe.com/xap/1.0/\0<x/>")], b"pixels");
+        data.extend_from_slice(b"SEFH....PhotoEditor_Re_Edit_Data....\0\0\0\0SEFT");
+
+        assert_eq!(find(&data, None, b"<rdf:Description GImage:Data=\"/9j/4AAQ\"/>"), vec![
+            EmbeddedOriginal::SamsungReEdit,
+            EmbeddedOriginal::GoogleOriginal,
+        ]);
+        assert!(find(&build_jpeg(&[], b"pixels"), None, b"").is_empty());
+    }
+
+    #[test]
+    fn test_aspect_ratio_comparison() {
+        assert!(!aspect_ratios_differ((320, 240), (4000, 3000)));
+        assert!(aspect_ratios_differ((320, 240), (3000, 3000)));
+        assert!(!aspect_ratios_differ((0, 0), (3000, 3000)));
+    }
+}
@@ -0,0 +1,139 @@
+//! Padding cleaned files back to their original size (`--keep-size`).
+//!
+//! Some forensic and archival workflows flag any file whose size changed.
+//! The padding is cosmetic: it is filler that viewers skip, not a
+//! restoration of anything removed, and the file's hash still changes.
+//! JPEGs get zero-filled comment segments before the scan, PNGs a private
+//! ancillary chunk before `IEND`, and TIFFs trailing zeros, which no IFD
+//! points to.
+
+use flate2::Crc;
+use crate::formats::{self, ImageFormat};
+use crate::jpeg;
+use crate::png;
+
+/// Size of a JPEG segment's marker and length
+const SEGMENT_OVERHEAD: usize = 4;
+/// Largest JPEG segment, marker and length included
+const MAX_SEGMENT_LEN: usize = 0xFFFF + 2;
+/// Size of a PNG chunk's length, type, and CRC
+const CHUNK_OVERHEAD: usize = 12;
+/// Ancillary, private, safe-to-copy chunk type for the filler
+const PADDING_CHUNK: &[u8; 4] = b"paDd";
+
+/// `data` grown to exactly `target` bytes with filler
+///
+/// Fails when the file is already larger than `target`, when the format
+/// can't hold filler, or when the gap is smaller than the format's smallest
+/// filler (4 bytes for JPEG, 12 for PNG).
+pub fn pad_to_size(data: &[u8], target: usize) -> Result<Vec<u8>, String> {
+    if data.len() > target {
+        return Err(format!("Cleaned file is larger than the original ({} > {} bytes)", data.len(), target));
+    }
+    let gap = target - data.len();
+    if gap == 0 {
+        return Ok(data.to_vec());
+    }
+
+    match formats::sniff_image_format(data) {
+        Some(ImageFormat::Jpeg) => pad_jpeg(data, gap),
+        Some(ImageFormat::Png) => pad_png(data, gap),
+        Some(ImageFormat::Tiff) => {
+            let mut padded = data.to_vec();
+            padded.resize(target, 0);
+            Ok(padded)
+        }
+        Some(format) => Err(format!("{} files can't be padded", format)),
+        None => Err("Unrecognized format can't be padded".to_string()),
+    }
+}
+
+fn pad_jpeg(data: &[u8], gap: usize) -> Result<Vec<u8>, String> {
+    if gap < SEGMENT_OVERHEAD {
+        return Err(format!("{} bytes is too small a gap for a JPEG comment segment", gap));
+    }
+    let layout = jpeg::parse_layout(data).map_err(|e| e.to_string())?;
+    // The SOS segment is always last; filler goes right before it
+    let scan = layout.segments.last().map(|segment| segment.offset).ok_or("JPEG has no scan")?;
+
+    let mut filler = Vec::with_capacity(gap);
+    let mut remaining = gap;
+    while remaining > 0 {
+        let mut len = remaining.min(MAX_SEGMENT_LEN);
+        // Never leave a remainder too small for a segment of its own
+        if (1..SEGMENT_OVERHEAD).contains(&(remaining - len)) {
+            len -= SEGMENT_OVERHEAD;
+        }
+        filler.extend_from_slice(&[0xFF, jpeg::COM]);
+        filler.extend_from_slice(&((len - 2) as u16).to_be_bytes());
+        filler.resize(filler.len() + len - SEGMENT_OVERHEAD, 0);
+        remaining -= len;
+    }
+
+    Ok([&data[..scan], &filler, &data[scan..]].concat())
+}
+
+fn pad_png(data: &[u8], gap: usize) -> Result<Vec<u8>, String> {
+    if gap < CHUNK_OVERHEAD {
+        return Err(format!("{} bytes is too small a gap for a PNG chunk", gap));
+    }
+    png::parse_chunks(data).map_err(|e| e.to_string())?;
+    // IEND is the last chunk: 12 bytes with no data
+    let end = data.len().checked_sub(CHUNK_OVERHEAD).ok_or("PNG is too short")?;
+    if &data[end + 4..end + 8] != b"IEND" {
+        return Err("PNG doesn't end with IEND".to_string());
+    }
+
+    let mut chunk = Vec::with_capacity(gap);
+    chunk.extend_from_slice(&((gap - CHUNK_OVERHEAD) as u32).to_be_bytes());
+    chunk.extend_from_slice(PADDING_CHUNK);
+    chunk.resize(gap - 4, 0);
+    let mut crc = Crc::new();
+    crc.update(&chunk[4..]);
+    chunk.extend_from_slice(&crc.sum().to_be_bytes());
+
+    Ok([&data[..end], &chunk, &data[end..]].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::tests::build_jpeg;
+
+    #[test]
+    fn test_jpeg_is_padded_with_comment_segments() {
+        let data = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        let target = data.len() + 70_000;
+
+        let padded = pad_to_size(&data, target).unwrap();
+        assert_eq!(padded.len(), target);
+        let layout = jpeg::parse_layout(&padded).unwrap();
+        assert_eq!(layout.segments.iter().filter(|s| s.marker == jpeg::COM).count(), 2);
+        let scan_offset = jpeg::parse_layout(&data).unwrap().scan_offset;
+        assert_eq!(&padded[layout.scan_offset..], &data[scan_offset..]);
+
+        // A gap just over one segment still splits into two valid segments
+        let target = data.len() + MAX_SEGMENT_LEN + 2;
+        let padded = pad_to_size(&data, target).unwrap();
+        assert_eq!(padded.len(), target);
+        assert!(jpeg::parse_layout(&padded).is_ok());
+        assert!(pad_to_size(&data, data.len() + 3).is_err());
+    }
+
+    #[test]
+    fn test_png_and_tiff_padding() {
+        let mut png = png::SIGNATURE.to_vec();
+        png.extend_from_slice(&[0, 0, 0, 0]);
+        png.extend_from_slice(b"IEND");
+        png.extend_from_slice(&[0xAE, 0x42, 0x60, 0x82]);
+        let padded = pad_to_size(&png, png.len() + 100).unwrap();
+        assert_eq!(padded.len(), png.len() + 100);
+        let chunks = png::parse_chunks(&padded).unwrap();
+        assert_eq!(&chunks[0].kind, PADDING_CHUNK);
+        assert_eq!(chunks[0].data.len(), 88);
+
+        let tiff = b"II*\0\x08\0\0\0\0\0".to_vec();
+        assert_eq!(pad_to_size(&tiff, 16).unwrap(), [&tiff[..], &[0; 6]].concat());
+        assert!(pad_to_size(&tiff, 4).is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Heuristics for data hidden in free-form metadata.
+//!
+//! Apps sometimes stash JSON blobs, device IDs, or coordinates in fields like
+//! `UserComment` and `MakerNote` that privacy levels otherwise keep. These
+//! checks look at the printable text in such a field and flag what looks like
+//! contact details, coordinates, or encoded identifiers. Flagged fields are
+//! only reported; whether they are removed is up to the privacy level.
+
+use std::sync::OnceLock;
+use exif::{Tag, Value};
+use regex::Regex;
+use crate::analyzer::PrivacyCategory;
+use crate::pii;
+use crate::privacy;
+
+/// Shortest unbroken token checked for being an encoded identifier
+const MIN_TOKEN_LEN: usize = 20;
+/// Bits of entropy per character above which a long token looks like an ID or encoded data
+const TOKEN_ENTROPY_THRESHOLD: f64 = 3.5;
+/// Shortest printable run extracted from binary values, as `strings` does
+const MIN_RUN_LEN: usize = 4;
+
+/// Why a field's content was flagged
+#[derive(Debug, Clone, PartialEq)]
+pub struct SuspiciousPayload {
+    pub reason: &'static str,
+    pub category: PrivacyCategory,
+}
+
+/// Whether `tag` holds free-form data worth inspecting
+pub fn is_free_form(tag: Tag) -> bool {
+    matches!(tag, Tag::UserComment | Tag::MakerNote | Tag::ImageDescription)
+}
+
+/// Whether a finding under `tag` in a field the level keeps comes from inspecting the field's text
+///
+/// That covers hidden payloads here and personal data found by `pii`, in EXIF
+/// fields and in the XMP, IPTC, PNG text, and GIF comment blocks.
+pub fn is_content_finding(tag: Tag) -> bool {
+    is_free_form(tag)
+        || pii::is_textual(tag)
+        || matches!(tag, privacy::XMP_PACKET | privacy::IPTC_NAA | privacy::PNG_TEXT | privacy::GIF_COMMENT)
+}
+
+/// Inspect a field value for hidden personal data
+pub fn inspect_value(value: &Value) -> Option<SuspiciousPayload> {
+    inspect_text(&value_text(value)?)
+}
+
+/// The readable text of a string or byte value
+///
+/// UTF-16 values (`UNICODE` user comments, the Windows `XP*` tags) are decoded first.
+pub fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Ascii(strings) => Some(
+            strings.iter()
+                .map(|s| String::from_utf8_lossy(s).into_owned())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        Value::Byte(bytes) | Value::Undefined(bytes, _) => Some(match utf16_units(bytes) {
+            Some(units) => printable_text(char::decode_utf16(units).map(|c| c.unwrap_or('\0'))),
+            None => printable_text(bytes.iter().map(|&byte| if byte.is_ascii() { byte as char } else { '\0' })),
+        }),
+        _ => None,
+    }
+}
+
+/// Inspect text for hidden personal data, most revealing finding first
+pub fn inspect_text(text: &str) -> Option<SuspiciousPayload> {
+    static COORDINATES: OnceLock<Regex> = OnceLock::new();
+    static URL: OnceLock<Regex> = OnceLock::new();
+    static JSON: OnceLock<Regex> = OnceLock::new();
+
+    let coordinates = COORDINATES.get_or_init(|| {
+        Regex::new(r#"(?i)-?\b\d{1,2}\.\d{4,}\s*,\s*-?\d{1,3}\.\d{4,}\b|"(lat|latitude|lng|lon|longitude)"\s*:"#).unwrap()
+    });
+    let url = URL.get_or_init(|| Regex::new(r"(?i)\b(https?|ftp)://\S+|\bwww\.\S+\.\S+").unwrap());
+    let json = JSON.get_or_init(|| Regex::new(r#"\{\s*"[^"]+"\s*:"#).unwrap());
+
+    let (reason, category) = if coordinates.is_match(text) {
+        ("contains coordinates", PrivacyCategory::Location)
+    } else if pii::contains_email(text) {
+        ("contains an email address", PrivacyCategory::PersonalInfo)
+    } else if url.is_match(text) {
+        ("contains a URL", PrivacyCategory::PersonalInfo)
+    } else if json.is_match(text) {
+        ("contains a JSON object", PrivacyCategory::PersonalInfo)
+    } else if text.split_whitespace().any(looks_encoded) {
+        ("contains an identifier or encoded data", PrivacyCategory::DeviceIdentifier)
+    } else {
+        return None;
+    };
+    Some(SuspiciousPayload { reason, category })
+}
+
+/// The UTF-16 code units of a value holding UTF-16 text
+///
+/// A `UNICODE` prefix marks a user comment in the file's byte order, told apart
+/// by which half of each unit is mostly zero. Without one, only values whose
+/// every other byte is NUL, as ASCII-range UTF-16LE in `XP*` tags is, count.
+fn utf16_units(bytes: &[u8]) -> Option<Vec<u16>> {
+    let (text, prefixed) = match bytes.strip_prefix(b"UNICODE\0") {
+        Some(text) => (text, true),
+        None => (bytes, false),
+    };
+    let zeros_at = |start: usize| text.iter().skip(start).step_by(2).filter(|&&byte| byte == 0).count();
+    if !prefixed && (text.len() < 2 || text.len() % 2 != 0 || zeros_at(1) < text.len() / 2) {
+        return None;
+    }
+
+    let little_endian = zeros_at(1) >= zeros_at(0);
+    Some(text.chunks_exact(2)
+        .map(|pair| match little_endian {
+            true => u16::from_le_bytes([pair[0], pair[1]]),
+            false => u16::from_be_bytes([pair[0], pair[1]]),
+        })
+        .collect())
+}
+
+/// Printable runs of a binary value's characters, one per line
+///
+/// Anything unprintable, NUL included, ends a run, so separate strings aren't joined into one.
+fn printable_text(chars: impl IntoIterator<Item = char>) -> String {
+    let mut runs = Vec::new();
+    let mut run = String::new();
+    for c in chars {
+        if c == ' ' || c.is_ascii_graphic() || (!c.is_ascii() && !c.is_control() && !c.is_whitespace()) {
+            run.push(c);
+        } else {
+            if run.trim().len() >= MIN_RUN_LEN {
+                runs.push(run.trim().to_string());
+            }
+            run.clear();
+        }
+    }
+    if run.trim().len() >= MIN_RUN_LEN {
+        runs.push(run.trim().to_string());
+    }
+    runs.join("\n")
+}
+
+/// Whether a whitespace-free token is long and random enough to be an ID, key, or base64 data
+fn looks_encoded(token: &str) -> bool {
+    token.len() >= MIN_TOKEN_LEN
+        && token.chars().any(|c| c.is_ascii_digit())
+        && shannon_entropy(token.as_bytes()) >= TOKEN_ENTROPY_THRESHOLD
+}
+
+/// Bits of entropy per byte, from 0 for one repeated byte to 8 for uniformly random bytes
+pub(crate) fn shannon_entropy(bytes: &[u8]) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in bytes {
+        counts[*byte as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    counts.iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_hidden_data() {
+        let flagged = |text| inspect_text(text).map(|payload| payload.category);
+
+        assert_eq!(flagged("{\"lat\": 48.8584, \"lng\": 2.2945}"), Some(PrivacyCategory::Location));
+        assert_eq!(flagged("shot at 48.85840, 2.29450"), Some(PrivacyCategory::Location));
+        assert_eq!(flagged("contact jane.doe@example.com"), Some(PrivacyCategory::PersonalInfo));
+        assert_eq!(flagged("https://example.com/u/12345"), Some(PrivacyCategory::PersonalInfo));
+        assert_eq!(flagged("{\"device\": \"pixel\"}"), Some(PrivacyCategory::PersonalInfo));
+        assert_eq!(flagged("id=9f8A3kQ2zX7mB1vN5cL0pR4t"), Some(PrivacyCategory::DeviceIdentifier));
+    }
+
+    #[test]
+    fn test_ordinary_text_is_not_flagged() {
+        assert_eq!(inspect_text("Sunset over the harbour, shot handheld"), None);
+        assert_eq!(inspect_text("f/2.8 1/250s ISO 400"), None);
+        assert_eq!(inspect_text(""), None);
+    }
+
+    #[test]
+    fn test_user_comment_with_charset_prefix() {
+        for big_endian in [false, true] {
+            let mut comment = b"UNICODE\0".to_vec();
+            for c in "mail me@example.org".encode_utf16() {
+                comment.extend_from_slice(&if big_endian { c.to_be_bytes() } else { c.to_le_bytes() });
+            }
+            let value = Value::Undefined(comment, 0);
+
+            assert_eq!(inspect_value(&value).unwrap().reason, "contains an email address");
+        }
+    }
+
+    #[test]
+    fn test_nul_separates_binary_strings() {
+        // Two harmless strings must not be read as one long token
+        let value = Value::Undefined(b"Nikon\0AF-S 50mm\0\0\0\x01\x02firmware 1.02".to_vec(), 0);
+        assert_eq!(value_text(&value).unwrap(), "Nikon\nAF-S 50mm\nfirmware 1.02");
+
+        let mut xp_title = Vec::new();
+        for c in "Beach day".encode_utf16().chain([0]) {
+            xp_title.extend_from_slice(&c.to_le_bytes());
+        }
+        assert_eq!(value_text(&Value::Byte(xp_title)).unwrap(), "Beach day");
+    }
+}
@@ -0,0 +1,286 @@
+//! PDF photo exports.
+//!
+//! Photo sets are often shared as PDFs, which carry the author and the
+//! creating software in their document info, XMP in the catalog, and the
+//! photos as embedded JPEGs with their EXIF intact. Document info entries
+//! follow the privacy level of their EXIF counterparts (`Author` is `Artist`,
+//! `Producer` is `Software`, custom entries are `DocumentName`); embedded
+//! JPEGs are cleaned like any image.
+
+use std::path::{Path, PathBuf};
+use exif::Tag;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use tracing::{error, info};
+use crate::analyzer::{PrivacyCategory, PrivacyField};
+use crate::pii;
+use crate::privacy::{self, PrivacyLevel};
+use crate::processor::ImageProcessor;
+
+/// Longest document info value shown in a finding
+const MAX_VALUE_CHARS: usize = 80;
+
+pub use crate::formats::is_pdf;
+
+/// Determine where the sanitized PDF should be written
+///
+/// With an output directory the PDF keeps its name; otherwise a
+/// `<name>.cleaned.pdf` sibling is created so the original is never overwritten.
+pub fn get_pdf_output_path(pdf_path: &Path, output_dir: Option<&Path>) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let file_name = pdf_path.file_name().ok_or("Invalid PDF name")?;
+
+    let output_path = match output_dir {
+        Some(out_dir) => out_dir.join(file_name),
+        None => {
+            let stem = pdf_path.file_stem().unwrap_or_default().to_string_lossy();
+            pdf_path.with_file_name(format!("{}.cleaned.pdf", stem))
+        }
+    };
+
+    Ok(output_path)
+}
+
+/// Counters for a single PDF run; the document itself counts as one processed item
+#[derive(Debug, Default)]
+pub struct PdfStats {
+    pub processed: u32,
+    pub privacy_data_found: u32,
+    pub errors: u32,
+}
+
+/// The EXIF tag whose policy decides if a document info entry goes, and how it is reported
+pub fn info_field_policy(key: &[u8]) -> (Tag, PrivacyCategory) {
+    match key {
+        b"Author" => (Tag::Artist, PrivacyCategory::PersonalInfo),
+        b"Creator" | b"Producer" => (Tag::Software, PrivacyCategory::Software),
+        b"CreationDate" | b"ModDate" => (Tag::DateTime, PrivacyCategory::Temporal),
+        b"Title" | b"Subject" | b"Keywords" => (Tag::ImageDescription, PrivacyCategory::Metadata),
+        _ => (Tag::DocumentName, PrivacyCategory::Metadata),
+    }
+}
+
+/// Analyze a PDF's document info, XMP, and embedded JPEGs, and write a cleaned copy to `output_path`
+///
+/// Embedded JPEGs that fail to clean are left out of the output rather than
+/// passed through. In dry-run mode the PDF is only analyzed and no output is written.
+pub fn process_pdf(
+    processor: &ImageProcessor,
+    pdf_path: &Path,
+    output_path: &Path,
+) -> Result<PdfStats, Box<dyn std::error::Error>> {
+    let mut document = Document::load(pdf_path)?;
+    let level = &processor.config().privacy_level;
+    let mut stats = PdfStats::default();
+
+    // Document info and XMP
+    let (findings, info_keys, remove_xmp) = document_findings(processor, &document, level);
+    for finding in &findings {
+        info!(path = %pdf_path.display(), category = ?finding.category, "{}", finding.description);
+    }
+    processor.category_tally().record(&findings);
+    stats.processed += 1;
+    if !findings.is_empty() {
+        stats.privacy_data_found += 1;
+    }
+
+    // Embedded photos
+    for id in jpeg_streams(&document) {
+        let entry_path = pdf_path.join(format!("object-{}", id.0));
+        let stream = match document.get_object_mut(id) {
+            Ok(Object::Stream(stream)) => stream,
+            _ => continue,
+        };
+
+        match processor.process_image_data(&stream.content, &entry_path) {
+            Ok(result) => {
+                stats.processed += 1;
+                if result.had_privacy_data {
+                    stats.privacy_data_found += 1;
+                }
+                if let Some(cleaned) = result.cleaned {
+                    stream.set_content(cleaned);
+                }
+            }
+            Err(e) => {
+                // Never pass through an image we failed to clean
+                error!(path = %entry_path.display(), "Error processing embedded image: {} (image removed from output)", e);
+                stats.errors += 1;
+                stream.set_content(Vec::new());
+            }
+        }
+    }
+
+    if processor.config().dry_run {
+        return Ok(stats);
+    }
+
+    if let Some(info) = info_dictionary_mut(&mut document) {
+        for key in &info_keys {
+            info.remove(key);
+        }
+    }
+    if remove_xmp {
+        if let Some(catalog) = catalog_mut(&mut document) {
+            catalog.remove(b"Metadata");
+        }
+    }
+    document.save(output_path)?;
+
+    Ok(stats)
+}
+
+/// Findings in the document info and XMP, the info keys to remove, and whether the XMP goes
+fn document_findings(
+    processor: &ImageProcessor,
+    document: &Document,
+    level: &PrivacyLevel,
+) -> (Vec<PrivacyField>, Vec<Vec<u8>>, bool) {
+    let overrides = &processor.config().policy_override;
+    let mut findings = Vec::new();
+    let mut info_keys = Vec::new();
+
+    if let Some(info) = info_dictionary(document) {
+        for (key, value) in info.iter() {
+            let text = match text_value(value) {
+                Some(text) => text,
+                None => continue,
+            };
+            let name = String::from_utf8_lossy(key);
+            let (policy_tag, category) = info_field_policy(key);
+            let removed = !overrides.should_preserve_tag(policy_tag, level);
+
+            let (description, category) = if removed {
+                let value: String = text.chars().take(MAX_VALUE_CHARS).collect();
+                (format!("PDF {}: {}", name, value), category)
+            } else if let Some(found) = pii::scan(&text).into_iter().next() {
+                (format!("PDF {}: contains {} ({})", name, found.kind, found.redacted), PrivacyCategory::PersonalInfo)
+            } else {
+                continue;
+            };
+            findings.push(PrivacyField { tag: policy_tag, description, category, ifd: None, value: None });
+            info_keys.push(key.clone());
+        }
+    }
+
+    let mut remove_xmp = false;
+    if let Some(xmp) = xmp_metadata(document) {
+        if !overrides.should_preserve_tag(privacy::XMP_PACKET, level) {
+            findings.push(PrivacyField {
+                tag: privacy::XMP_PACKET,
+                description: format!("PDF XMP metadata ({} bytes)", xmp.len()),
+                category: PrivacyCategory::Metadata,
+                ifd: None,
+                value: None,
+            });
+            remove_xmp = true;
+        } else {
+            let xmp = String::from_utf8_lossy(&xmp);
+            if let Some((property, found)) = pii::xmp_text_fields(&xmp).into_iter()
+                .find_map(|(property, text)| Some((property, pii::scan(&text).into_iter().next()?)))
+            {
+                findings.push(PrivacyField {
+                    tag: privacy::XMP_PACKET,
+                    description: format!("PDF XMP {}: contains {} ({})", property, found.kind, found.redacted),
+                    category: PrivacyCategory::PersonalInfo,
+                    ifd: None,
+                    value: None,
+                });
+                remove_xmp = true;
+            }
+        }
+    }
+
+    (findings, info_keys, remove_xmp)
+}
+
+fn info_id(document: &Document) -> Option<ObjectId> {
+    document.trailer.get(b"Info").ok()?.as_reference().ok()
+}
+
+fn info_dictionary(document: &Document) -> Option<&Dictionary> {
+    match document.trailer.get(b"Info").ok()? {
+        Object::Dictionary(info) => Some(info),
+        Object::Reference(id) => document.get_dictionary(*id).ok(),
+        _ => None,
+    }
+}
+
+fn info_dictionary_mut(document: &mut Document) -> Option<&mut Dictionary> {
+    match info_id(document) {
+        Some(id) => document.get_object_mut(id).ok()?.as_dict_mut().ok(),
+        None => document.trailer.get_mut(b"Info").ok()?.as_dict_mut().ok(),
+    }
+}
+
+fn catalog_mut(document: &mut Document) -> Option<&mut Dictionary> {
+    let id = document.trailer.get(b"Root").ok()?.as_reference().ok()?;
+    document.get_object_mut(id).ok()?.as_dict_mut().ok()
+}
+
+/// The catalog's XMP metadata stream, decompressed
+fn xmp_metadata(document: &Document) -> Option<Vec<u8>> {
+    let id = document.catalog().ok()?.get(b"Metadata").ok()?.as_reference().ok()?;
+    let stream = document.get_object(id).ok()?.as_stream().ok()?;
+    stream.decompressed_content().ok().or_else(|| Some(stream.content.clone()))
+}
+
+/// Image XObjects stored as plain JPEG (`DCTDecode` as their only filter)
+fn jpeg_streams(document: &Document) -> Vec<ObjectId> {
+    document.objects.iter()
+        .filter_map(|(id, object)| {
+            let stream = object.as_stream().ok()?;
+            let is_image = stream.dict.get(b"Subtype").and_then(Object::as_name).ok()? == b"Image";
+            let is_jpeg = match stream.dict.get(b"Filter").ok()? {
+                Object::Name(name) => name == b"DCTDecode",
+                Object::Array(filters) => matches!(filters.as_slice(), [Object::Name(name)] if name == b"DCTDecode"),
+                _ => false,
+            };
+            (is_image && is_jpeg).then_some(*id)
+        })
+        .collect()
+}
+
+/// A document info string: UTF-16 with a byte order mark, otherwise PDFDocEncoding (close to Latin-1)
+fn text_value(value: &Object) -> Option<String> {
+    let bytes = value.as_str().ok()?;
+    match bytes.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+            Some(String::from_utf16_lossy(&units))
+        }
+        None => Some(bytes.iter().map(|&byte| byte as char).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, StringFormat};
+    use crate::cli::Config;
+
+    #[test]
+    fn test_pdf_output_path() {
+        assert_eq!(get_pdf_output_path(Path::new("/test/album.pdf"), None).unwrap(), Path::new("/test/album.cleaned.pdf"));
+        assert_eq!(get_pdf_output_path(Path::new("/test/album.pdf"), Some(Path::new("/out"))).unwrap(), Path::new("/out/album.pdf"));
+        assert!(is_pdf(Path::new("ALBUM.PDF")));
+    }
+
+    #[test]
+    fn test_document_info_follows_privacy_level() {
+        let mut document = Document::with_version("1.5");
+        let info = document.add_object(dictionary! {
+            "Author" => Object::String(b"Jane Doe".to_vec(), StringFormat::Literal),
+            "Producer" => Object::String(b"\xFE\xFF\0S\0c\0a\0n".to_vec(), StringFormat::Literal),
+            "Title" => Object::String(b"Holiday 2023".to_vec(), StringFormat::Literal),
+        });
+        document.trailer.set("Info", info);
+
+        let processor = ImageProcessor::new(Config::default());
+        let (findings, keys, remove_xmp) = document_findings(&processor, &document, &PrivacyLevel::Standard);
+
+        assert!(keys.contains(&b"Author".to_vec()));
+        assert!(!keys.contains(&b"Title".to_vec()));
+        assert!(findings.iter().any(|finding| finding.description == "PDF Author: Jane Doe"));
+        assert!(!remove_xmp);
+        assert_eq!(text_value(&Object::String(b"\xFE\xFF\0S\0c\0a\0n".to_vec(), StringFormat::Literal)).unwrap(), "Scan");
+    }
+}
@@ -0,0 +1,180 @@
+//! Personal data written into textual metadata.
+//!
+//! Captions, titles, and comments are typed by people, who put email
+//! addresses, phone numbers, street addresses, and names in them. These
+//! patterns find such text; matches are reported redacted, so the report
+//! doesn't repeat what it warns about.
+
+use std::sync::OnceLock;
+use exif::Tag;
+use regex::Regex;
+
+/// Kind of personal data found in a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PiiKind {
+    Email,
+    Phone,
+    StreetAddress,
+    Name,
+}
+
+impl std::fmt::Display for PiiKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PiiKind::Email => write!(f, "an email address"),
+            PiiKind::Phone => write!(f, "a phone number"),
+            PiiKind::StreetAddress => write!(f, "a street address"),
+            PiiKind::Name => write!(f, "a personal name"),
+        }
+    }
+}
+
+/// One piece of personal data, with the matched text redacted
+#[derive(Debug, Clone, PartialEq)]
+pub struct PiiMatch {
+    pub kind: PiiKind,
+    pub redacted: String,
+}
+
+/// EXIF tags holding text people type in
+pub fn is_textual(tag: Tag) -> bool {
+    matches!(
+        tag,
+        Tag::ImageDescription | Tag::UserComment | Tag::XPTitle | Tag::XPComment
+            | Tag::XPAuthor | Tag::XPKeywords | Tag::XPSubject
+    )
+}
+
+/// Find personal data in `text`, grouped by kind
+pub fn scan(text: &str) -> Vec<PiiMatch> {
+    let mut matches = Vec::new();
+    for (kind, regex) in patterns() {
+        for captures in regex.captures_iter(text) {
+            // Names are matched after a cue like "by"; only the name itself is redacted
+            let matched = captures.get(1).or_else(|| captures.get(0)).map_or("", |m| m.as_str());
+            matches.push(PiiMatch { kind: *kind, redacted: redact(*kind, matched) });
+        }
+    }
+    matches
+}
+
+/// Whether `text` contains an email address
+pub fn contains_email(text: &str) -> bool {
+    patterns().iter().any(|(kind, regex)| *kind == PiiKind::Email && regex.is_match(text))
+}
+
+fn patterns() -> &'static [(PiiKind, Regex)] {
+    static PATTERNS: OnceLock<Vec<(PiiKind, Regex)>> = OnceLock::new();
+
+    PATTERNS.get_or_init(|| {
+        vec![
+            (PiiKind::Email, r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}"),
+            (PiiKind::Phone, r"(?:\+\d{1,3}[\s.-]?)?\b(?:\(\d{2,4}\)|\d{2,4})[\s.-]\d{3,4}[\s.-]\d{3,4}\b"),
+            (
+                PiiKind::StreetAddress,
+                r"\b\d{1,5}\s+(?:[A-Z][a-z]+\s+){1,3}(?:Street|St|Avenue|Ave|Road|Rd|Boulevard|Blvd|Lane|Ln|Drive|Dr|Court|Ct|Way|Place|Pl)\b",
+            ),
+            (PiiKind::Name, r"(?:\b(?i:photo(?:graph)?(?:ed)? by|by|author:?)|©|\(c\))\s+([A-Z][a-z]+(?:\s+[A-Z][a-z]+)+)"),
+        ]
+        .into_iter()
+        .map(|(kind, pattern)| (kind, Regex::new(pattern).unwrap()))
+        .collect()
+    })
+}
+
+/// Blank out the identifying part of a match, keeping enough to recognize the kind
+fn redact(kind: PiiKind, matched: &str) -> String {
+    match kind {
+        PiiKind::Email => {
+            let first = matched.chars().next().unwrap_or('*');
+            let tld = matched.rsplit('.').next().unwrap_or("");
+            format!("{}***@***.{}", first, tld)
+        }
+        PiiKind::Phone => {
+            // Keep the last two digits and the formatting
+            let digits = matched.chars().filter(|c| c.is_ascii_digit()).count();
+            let mut seen = 0;
+            matched.chars()
+                .map(|c| {
+                    if !c.is_ascii_digit() {
+                        return c;
+                    }
+                    seen += 1;
+                    if seen > digits.saturating_sub(2) { c } else { '*' }
+                })
+                .collect()
+        }
+        PiiKind::StreetAddress => {
+            let suffix = matched.rsplit(' ').next().unwrap_or("");
+            format!("*** {}", suffix)
+        }
+        PiiKind::Name => matched.split_whitespace()
+            .filter_map(|part| part.chars().next())
+            .map(|initial| format!("{}.", initial))
+            .collect::<Vec<_>>()
+            .join(" "),
+    }
+}
+
+/// Text of the user-editable XMP properties, as `(property, text)` pairs
+///
+/// Only Dublin Core, Photoshop, and IPTC Core properties are read; the rest of
+/// the packet is IDs and tool names that would only produce false matches.
+pub fn xmp_text_fields(xmp: &str) -> Vec<(String, String)> {
+    static ELEMENT: OnceLock<Regex> = OnceLock::new();
+    static ATTRIBUTE: OnceLock<Regex> = OnceLock::new();
+    static MARKUP: OnceLock<Regex> = OnceLock::new();
+
+    let element = ELEMENT.get_or_init(|| {
+        Regex::new(r"(?s)<((?:dc|photoshop|Iptc4xmpCore):\w+)(?:\s[^>]*)?>(.*?)</(?:dc|photoshop|Iptc4xmpCore):\w+>").unwrap()
+    });
+    let attribute = ATTRIBUTE.get_or_init(|| Regex::new(r#"\b((?:photoshop|Iptc4xmpCore):\w+)="([^"]*)""#).unwrap());
+    let markup = MARKUP.get_or_init(|| Regex::new(r"<[^>]*>").unwrap());
+
+    element.captures_iter(xmp)
+        .chain(attribute.captures_iter(xmp))
+        .map(|captures| {
+            let text = markup.replace_all(&captures[2], " ");
+            (captures[1].to_string(), text.split_whitespace().collect::<Vec<_>>().join(" "))
+        })
+        .filter(|(_, text)| !text.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_redacts_matches() {
+        let found = scan("Call +1 555-867-5309 or mail jane.doe@example.com");
+
+        assert_eq!(found, vec![
+            PiiMatch { kind: PiiKind::Email, redacted: "j***@***.com".to_string() },
+            PiiMatch { kind: PiiKind::Phone, redacted: "+* ***-***-**09".to_string() },
+        ]);
+    }
+
+    #[test]
+    fn test_scan_addresses_and_names() {
+        let found = scan("Our house at 42 Elm Tree Road, photo by Jane Doe");
+
+        assert_eq!(found, vec![
+            PiiMatch { kind: PiiKind::StreetAddress, redacted: "*** Road".to_string() },
+            PiiMatch { kind: PiiKind::Name, redacted: "J. D.".to_string() },
+        ]);
+        assert!(scan("Sunset by the harbour, 2023-10-15").is_empty());
+    }
+
+    #[test]
+    fn test_xmp_text_fields() {
+        let xmp = r#"<rdf:Description photoshop:City="Springfield" xmpMM:DocumentID="xmp.did:1234-5678-9012">
+            <dc:description><rdf:Alt><rdf:li xml:lang="x-default">Call 555-867-5309</rdf:li></rdf:Alt></dc:description>
+        </rdf:Description>"#;
+
+        assert_eq!(xmp_text_fields(xmp), vec![
+            ("dc:description".to_string(), "Call 555-867-5309".to_string()),
+            ("photoshop:City".to_string(), "Springfield".to_string()),
+        ]);
+    }
+}
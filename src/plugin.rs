@@ -0,0 +1,121 @@
+//! Dynamically loaded plugins (`--plugin path`).
+//!
+//! A plugin is a shared library (`cdylib` or `dylib`) built against this
+//! crate with the `plugins` feature. It exports its API version and a
+//! registration function that adds format handlers and named policies:
+//!
+//! ```ignore
+//! #[no_mangle]
+//! pub static PRIVACY_CLEANER_PLUGIN_API: u32 = privacy_exif_cleaner::plugin::API_VERSION;
+//!
+//! #[no_mangle]
+//! pub fn privacy_cleaner_plugin_register(registrar: &mut PluginRegistrar) {
+//!     registrar.register_handler(Arc::new(BadgeScrubber));
+//! }
+//! ```
+//!
+//! The registration function is called with the Rust ABI and handlers cross
+//! the library boundary as trait objects, so a plugin must be built with the
+//! same compiler and crate version as the CLI loading it.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use libloading::{Library, Symbol};
+use tracing::info;
+use crate::formats::{self, FormatHandler};
+use crate::privacy::PolicySpec;
+
+/// Bumped whenever `PluginRegistrar` or `FormatHandler` change incompatibly
+pub const API_VERSION: u32 = 1;
+const API_SYMBOL: &[u8] = b"PRIVACY_CLEANER_PLUGIN_API";
+const REGISTER_SYMBOL: &[u8] = b"privacy_cleaner_plugin_register";
+
+/// What a plugin adds, collected during its registration call
+#[derive(Default)]
+pub struct PluginRegistrar {
+    handlers: Vec<Arc<dyn FormatHandler>>,
+    policies: Vec<(String, PolicySpec)>,
+}
+
+impl PluginRegistrar {
+    /// Add a format handler (see `formats::FormatHandler`)
+    pub fn register_handler(&mut self, handler: Arc<dyn FormatHandler>) {
+        self.handlers.push(handler);
+    }
+
+    /// Add a policy that `--plugin-policy <name>` selects
+    pub fn register_policy(&mut self, name: &str, spec: PolicySpec) {
+        self.policies.push((name.to_string(), spec));
+    }
+}
+
+/// Policies registered by the loaded plugins, by name
+#[derive(Debug, Default)]
+pub struct LoadedPlugins {
+    pub policies: BTreeMap<String, PolicySpec>,
+}
+
+/// Load each plugin and register its handlers with the process-wide format registry
+///
+/// Libraries are never unloaded: the handlers they registered live in their code.
+pub fn load_plugins(paths: &[PathBuf]) -> Result<LoadedPlugins, Box<dyn std::error::Error>> {
+    let mut loaded = LoadedPlugins::default();
+
+    for path in paths {
+        let registrar = load_plugin(path).map_err(|e| format!("Failed to load plugin '{}': {}", path.display(), e))?;
+        info!(plugin = %path.display(), handlers = registrar.handlers.len(), policies = registrar.policies.len(), "Loaded plugin");
+
+        for handler in registrar.handlers {
+            formats::register_handler(handler);
+        }
+        for (name, spec) in registrar.policies {
+            if loaded.policies.insert(name.clone(), spec).is_some() {
+                return Err(format!("Plugin '{}' registers policy '{}', which another plugin already registered", path.display(), name).into());
+            }
+        }
+    }
+
+    Ok(loaded)
+}
+
+fn load_plugin(path: &Path) -> Result<PluginRegistrar, Box<dyn std::error::Error>> {
+    // SAFETY: loading runs the library's initializers, and the symbols are only
+    // used with the types documented above; the API version is checked before
+    // the registration function is called.
+    let library = unsafe { Library::new(path)? };
+    let mut registrar = PluginRegistrar::default();
+    unsafe {
+        let version: Symbol<*const u32> = library.get(API_SYMBOL)?;
+        let version = **version;
+        if version != API_VERSION {
+            return Err(format!("built for plugin API {}, but this build supports {}", version, API_VERSION).into());
+        }
+        let register: Symbol<fn(&mut PluginRegistrar)> = library.get(REGISTER_SYMBOL)?;
+        register(&mut registrar);
+    }
+    std::mem::forget(library);
+
+    Ok(registrar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::privacy::PrivacyLevel;
+
+    #[test]
+    fn test_missing_plugin_is_an_error() {
+        let error = load_plugins(&[PathBuf::from("/nonexistent/libscrubber.so")]).unwrap_err();
+        assert!(error.to_string().contains("/nonexistent/libscrubber.so"));
+        assert!(load_plugins(&[]).unwrap().policies.is_empty());
+    }
+
+    #[test]
+    fn test_registrar_collects_handlers_and_policies() {
+        let mut registrar = PluginRegistrar::default();
+        registrar.register_policy("press", PolicySpec::from_level(&PrivacyLevel::Strict));
+        assert_eq!(registrar.policies[0].0, "press");
+        assert!(registrar.handlers.is_empty());
+    }
+}
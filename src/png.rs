@@ -0,0 +1,188 @@
+//! Minimal PNG chunk parser.
+//!
+//! Reads the chunk sequence and the metadata chunks in it: `eXIf` for EXIF,
+//! and the `tEXt`, `zTXt`, and `iTXt` text chunks where screenshot tools and
+//! editors put the source app, device model, page URL, and XMP.
+
+use std::io::Read;
+use exif::Tag;
+use flate2::read::ZlibDecoder;
+use crate::analyzer::PrivacyCategory;
+
+/// Signature every PNG starts with
+pub const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+/// Keyword of the iTXt chunk holding the XMP packet
+pub const XMP_KEYWORD: &str = "XML:com.adobe.xmp";
+/// Upper bound on the decompressed size of one text chunk, guarding against zlib bombs
+const MAX_TEXT_BYTES: u64 = 4 * 1024 * 1024;
+
+/// One chunk of a PNG file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk<'a> {
+    pub kind: [u8; 4],
+    pub data: &'a [u8],
+}
+
+/// A decoded text chunk
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextChunk {
+    pub keyword: String,
+    pub text: String,
+}
+
+/// Check for the PNG signature
+pub fn is_png(data: &[u8]) -> bool {
+    data.starts_with(SIGNATURE)
+}
+
+/// Parse the chunks of a PNG up to and including IEND
+pub fn parse_chunks(data: &[u8]) -> Result<Vec<Chunk<'_>>, Box<dyn std::error::Error>> {
+    if !is_png(data) {
+        return Err("Not a PNG file (missing signature)".into());
+    }
+
+    let mut chunks = Vec::new();
+    let mut pos = SIGNATURE.len();
+    loop {
+        let header = data.get(pos..pos + 8).ok_or("Unexpected end of PNG data")?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let kind = [header[4], header[5], header[6], header[7]];
+        let chunk_data = data.get(pos + 8..pos + 8 + len).ok_or("PNG chunk runs past the end of the file")?;
+
+        chunks.push(Chunk { kind, data: chunk_data });
+        if &kind == b"IEND" {
+            return Ok(chunks);
+        }
+        // Data is followed by a 4-byte CRC
+        pos += 12 + len;
+    }
+}
+
+/// The EXIF block of a PNG's `eXIf` chunk
+pub fn exif_block(chunks: &[Chunk<'_>]) -> Option<Vec<u8>> {
+    chunks.iter().find(|chunk| &chunk.kind == b"eXIf").map(|chunk| chunk.data.to_vec())
+}
+
+/// Decode every text chunk; chunks that can't be decoded are left out
+pub fn text_chunks(chunks: &[Chunk<'_>]) -> Vec<TextChunk> {
+    chunks.iter().filter_map(decode_text).collect()
+}
+
+fn decode_text(chunk: &Chunk<'_>) -> Option<TextChunk> {
+    let separator = chunk.data.iter().position(|&byte| byte == 0)?;
+    let keyword = latin1(&chunk.data[..separator]);
+    let rest = &chunk.data[separator + 1..];
+
+    let text = match &chunk.kind {
+        b"tEXt" => latin1(rest),
+        b"zTXt" => latin1(&inflate(rest.get(1..)?)?),
+        b"iTXt" => {
+            // Compression flag and method, then language tag and translated keyword
+            let (&compressed, rest) = rest.split_first()?;
+            let rest = rest.get(1..)?;
+            let language_end = rest.iter().position(|&byte| byte == 0)?;
+            let rest = &rest[language_end + 1..];
+            let translated_end = rest.iter().position(|&byte| byte == 0)?;
+            let text = &rest[translated_end + 1..];
+            if compressed == 1 {
+                String::from_utf8_lossy(&inflate(text)?).into_owned()
+            } else {
+                String::from_utf8_lossy(text).into_owned()
+            }
+        }
+        _ => return None,
+    };
+    Some(TextChunk { keyword, text })
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+fn inflate(data: &[u8]) -> Option<Vec<u8>> {
+    let mut inflated = Vec::new();
+    ZlibDecoder::new(data).take(MAX_TEXT_BYTES).read_to_end(&mut inflated).ok()?;
+    Some(inflated)
+}
+
+/// How a text chunk keyword is reported, and the EXIF tag whose policy decides if it goes
+///
+/// Covers the standard PNG keywords and those phone and browser screenshot
+/// tools write. Unknown keywords follow the level's rule for unlisted tags.
+pub fn text_field_policy(keyword: &str) -> (PrivacyCategory, Option<Tag>) {
+    match keyword.to_ascii_lowercase().as_str() {
+        "author" | "artist" | "creator" => (PrivacyCategory::PersonalInfo, Some(Tag::Artist)),
+        "copyright" => (PrivacyCategory::PersonalInfo, Some(Tag::Copyright)),
+        "comment" | "disclaimer" | "warning" => (PrivacyCategory::PersonalInfo, Some(Tag::UserComment)),
+        // The page a browser screenshot was taken of
+        "url" | "uri" | "source url" | "page url" | "referrer" | "link" => {
+            (PrivacyCategory::PersonalInfo, Some(Tag::UserComment))
+        }
+        "source" | "device" | "make" | "model" | "device model" => {
+            (PrivacyCategory::DeviceIdentifier, Some(Tag::UniqueCameraModel))
+        }
+        "creation time" | "date:create" | "date:modify" | "date:timestamp" => {
+            (PrivacyCategory::Temporal, Some(Tag::DateTime))
+        }
+        "software" | "application" | "app" | "source app" => (PrivacyCategory::Software, Some(Tag::Software)),
+        "title" | "description" => (PrivacyCategory::Metadata, Some(Tag::ImageDescription)),
+        _ => (PrivacyCategory::Other, None),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::Write;
+    use flate2::write::ZlibEncoder;
+    use flate2::Compression;
+
+    /// Assemble a PNG from `(type, data)` chunks; CRCs are not checked by the parser and left zero
+    pub(crate) fn build_png(chunks: &[(&[u8; 4], &[u8])]) -> Vec<u8> {
+        let mut data = SIGNATURE.to_vec();
+        for (kind, chunk_data) in chunks.iter().chain([(b"IEND", &[][..])].iter()) {
+            data.extend_from_slice(&(chunk_data.len() as u32).to_be_bytes());
+            data.extend_from_slice(*kind);
+            data.extend_from_slice(chunk_data);
+            data.extend_from_slice(&[0; 4]);
+        }
+        data
+    }
+
+    #[test]
+    fn test_text_chunks() {
+        let mut compressed = ZlibEncoder::new(Vec::new(), Compression::default());
+        compressed.write_all(b"2024-05-01T10:00:00").unwrap();
+        let mut ztxt = b"Creation Time\0\0".to_vec();
+        ztxt.extend_from_slice(&compressed.finish().unwrap());
+
+        let data = build_png(&[
+            (b"IHDR", &[0; 13]),
+            (b"tEXt", b"Software\0Screenshot"),
+            (b"zTXt", &ztxt),
+            (b"iTXt", b"URL\0\0\0en\0\0https://example.com/account"),
+        ]);
+        let chunks = parse_chunks(&data).unwrap();
+        let texts = text_chunks(&chunks);
+
+        assert_eq!(texts.len(), 3);
+        assert_eq!(texts[0], TextChunk { keyword: "Software".to_string(), text: "Screenshot".to_string() });
+        assert_eq!(texts[1].text, "2024-05-01T10:00:00");
+        assert_eq!(texts[2].keyword, "URL");
+        assert_eq!(texts[2].text, "https://example.com/account");
+    }
+
+    #[test]
+    fn test_truncated_png_is_an_error() {
+        let mut data = build_png(&[(b"tEXt", b"Software\0Screenshot")]);
+        data.truncate(data.len() - 20);
+        assert!(parse_chunks(&data).is_err());
+    }
+
+    #[test]
+    fn test_screenshot_fields_are_categorized() {
+        assert_eq!(text_field_policy("Source URL").0, PrivacyCategory::PersonalInfo);
+        assert_eq!(text_field_policy("Device Model"), (PrivacyCategory::DeviceIdentifier, Some(Tag::UniqueCameraModel)));
+        assert_eq!(text_field_policy("Raw profile type exif"), (PrivacyCategory::Other, None));
+    }
+}
@@ -1,8 +1,11 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::sync::OnceLock;
+use clap::builder::PossibleValue;
 use clap::ValueEnum;
-use exif::Tag;
+use exif::{Context, In, Tag};
+use crate::analyzer::{ExifAnalyzer, PrivacyCategory};
 
-#[derive(Clone, Debug, ValueEnum)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum PrivacyLevel {
     /// Remove only location data (GPS)
     Minimal,
@@ -10,56 +13,412 @@ pub enum PrivacyLevel {
     Standard,
     /// Remove all potentially identifying information
     Strict,
+    /// For posting online: keep only orientation, color, and basic exposure data
+    WebShare,
     /// Remove everything except basic technical settings
     Paranoid,
+    /// A policy built in code; not selectable on the command line
+    Custom(PolicySpec),
+}
+
+impl ValueEnum for PrivacyLevel {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[
+            PrivacyLevel::Minimal,
+            PrivacyLevel::Standard,
+            PrivacyLevel::Strict,
+            PrivacyLevel::WebShare,
+            PrivacyLevel::Paranoid,
+        ]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        let (name, help) = match self {
+            PrivacyLevel::Minimal => ("minimal", "Remove only location data (GPS)"),
+            PrivacyLevel::Standard => ("standard", "Remove location + identifying device information"),
+            PrivacyLevel::Strict => ("strict", "Remove all potentially identifying information"),
+            PrivacyLevel::WebShare => ("web-share", "Keep only orientation, color, and basic exposure data"),
+            PrivacyLevel::Paranoid => ("paranoid", "Remove everything except basic technical settings"),
+            PrivacyLevel::Custom(_) => return None,
+        };
+        Some(PossibleValue::new(name).help(help))
+    }
 }
 
 pub struct PrivacyPolicy;
 
+/// TIFF tag holding the XMP packet; stands for all XMP metadata in findings
+pub const XMP_PACKET: Tag = Tag(Context::Tiff, 0x02BC);
+/// TIFF tag holding the IPTC-IIM stream; stands for all IPTC metadata in findings
+pub const IPTC_NAA: Tag = Tag(Context::Tiff, 0x83BB);
+
+/// Not a TIFF tag; stands for a PNG's text chunks in findings
+pub const PNG_TEXT: Tag = Tag(Context::Tiff, 0xFFFF);
+/// Not a TIFF tag; stands for edit data trailing the image (Samsung re-edit data) in findings
+pub const EDIT_TRAILER: Tag = Tag(Context::Tiff, 0xFFFE);
+/// Not a TIFF tag; stands for Google Camera's original image in XMP in findings
+pub const XMP_ORIGINAL: Tag = Tag(Context::Tiff, 0xFFFD);
+/// Not a TIFF tag; stands for a motion photo's video and the XMP announcing it in findings
+pub const MOTION_VIDEO: Tag = Tag(Context::Tiff, 0xFFFC);
+/// Not a TIFF tag; stands for a GIF comment extension in findings
+pub const GIF_COMMENT: Tag = Tag(Context::Tiff, 0xFFFB);
+/// Not a TIFF tag; stands for the path of a BMP's linked color profile in findings
+pub const LINKED_PROFILE: Tag = Tag(Context::Tiff, 0xFFFA);
+/// Not a TIFF tag; stands for metadata left in bytes no structure references in findings
+pub const REMNANT: Tag = Tag(Context::Tiff, 0xFFF9);
+
+/// The ExifTool groups removed in place of `tag`, for tags that stand for a whole metadata block
+fn metadata_groups(tag: Tag) -> &'static [&'static str] {
+    match tag {
+        XMP_PACKET => &["XMP"],
+        IPTC_NAA => &["IPTC"],
+        PNG_TEXT => &["PNG"],
+        EDIT_TRAILER => &["Trailer"],
+        XMP_ORIGINAL => &["XMP-GImage"],
+        // Samsung keeps the video in its trailer, Google appends it and describes it in XMP
+        MOTION_VIDEO => &["Trailer", "XMP-GCamera", "XMP-Container"],
+        // The thumbnail goes with its whole IFD
+        Tag::JPEGInterchangeFormat => &["IFD1"],
+        _ => &[],
+    }
+}
+
+/// Parts of the GPS data that can be kept independently (`--keep-gps`)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum GpsGroup {
+    /// Latitude and longitude of the camera and of the destination
+    Position,
+    /// Altitude above or below sea level
+    Altitude,
+    /// Direction the camera faced, direction of travel, and bearing to the destination
+    Direction,
+    /// Speed of the receiver
+    Speed,
+    /// GPS date and time
+    Time,
+    /// Receiver details: satellites, precision, map datum, and the like
+    Technical,
+}
+
+/// The IFD of a TIFF structure a tag was read from
+///
+/// Tag numbers of the TIFF context mean different things in the image's IFD0
+/// and the thumbnail's IFD1: `Software` in IFD1 may name the tool that made
+/// the thumbnail, not the photo.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Ifd {
+    /// The main image
+    Ifd0,
+    /// The thumbnail
+    Ifd1,
+    Exif,
+    Gps,
+    Interop,
+}
+
+impl Ifd {
+    /// The IFD `field` was read from
+    pub fn of(field: &exif::Field) -> Self {
+        match field.tag.context() {
+            Context::Exif => Ifd::Exif,
+            Context::Gps => Ifd::Gps,
+            Context::Interop => Ifd::Interop,
+            _ if field.ifd_num == In::PRIMARY => Ifd::Ifd0,
+            _ => Ifd::Ifd1,
+        }
+    }
+
+    /// The ExifTool group of the IFD
+    pub fn exiftool_group(&self) -> &'static str {
+        match self {
+            Ifd::Ifd0 => "IFD0",
+            Ifd::Ifd1 => "IFD1",
+            Ifd::Exif => "ExifIFD",
+            Ifd::Gps => "GPS",
+            Ifd::Interop => "InteropIFD",
+        }
+    }
+}
+
+impl std::fmt::Display for Ifd {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ifd::Ifd0 => write!(f, "IFD0"),
+            Ifd::Ifd1 => write!(f, "IFD1"),
+            Ifd::Exif => write!(f, "Exif"),
+            Ifd::Gps => write!(f, "GPS"),
+            Ifd::Interop => write!(f, "Interop"),
+        }
+    }
+}
+
+/// What happens to tags no EXIF specification defines (`--unknown-tags`)
+///
+/// Vendors park private data in undocumented IFD0 and Exif IFD tags. Allowlist
+/// levels drop them anyway; this decides for the levels that keep unlisted tags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum UnknownTags {
+    /// Keep unknown tags unless the level is an allowlist
+    #[default]
+    Preserve,
+    /// Remove unknown tags at every level
+    Remove,
+    /// Remove unknown tags at strict and the levels above it
+    RemoveAboveStrict,
+}
+
+impl UnknownTags {
+    /// Whether unknown tags are removed at `privacy_level`
+    pub fn removes(&self, privacy_level: &PrivacyLevel) -> bool {
+        if !PrivacyPolicy::preserves_unlisted_tags(privacy_level) {
+            return true;
+        }
+        match self {
+            UnknownTags::Preserve => false,
+            UnknownTags::Remove => true,
+            UnknownTags::RemoveAboveStrict => PrivacyPolicy::is_at_least_as_strict(privacy_level, &PrivacyLevel::Strict),
+        }
+    }
+}
+
+/// Whether `tag` is one no EXIF specification defines
+///
+/// This goes by kamadak-exif's tag table, while the removal relies on
+/// ExifTool's, which also knows DNG and some vendor tags. Those are reported
+/// as unknown here but survive the removal.
+pub fn is_unknown_tag(tag: Tag) -> bool {
+    tag.description().is_none()
+}
+
+impl GpsGroup {
+    pub fn tags(&self) -> Vec<Tag> {
+        match self {
+            GpsGroup::Position => vec![
+                Tag::GPSLatitudeRef,
+                Tag::GPSLatitude,
+                Tag::GPSLongitudeRef,
+                Tag::GPSLongitude,
+                Tag::GPSDestLatitudeRef,
+                Tag::GPSDestLatitude,
+                Tag::GPSDestLongitudeRef,
+                Tag::GPSDestLongitude,
+                Tag::GPSDestDistanceRef,
+                Tag::GPSDestDistance,
+                Tag::GPSAreaInformation,
+            ],
+            GpsGroup::Altitude => vec![Tag::GPSAltitudeRef, Tag::GPSAltitude],
+            GpsGroup::Direction => vec![
+                Tag::GPSImgDirectionRef,
+                Tag::GPSImgDirection,
+                Tag::GPSTrackRef,
+                Tag::GPSTrack,
+                Tag::GPSDestBearingRef,
+                Tag::GPSDestBearing,
+            ],
+            GpsGroup::Speed => vec![Tag::GPSSpeedRef, Tag::GPSSpeed],
+            GpsGroup::Time => vec![Tag::GPSTimeStamp, Tag::GPSDateStamp],
+            GpsGroup::Technical => vec![
+                Tag::GPSVersionID,
+                Tag::GPSSatellites,
+                Tag::GPSStatus,
+                Tag::GPSMeasureMode,
+                Tag::GPSDOP,
+                Tag::GPSMapDatum,
+                Tag::GPSProcessingMethod,
+                Tag::GPSDifferential,
+            ],
+        }
+    }
+}
+
+/// What a privacy level removes
+///
+/// The analyzer reports and the ExifTool remover deletes from this one
+/// description, so the two can't disagree about a tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolicySpec {
+    /// Tags removed one by one, in a stable order
+    pub remove: Vec<Tag>,
+    /// ExifTool groups deleted wholesale (`gps`, `XMP`, `IPTC`)
+    pub remove_groups: Vec<&'static str>,
+    /// For allowlist levels, the only tags kept; everything else is removed
+    pub keep_only: Option<Vec<Tag>>,
+    /// ExifTool groups an allowlist level keeps whole (`ICC_Profile`)
+    pub keep_groups: Vec<&'static str>,
+}
+
+impl PolicySpec {
+    /// Start a custom policy from one of the levels
+    pub fn from_level(privacy_level: &PrivacyLevel) -> Self {
+        PrivacyPolicy::spec(privacy_level)
+    }
+
+    /// Also remove `tags`
+    ///
+    /// `XMP_PACKET`, `IPTC_NAA`, and `PNG_TEXT` remove all XMP, IPTC, or PNG text metadata.
+    pub fn removing_tags(mut self, tags: impl IntoIterator<Item = Tag>) -> Self {
+        for tag in tags {
+            let groups = metadata_groups(tag);
+            if !groups.is_empty() {
+                for group in groups {
+                    if self.keep_only.is_none() && !self.remove_groups.contains(group) {
+                        self.remove_groups.push(group);
+                    }
+                }
+            } else if let Some(ref mut keep) = self.keep_only {
+                keep.retain(|kept| *kept != tag);
+            } else if !self.remove.contains(&tag) {
+                self.remove.push(tag);
+            }
+        }
+        self
+    }
+
+    /// Also remove every known tag reported under `category`
+    pub fn removing_category(self, category: PrivacyCategory) -> Self {
+        let analyzer = ExifAnalyzer::new();
+        let tags: Vec<Tag> = PrivacyPolicy::tag_groups().into_iter()
+            .flat_map(|(_, tags)| tags)
+            .filter(|tag| analyzer.categorize_privacy_field(*tag) == category)
+            .collect();
+        self.removing_tags(tags)
+    }
+
+    /// Keep `tags` even though the spec would remove them
+    ///
+    /// Keeping any GPS tag stops the GPS IFD from being cleared wholesale; the
+    /// remaining GPS tags are then removed one by one.
+    pub fn keeping_tags(mut self, tags: impl IntoIterator<Item = Tag>) -> Self {
+        for tag in tags {
+            self.remove.retain(|removed| *removed != tag);
+            if tag.context() == Context::Gps {
+                self.remove_groups.retain(|group| *group != "gps");
+            }
+            if let Some(ref mut keep) = self.keep_only {
+                if !keep.contains(&tag) {
+                    keep.push(tag);
+                }
+            }
+        }
+        self
+    }
+
+    /// Keep whole groups of GPS tags, e.g. altitude and direction without the position
+    pub fn keeping_gps(self, groups: &[GpsGroup]) -> Self {
+        self.keeping_tags(groups.iter().flat_map(|group| group.tags()))
+    }
+
+    /// Also remove camera and lens make and model, for equipment rare enough to identify its owner
+    pub fn removing_equipment(self) -> Self {
+        self.removing_tags(PrivacyPolicy::get_equipment_tags())
+    }
+
+    /// The same policy as an allowlist: every named tag it doesn't remove is kept, and nothing else
+    ///
+    /// Cleaning with the allowlist writes metadata from scratch (`--rebuild-metadata`), so
+    /// tags the policy doesn't know about can't ride along. XMP, IPTC, MakerNotes, and
+    /// any other tag outside `PrivacyPolicy::tag_groups` are dropped; the ICC profile is kept.
+    pub fn as_allowlist(self) -> Self {
+        if self.keep_only.is_some() {
+            return self;
+        }
+        let mut keep: Vec<Tag> = Vec::new();
+        for tag in PrivacyPolicy::named_tags() {
+            if !self.removes(tag) && !keep.contains(&tag) {
+                keep.push(tag);
+            }
+        }
+        PolicySpec { remove: Vec::new(), remove_groups: Vec::new(), keep_only: Some(keep), keep_groups: vec!["ICC_Profile"] }
+    }
+
+    /// Whether XMP metadata is deleted along with EXIF
+    pub fn removes_xmp(&self) -> bool {
+        self.keep_only.is_some() || self.remove_groups.contains(&"XMP")
+    }
+
+    /// Whether this spec removes `tag`
+    pub fn removes(&self, tag: Tag) -> bool {
+        let groups = metadata_groups(tag);
+        if !groups.is_empty() {
+            return self.keep_only.is_some() || groups.iter().all(|group| self.remove_groups.contains(group));
+        }
+        match self.keep_only {
+            Some(ref keep) => !keep.contains(&tag),
+            None => self.remove.contains(&tag),
+        }
+    }
+}
+
 impl PrivacyPolicy {
-    /// Get the set of EXIF tags that should be removed for a given privacy level
-    pub fn get_tags_to_remove(privacy_level: &PrivacyLevel) -> HashSet<Tag> {
-        let mut tags = HashSet::new();
+    /// The removal spec for a privacy level
+    pub fn spec(privacy_level: &PrivacyLevel) -> PolicySpec {
+        if let PrivacyLevel::Custom(spec) = privacy_level {
+            return spec.clone();
+        }
 
         // Always remove GPS data (all privacy levels)
-        tags.extend(Self::get_gps_tags());
+        let mut remove = Self::get_gps_tags();
+        let mut remove_groups = vec!["gps"];
+        let mut keep_only = None;
+        let mut keep_groups = Vec::new();
 
         match privacy_level {
             PrivacyLevel::Minimal => {
                 // Only GPS data removed above
             }
             PrivacyLevel::Standard => {
-                tags.extend(Self::get_device_identifying_tags());
-                tags.extend(Self::get_personal_info_tags());
+                remove.extend(Self::get_device_identifying_tags());
+                remove.extend(Self::get_personal_info_tags());
             }
             PrivacyLevel::Strict => {
-                tags.extend(Self::get_device_identifying_tags());
-                tags.extend(Self::get_personal_info_tags());
-                tags.extend(Self::get_temporal_tags());
-                tags.extend(Self::get_software_tags());
-                tags.extend(Self::get_metadata_tags());
+                remove.extend(Self::get_device_identifying_tags());
+                remove.extend(Self::get_personal_info_tags());
+                remove.extend(Self::get_temporal_tags());
+                remove.extend(Self::get_software_tags());
+                remove.extend(Self::get_metadata_tags());
+                remove_groups.extend(["XMP", "IPTC"]);
+            }
+            PrivacyLevel::WebShare => {
+                // Whitelist too, but without make/model; the ICC profile keeps colors right in browsers
+                keep_only = Some(Self::get_web_share_settings());
+                keep_groups.push("ICC_Profile");
             }
             PrivacyLevel::Paranoid => {
                 // In paranoid mode, we use a whitelist approach
-                // This is handled in should_preserve_tag()
+                keep_only = Some(Self::get_essential_camera_settings());
             }
+            PrivacyLevel::Custom(_) => unreachable!("custom specs are returned above"),
         }
 
-        tags
+        PolicySpec { remove, remove_groups, keep_only, keep_groups }
+    }
+
+    /// Get the set of EXIF tags that should be removed for a given privacy level
+    pub fn get_tags_to_remove(privacy_level: &PrivacyLevel) -> HashSet<Tag> {
+        Self::spec(privacy_level).remove.into_iter().collect()
     }
 
     /// Determine if a tag should be preserved (inverse of removal logic)
     pub fn should_preserve_tag(tag: Tag, privacy_level: &PrivacyLevel) -> bool {
-        match privacy_level {
-            PrivacyLevel::Paranoid => {
-                // In paranoid mode, only preserve essential technical settings
-                Self::is_essential_camera_setting(tag)
-            }
-            _ => {
-                // For other levels, check if the tag is in the removal list
-                !Self::get_tags_to_remove(privacy_level).contains(&tag)
-            }
-        }
+        !Self::spec(privacy_level).removes(tag)
+    }
+
+    /// The name ExifTool reads and writes `tag` under, where it differs from the EXIF name
+    pub fn exiftool_name(tag: Tag) -> String {
+        let name = match tag {
+            Tag::DateTime => "ModifyDate",
+            Tag::DateTimeDigitized => "CreateDate",
+            Tag::BodySerialNumber | Tag::CameraSerialNumber => "SerialNumber",
+            Tag::CameraOwnerName => "OwnerName",
+            Tag::ISOSpeedRatings => "ISO",
+            Tag::LensSpecification => "LensInfo",
+            Tag::MakerNote => "MakerNotes",
+            Tag::PixelXDimension => "ExifImageWidth",
+            Tag::PixelYDimension => "ExifImageHeight",
+            GIF_COMMENT => "GIF:Comment",
+            _ => return tag.to_string(),
+        };
+        name.to_string()
     }
 
     /// GPS and location-related tags
@@ -156,38 +515,118 @@ impl PrivacyPolicy {
     }
 
     /// Essential camera settings that should be preserved even in paranoid mode
-    fn is_essential_camera_setting(tag: Tag) -> bool {
-        matches!(
-            tag,
-            Tag::ExposureTime
-                | Tag::FNumber
-                | Tag::ISO
-                | Tag::ISOSpeedRatings
-                | Tag::FocalLength
-                | Tag::FocalLengthIn35mmFilm
-                | Tag::ExposureProgram
-                | Tag::MeteringMode
-                | Tag::Flash
-                | Tag::ColorSpace
-                | Tag::WhiteBalance
-                | Tag::ExposureMode
-                | Tag::SceneCaptureType
-                | Tag::Contrast
-                | Tag::Saturation
-                | Tag::Sharpness
-                | Tag::Make
-                | Tag::Model // Keep camera make/model but not serial numbers
-                | Tag::Orientation
-                | Tag::XResolution
-                | Tag::YResolution
-                | Tag::ResolutionUnit
-                | Tag::YCbCrPositioning
-                | Tag::ExifVersion
-                | Tag::ComponentsConfiguration
-                | Tag::CompressedBitsPerPixel
-                | Tag::PixelXDimension
-                | Tag::PixelYDimension
-        )
+    fn get_essential_camera_settings() -> Vec<Tag> {
+        vec![
+            Tag::ExposureTime,
+            Tag::FNumber,
+            Tag::ISO,
+            Tag::ISOSpeedRatings,
+            Tag::FocalLength,
+            Tag::FocalLengthIn35mmFilm,
+            Tag::ExposureProgram,
+            Tag::MeteringMode,
+            Tag::Flash,
+            Tag::ColorSpace,
+            Tag::WhiteBalance,
+            Tag::ExposureMode,
+            Tag::SceneCaptureType,
+            Tag::Contrast,
+            Tag::Saturation,
+            Tag::Sharpness,
+            Tag::Make,
+            Tag::Model, // Keep camera make/model but not serial numbers
+            Tag::Orientation,
+            Tag::XResolution,
+            Tag::YResolution,
+            Tag::ResolutionUnit,
+            Tag::YCbCrPositioning,
+            Tag::ExifVersion,
+            Tag::ComponentsConfiguration,
+            Tag::CompressedBitsPerPixel,
+            Tag::PixelXDimension,
+            Tag::PixelYDimension,
+        ]
+    }
+
+    /// Camera body and lens make and model (`--anonymize-equipment`)
+    ///
+    /// No level removes these on its own; exposure settings stay either way.
+    pub fn get_equipment_tags() -> Vec<Tag> {
+        vec![
+            Tag::Make,
+            Tag::Model,
+            Tag::LensMake,
+            Tag::LensModel,
+            Tag::LensSpecification,
+        ]
+    }
+
+    /// Settings kept by the web-share level: orientation, color, and basic exposure
+    fn get_web_share_settings() -> Vec<Tag> {
+        vec![
+            Tag::Orientation,
+            Tag::ColorSpace,
+            Tag::ExposureTime,
+            Tag::FNumber,
+            Tag::ISO,
+            Tag::ISOSpeedRatings,
+            Tag::FocalLength,
+            Tag::ExposureProgram,
+            Tag::Flash,
+            Tag::XResolution,
+            Tag::YResolution,
+            Tag::ResolutionUnit,
+            Tag::YCbCrPositioning,
+            Tag::ExifVersion,
+            Tag::ComponentsConfiguration,
+            Tag::PixelXDimension,
+            Tag::PixelYDimension,
+        ]
+    }
+
+    /// Every tag the policy names, in named groups
+    pub fn tag_groups() -> Vec<(&'static str, Vec<Tag>)> {
+        vec![
+            ("GPS", Self::get_gps_tags()),
+            ("Device identifiers", Self::get_device_identifying_tags()),
+            ("Personal information", Self::get_personal_info_tags()),
+            ("Timestamps", Self::get_temporal_tags()),
+            ("Software", Self::get_software_tags()),
+            ("Descriptive metadata", Self::get_metadata_tags()),
+            ("Essential camera settings", Self::get_essential_camera_settings()),
+        ]
+    }
+
+    /// Every tag any level or option names
+    fn named_tags() -> Vec<Tag> {
+        Self::tag_groups().into_iter()
+            .flat_map(|(_, tags)| tags)
+            .chain(Self::get_equipment_tags())
+            .chain(Self::get_web_share_settings())
+            .collect()
+    }
+
+    /// Whether tags outside `tag_groups` survive this level
+    ///
+    /// Web-share and paranoid work from an allowlist, so they remove every tag they don't name.
+    pub fn preserves_unlisted_tags(privacy_level: &PrivacyLevel) -> bool {
+        Self::spec(privacy_level).keep_only.is_none()
+    }
+
+    /// Whether `privacy_level` removes everything `baseline` removes
+    ///
+    /// Allowlist levels are stricter than any level that keeps unlisted tags.
+    pub fn is_at_least_as_strict(privacy_level: &PrivacyLevel, baseline: &PrivacyLevel) -> bool {
+        let (spec, baseline) = (Self::spec(privacy_level), Self::spec(baseline));
+        match (&spec.keep_only, &baseline.keep_only) {
+            (Some(kept), Some(baseline_kept)) => kept.iter().all(|tag| baseline_kept.contains(tag)),
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => {
+                baseline.remove.iter().all(|tag| spec.remove.contains(tag))
+                    && baseline.remove_groups.iter().all(|group| spec.remove_groups.contains(group))
+            }
+        }
     }
 
     /// Get a human-readable description of what each privacy level removes
@@ -208,8 +647,173 @@ impl PrivacyPolicy {
                 "software information",
                 "additional metadata",
             ],
+            PrivacyLevel::WebShare => vec!["all metadata except orientation, color, and basic exposure data"],
             PrivacyLevel::Paranoid => vec!["all metadata except essential camera settings"],
+            PrivacyLevel::Custom(_) => vec!["the tags the custom policy removes"],
+        }
+    }
+
+    /// List the tag groups a privacy level removes, generated from its tag lists
+    pub fn get_removed_description(level: &PrivacyLevel) -> Vec<String> {
+        Self::describe_groups(level, false)
+    }
+
+    /// Get a human-readable description of what each privacy level preserves
+    pub fn get_preserved_description(level: &PrivacyLevel) -> Vec<String> {
+        Self::describe_groups(level, true)
+    }
+
+    /// Name the tag groups a level preserves (or removes), listing tags of partly affected groups
+    fn describe_groups(level: &PrivacyLevel, preserved: bool) -> Vec<String> {
+        let mut descriptions = Vec::new();
+
+        for (group, tags) in Self::tag_groups() {
+            let matching: Vec<String> = tags.iter()
+                .filter(|tag| Self::should_preserve_tag(**tag, level) == preserved)
+                .map(|tag| tag.to_string())
+                .collect();
+            if matching.len() == tags.len() {
+                descriptions.push(group.to_string());
+            } else if !matching.is_empty() {
+                descriptions.push(format!("{} ({})", group, matching.join(", ")));
+            }
+        }
+
+        if Self::preserves_unlisted_tags(level) == preserved {
+            descriptions.push("any other tag".to_string());
+        }
+        descriptions
+    }
+}
+
+/// Per-tag exceptions layered on top of a privacy level (`--keep-tag`, `--remove-tag`)
+///
+/// The analyzer and the remover both consult it, so a kept tag is neither
+/// reported nor removed, and a removed tag is both. Scoped exceptions
+/// (`--keep-tag IFD0:Software`) apply to the tag in one IFD only.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyOverride {
+    keep: Vec<Tag>,
+    remove: Vec<Tag>,
+    keep_in: Vec<(Ifd, Tag)>,
+    remove_in: Vec<(Ifd, Tag)>,
+}
+
+impl PolicyOverride {
+    pub fn new(keep: Vec<Tag>, remove: Vec<Tag>) -> Result<Self, String> {
+        if let Some(tag) = keep.iter().find(|tag| remove.contains(tag)) {
+            return Err(format!("Tag {} is both kept and removed", tag));
+        }
+        Ok(Self { keep, remove, keep_in: Vec::new(), remove_in: Vec::new() })
+    }
+
+    /// Add exceptions that apply to a tag in one IFD only
+    pub fn scoped(mut self, keep_in: Vec<(Ifd, Tag)>, remove_in: Vec<(Ifd, Tag)>) -> Result<Self, String> {
+        if let Some((ifd, tag)) = keep_in.iter().find(|scoped| remove_in.contains(scoped)) {
+            return Err(format!("Tag {}:{} is both kept and removed", ifd, tag));
+        }
+        self.keep_in = keep_in;
+        self.remove_in = remove_in;
+        Ok(self)
+    }
+
+    /// Tags with an exception scoped to an IFD, each once
+    pub fn scoped_tags(&self) -> Vec<Tag> {
+        let mut tags: Vec<Tag> = Vec::new();
+        for (_, tag) in self.keep_in.iter().chain(&self.remove_in) {
+            if !tags.contains(tag) {
+                tags.push(*tag);
+            }
+        }
+        tags
+    }
+
+    /// Tags kept regardless of the privacy level
+    pub fn keep(&self) -> &[Tag] {
+        &self.keep
+    }
+
+    /// Tags removed regardless of the privacy level
+    pub fn remove(&self) -> &[Tag] {
+        &self.remove
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keep.is_empty() && self.remove.is_empty() && self.keep_in.is_empty() && self.remove_in.is_empty()
+    }
+
+    /// Layer the overrides on a privacy level's spec
+    pub fn apply(&self, spec: PolicySpec) -> PolicySpec {
+        spec.keeping_tags(self.keep.iter().copied()).removing_tags(self.remove.iter().copied())
+    }
+
+    /// `PrivacyPolicy::should_preserve_tag` with the overrides applied
+    pub fn should_preserve_tag(&self, tag: Tag, privacy_level: &PrivacyLevel) -> bool {
+        if self.keep.contains(&tag) {
+            true
+        } else if self.remove.contains(&tag) {
+            false
+        } else {
+            PrivacyPolicy::should_preserve_tag(tag, privacy_level)
+        }
+    }
+
+    /// `should_preserve_tag` for the tag as found in `ifd`, with scoped exceptions applied first
+    pub fn should_preserve_tag_in(&self, tag: Tag, ifd: Ifd, privacy_level: &PrivacyLevel) -> bool {
+        if self.keep_in.contains(&(ifd, tag)) {
+            true
+        } else if self.remove_in.contains(&(ifd, tag)) {
+            false
+        } else {
+            self.should_preserve_tag(tag, privacy_level)
+        }
+    }
+}
+
+/// Look up an EXIF tag by name, e.g. `DateTimeOriginal` (case-insensitive)
+pub fn parse_tag(name: &str) -> Result<Tag, String> {
+    // Every known tag by lowercase name, built on first use; a name in several contexts keeps the first
+    static TAGS: OnceLock<HashMap<String, Tag>> = OnceLock::new();
+    let tags = TAGS.get_or_init(|| {
+        let mut tags = HashMap::new();
+        for context in [Context::Tiff, Context::Exif, Context::Gps, Context::Interop] {
+            for tag in (0..=u16::MAX).map(|number| Tag(context, number)) {
+                if tag.description().is_some() {
+                    tags.entry(tag.to_string().to_ascii_lowercase()).or_insert(tag);
+                }
+            }
         }
+        tags
+    });
+
+    tags.get(&name.to_ascii_lowercase())
+        .copied()
+        .ok_or_else(|| format!("Unknown EXIF tag '{}'", name))
+}
+
+/// Look up an EXIF tag, optionally scoped to IFD0 or IFD1, e.g. `IFD1:Software`
+///
+/// Only TIFF tags can be scoped; the others live in a single IFD.
+pub fn parse_scoped_tag(name: &str) -> Result<(Option<Ifd>, Tag), String> {
+    let (ifd, name) = match name.split_once(':') {
+        Some((ifd, name)) if ifd.eq_ignore_ascii_case("IFD0") => (Ifd::Ifd0, name),
+        Some((ifd, name)) if ifd.eq_ignore_ascii_case("IFD1") => (Ifd::Ifd1, name),
+        Some((ifd, _)) => return Err(format!("Unknown IFD '{}': tags can be scoped to IFD0 or IFD1", ifd)),
+        None => return Ok((None, parse_tag(name)?)),
+    };
+    let tag = parse_tag(name)?;
+    if tag.context() != Context::Tiff {
+        return Err(format!("{} is only found in the {} IFD and can't be scoped", tag, context_ifd(tag)));
+    }
+    Ok((Some(ifd), tag))
+}
+
+/// The IFD a tag outside the TIFF context lives in
+fn context_ifd(tag: Tag) -> Ifd {
+    match tag.context() {
+        Context::Gps => Ifd::Gps,
+        Context::Interop => Ifd::Interop,
+        _ => Ifd::Exif,
     }
 }
 
@@ -245,6 +849,200 @@ mod tests {
         assert!(!PrivacyPolicy::should_preserve_tag(Tag::GPSLatitude, &PrivacyLevel::Paranoid));
     }
 
+    #[test]
+    fn test_scoped_overrides() {
+        assert_eq!(parse_scoped_tag("ifd1:Software"), Ok((Some(Ifd::Ifd1), Tag::Software)));
+        assert_eq!(parse_scoped_tag("Software"), Ok((None, Tag::Software)));
+        assert!(parse_scoped_tag("IFD0:GPSLatitude").is_err());
+        assert!(parse_scoped_tag("Exif:Software").is_err());
+
+        let overrides = PolicyOverride::new(vec![], vec![]).unwrap()
+            .scoped(vec![(Ifd::Ifd0, Tag::Software)], vec![(Ifd::Ifd1, Tag::Make)])
+            .unwrap();
+        assert!(overrides.should_preserve_tag_in(Tag::Software, Ifd::Ifd0, &PrivacyLevel::Strict));
+        assert!(!overrides.should_preserve_tag_in(Tag::Software, Ifd::Ifd1, &PrivacyLevel::Strict));
+        assert!(!overrides.should_preserve_tag_in(Tag::Make, Ifd::Ifd1, &PrivacyLevel::Minimal));
+        assert!(overrides.should_preserve_tag_in(Tag::Make, Ifd::Ifd0, &PrivacyLevel::Minimal));
+        assert_eq!(overrides.scoped_tags(), vec![Tag::Software, Tag::Make]);
+
+        let conflicting = PolicyOverride::default().scoped(vec![(Ifd::Ifd1, Tag::Make)], vec![(Ifd::Ifd1, Tag::Make)]);
+        assert!(conflicting.is_err());
+    }
+
+    #[test]
+    fn test_unknown_tag_policy() {
+        assert!(is_unknown_tag(Tag(Context::Tiff, 0xC7A1)));
+        assert!(!is_unknown_tag(Tag::Artist));
+
+        assert!(!UnknownTags::Preserve.removes(&PrivacyLevel::Strict));
+        assert!(UnknownTags::Remove.removes(&PrivacyLevel::Minimal));
+        assert!(!UnknownTags::RemoveAboveStrict.removes(&PrivacyLevel::Standard));
+        assert!(UnknownTags::RemoveAboveStrict.removes(&PrivacyLevel::Strict));
+        // Allowlist levels never keep them
+        assert!(UnknownTags::Preserve.removes(&PrivacyLevel::Paranoid));
+
+        // Custom policies count by what they remove
+        let stricter = PolicySpec::from_level(&PrivacyLevel::Strict).removing_tags([Tag::LensModel]);
+        assert!(UnknownTags::RemoveAboveStrict.removes(&PrivacyLevel::Custom(stricter)));
+        let laxer = PolicySpec::from_level(&PrivacyLevel::Standard);
+        assert!(!UnknownTags::RemoveAboveStrict.removes(&PrivacyLevel::Custom(laxer)));
+    }
+
+    #[test]
+    fn test_is_at_least_as_strict() {
+        let levels = [PrivacyLevel::Minimal, PrivacyLevel::Standard, PrivacyLevel::Strict, PrivacyLevel::Paranoid];
+        for (i, level) in levels.iter().enumerate() {
+            for (j, baseline) in levels.iter().enumerate() {
+                assert_eq!(PrivacyPolicy::is_at_least_as_strict(level, baseline), i >= j, "{:?} vs {:?}", level, baseline);
+            }
+        }
+        assert!(PrivacyPolicy::is_at_least_as_strict(&PrivacyLevel::WebShare, &PrivacyLevel::Strict));
+    }
+
+    #[test]
+    fn test_policy_override_layers_on_level() {
+        let overrides = PolicyOverride::new(vec![Tag::DateTimeOriginal], vec![Tag::LensModel]).unwrap();
+
+        assert!(overrides.should_preserve_tag(Tag::DateTimeOriginal, &PrivacyLevel::Strict));
+        assert!(!overrides.should_preserve_tag(Tag::LensModel, &PrivacyLevel::Minimal));
+        // Everything else follows the level
+        assert!(!overrides.should_preserve_tag(Tag::GPSLatitude, &PrivacyLevel::Minimal));
+        assert!(overrides.should_preserve_tag(Tag::Make, &PrivacyLevel::Standard));
+
+        assert!(PolicyOverride::new(vec![Tag::Artist], vec![Tag::Artist]).is_err());
+    }
+
+    #[test]
+    fn test_parse_tag() {
+        assert_eq!(parse_tag("DateTimeOriginal"), Ok(Tag::DateTimeOriginal));
+        assert_eq!(parse_tag("lensmodel"), Ok(Tag::LensModel));
+        assert_eq!(parse_tag("GPSLatitude"), Ok(Tag::GPSLatitude));
+        assert!(parse_tag("NoSuchTag").is_err());
+    }
+
+    #[test]
+    fn test_descriptions_follow_tag_lists() {
+        let removed = PrivacyPolicy::get_removed_description(&PrivacyLevel::Strict);
+        let preserved = PrivacyPolicy::get_preserved_description(&PrivacyLevel::Strict);
+
+        assert!(removed.contains(&"Timestamps".to_string()));
+        assert!(preserved.contains(&"Essential camera settings".to_string()));
+        assert!(preserved.contains(&"any other tag".to_string()));
+
+        let paranoid_removed = PrivacyPolicy::get_removed_description(&PrivacyLevel::Paranoid);
+        assert!(paranoid_removed.contains(&"any other tag".to_string()));
+        assert_eq!(PrivacyPolicy::get_preserved_description(&PrivacyLevel::Paranoid), vec!["Essential camera settings"]);
+    }
+
+    #[test]
+    fn test_spec_matches_preservation() {
+        let strict = PrivacyPolicy::spec(&PrivacyLevel::Strict);
+        assert!(strict.removes(Tag::XPAuthor));
+        assert!(strict.remove_groups.contains(&"XMP"));
+        assert!(!strict.removes(Tag::FNumber));
+
+        let paranoid = PrivacyPolicy::spec(&PrivacyLevel::Paranoid);
+        assert!(paranoid.removes(Tag::LensModel));
+        assert!(!paranoid.removes(Tag::FNumber));
+    }
+
+    #[test]
+    fn test_custom_level() {
+        let spec = PolicySpec::from_level(&PrivacyLevel::Minimal)
+            .removing_category(PrivacyCategory::Software)
+            .keeping_tags([Tag::GPSAltitude]);
+        let custom = PrivacyLevel::Custom(spec);
+
+        assert!(!PrivacyPolicy::should_preserve_tag(Tag::Software, &custom));
+        assert!(!PrivacyPolicy::should_preserve_tag(Tag::GPSLatitude, &custom));
+        assert!(PrivacyPolicy::should_preserve_tag(Tag::GPSAltitude, &custom));
+        assert!(PrivacyPolicy::should_preserve_tag(Tag::Artist, &custom));
+
+        // Custom policies exist only in code
+        assert_eq!(PrivacyLevel::value_variants().len(), 5);
+        assert!(PrivacyLevel::from_str("custom", true).is_err());
+    }
+
+    #[test]
+    fn test_web_share_level() {
+        let level = PrivacyLevel::WebShare;
+
+        for tag in [Tag::Orientation, Tag::ColorSpace, Tag::ExposureTime, Tag::FNumber] {
+            assert!(PrivacyPolicy::should_preserve_tag(tag, &level), "{} should be kept", tag);
+        }
+        for tag in [Tag::Make, Tag::Model, Tag::DateTimeOriginal, Tag::ImageDescription, Tag::UserComment, Tag::LensModel] {
+            assert!(!PrivacyPolicy::should_preserve_tag(tag, &level), "{} should be removed", tag);
+        }
+        assert_eq!(PrivacyLevel::from_str("web-share", true), Ok(PrivacyLevel::WebShare));
+    }
+
+    #[test]
+    fn test_gps_groups_cover_gps_tags() {
+        let mut grouped: Vec<Tag> = GpsGroup::value_variants().iter().flat_map(|group| group.tags()).collect();
+        let mut gps = PrivacyPolicy::get_gps_tags();
+        let key = |tag: &Tag| tag.number();
+        grouped.sort_by_key(key);
+        gps.sort_by_key(key);
+        assert_eq!(grouped, gps);
+    }
+
+    #[test]
+    fn test_as_allowlist_keeps_only_named_tags_the_level_keeps() {
+        let spec = PolicySpec::from_level(&PrivacyLevel::Minimal).as_allowlist();
+
+        assert!(!spec.removes(Tag::Artist));
+        assert!(!spec.removes(Tag::Make));
+        assert!(spec.removes(Tag::GPSLatitude));
+        assert!(spec.removes(Tag::InteroperabilityIndex));
+        assert!(spec.removes_xmp());
+        assert_eq!(spec.keep_groups, vec!["ICC_Profile"]);
+
+        let paranoid = PolicySpec::from_level(&PrivacyLevel::Paranoid);
+        assert_eq!(paranoid.clone().as_allowlist().keep_only, paranoid.keep_only);
+    }
+
+    #[test]
+    fn test_keeping_gps_groups() {
+        let spec = PolicySpec::from_level(&PrivacyLevel::Standard).keeping_gps(&[GpsGroup::Altitude, GpsGroup::Direction]);
+
+        assert!(!spec.remove_groups.contains(&"gps"));
+        assert!(spec.removes(Tag::GPSLatitude));
+        assert!(!spec.removes(Tag::GPSAltitude));
+        assert!(!spec.removes(Tag::GPSImgDirection));
+    }
+
+    #[test]
+    fn test_removing_equipment() {
+        let level = PrivacyLevel::Custom(PolicySpec::from_level(&PrivacyLevel::Paranoid).removing_equipment());
+
+        assert!(!PrivacyPolicy::should_preserve_tag(Tag::Make, &level));
+        assert!(!PrivacyPolicy::should_preserve_tag(Tag::LensModel, &level));
+        assert!(PrivacyPolicy::should_preserve_tag(Tag::FNumber, &level));
+    }
+
+    #[test]
+    fn test_metadata_block_tags_map_to_groups() {
+        let standard = PolicySpec::from_level(&PrivacyLevel::Standard);
+        assert!(!standard.removes(XMP_PACKET));
+        assert!(PolicySpec::from_level(&PrivacyLevel::Strict).removes(IPTC_NAA));
+
+        let spec = standard.removing_tags([XMP_PACKET]);
+        assert!(spec.removes_xmp());
+        assert!(!spec.remove.contains(&XMP_PACKET));
+
+        let spec = PolicySpec::from_level(&PrivacyLevel::Minimal).removing_tags([MOTION_VIDEO]);
+        assert!(spec.removes(MOTION_VIDEO));
+        assert!(spec.remove_groups.contains(&"XMP-Container"));
+    }
+
+    #[test]
+    fn test_exiftool_names() {
+        assert_eq!(PrivacyPolicy::exiftool_name(Tag::DateTime), "ModifyDate");
+        assert_eq!(PrivacyPolicy::exiftool_name(Tag::BodySerialNumber), "SerialNumber");
+        assert_eq!(PrivacyPolicy::exiftool_name(Tag::HostComputer), "HostComputer");
+        assert_eq!(PrivacyPolicy::exiftool_name(GIF_COMMENT), "GIF:Comment");
+    }
+
     #[test]
     fn test_gps_coverage() {
         let minimal_tags = PrivacyPolicy::get_tags_to_remove(&PrivacyLevel::Minimal);
@@ -274,7 +1072,7 @@ mod tests {
         ];
 
         for tag in essential_tags.iter() {
-            assert!(PrivacyPolicy::is_essential_camera_setting(*tag), 
+            assert!(PrivacyPolicy::get_essential_camera_settings().contains(tag), 
                     "Tag {:?} should be considered essential camera setting", tag);
         }
 
@@ -287,7 +1085,7 @@ mod tests {
         ];
 
         for tag in non_essential_tags.iter() {
-            assert!(!PrivacyPolicy::is_essential_camera_setting(*tag), 
+            assert!(!PrivacyPolicy::get_essential_camera_settings().contains(tag), 
                     "Tag {:?} should NOT be considered essential camera setting", tag);
         }
     }
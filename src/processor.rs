@@ -1,79 +1,919 @@
-use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
 use std::fs;
-use crate::cli::Config;
-use crate::analyzer::ExifAnalyzer;
-use crate::remover::MetadataRemover;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::cli::{Config, DuplicatePolicy, UnsupportedPolicy};
+use crate::analyzer::{AnalysisReport, ExifAnalyzer, PrivacyCategory, PrivacyField, UnparseableMetadata};
+use crate::audit::AuditLog;
+use crate::dedup::{self, DuplicateIndex};
+use crate::formats::{self, FormatHandler};
+use crate::jpeg;
+use crate::manifest::{Manifest, OutputAction};
+use crate::multipicture;
+use crate::padding;
+use crate::payload;
+use crate::privacy::{self, PrivacyLevel, PrivacyPolicy};
+use crate::progress::{FileFindings, FindingsReporter, Skipped};
+use crate::remover::{CorruptStructure, MetadataRemover};
+use crate::review::{InteractiveReview, ReviewDecision};
+use crate::savings;
+use crate::tiff;
+use crate::utils;
+use crate::walker::{self, HardlinkTracker};
+use tracing::{debug, error, info, info_span, warn};
 
+/// Cleans images; `Send + Sync`, so one processor can serve many threads
 pub struct ImageProcessor {
-    config: Config,
+    config: Arc<Config>,
     analyzer: ExifAnalyzer,
     remover: MetadataRemover,
+    audit_log: Option<AuditLog>,
+    duplicate_index: Option<DuplicateIndex>,
+    hardlinks: HardlinkTracker,
+    manifest: Option<Manifest>,
+    review: Option<InteractiveReview>,
+    findings_reporter: Option<Box<dyn FindingsReporter>>,
+    tally: Arc<CategoryTally>,
+    removed: Arc<CategoryTally>,
+    bytes_removed: AtomicU64,
+}
+
+/// Privacy fields counted so far in a run, by category
+#[derive(Debug, Default)]
+pub struct CategoryTally {
+    counts: Mutex<BTreeMap<PrivacyCategory, usize>>,
+}
+
+impl CategoryTally {
+    pub fn record<'a>(&self, fields: impl IntoIterator<Item = &'a PrivacyField>) {
+        if let Ok(mut counts) = self.counts.lock() {
+            for field in fields {
+                *counts.entry(field.category).or_default() += 1;
+            }
+        }
+    }
+
+    /// Counts per category, in category order
+    pub fn counts(&self) -> Vec<(PrivacyCategory, usize)> {
+        self.counts.lock()
+            .map(|counts| counts.iter().map(|(category, count)| (*category, *count)).collect())
+            .unwrap_or_default()
+    }
 }
 
 impl ImageProcessor {
-    pub fn new(config: Config) -> Self {
+    /// A processor for `config`, which may already be shared with other processors
+    pub fn new(config: impl Into<Arc<Config>>) -> Self {
+        let config = config.into();
         Self {
-            analyzer: ExifAnalyzer::new(),
-            remover: MetadataRemover::new(),
+            analyzer: ExifAnalyzer::new()
+                .with_overrides(config.policy_override.clone())
+                .with_strip_originals(config.strip_originals)
+                .with_drop_motion(config.drop_motion)
+                .with_unknown_tags(config.unknown_tags)
+                .with_deep_scan(config.deep_scan),
+            remover: MetadataRemover::new()
+                .with_timeout(Duration::from_secs(config.timeout_secs))
+                .with_overrides(config.policy_override.clone())
+                .with_unknown_tags(config.unknown_tags)
+                .with_sandbox(config.sandbox)
+                .with_stay_open(config.jobs > 1),
+            audit_log: None,
+            duplicate_index: None,
+            hardlinks: HardlinkTracker::new(),
+            manifest: None,
+            review: None,
+            findings_reporter: None,
+            tally: Arc::new(CategoryTally::default()),
+            removed: Arc::new(CategoryTally::default()),
+            bytes_removed: AtomicU64::new(0),
             config,
         }
     }
 
+    /// Record every removal in the given audit log
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(audit_log);
+        self
+    }
+
     pub fn config(&self) -> &Config {
         &self.config
     }
 
+    /// The configuration, for building more processors without copying it
+    pub fn shared_config(&self) -> Arc<Config> {
+        Arc::clone(&self.config)
+    }
+
+    /// Detect images whose cleaned copy is already in the index (see `Config::duplicates`)
+    pub fn with_duplicate_index(mut self, duplicate_index: DuplicateIndex) -> Self {
+        self.duplicate_index = Some(duplicate_index);
+        self
+    }
+
+    /// Hash every output file into the given manifest
+    pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    pub fn manifest(&self) -> Option<&Manifest> {
+        self.manifest.as_ref()
+    }
+
+    /// Running per-category count of the privacy fields found, for live displays
+    pub fn category_tally(&self) -> Arc<CategoryTally> {
+        Arc::clone(&self.tally)
+    }
+
+    /// Privacy fields confirmed gone from cleaned output, by category
+    pub fn removal_tally(&self) -> Arc<CategoryTally> {
+        Arc::clone(&self.removed)
+    }
+
+    /// Metadata bytes removed so far, or in dry runs the estimate of what would be
+    pub fn bytes_removed(&self) -> u64 {
+        self.bytes_removed.load(Ordering::Relaxed)
+    }
+
+    /// Ask before changing each file with findings (directory runs only)
+    pub fn with_review(mut self, review: InteractiveReview) -> Self {
+        self.review = Some(review);
+        self
+    }
+
+    /// Hand the findings of every analyzed file to `reporter`, e.g. to print them
+    pub fn with_findings_reporter(mut self, reporter: Box<dyn FindingsReporter>) -> Self {
+        self.findings_reporter = Some(reporter);
+        self
+    }
+
     /// Process a single image file
-    pub fn process_image(&self, input_path: &Path) -> Result<bool, Box<dyn std::error::Error>> {
-        // Read the file data
-        let file_data = fs::read(input_path)?;
+    ///
+    /// Returns why the file was left untouched, or `None` if it was cleaned
+    /// (or would be, in a dry run). Files that fail are copied to the
+    /// quarantine directory, if configured, before the error is returned.
+    pub fn process_image(&self, input_path: &Path) -> Result<Option<Skipped>, Box<dyn std::error::Error>> {
+        self.process_image_with_level(input_path, &self.config.privacy_level)
+    }
+
+    /// Process a single image file at `level` instead of the configured privacy level
+    ///
+    /// Used for subtrees whose `.privacy-clean.toml` sets their own policy.
+    pub fn process_image_with_level(&self, input_path: &Path, level: &PrivacyLevel) -> Result<Option<Skipped>, Box<dyn std::error::Error>> {
+        let _span = info_span!("file", path = %input_path.display()).entered();
+
+        let result = self.clean_image(input_path, &self.rebuilding(level));
+
+        if let Err(ref e) = result {
+            self.quarantining(input_path, &e.to_string(), |dest| {
+                fs::copy(utils::long_path(input_path), utils::long_path(dest)).map(|_| ())
+            });
+        }
+
+        result
+    }
+
+    /// Copy an in-memory image that failed cleaning to the quarantine directory, if one is configured
+    ///
+    /// For images without a file of their own, such as archive entries and cloud objects.
+    pub fn quarantine_data(&self, data: &[u8], display_path: &Path, error: &str) {
+        self.quarantining(display_path, error, |dest| fs::write(utils::long_path(dest), data));
+    }
+
+    fn quarantining(&self, source: &Path, error: &str, copy: impl FnOnce(&Path) -> io::Result<()>) {
+        let Some(ref quarantine_dir) = self.config.quarantine else { return };
+        if self.config.dry_run {
+            info!("Would quarantine file after error: {}", error);
+            return;
+        }
+        match self.quarantine_with(source, quarantine_dir, error, copy) {
+            Ok(dest) => warn!(quarantined = %dest.display(), "Quarantined file after error: {}", error),
+            Err(qe) => error!("Failed to quarantine file: {}", qe),
+        }
+    }
+
+    fn clean_image(&self, input_path: &Path, level: &PrivacyLevel) -> Result<Option<Skipped>, Box<dyn std::error::Error>> {
+        // Another link to this file was already cleaned; link to its output instead
+        let link_id = match self.config.output {
+            Some(_) if self.config.preserve_hardlinks => {
+                walker::hardlinked_file_id(&fs::metadata(utils::long_path(input_path))?)
+            }
+            _ => None,
+        };
+        if let Some(existing) = link_id.and_then(|id| self.hardlinks.find(id)) {
+            let output_path = self.get_output_path(input_path)?;
+            if self.config.dry_run {
+                info!(existing = %existing.display(), "Would hard-link to cleaned copy of the same file");
+            } else if output_path != existing {
+                link_or_copy(&existing, &output_path)?;
+                self.record_output(input_path, &output_path, OutputAction::Linked)?;
+                info!(existing = %existing.display(), output = %output_path.display(), "Hard-linked to cleaned copy of the same file");
+            }
+            return Ok(None);
+        }
+
+        // Map the file rather than reading it, so large TIFFs aren't copied into memory
+        let file_data = utils::map_file(input_path)?;
+        let handler = formats::handler_for(&file_data);
+        let handler = handler.as_deref();
+
+        // Handlers report unreadable metadata from `analyze` instead
+        if self.config.require_clean && handler.is_none() {
+            self.check_readable(&file_data)?;
+        }
+
+        // Skip images whose cleaned copy already exists in the output directory
+        let content_hash = match self.duplicate_index {
+            Some(_) if self.config.duplicates != DuplicatePolicy::Process => dedup::content_hash(&file_data),
+            _ => None,
+        };
+        if let (Some(index), Some(hash)) = (&self.duplicate_index, &content_hash) {
+            if let Some(existing) = index.find(hash) {
+                self.handle_duplicate(input_path, &existing)?;
+                return Ok(Some(Skipped::Duplicate));
+            }
+        }
         
         // Analyze what privacy data exists
-        let privacy_data = self.analyzer.analyze_privacy_data(
-            &file_data, 
-            input_path, 
-            &self.config.privacy_level, 
-            self.config.verbose
-        )?;
+        let (privacy_data, strip_all) = self.analyze(handler, &file_data, input_path, level)?;
+        let privacy_data = self.reporting_kept_content(level, privacy_data, strip_all);
+        let level = &self.removing_requested(level, &privacy_data);
         
-        if privacy_data.is_empty() {
+        // Already conforms to the policy: rewriting it would only produce the same file
+        if privacy_data.is_empty() && !strip_all {
             if self.config.verbose {
-                println!("  No privacy-sensitive data found in {}", input_path.display());
+                info!("No privacy-sensitive data found; already clean");
+            }
+            if self.config.only_if_dirty && self.config.output.is_some() {
+                let output_path = self.copy_unchanged(input_path, OutputAction::Unchanged)?;
+                if let (Some(index), Some(hash)) = (&self.duplicate_index, content_hash) {
+                    index.insert(hash, output_path.clone());
+                }
+                if let Some(id) = link_id {
+                    self.hardlinks.insert(id, output_path);
+                }
             }
-            return Ok(false);
+            return Ok(Some(Skipped::AlreadyClean));
         }
 
+        self.report_location_disclosure(&file_data, &privacy_data);
+
+        if let Some(ref review) = self.review {
+            match review.decide(input_path, &privacy_data)? {
+                ReviewDecision::Remove => {}
+                ReviewDecision::Keep => {
+                    info!("Keeping metadata as reviewed");
+                    if self.config.output.is_some() {
+                        self.copy_unchanged(input_path, OutputAction::Kept)?;
+                    }
+                    return Ok(Some(Skipped::Reviewed));
+                }
+                ReviewDecision::Skip => {
+                    info!("Skipped on review");
+                    return Ok(Some(Skipped::Reviewed));
+                }
+            }
+        }
+        // Counted only once review has let the removal go ahead
+        self.tally.record(&privacy_data);
+
+        // In-place cleaning has to rewrite the input, which read-only files (e.g. from SD cards) forbid
+        let is_readonly = self.config.output.is_none()
+            && fs::metadata(utils::long_path(input_path))?.permissions().readonly();
+
         if self.config.dry_run {
-            println!("  Would remove {} privacy-sensitive fields from {}", 
-                privacy_data.len(), input_path.display());
-            return Ok(true);
+            if strip_all {
+                info!("Would remove all metadata");
+            } else {
+                info!(fields = privacy_data.len(), "Would remove {} privacy-sensitive fields", privacy_data.len());
+            }
+            self.report_estimated_savings(&file_data, level, strip_all);
+            if let Some(ref export_dir) = self.config.export_metadata {
+                info!(sidecar = %self.sidecar_path(input_path, export_dir)?.display(), "Would export metadata");
+            }
+            if is_readonly && self.config.unlock_readonly {
+                info!("Would temporarily make read-only file writable");
+            } else if is_readonly {
+                warn!("File is read-only; cleaning it in place needs --unlock-readonly");
+            }
+            return Ok(None);
+        }
+
+        if is_readonly && !self.config.unlock_readonly {
+            return Err("File is read-only (use --unlock-readonly to clean it in place)".into());
         }
+        check_can_clean(handler)?;
 
         // Determine output path
         let output_path = self.get_output_path(input_path)?;
 
+        // The only copy of the metadata the user keeps; cleaning without it would lose it for good
+        if let Some(ref export_dir) = self.config.export_metadata {
+            let sidecar = self.sidecar_path(input_path, export_dir)?;
+            self.remover.export_metadata(input_path, &sidecar, &self.config.export_format)
+                .map_err(|e| format!("Failed to export metadata: {}", e))?;
+            info!(sidecar = %sidecar.display(), "Exported metadata");
+        }
+
         // Create backup if requested and we're doing in-place modification
-        if self.config.create_backup && self.config.output_dir.is_none() {
+        if self.config.create_backup && self.config.output.is_none() {
             self.create_backup(input_path)?;
         }
 
+        // ExifTool refuses files whose extension contradicts their content (a TIFF
+        // named .jpg), so those are streamed through it by content instead
+        let mislabeled = formats::sniff_image_format(&file_data)
+            .map_or(false, |format| !formats::has_matching_extension(input_path, format));
+
         // Remove the privacy data
-        self.remover.remove_privacy_data(
-            input_path,
-            &output_path,
-            &self.config.privacy_level,
+        let remove = || {
+            if let Some(handler) = handler {
+                let cleaned = if strip_all {
+                    handler.strip(&file_data)?
+                } else {
+                    handler.clean(&file_data, level)?
+                };
+                return Ok(utils::write_atomically(&output_path, &cleaned)?);
+            }
+            if strip_all && jpeg::is_jpeg(&file_data) {
+                return Ok(utils::write_atomically(&output_path, &strip_jpeg(&file_data)?)?);
+            }
+            if mislabeled {
+                debug!("Extension doesn't match content; cleaning by content");
+                let cleaned = if strip_all {
+                    self.remover.remove_all_metadata_from_bytes(&file_data)?
+                } else {
+                    self.remover.remove_privacy_data_from_bytes(&file_data, level)?
+                };
+                Ok(utils::write_atomically(&output_path, &cleaned)?)
+            } else if strip_all {
+                self.remover.remove_all_metadata(input_path, &output_path)
+            } else {
+                self.remover.remove_privacy_data(input_path, &output_path, level)
+            }?;
+            // ExifTool only rewrites the first picture of multi-picture files
+            if !strip_all && self.has_embedded_parts(&file_data) {
+                let cleaned = fs::read(utils::long_path(&output_path))?;
+                if let Some(cleaned) = self.clean_embedded(&file_data, &cleaned, level)? {
+                    utils::write_atomically(&output_path, &cleaned)?;
+                }
+            }
+            Ok(())
+        };
+        let clean = || {
+            if let Err(e) = remove() {
+                let reencoded = self.reencode_after(e, &file_data)?;
+                utils::write_atomically(&output_path, &reencoded)?;
+            }
+            if self.config.compact_tiff {
+                let cleaned = fs::read(utils::long_path(&output_path))?;
+                if let Some(compacted) = self.compacted(&cleaned)? {
+                    utils::write_atomically(&output_path, &compacted)?;
+                }
+            }
+            if self.config.keep_size {
+                let cleaned = fs::read(utils::long_path(&output_path))?;
+                if let Some(padded) = self.pad_to_original(&cleaned, file_data.len()) {
+                    utils::write_atomically(&output_path, &padded)?;
+                }
+            }
+            Ok(())
+        };
+        if is_readonly {
+            utils::with_write_access(&utils::long_path(input_path), clean)?;
+            debug!("Restored read-only permissions");
+        } else {
+            clean()?;
+        }
+        debug!(output = %output_path.display(), fields = privacy_data.len(), "Removed privacy data");
+
+        let cleaned_data = utils::map_file(&output_path)?;
+        self.report_savings(file_data.len() as u64, utils::get_file_info(&output_path)?.size);
+
+        if self.config.require_clean || self.config.lossless_only {
+            if let Err(e) = self.verify_output(handler, &file_data, &cleaned_data, &output_path, level) {
+                // Only a separate output file can be withdrawn; in-place files are already replaced
+                if output_path != input_path {
+                    let _ = fs::remove_file(utils::long_path(&output_path));
+                }
+                return Err(e);
+            }
+        }
+
+        if let (Some(index), Some(hash)) = (&self.duplicate_index, content_hash) {
+            index.insert(hash, output_path.clone());
+        }
+        if let Some(id) = link_id {
+            self.hardlinks.insert(id, output_path.clone());
+        }
+        self.record_output(input_path, &output_path, OutputAction::Cleaned)?;
+        self.record_removed(handler, &privacy_data, &cleaned_data, &output_path, level);
+
+        if let Some(ref audit_log) = self.audit_log {
+            audit_log.record(
+                input_path,
+                &output_path,
+                &file_data,
+                &cleaned_data,
+                level,
+                &privacy_data,
+            )?;
+        }
+
+        Ok(None)
+    }
+
+    /// Analyze an in-memory image without printing or modifying anything
+    pub fn analyze_bytes(&self, data: &[u8]) -> Result<AnalysisReport, Box<dyn std::error::Error>> {
+        self.analyzer.analyze_bytes(data, &self.config.privacy_level)
+    }
+
+    /// Process an image held in memory
+    ///
+    /// `display_path` is only used for reporting. The cleaned bytes are `None`
+    /// when nothing needed removing (or in dry-run mode), in which case the
+    /// original bytes should be used as-is.
+    pub fn process_image_data(
+        &self,
+        data: &[u8],
+        display_path: &Path,
+    ) -> Result<ProcessedImage, Box<dyn std::error::Error>> {
+        let _span = info_span!("file", path = %display_path.display()).entered();
+        let handler = formats::handler_for(data);
+        let handler = handler.as_deref();
+
+        if self.config.require_clean && handler.is_none() {
+            self.check_readable(data)?;
+        }
+
+        let level = &self.rebuilding(&self.config.privacy_level);
+        let (privacy_data, strip_all) = self.analyze(handler, data, display_path, level)?;
+        let privacy_data = self.reporting_kept_content(level, privacy_data, strip_all);
+        let level = &self.removing_requested(level, &privacy_data);
+
+        if privacy_data.is_empty() && !strip_all {
+            if self.config.verbose {
+                info!("No privacy-sensitive data found");
+            }
+            return Ok(ProcessedImage { had_privacy_data: false, cleaned: None });
+        }
+
+        self.report_location_disclosure(data, &privacy_data);
+        self.tally.record(&privacy_data);
+
+        if self.config.dry_run {
+            if strip_all {
+                info!("Would remove all metadata");
+            } else {
+                info!(fields = privacy_data.len(), "Would remove {} privacy-sensitive fields", privacy_data.len());
+            }
+            self.report_estimated_savings(data, level, strip_all);
+            return Ok(ProcessedImage { had_privacy_data: true, cleaned: None });
+        }
+
+        check_can_clean(handler)?;
+        let removed = match handler {
+            Some(handler) if strip_all => handler.strip(data),
+            Some(handler) => handler.clean(data, level),
+            None if strip_all && jpeg::is_jpeg(data) => strip_jpeg(data),
+            None if strip_all => self.remover.remove_all_metadata_from_bytes(data),
+            None => self.remover.remove_privacy_data_from_bytes(data, level).and_then(|cleaned| {
+                Ok(self.clean_embedded(data, &cleaned, level)?.unwrap_or(cleaned))
+            }),
+        };
+        let cleaned = match removed {
+            Ok(cleaned) => cleaned,
+            Err(e) => self.reencode_after(e, data)?,
+        };
+        let cleaned = match self.compacted(&cleaned)? {
+            Some(compacted) => compacted,
+            None => cleaned,
+        };
+        let cleaned = match self.config.keep_size {
+            true => self.pad_to_original(&cleaned, data.len()).unwrap_or(cleaned),
+            false => cleaned,
+        };
+
+        self.verify_output(handler, data, &cleaned, display_path, level)?;
+        self.record_removed(handler, &privacy_data, &cleaned, display_path, level);
+        self.report_savings(data.len() as u64, cleaned.len() as u64);
+
+        if let Some(ref audit_log) = self.audit_log {
+            audit_log.record(
+                display_path,
+                display_path,
+                data,
+                &cleaned,
+                level,
+                &privacy_data,
+            )?;
+        }
+
+        Ok(ProcessedImage { had_privacy_data: true, cleaned: Some(cleaned) })
+    }
+
+    /// Find the privacy data in an image, and whether all its metadata must be stripped instead
+    ///
+    /// Unparseable metadata might hide anything, so it is never treated as clean:
+    /// it fails the file unless `force_strip` allows removing all of it.
+    fn analyze(
+        &self,
+        handler: Option<&dyn FormatHandler>,
+        data: &[u8],
+        path: &Path,
+        level: &PrivacyLevel,
+    ) -> Result<(Vec<PrivacyField>, bool), Box<dyn std::error::Error>> {
+        match self.find_privacy_data(handler, data, path, level) {
+            Ok(privacy_data) => {
+                if let Some(ref reporter) = self.findings_reporter {
+                    reporter.report(&FileFindings { path, level, fields: &privacy_data });
+                }
+                if !self.config.strip_originals {
+                    for original in self.analyzer.embedded_originals(data) {
+                        warn!("{} (use --strip-originals to remove it)", original);
+                    }
+                }
+                Ok((privacy_data, false))
+            }
+            Err(e) if e.is::<UnparseableMetadata>() && (self.config.force_strip || self.config.reencode_fallback) => {
+                warn!("{}; removing all metadata", e);
+                Ok((Vec::new(), true))
+            }
+            Err(e) if e.is::<UnparseableMetadata>() => {
+                Err(format!("{} (use --force-strip to remove all metadata)", e).into())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Analyze with the registered handler for the data's format, or the built-in analyzer
+    fn find_privacy_data(
+        &self,
+        handler: Option<&dyn FormatHandler>,
+        data: &[u8],
+        path: &Path,
+        level: &PrivacyLevel,
+    ) -> Result<Vec<PrivacyField>, Box<dyn std::error::Error>> {
+        match handler {
+            Some(handler) => handler.analyze(data, level),
+            None => self.analyzer.analyze_privacy_data(data, path, level),
+        }
+    }
+
+    /// Warn about, and leave out, findings in the text of fields the level keeps
+    ///
+    /// Hidden payloads and personal data are only reported: the level (or
+    /// `--remove-tag`) decides whether a field goes. When everything is stripped
+    /// they go anyway and stay in.
+    fn reporting_kept_content(&self, level: &PrivacyLevel, privacy_data: Vec<PrivacyField>, strip_all: bool) -> Vec<PrivacyField> {
+        if strip_all {
+            return privacy_data;
+        }
+        let (kept, removed): (Vec<_>, Vec<_>) = privacy_data.into_iter()
+            .partition(|field| self.is_kept_content(field, level));
+        for field in kept {
+            warn!("{} (kept at this privacy level; use --remove-tag or a stricter level to remove it)", field.description);
+        }
+        removed
+    }
+
+    fn is_kept_content(&self, field: &PrivacyField, level: &PrivacyLevel) -> bool {
+        payload::is_content_finding(field.tag) && self.config.policy_override.should_preserve_tag(field.tag, level)
+    }
+
+    /// The level extended to remove findings it keeps but an option asked for
+    ///
+    /// `--strip-originals`, `--drop-motion`, and `--unknown-tags` report what they
+    /// want gone under tags the level itself would keep.
+    fn removing_requested(&self, level: &PrivacyLevel, privacy_data: &[PrivacyField]) -> PrivacyLevel {
+        let requested: Vec<_> = privacy_data.iter()
+            .map(|field| field.tag)
+            // Remnants are no tag ExifTool can address; rewriting the file is what drops them
+            .filter(|tag| *tag != privacy::REMNANT)
+            .filter(|tag| self.config.policy_override.should_preserve_tag(*tag, level))
+            .collect();
+        if requested.is_empty() {
+            return level.clone();
+        }
+        debug!(fields = requested.len(), "Also removing kept fields the options ask for");
+        PrivacyLevel::Custom(PrivacyPolicy::spec(level).removing_tags(requested))
+    }
+
+    /// With `--rebuild-metadata`, `level` as an allowlist, so analysis reports and
+    /// removal drops every tag it doesn't keep; otherwise `level` unchanged
+    fn rebuilding(&self, level: &PrivacyLevel) -> PrivacyLevel {
+        if self.config.rebuild_metadata {
+            PrivacyLevel::Custom(PrivacyPolicy::spec(level).as_allowlist())
+        } else {
+            level.clone()
+        }
+    }
+
+    /// With `--reencode-fallback`, the image re-encoded from its pixels after cleaning failed with `error`
+    ///
+    /// Only damaged files are re-encoded; other failures (a full disk, a
+    /// timeout, a missing ExifTool) are returned as they are.
+    fn reencode_after(&self, error: Box<dyn std::error::Error>, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let damaged = error.is::<UnparseableMetadata>() || error.is::<CorruptStructure>();
+        if !damaged || !self.config.reencode_fallback || self.config.lossless_only {
+            return Err(error);
+        }
+        warn!("Cleaning failed ({}); re-encoding the pixels into a new file without metadata, which can lose quality", error);
+        reencode(data).map_err(|e| format!("{}; re-encoding also failed: {}", error, e).into())
+    }
+
+    /// With `--compact-tiff`, a cleaned TIFF rewritten without the bytes no IFD references
+    fn compacted(&self, cleaned: &[u8]) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        if !self.config.compact_tiff || formats::sniff_image_format(cleaned) != Some(formats::ImageFormat::Tiff) {
+            return Ok(None);
+        }
+        let compacted = tiff::compact(cleaned).map_err(|e| format!("Cannot compact TIFF: {}", e))?;
+        debug!(bytes = cleaned.len().saturating_sub(compacted.len()), "Compacted TIFF");
+        Ok(Some(compacted))
+    }
+
+    /// Cleaned data padded back to the original size (`--keep-size`), or `None` if it can't be
+    fn pad_to_original(&self, cleaned: &[u8], original_size: usize) -> Option<Vec<u8>> {
+        match padding::pad_to_size(cleaned, original_size) {
+            Ok(padded) => {
+                debug!(bytes = original_size - cleaned.len(), "Padded to the original size");
+                Some(padded)
+            }
+            Err(e) => {
+                warn!("Not padded to the original size: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Log and count the bytes cleaning removed
+    fn report_savings(&self, original_size: u64, cleaned_size: u64) {
+        // Rewriting can grow a file slightly (padding, a restored tag); that saves nothing
+        let removed = original_size.saturating_sub(cleaned_size);
+        self.bytes_removed.fetch_add(removed, Ordering::Relaxed);
+        info!(bytes = removed, "Removed {} of metadata", utils::format_file_size(removed));
+    }
+
+    /// Log and count the bytes a dry run's cleaning would remove, where they can be estimated
+    fn report_estimated_savings(&self, data: &[u8], level: &PrivacyLevel, strip_all: bool) {
+        let spec = self.config.policy_override.apply(PrivacyPolicy::spec(level));
+        if let Some(removed) = savings::estimate_removed_bytes(data, &spec, strip_all) {
+            self.bytes_removed.fetch_add(removed, Ordering::Relaxed);
+            info!(bytes = removed, "Would remove about {} of metadata", utils::format_file_size(removed));
+        }
+    }
+
+    /// Whether an image has pictures after the first or a motion video, which ExifTool leaves as they are
+    fn has_embedded_parts(&self, data: &[u8]) -> bool {
+        let xmp = String::from_utf8_lossy(&self.analyzer.xmp_bytes(data)).into_owned();
+        !multipicture::embedded_images(data).is_empty() || multipicture::motion_video(data, &xmp).is_some()
+    }
+
+    /// Clean the pictures after the first and the motion video of an image ExifTool has cleaned
+    ///
+    /// Each picture is cleaned at the same level. The video's GPS position goes
+    /// unless GPS is kept; with `--drop-motion` ExifTool has already removed the video.
+    fn clean_embedded(&self, original: &[u8], cleaned: &[u8], level: &PrivacyLevel) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+        let xmp = String::from_utf8_lossy(&self.analyzer.xmp_bytes(original)).into_owned();
+        let scrub_video_gps = !self.config.drop_motion
+            && !self.config.policy_override.should_preserve_tag(exif::Tag::GPSLatitude, level);
+        multipicture::clean(original, &xmp, cleaned, scrub_video_gps, |picture| {
+            self.remover.remove_privacy_data_from_bytes(picture, level)
+        })
+    }
+
+    /// Refuse input whose metadata can't be parsed, unless it is going to be stripped entirely
+    fn check_readable(&self, data: &[u8]) -> Result<(), Box<dyn std::error::Error>> {
+        match self.analyzer.check_metadata_readable(data) {
+            Err(e) if e.is::<UnparseableMetadata>() && (self.config.force_strip || self.config.reencode_fallback) => Ok(()),
+            result => result,
+        }
+    }
+
+    fn record_output(&self, source: &Path, output: &Path, action: OutputAction) -> Result<(), Box<dyn std::error::Error>> {
+        match self.manifest {
+            Some(ref manifest) => manifest.record(source, output, action),
+            None => Ok(()),
+        }
+    }
+
+    /// Copy an image to the output directory byte-for-byte, recording why as `action`
+    fn copy_unchanged(&self, input_path: &Path, action: OutputAction) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let output_path = self.get_output_path(input_path)?;
+
+        if self.config.dry_run {
+            info!(output = %output_path.display(), "Would copy unchanged");
+        } else {
+            fs::copy(utils::long_path(input_path), utils::long_path(&output_path))?;
+            self.record_output(input_path, &output_path, action)?;
+            debug!(output = %output_path.display(), "Copied unchanged");
+        }
+
+        Ok(output_path)
+    }
+
+    /// Apply the duplicate policy for an image whose cleaned copy is `existing`
+    fn handle_duplicate(&self, input_path: &Path, existing: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let output_path = self.get_output_path(input_path)?;
+
+        if self.config.duplicates != DuplicatePolicy::Link || output_path == existing || output_path.exists() {
+            info!(existing = %existing.display(), "Skipping duplicate of already cleaned image");
+            return Ok(());
+        }
+
+        if self.config.dry_run {
+            info!(existing = %existing.display(), "Would link duplicate to already cleaned image");
+            return Ok(());
+        }
+
+        link_or_copy(existing, &output_path)?;
+        self.record_output(input_path, &output_path, OutputAction::Linked)?;
+        info!(existing = %existing.display(), output = %output_path.display(), "Linked duplicate to already cleaned image");
+
+        Ok(())
+    }
+
+    /// Tell the user where the GPS data being removed places the photo
+    fn report_location_disclosure(&self, data: &[u8], privacy_data: &[PrivacyField]) {
+        if !(self.config.verbose || self.config.dry_run) {
+            return;
+        }
+        if !privacy_data.iter().any(|field| field.category == PrivacyCategory::Location) {
+            return;
+        }
+        if let Some(disclosure) = self.analyzer.location_disclosure(data) {
+            info!(location = %disclosure, "GPS places this photo {}", disclosure);
+        }
+    }
+
+    /// Apply the unsupported-file policy to a file that isn't a supported image
+    ///
+    /// Returns whether the file was copied to the output directory.
+    pub fn process_unsupported(&self, input_path: &Path) -> Result<bool, Box<dyn std::error::Error>> {
+        match self.config.unsupported {
+            UnsupportedPolicy::Skip => {
+                debug!(path = %input_path.display(), "Skipping unsupported file");
+                Ok(false)
+            }
+            UnsupportedPolicy::Fail => {
+                Err(format!("Unsupported file '{}' (use --unsupported skip or copy)", input_path.display()).into())
+            }
+            UnsupportedPolicy::Copy => {
+                // In-place runs leave unsupported files where they are
+                if self.config.output.is_none() {
+                    return Ok(false);
+                }
+                let output_path = self.get_output_path(input_path)?;
+                if self.config.dry_run {
+                    info!(path = %input_path.display(), "Would copy unsupported file verbatim");
+                    return Ok(false);
+                }
+
+                fs::copy(input_path, &output_path)?;
+                self.record_output(input_path, &output_path, OutputAction::Uncleaned)?;
+                warn!(path = %input_path.display(), "Copied unsupported file verbatim; its metadata was NOT cleaned");
+                Ok(true)
+            }
+        }
+    }
+
+    /// Count the fields of `found` that are no longer in the cleaned data
+    fn record_removed(
+        &self,
+        handler: Option<&dyn FormatHandler>,
+        found: &[PrivacyField],
+        cleaned: &[u8],
+        path: &Path,
+        level: &PrivacyLevel,
+    ) {
+        match self.find_privacy_data(handler, cleaned, path, level) {
+            Ok(remaining) => self.removed.record(removed_fields(found, &remaining)),
+            Err(e) => debug!("Cleaned output could not be re-analyzed, not counting removals: {}", e),
+        }
+    }
+
+    /// Check the cleaned output against `--lossless-only` and `--require-clean`
+    fn verify_output(
+        &self,
+        handler: Option<&dyn FormatHandler>,
+        original: &[u8],
+        cleaned: &[u8],
+        path: &Path,
+        level: &PrivacyLevel,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if self.config.lossless_only && jpeg::is_jpeg(original) && !jpeg::same_image_data(original, cleaned)? {
+            return Err("Cleaning changed the compressed image data (--lossless-only)".into());
+        }
+        if self.config.require_clean {
+            self.verify_clean(handler, cleaned, path, level)?;
+        }
+        Ok(())
+    }
+
+    /// Re-analyze cleaned data and fail if anything the privacy level covers survived
+    fn verify_clean(
+        &self,
+        handler: Option<&dyn FormatHandler>,
+        cleaned: &[u8],
+        path: &Path,
+        level: &PrivacyLevel,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(handler) = handler {
+            if !handler.capabilities().verify {
+                return Err(format!("{} output can't be verified (--require-clean)", handler.name()).into());
+            }
+            return handler.verify(cleaned, level);
+        }
+
+        self.analyzer.check_metadata_readable(cleaned)?;
+
+        let remaining = self.analyzer.analyze_privacy_data(cleaned, path, level)?.into_iter()
+            .filter(|field| !self.is_kept_content(field, level))
+            .count();
+        if remaining > 0 {
+            return Err(format!("{} privacy-sensitive fields remain after cleaning", remaining).into());
+        }
+
+        // Levels that remove XMP remove all of it, including extended XMP chunks left in later segments
+        let removes_xmp = PrivacyPolicy::spec(level).removes_xmp();
+        if removes_xmp && self.analyzer.xmp_packet(cleaned).is_some() {
+            return Err("XMP metadata remains after cleaning".into());
+        }
+
+        Ok(())
+    }
+
+    /// Put a file that failed cleaning into `quarantine_dir` with `copy`, next to a note explaining why
+    fn quarantine_with(
+        &self,
+        source: &Path,
+        quarantine_dir: &Path,
+        error: &str,
+        copy: impl FnOnce(&Path) -> io::Result<()>,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let dest = self.quarantine_path(source, quarantine_dir)?;
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(utils::long_path(parent))?;
+        }
+        copy(&dest)?;
+
+        let note_path = utils::append_to_file_name(&dest, ".error.txt");
+        fs::write(
+            utils::long_path(&note_path),
+            format!(
+                "source: {}\nprivacy level: {:?}\nerror: {}\n",
+                source.display(),
+                self.config.privacy_level,
+                error
+            ),
         )?;
 
-        Ok(true)
+        Ok(dest)
+    }
+
+    /// Where a failed file goes in `quarantine_dir`
+    ///
+    /// Files keep their path below the input, so same-named files from
+    /// different directories don't collide; a name that is still taken (from
+    /// an earlier run, say) gets a `.1`, `.2`, ... suffix.
+    fn quarantine_path(&self, source: &Path, quarantine_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let relative = match source.strip_prefix(&self.config.input) {
+            Ok(relative) if relative.file_name().is_some() => relative,
+            _ => Path::new(source.file_name().ok_or("Invalid file name")?),
+        };
+
+        let mut dest = quarantine_dir.to_path_buf();
+        for component in relative.components() {
+            if let Component::Normal(name) = component {
+                dest.push(utils::windows_safe_file_name(name));
+            }
+        }
+
+        let mut candidate = dest.clone();
+        let mut suffix = 1;
+        while utils::long_path(&candidate).exists() {
+            candidate = utils::append_to_file_name(&dest, &format!(".{}", suffix));
+            suffix += 1;
+        }
+        Ok(candidate)
     }
 
     /// Determine the output path for a processed file
     fn get_output_path(&self, input_path: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let output_path = if let Some(ref out_dir) = self.config.output_dir {
+        let output_path = if let Some(ref out_dir) = self.config.output {
             // Save to output directory, preserving filename
             let file_name = input_path.file_name()
                 .ok_or("Invalid file name")?;
-            PathBuf::from(out_dir).join(file_name)
+            if cfg!(windows) {
+                out_dir.join(utils::windows_safe_file_name(file_name))
+            } else {
+                out_dir.join(file_name)
+            }
         } else {
             // In-place modification
             input_path.to_path_buf()
@@ -82,38 +922,163 @@ impl ImageProcessor {
         Ok(output_path)
     }
 
+    /// Where `--export-metadata` saves a file's sidecar: the export directory, named like the output plus the format's extension
+    fn sidecar_path(&self, input_path: &Path, export_dir: &Path) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let file_name = input_path.file_name().ok_or("Invalid file name")?;
+        let sidecar = if cfg!(windows) {
+            export_dir.join(utils::windows_safe_file_name(file_name))
+        } else {
+            export_dir.join(file_name)
+        };
+        // Appending keeps photo.jpg and photo.png from sharing a sidecar
+        Ok(utils::append_to_file_name(&sidecar, &format!(".{}", self.config.export_format.extension())))
+    }
+
     /// Create a backup of the original file
-    fn create_backup(&self, input_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        let backup_path = input_path.with_extension(
-            format!("{}.bak", 
-                input_path.extension()
-                    .unwrap_or_default()
-                    .to_string_lossy())
-        );
-        
-        fs::copy(input_path, backup_path)?;
-        Ok(())
+    ///
+    /// The first backup (`photo.jpg.bak`) holds the true original and is never
+    /// overwritten. Later runs write `photo.jpg.bak.1`, `.bak.2`, ..., keeping
+    /// only the newest `backup_keep` of those.
+    fn create_backup(&self, input_path: &Path) -> Result<Option<PathBuf>, Box<dyn std::error::Error>> {
+        // Appending to the raw file name keeps non-UTF-8 names intact (photo.jpg -> photo.jpg.bak)
+        let original_backup = utils::append_to_file_name(input_path, ".bak");
+
+        let backup_path = if !utils::long_path(&original_backup).exists() {
+            original_backup
+        } else if self.config.backup_keep == 0 {
+            debug!("Original backup already exists; not creating another");
+            return Ok(None);
+        } else {
+            let next = backup_versions(input_path)?.last().map_or(1, |(version, _)| version + 1);
+            utils::append_to_file_name(input_path, &format!(".bak.{}", next))
+        };
+
+        fs::copy(utils::long_path(input_path), utils::long_path(&backup_path))?;
+
+        // Drop the oldest numbered backups beyond the retention count
+        let versions = backup_versions(input_path)?;
+        let excess = versions.len().saturating_sub(self.config.backup_keep);
+        for (_, old_backup) in versions.into_iter().take(excess) {
+            fs::remove_file(utils::long_path(&old_backup))?;
+        }
+
+        Ok(Some(backup_path))
+    }
+}
+
+/// Numbered backups (`<name>.bak.N`) of a file, oldest first
+fn backup_versions(input_path: &Path) -> Result<Vec<(u32, PathBuf)>, Box<dyn std::error::Error>> {
+    let file_name = input_path.file_name().ok_or("Invalid file name")?;
+    // Compared as raw bytes: lossy conversion would mix up non-UTF-8 names that differ only in invalid bytes
+    let mut prefix = file_name.as_encoded_bytes().to_vec();
+    prefix.extend_from_slice(b".bak.");
+    let dir = match input_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut versions = Vec::new();
+    for entry in fs::read_dir(utils::long_path(dir))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let version = name.as_encoded_bytes()
+            .strip_prefix(prefix.as_slice())
+            .and_then(|suffix| std::str::from_utf8(suffix).ok())
+            .and_then(|suffix| suffix.parse::<u32>().ok());
+        if let Some(version) = version {
+            versions.push((version, dir.join(name)));
+        }
+    }
+
+    versions.sort();
+    Ok(versions)
+}
+
+/// Fail for formats whose handler can only analyze
+fn check_can_clean(handler: Option<&dyn FormatHandler>) -> Result<(), Box<dyn std::error::Error>> {
+    match handler {
+        Some(handler) if !handler.capabilities().clean => Err(format!("{} files can't be cleaned", handler.name()).into()),
+        _ => Ok(()),
+    }
+}
+
+/// A JPEG stripped natively; segments that can't be parsed mean a damaged file
+fn strip_jpeg(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    jpeg::strip_metadata(data).map_err(|e| CorruptStructure(e.to_string()).into())
+}
+
+#[cfg(feature = "reencode")]
+fn reencode(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    crate::reencode::reencode(data)
+}
+
+#[cfg(not(feature = "reencode"))]
+fn reencode(_data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Err("--reencode-fallback requires building with `--features reencode`".into())
+}
+
+/// Fields of `found` with no counterpart in `remaining`, matching repeated tags one for one
+fn removed_fields<'a>(found: &'a [PrivacyField], remaining: &[PrivacyField]) -> Vec<&'a PrivacyField> {
+    let mut remaining: Vec<&PrivacyField> = remaining.iter().collect();
+    found.iter()
+        .filter(|field| match remaining.iter().position(|left| left.tag == field.tag) {
+            Some(index) => {
+                remaining.swap_remove(index);
+                false
+            }
+            None => true,
+        })
+        .collect()
+}
+
+/// Hard-link `existing` to `output_path`, copying when links aren't possible (e.g. across filesystems)
+fn link_or_copy(existing: &Path, output_path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let (existing, output_path) = (utils::long_path(existing), utils::long_path(output_path));
+    if fs::hard_link(&existing, &output_path).is_err() {
+        fs::copy(&existing, &output_path)?;
+    }
+    Ok(())
+}
+
+/// Result of processing an in-memory image
+#[derive(Debug)]
+pub struct ProcessedImage {
+    pub had_privacy_data: bool,
+    pub cleaned: Option<Vec<u8>>,
+}
+
+impl ProcessedImage {
+    /// The bytes that should be written out: cleaned data if any, else the original
+    pub fn output<'a>(&'a self, original: &'a [u8]) -> &'a [u8] {
+        self.cleaned.as_deref().unwrap_or(original)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::privacy::PrivacyLevel;
     use tempfile::TempDir;
 
     fn create_test_config() -> Config {
         Config {
-            input_dir: "/tmp".to_string(),
-            output_dir: None,
-            recursive: false,
-            create_backup: false,
+            input: PathBuf::from("/tmp"),
             privacy_level: PrivacyLevel::Standard,
-            verbose: false,
-            dry_run: false,
+            ..Config::default()
         }
     }
 
+    #[test]
+    fn test_reencode_fallback_only_for_damaged_files() {
+        let mut config = create_test_config();
+        config.reencode_fallback = true;
+        let processor = ImageProcessor::new(config);
+
+        let error = processor.reencode_after("ExifTool timed out".into(), b"data").unwrap_err();
+        assert_eq!(error.to_string(), "ExifTool timed out");
+        let error = processor.reencode_after(Box::new(CorruptStructure("truncated".into())), b"data").unwrap_err();
+        assert!(error.to_string().contains("re-encoding also failed"));
+    }
+
     #[test]
     fn test_output_path_in_place() {
         let config = create_test_config();
@@ -128,7 +1093,7 @@ mod tests {
     #[test]
     fn test_output_path_separate_directory() {
         let mut config = create_test_config();
-        config.output_dir = Some("/output".to_string());
+        config.output = Some(PathBuf::from("/output"));
         let processor = ImageProcessor::new(config);
         
         let input_path = Path::new("/test/photo.jpg");
@@ -137,6 +1102,232 @@ mod tests {
         assert_eq!(output_path, Path::new("/output/photo.jpg"));
     }
 
+    #[test]
+    fn test_sidecar_path_appends_format_extension() {
+        let mut config = create_test_config();
+        config.export_format = crate::cli::ExportFormat::Xmp;
+        let processor = ImageProcessor::new(config);
+
+        let sidecar = processor.sidecar_path(Path::new("/in/photo.jpg"), Path::new("/private")).unwrap();
+        assert_eq!(sidecar, Path::new("/private/photo.jpg.xmp"));
+    }
+
+    #[test]
+    fn test_duplicate_is_linked_to_existing_output() {
+        let temp_dir = TempDir::new().unwrap();
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir(&output_dir).unwrap();
+        let existing = output_dir.join("original.jpg");
+        fs::write(&existing, b"cleaned").unwrap();
+
+        let mut config = create_test_config();
+        config.output = Some(output_dir.clone());
+        config.duplicates = DuplicatePolicy::Link;
+        let processor = ImageProcessor::new(config);
+
+        processor.handle_duplicate(Path::new("/in/copy.jpg"), &existing).unwrap();
+
+        assert_eq!(fs::read(output_dir.join("copy.jpg")).unwrap(), b"cleaned");
+    }
+
+    /// Quarantine a file on disk the way a failed clean does
+    fn quarantine(processor: &ImageProcessor, input: &Path, quarantine_dir: &Path, error: &str) -> PathBuf {
+        processor.quarantine_with(input, quarantine_dir, error, |dest| fs::copy(input, dest).map(|_| ())).unwrap()
+    }
+
+    #[test]
+    fn test_quarantine_copies_file_with_note() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("broken.jpg");
+        fs::write(&input, b"not really a jpeg").unwrap();
+        let quarantine_dir = temp_dir.path().join("quarantine");
+
+        let mut config = create_test_config();
+        config.input = temp_dir.path().to_path_buf();
+        let processor = ImageProcessor::new(config);
+        let dest = quarantine(&processor, &input, &quarantine_dir, "ExifTool failed");
+
+        assert_eq!(dest, quarantine_dir.join("broken.jpg"));
+        assert_eq!(fs::read(&dest).unwrap(), b"not really a jpeg");
+        let note = fs::read_to_string(quarantine_dir.join("broken.jpg.error.txt")).unwrap();
+        assert!(note.contains("error: ExifTool failed"));
+    }
+
+    #[test]
+    fn test_quarantine_keeps_same_named_files_apart() {
+        let temp_dir = TempDir::new().unwrap();
+        let input_dir = temp_dir.path().join("input");
+        for dir in ["a", "b"] {
+            fs::create_dir_all(input_dir.join(dir)).unwrap();
+            fs::write(input_dir.join(dir).join("IMG_0001.jpg"), dir).unwrap();
+        }
+        let quarantine_dir = temp_dir.path().join("quarantine");
+
+        let mut config = create_test_config();
+        config.input = input_dir.clone();
+        config.quarantine = Some(quarantine_dir.clone());
+        let processor = ImageProcessor::new(config);
+
+        let a = quarantine(&processor, &input_dir.join("a/IMG_0001.jpg"), &quarantine_dir, "failed");
+        let b = quarantine(&processor, &input_dir.join("b/IMG_0001.jpg"), &quarantine_dir, "failed");
+        assert_eq!(a, quarantine_dir.join("a/IMG_0001.jpg"));
+        assert_eq!(b, quarantine_dir.join("b/IMG_0001.jpg"));
+        assert_eq!(fs::read(&b).unwrap(), b"b");
+
+        // Sources outside the input fall back to their name, made unique
+        processor.quarantine_data(b"first", Path::new("elsewhere/x.jpg"), "failed");
+        let second = processor.quarantine_with(Path::new("other/x.jpg"), &quarantine_dir, "failed", |dest| fs::write(dest, b"second")).unwrap();
+        assert_eq!(second, quarantine_dir.join("x.jpg.1"));
+    }
+
+    #[test]
+    fn test_unsupported_policy() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("clip.mp4");
+        fs::write(&input, b"video").unwrap();
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir(&output_dir).unwrap();
+
+        let mut config = create_test_config();
+        config.output = Some(output_dir.clone());
+
+        let skip = ImageProcessor::new(config.clone());
+        assert!(!skip.process_unsupported(&input).unwrap());
+        assert!(!output_dir.join("clip.mp4").exists());
+
+        config.unsupported = UnsupportedPolicy::Fail;
+        assert!(ImageProcessor::new(config.clone()).process_unsupported(&input).is_err());
+
+        config.unsupported = UnsupportedPolicy::Copy;
+        assert!(ImageProcessor::new(config).process_unsupported(&input).unwrap());
+        assert_eq!(fs::read(output_dir.join("clip.mp4")).unwrap(), b"video");
+    }
+
+    #[test]
+    fn test_corrupt_metadata_needs_force_strip() {
+        let mut corrupt = vec![0xFF, 0xD8, 0xFF, 0xE1, 0x00, 0x0C];
+        corrupt.extend_from_slice(b"Exif\0\0XX\0\0");
+        corrupt.extend_from_slice(&[0xFF, 0xD9]);
+
+        let mut config = create_test_config();
+        config.dry_run = true;
+        let processor = ImageProcessor::new(config.clone());
+        let error = processor.process_image_data(&corrupt, Path::new("corrupt.jpg")).unwrap_err();
+        assert!(error.to_string().contains("--force-strip"));
+
+        config.force_strip = true;
+        let processor = ImageProcessor::new(config);
+        let processed = processor.process_image_data(&corrupt, Path::new("corrupt.jpg")).unwrap();
+        assert!(processed.had_privacy_data);
+        assert!(processed.cleaned.is_none());
+    }
+
+    #[test]
+    fn test_force_strip_rewrites_jpeg_losslessly() {
+        let corrupt = jpeg::tests::build_jpeg(&[(jpeg::APP1, b"Exif\0\0XX\0\0"), (0xDB, b"qtable")], b"pixels");
+
+        let mut config = create_test_config();
+        config.force_strip = true;
+        config.lossless_only = true;
+        let processed = ImageProcessor::new(config).process_image_data(&corrupt, Path::new("corrupt.jpg")).unwrap();
+
+        let cleaned = processed.cleaned.unwrap();
+        assert!(!cleaned.windows(4).any(|window| window == b"Exif"));
+        assert!(jpeg::same_image_data(&corrupt, &cleaned).unwrap());
+    }
+
+    #[test]
+    fn test_removed_fields_match_repeated_tags_once() {
+        let field = |tag, category| PrivacyField { tag, description: String::new(), category, ifd: None, value: None };
+        let found = vec![
+            field(exif::Tag::GPSLatitude, PrivacyCategory::Location),
+            field(exif::Tag::DateTimeOriginal, PrivacyCategory::Temporal),
+            field(exif::Tag::DateTimeOriginal, PrivacyCategory::Temporal),
+        ];
+        let remaining = vec![field(exif::Tag::DateTimeOriginal, PrivacyCategory::Temporal)];
+
+        let removed = removed_fields(&found, &remaining);
+        assert_eq!(removed.len(), 2);
+        assert_eq!(removed[0].tag, exif::Tag::GPSLatitude);
+        assert_eq!(removed[1].tag, exif::Tag::DateTimeOriginal);
+    }
+
+    #[test]
+    fn test_findings_are_reported_per_file() {
+        struct Collect(Arc<Mutex<Vec<(PathBuf, Vec<exif::Tag>)>>>);
+        impl FindingsReporter for Collect {
+            fn report(&self, findings: &FileFindings) {
+                let tags = findings.fields.iter().map(|field| field.tag).collect();
+                self.0.lock().unwrap().push((findings.path.to_path_buf(), tags));
+            }
+        }
+
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("artist.jpg");
+        let exif = crate::jpeg::tests::exif_with_artist("Ann");
+        fs::write(&input, crate::jpeg::tests::build_jpeg(&[(crate::jpeg::APP1, &exif)], b"pixels")).unwrap();
+
+        let reported = Arc::new(Mutex::new(Vec::new()));
+        let processor = ImageProcessor::new(Config { dry_run: true, ..create_test_config() })
+            .with_findings_reporter(Box::new(Collect(Arc::clone(&reported))));
+        processor.process_image(&input).unwrap();
+
+        assert_eq!(*reported.lock().unwrap(), [(input.clone(), vec![exif::Tag::Artist])]);
+    }
+
+    #[test]
+    fn test_clean_image_is_skipped_without_rewriting() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("clean.jpg");
+        let image = crate::jpeg::tests::build_jpeg(&[(crate::jpeg::APP0, b"JFIF\0")], b"pixels");
+        fs::write(&input, &image).unwrap();
+        let modified = fs::metadata(&input).unwrap().modified().unwrap();
+
+        let processor = ImageProcessor::new(create_test_config());
+        assert_eq!(processor.process_image(&input).unwrap(), Some(Skipped::AlreadyClean));
+        assert_eq!(fs::read(&input).unwrap(), image);
+        assert_eq!(fs::metadata(&input).unwrap().modified().unwrap(), modified);
+    }
+
+    #[test]
+    fn test_copy_unchanged_is_byte_identical() {
+        let temp_dir = TempDir::new().unwrap();
+        let input = temp_dir.path().join("clean.jpg");
+        fs::write(&input, b"\xFF\xD8already clean\xFF\xD9").unwrap();
+        let output_dir = temp_dir.path().join("out");
+        fs::create_dir(&output_dir).unwrap();
+
+        let mut config = create_test_config();
+        config.output = Some(output_dir.clone());
+        config.only_if_dirty = true;
+        let processor = ImageProcessor::new(config);
+
+        let output = processor.copy_unchanged(&input, OutputAction::Unchanged).unwrap();
+        assert_eq!(fs::read(output).unwrap(), fs::read(&input).unwrap());
+    }
+
+    #[test]
+    fn test_backup_of_file_without_extension() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("IMG_0001");
+        fs::write(&test_file, b"fake jpeg data").unwrap();
+
+        let processor = ImageProcessor::new(create_test_config());
+        processor.create_backup(&test_file).unwrap();
+
+        assert!(temp_dir.path().join("IMG_0001.bak").exists());
+    }
+
+    #[test]
+    fn test_output_path_keeps_unicode_file_name() {
+        let mut config = create_test_config();
+        config.output = Some(PathBuf::from("/output"));
+        let processor = ImageProcessor::new(config);
+
+        let output_path = processor.get_output_path(Path::new("/test/Straße 東京.jpg")).unwrap();
+        assert_eq!(output_path, Path::new("/output/Straße 東京.jpg"));
+    }
+
     #[test]
     fn test_backup_creation() {
         let temp_dir = TempDir::new().unwrap();
@@ -155,4 +1346,59 @@ mod tests {
         let backup_content = fs::read(backup_file).unwrap();
         assert_eq!(backup_content, b"fake jpeg data");
     }
+
+    #[test]
+    fn test_backup_rotation_keeps_original() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("test.jpg");
+
+        let mut config = create_test_config();
+        config.create_backup = true;
+        config.backup_keep = 2;
+        let processor = ImageProcessor::new(config);
+
+        for run in 0..4 {
+            fs::write(&test_file, format!("run {}", run)).unwrap();
+            processor.create_backup(&test_file).unwrap();
+        }
+
+        let read = |name: &str| fs::read_to_string(temp_dir.path().join(name)).ok();
+        assert_eq!(read("test.jpg.bak").as_deref(), Some("run 0"));
+        assert_eq!(read("test.jpg.bak.1"), None);
+        assert_eq!(read("test.jpg.bak.2").as_deref(), Some("run 2"));
+        assert_eq!(read("test.jpg.bak.3").as_deref(), Some("run 3"));
+    }
+
+    #[test]
+    fn test_backup_keep_zero_never_overwrites() {
+        let temp_dir = TempDir::new().unwrap();
+        let test_file = temp_dir.path().join("test.jpg");
+
+        let mut config = create_test_config();
+        config.create_backup = true;
+        config.backup_keep = 0;
+        let processor = ImageProcessor::new(config);
+
+        fs::write(&test_file, b"original").unwrap();
+        processor.create_backup(&test_file).unwrap();
+        fs::write(&test_file, b"cleaned").unwrap();
+        assert_eq!(processor.create_backup(&test_file).unwrap(), None);
+
+        assert_eq!(fs::read(temp_dir.path().join("test.jpg.bak")).unwrap(), b"original");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_backup_versions_compare_raw_names() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let temp_dir = TempDir::new().unwrap();
+        let file = temp_dir.path().join(OsStr::from_bytes(b"photo\xff.jpg"));
+        // Lossily converted, both names read as `photo\u{FFFD}.jpg`
+        fs::write(temp_dir.path().join(OsStr::from_bytes(b"photo\xfe.jpg.bak.4")), b"other").unwrap();
+        fs::write(temp_dir.path().join(OsStr::from_bytes(b"photo\xff.jpg.bak.2")), b"mine").unwrap();
+
+        assert_eq!(backup_versions(&file).unwrap().iter().map(|(version, _)| *version).collect::<Vec<_>>(), [2]);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,107 @@
+//! Per-file progress of directory runs.
+//!
+//! The walker loop reports every file it finishes to a `Progress` sink, which
+//! live displays use to show what is happening and to cancel the run, and the
+//! running totals after every chunk of files. What the analysis of each file
+//! found goes to a `FindingsReporter` instead, since the processor itself
+//! prints nothing: `--verbose` logs it, and library users can collect it.
+
+use std::path::Path;
+use tracing::info;
+use crate::analyzer::PrivacyField;
+use crate::batch::ProcessingStats;
+use crate::privacy::PrivacyLevel;
+use crate::style::{self, Stream};
+
+/// How one file of a run ended
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileOutcome {
+    /// Privacy data was found (and removed, unless this is a dry run)
+    Cleaned,
+    /// A supported image that was left untouched
+    Skipped(Skipped),
+    /// Not a supported image
+    Unsupported,
+    /// The file could not be processed
+    Failed(String),
+}
+
+/// Why a supported image was left untouched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Skipped {
+    /// Nothing the policy covers was found, so the removal backend never ran
+    AlreadyClean,
+    /// A cleaned copy of the same content is already in the output directory
+    Duplicate,
+    /// Kept or skipped on interactive review
+    Reviewed,
+}
+
+/// Receives the progress of a directory run
+pub trait Progress {
+    /// Called after each file, with its size in bytes
+    fn file_done(&mut self, path: &Path, size: u64, outcome: &FileOutcome);
+
+    /// Called before each file; returning false cancels the rest of the run
+    fn keep_going(&mut self) -> bool {
+        true
+    }
+
+    /// Called with the running totals after each chunk of files (see `batch::CHUNK_SIZE`)
+    fn checkpoint(&mut self, _stats: &ProcessingStats) {}
+}
+
+/// Progress sink for runs without a live display
+pub struct NoProgress;
+
+impl Progress for NoProgress {
+    fn file_done(&mut self, _path: &Path, _size: u64, _outcome: &FileOutcome) {}
+}
+
+/// Progress sink that logs the running totals at each checkpoint, for long runs without a live display
+pub struct LogProgress;
+
+impl Progress for LogProgress {
+    fn file_done(&mut self, _path: &Path, _size: u64, _outcome: &FileOutcome) {}
+
+    fn checkpoint(&mut self, stats: &ProcessingStats) {
+        info!(
+            processed = stats.processed,
+            privacy_data_found = stats.privacy_data_found,
+            errors = stats.errors,
+            elapsed_secs = stats.duration.as_secs(),
+            "Progress"
+        );
+    }
+}
+
+/// What the analysis of one file found
+#[derive(Debug, Clone, Copy)]
+pub struct FileFindings<'a> {
+    pub path: &'a Path,
+    /// The level the file was analyzed against
+    pub level: &'a PrivacyLevel,
+    pub fields: &'a [PrivacyField],
+}
+
+/// Receives the findings of every file a processor analyzes, from any worker thread
+pub trait FindingsReporter: Send + Sync {
+    fn report(&self, findings: &FileFindings);
+}
+
+/// Logs each finding, tinted by severity (`--verbose`)
+pub struct LogFindings;
+
+impl FindingsReporter for LogFindings {
+    fn report(&self, findings: &FileFindings) {
+        for field in findings.fields {
+            info!(
+                path = %findings.path.display(),
+                category = %field.category,
+                ifd = field.ifd.map(tracing::field::display),
+                "Privacy data found: {}",
+                style::severity(&field.description, field.severity(), Stream::Stderr)
+            );
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Last-resort cleaning by decoding the pixels and encoding a new image (`--reencode-fallback`).
+//!
+//! Used when an image's metadata structures are too damaged for ExifTool or a
+//! format handler to rewrite. The new file is encoded from the pixels alone, so
+//! it carries no metadata at all, but a JPEG is recompressed and loses quality,
+//! an animated GIF keeps only its first frame, and colour profiles are dropped.
+//! Requires the `reencode` feature.
+
+use std::io::Cursor;
+use exif::{In, Tag};
+use image::codecs::jpeg::JpegEncoder;
+use image::{DynamicImage, ImageFormat};
+
+/// Quality of re-encoded JPEGs, high enough that the loss is rarely visible
+pub const JPEG_QUALITY: u8 = 92;
+
+/// Decode an image and encode its pixels into a new file of the same format
+///
+/// A readable EXIF orientation is applied to the pixels first, since the new
+/// file has no tag to rotate it by.
+pub fn reencode(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let format = image::guess_format(data).map_err(|e| format!("Cannot re-encode: {}", e))?;
+    let image = image::load_from_memory_with_format(data, format)
+        .map_err(|e| format!("Cannot decode the pixels to re-encode: {}", e))?;
+    let image = match orientation(data) {
+        Some(orientation) => oriented(image, orientation),
+        None => image,
+    };
+
+    let mut encoded = Cursor::new(Vec::new());
+    match format {
+        ImageFormat::Jpeg => image.write_with_encoder(JpegEncoder::new_with_quality(&mut encoded, JPEG_QUALITY))?,
+        ImageFormat::Png | ImageFormat::Tiff | ImageFormat::Gif | ImageFormat::Bmp => image.write_to(&mut encoded, format)?,
+        other => return Err(format!("Cannot re-encode {:?} images", other).into()),
+    }
+    Ok(encoded.into_inner())
+}
+
+/// The EXIF orientation of the primary image, if its metadata is still readable that far
+fn orientation(data: &[u8]) -> Option<u32> {
+    let exif = exif::Reader::new().read_from_container(&mut Cursor::new(data)).ok()?;
+    exif.get_field(Tag::Orientation, In::PRIMARY)?.value.get_uint(0)
+}
+
+/// Turn the pixels upright as EXIF orientation 1-8 describes
+fn oriented(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, RgbImage};
+
+    #[test]
+    fn test_reencoded_jpeg_has_no_metadata() {
+        let mut jpeg = Vec::new();
+        DynamicImage::ImageRgb8(RgbImage::new(4, 2))
+            .write_with_encoder(JpegEncoder::new(&mut jpeg))
+            .unwrap();
+        // A truncated EXIF segment right after SOI
+        let mut corrupt = jpeg[..2].to_vec();
+        corrupt.extend_from_slice(&[0xFF, 0xE1, 0x00, 0x0C]);
+        corrupt.extend_from_slice(b"Exif\0\0XX\0\0");
+        corrupt.extend_from_slice(&jpeg[2..]);
+
+        let cleaned = reencode(&corrupt).unwrap();
+        assert!(!cleaned.windows(4).any(|window| window == b"Exif"));
+        assert_eq!(image::load_from_memory(&cleaned).unwrap().dimensions(), (4, 2));
+    }
+
+    #[test]
+    fn test_orientation_is_applied() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(4, 2));
+        assert_eq!(oriented(image.clone(), 6).dimensions(), (2, 4));
+        assert_eq!(oriented(image.clone(), 3).dimensions(), (4, 2));
+        assert_eq!(oriented(image, 0).dimensions(), (4, 2));
+    }
+}
@@ -0,0 +1,267 @@
+//! Deep scan for metadata left outside any metadata structure (`--deep-scan`).
+//!
+//! Naive editors delete a tag by dropping its IFD entry, or shorten a value
+//! by writing the new one elsewhere, and leave the old bytes in place. Others
+//! append a rewritten file's old XMP after the end of the image. Nothing
+//! points to such bytes any more, so the ordinary analysis never sees them,
+//! but a hex editor does. The deep scan reads only the orphaned bytes: those
+//! in EXIF and TIFF structures no IFD references, and data after a JPEG's
+//! end-of-image or a PNG's `IEND` that isn't a known embedded picture or video.
+//! In them it looks for GPS coordinates stored as rationals, serial-number-like
+//! strings, and fragments of XMP.
+
+use std::ops::Range;
+use crate::analyzer::PrivacyCategory;
+use crate::formats::{self, ImageFormat};
+use crate::jpeg;
+use crate::multipicture;
+use crate::png;
+use crate::tiff;
+
+/// Most remnants reported per file; beyond that the file is plainly full of them
+const MAX_REMNANTS: usize = 20;
+/// Denominators cameras and editors use for degrees, minutes, and seconds
+const DENOMINATORS: [u32; 8] = [1, 10, 100, 1000, 10_000, 100_000, 1_000_000, 10_000_000];
+/// Shortest and longest serial-number-like strings
+const SERIAL_LEN: Range<usize> = 6..33;
+/// Fewest digits in a serial-number-like string
+const MIN_SERIAL_DIGITS: usize = 4;
+/// Markup found only in XMP
+const XMP_MARKERS: [&str; 6] = ["<x:xmpmeta", "<?xpacket", "<rdf:RDF", "xmlns:exif=", "exif:GPS", "aux:SerialNumber"];
+
+/// Metadata found in orphaned bytes
+#[derive(Debug, Clone, PartialEq)]
+pub struct Remnant {
+    /// Offset in the file
+    pub offset: usize,
+    pub description: String,
+    pub category: PrivacyCategory,
+}
+
+/// Find metadata remnants in the orphaned bytes of an image
+///
+/// `known` lists ranges the caller accounts for, such as a motion photo's
+/// video; they are not scanned.
+pub fn scan(data: &[u8], known: &[Range<usize>]) -> Vec<Remnant> {
+    let mut remnants = Vec::new();
+    for region in orphaned_regions(data) {
+        for region in uncovered(region, known) {
+            scan_region(data, region, &mut remnants);
+        }
+    }
+    remnants.sort_by_key(|remnant| remnant.offset);
+    remnants.truncate(MAX_REMNANTS);
+    remnants
+}
+
+/// Bytes of the file no metadata structure accounts for
+fn orphaned_regions(data: &[u8]) -> Vec<Range<usize>> {
+    match formats::sniff_image_format(data) {
+        Some(ImageFormat::Jpeg) => jpeg_regions(data),
+        Some(ImageFormat::Png) => png_regions(data),
+        Some(ImageFormat::Tiff) => tiff_regions(data, 0),
+        _ => Vec::new(),
+    }
+}
+
+/// Orphaned bytes of a TIFF structure starting at `base`; none when it can't be walked
+fn tiff_regions(tiff: &[u8], base: usize) -> Vec<Range<usize>> {
+    match tiff::referenced_ranges(tiff) {
+        Ok(referenced) => uncovered(0..tiff.len(), &referenced).into_iter()
+            .map(|range| base + range.start..base + range.end)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+fn jpeg_regions(data: &[u8]) -> Vec<Range<usize>> {
+    let layout = match jpeg::parse_layout(data) {
+        Ok(layout) => layout,
+        Err(_) => return Vec::new(),
+    };
+    let mut regions = Vec::new();
+    for segment in &layout.segments {
+        if segment.marker == jpeg::APP1 && segment.payload.starts_with(jpeg::EXIF_HEADER) {
+            // Marker and length precede the payload
+            let base = segment.offset + 4 + jpeg::EXIF_HEADER.len();
+            regions.extend(tiff_regions(&segment.payload[jpeg::EXIF_HEADER.len()..], base));
+        }
+    }
+    // Entropy-coded data escapes 0xFF bytes, so the first EOI after the scan ends the image
+    let eoi = data[layout.scan_offset..].windows(2).position(|pair| pair == [0xFF, jpeg::EOI]);
+    if let Some(eoi) = eoi {
+        let end = layout.scan_offset + eoi + 2;
+        let pictures: Vec<Range<usize>> = multipicture::embedded_images(data).into_iter()
+            .map(|image| image.range)
+            .collect();
+        regions.extend(uncovered(end..data.len(), &pictures));
+    }
+    regions
+}
+
+fn png_regions(data: &[u8]) -> Vec<Range<usize>> {
+    let chunks = match png::parse_chunks(data) {
+        Ok(chunks) => chunks,
+        Err(_) => return Vec::new(),
+    };
+    let mut regions = Vec::new();
+    let mut pos = png::SIGNATURE.len();
+    for chunk in &chunks {
+        if &chunk.kind == b"eXIf" {
+            regions.extend(tiff_regions(chunk.data, pos + 8));
+        }
+        // Length and type, the data, then a CRC
+        pos += 12 + chunk.data.len();
+    }
+    regions.push(pos.min(data.len())..data.len());
+    regions
+}
+
+/// The parts of `within` that none of `covered` overlaps
+fn uncovered(within: Range<usize>, covered: &[Range<usize>]) -> Vec<Range<usize>> {
+    let mut covered = covered.to_vec();
+    covered.sort_by_key(|range| range.start);
+
+    let mut gaps = Vec::new();
+    let mut pos = within.start;
+    for range in covered {
+        if range.start > pos {
+            gaps.push(pos..range.start.min(within.end));
+        }
+        pos = pos.max(range.end);
+        if pos >= within.end {
+            break;
+        }
+    }
+    if pos < within.end {
+        gaps.push(pos..within.end);
+    }
+    gaps.retain(|gap| !gap.is_empty());
+    gaps
+}
+
+fn scan_region(data: &[u8], region: Range<usize>, remnants: &mut Vec<Remnant>) {
+    let bytes = &data[region.clone()];
+    let remnant = |offset: usize, description: String, category| Remnant {
+        offset: region.start + offset,
+        description,
+        category,
+    };
+
+    let mut pos = 0;
+    while pos + 24 <= bytes.len() {
+        match gps_coordinate(&bytes[pos..pos + 24]) {
+            Some(coordinate) => {
+                remnants.push(remnant(pos, format!("GPS coordinate {}", coordinate), PrivacyCategory::Location));
+                pos += 24;
+            }
+            None => pos += 1,
+        }
+    }
+
+    for (offset, serial) in serial_strings(bytes) {
+        remnants.push(remnant(offset, format!("serial-number-like string {}", serial), PrivacyCategory::DeviceIdentifier));
+    }
+
+    let found: Vec<(usize, &str)> = XMP_MARKERS.iter()
+        .filter_map(|marker| Some((find(bytes, marker.as_bytes())?, *marker)))
+        .collect();
+    if let Some(&(offset, _)) = found.iter().min_by_key(|(offset, _)| *offset) {
+        let markers: Vec<&str> = found.iter().map(|(_, marker)| marker.trim_start_matches(&['<', '?'][..])).collect();
+        remnants.push(remnant(offset, format!("XMP fragment ({})", markers.join(", ")), PrivacyCategory::Metadata));
+    }
+}
+
+/// Degrees, minutes, and seconds as three rationals, in either byte order
+fn gps_coordinate(bytes: &[u8]) -> Option<String> {
+    [u32::from_le_bytes, u32::from_be_bytes].into_iter().find_map(|read: fn([u8; 4]) -> u32| {
+        let numbers: Vec<u32> = bytes.chunks_exact(4).map(|word| read(word.try_into().unwrap())).collect();
+        let [degrees, minutes, seconds] = [0, 1, 2].map(|i| (numbers[2 * i], numbers[2 * i + 1]));
+        let plausible = [degrees, minutes, seconds].iter().all(|(_, denominator)| DENOMINATORS.contains(denominator))
+            && degrees.0 <= 180 * degrees.1
+            && minutes.0 < 60 * minutes.1
+            && seconds.0 < 60 * seconds.1
+            && (degrees.0 > 0 || minutes.0 > 0);
+        let value = |(numerator, denominator): (u32, u32)| numerator as f64 / denominator as f64;
+        plausible.then(|| format!("{}° {}' {:.2}\"", value(degrees), value(minutes), value(seconds)))
+    })
+}
+
+/// NUL-terminated runs of capitals, digits, and dashes with enough digits to be a serial number
+fn serial_strings(bytes: &[u8]) -> Vec<(usize, &str)> {
+    let is_serial_char = |byte: &u8| byte.is_ascii_uppercase() || byte.is_ascii_digit() || *byte == b'-';
+    let mut found = Vec::new();
+    let mut start = 0;
+    for (pos, byte) in bytes.iter().enumerate() {
+        if is_serial_char(byte) {
+            continue;
+        }
+        let run = &bytes[start..pos];
+        // A run cut from a longer printable string is part of some other text
+        let starts_clean = start == 0 || !bytes[start - 1].is_ascii_graphic();
+        if *byte == 0
+            && starts_clean
+            && SERIAL_LEN.contains(&run.len())
+            && run.iter().filter(|byte| byte.is_ascii_digit()).count() >= MIN_SERIAL_DIGITS
+            && run.iter().any(|byte| *byte != run[0])
+        {
+            // The run is ASCII by construction
+            found.push((start, std::str::from_utf8(run).unwrap_or_default()));
+        }
+        start = pos + 1;
+    }
+    found
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rationals(values: [(u32, u32); 3]) -> Vec<u8> {
+        values.iter().flat_map(|(numerator, denominator)| {
+            numerator.to_be_bytes().into_iter().chain(denominator.to_be_bytes())
+        }).collect()
+    }
+
+    #[test]
+    fn test_finds_remnants_after_the_image() {
+        let mut jpeg = vec![0xFF, jpeg::SOI, 0xFF, jpeg::SOS, 0x00, 0x02, 0x12, 0x34, 0xFF, jpeg::EOI];
+        jpeg.extend(rationals([(48, 1), (51, 1), (2988, 100)]));
+        jpeg.extend_from_slice(b"\0SN-4711-0815\0<x:xmpmeta><exif:GPSLatitude>");
+
+        let remnants = scan(&jpeg, &[]);
+        let descriptions: Vec<&str> = remnants.iter().map(|remnant| remnant.description.as_str()).collect();
+        assert_eq!(descriptions, vec![
+            "GPS coordinate 48° 51' 29.88\"",
+            "serial-number-like string SN-4711-0815",
+            "XMP fragment (x:xmpmeta, exif:GPS)",
+        ]);
+        assert_eq!(remnants[0].offset, 10);
+        assert_eq!(remnants[0].category, PrivacyCategory::Location);
+
+        // Ranges the caller accounts for are not scanned
+        assert!(scan(&jpeg, &[10..jpeg.len()]).is_empty());
+    }
+
+    #[test]
+    fn test_ordinary_bytes_are_not_remnants() {
+        assert_eq!(gps_coordinate(&rationals([(0, 1), (0, 1), (0, 1)])), None);
+        assert_eq!(gps_coordinate(&rationals([(200, 1), (0, 1), (0, 1)])), None);
+        assert_eq!(gps_coordinate(&rationals([(48, 3), (51, 1), (0, 1)])), None);
+        assert!(serial_strings(b"\0Canon EOS 5D\0").is_empty());
+        assert!(serial_strings(b" 00000000\0").is_empty());
+        assert!(serial_strings(b"xABC12345\0").is_empty());
+        assert_eq!(serial_strings(b"\0ABC12345\0"), vec![(1, "ABC12345")]);
+    }
+
+    #[test]
+    fn test_uncovered() {
+        assert_eq!(uncovered(0..10, &[6..8, 2..4]), vec![0..2, 4..6, 8..10]);
+        assert_eq!(uncovered(5..10, &[0..6, 9..20]), vec![6..9]);
+        assert!(uncovered(0..4, &[0..4]).is_empty());
+    }
+}
@@ -1,12 +1,75 @@
-use std::path::Path;
-use std::process::Command;
-use crate::privacy::PrivacyLevel;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use crate::bmp;
+use crate::capabilities::{self, Feature};
+use crate::cli::ExportFormat;
+use crate::formats::{self, ImageFormat};
+use crate::privacy::{Ifd, PolicyOverride, PrivacyLevel, PrivacyPolicy, UnknownTags};
+use crate::sandbox;
+use crate::stayopen;
+use crate::utils;
 
-pub struct MetadataRemover;
+/// How long ExifTool may run on one file unless configured otherwise
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+/// How often a running ExifTool process is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+/// Groups of exported JSON that describe the file rather than its metadata; writing
+/// `System:FileName` or `System:Directory` back would rename or move the image
+const FILE_GROUPS: &[&str] = &["System", "File", "Composite", "ExifTool"];
+
+pub struct MetadataRemover {
+    timeout: Duration,
+    overrides: PolicyOverride,
+    unknown_tags: UnknownTags,
+    sandbox: bool,
+    stay_open: bool,
+}
 
 impl MetadataRemover {
     pub fn new() -> Self {
-        Self
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            overrides: PolicyOverride::default(),
+            unknown_tags: UnknownTags::default(),
+            sandbox: false,
+            stay_open: false,
+        }
+    }
+
+    /// Kill ExifTool and fail the file if it runs longer than `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Keep and remove individual tags on top of the privacy level
+    pub fn with_overrides(mut self, overrides: PolicyOverride) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    /// Remove tags no EXIF specification defines according to `unknown_tags`
+    pub fn with_unknown_tags(mut self, unknown_tags: UnknownTags) -> Self {
+        self.unknown_tags = unknown_tags;
+        self
+    }
+
+    /// Run ExifTool confined to the files it works on (see `sandbox`)
+    pub fn with_sandbox(mut self, sandbox: bool) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Write files through this thread's long-running ExifTool process (see `stayopen`)
+    ///
+    /// Sandboxed runs still start a process per file, confined to that file's directory.
+    pub fn with_stay_open(mut self, stay_open: bool) -> Self {
+        self.stay_open = stay_open;
+        self
     }
 
     /// Remove privacy data from an image using ExifTool
@@ -16,12 +79,109 @@ impl MetadataRemover {
         output_path: &Path,
         privacy_level: &PrivacyLevel,
     ) -> Result<(), Box<dyn std::error::Error>> {
-        // Check if ExifTool is available
+        self.write_file(self.build_exiftool_command(privacy_level), input_path, output_path)
+    }
+
+    /// Remove every metadata tag from an image, for files whose metadata can't be parsed
+    pub fn remove_all_metadata(
+        &self,
+        input_path: &Path,
+        output_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.write_file(self.build_strip_all_command(), input_path, output_path)
+    }
+
+    /// Save all of an image's metadata to a sidecar file
+    ///
+    /// JSON sidecars list every tag by group, with binary values base64-encoded,
+    /// so `exiftool -j=` can write them back. XMP sidecars hold the tags that
+    /// have an XMP equivalent.
+    pub fn export_metadata(
+        &self,
+        input_path: &Path,
+        sidecar_path: &Path,
+        format: &ExportFormat,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         self.check_exiftool_availability()?;
+        let mut cmd = self.base_command();
+
+        match format {
+            ExportFormat::Json => {
+                cmd.arg("-json").arg("-G1").arg("-a").arg("-b").arg("-struct").arg(input_path);
+                let output = self.run(cmd, None, &[])?;
+                if !output.status.success() {
+                    return Err(format!("ExifTool failed: {}", String::from_utf8_lossy(&output.stderr)).into());
+                }
+                Ok(utils::write_atomically(sidecar_path, &output.stdout)?)
+            }
+            ExportFormat::Xmp => {
+                // ExifTool updates an existing XMP file instead of creating a new one
+                match std::fs::remove_file(utils::long_path(sidecar_path)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                    _ => {}
+                }
+                cmd.arg("-tagsFromFile").arg(input_path).arg(sidecar_path);
+                let output = self.run(cmd, None, &[parent_dir(sidecar_path)])?;
+                if !output.status.success() {
+                    return Err(format!("ExifTool failed: {}", String::from_utf8_lossy(&output.stderr)).into());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Write a sidecar saved by `export_metadata` back into an image
+    ///
+    /// The sidecar's format is taken from its extension (`.json` or `.xmp`).
+    /// Tags the sidecar holds replace those in the image; others are left alone.
+    pub fn apply_metadata(
+        &self,
+        sidecar_path: &Path,
+        input_path: &Path,
+        output_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if formats::detect_image_format(input_path) == Some(ImageFormat::Bmp) {
+            return Err("BMP files can't hold EXIF or XMP metadata".into());
+        }
+        self.check_exiftool_availability()?;
+        let mut cmd = self.base_command();
+        cmd.arg("-m");
+
+        match utils::get_file_extension(sidecar_path).as_deref() {
+            Some("json") => {
+                let json = prepare_json_sidecar(&std::fs::read(utils::long_path(sidecar_path))?)?;
+                // Removed when dropped, after ExifTool has read it
+                let mut prepared = tempfile::NamedTempFile::new()?;
+                prepared.write_all(&json)?;
+                cmd.arg(format!("-json={}", prepared.path().display()));
+                self.write_file(cmd, input_path, output_path)
+            }
+            Some("xmp") => {
+                // Without a tag list, tags go to their preferred group: EXIF tags back into EXIF
+                cmd.arg("-tagsFromFile").arg(sidecar_path);
+                self.write_file(cmd, input_path, output_path)
+            }
+            _ => Err(format!("Unknown sidecar format '{}' (expected .json or .xmp)", sidecar_path.display()).into()),
+        }
+    }
+
+    /// Run an ExifTool write command on a file
+    fn write_file(
+        &self,
+        mut cmd: Command,
+        input_path: &Path,
+        output_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if formats::detect_image_format(input_path) == Some(ImageFormat::Bmp) {
+            let data = std::fs::read(utils::long_path(input_path))?;
+            return Ok(utils::write_atomically(output_path, &clean_bmp(&data))?);
+        }
+
+        // Check if ExifTool is available; a stay-open process reports that itself when it starts
+        if !self.stays_open() {
+            self.check_exiftool_availability()?;
+        }
 
-        // Build and execute the ExifTool command
-        let mut cmd = self.build_exiftool_command(privacy_level);
-        
         // Configure input/output
         if input_path != output_path {
             // Writing to different file
@@ -33,124 +193,252 @@ impl MetadataRemover {
 
         cmd.arg(input_path);
 
+        // Where a killed process would leave a half-written file
+        let partial = if input_path != output_path {
+            output_path.to_path_buf()
+        } else {
+            exiftool_temp_path(input_path)
+        };
+        let partial_existed = partial.exists();
+
         // Execute the command
-        let output = cmd.output()?;
+        let result = match argument_file_contents(&cmd).filter(|_| self.stays_open()) {
+            Some(args) => stayopen::execute(&args, self.timeout)
+                .map(|response| (!response.failed(), response.stderr)),
+            None => self.run(cmd, None, &[parent_dir(&partial)])
+                .map(|output| (output.status.success(), output.stderr)),
+        };
+        let (succeeded, stderr) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                if !partial_existed {
+                    let _ = std::fs::remove_file(&partial);
+                }
+                return Err(e);
+            }
+        };
         
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(format!("ExifTool failed: {}", stderr).into());
+        if !succeeded {
+            return Err(write_error(&stderr));
         }
 
         Ok(())
     }
 
+    /// Whether file writes go through a stay-open process rather than one process each
+    fn stays_open(&self) -> bool {
+        self.stay_open && !self.sandbox
+    }
+
+    /// Remove privacy data from an in-memory image, returning the cleaned bytes
+    ///
+    /// ExifTool reads the image from stdin and writes the result to stdout, so
+    /// nothing touches the filesystem.
+    pub fn remove_privacy_data_from_bytes(
+        &self,
+        data: &[u8],
+        privacy_level: &PrivacyLevel,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.write_bytes(self.build_exiftool_command(privacy_level), data)
+    }
+
+    /// Remove every metadata tag from an in-memory image, returning the stripped bytes
+    pub fn remove_all_metadata_from_bytes(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        self.write_bytes(self.build_strip_all_command(), data)
+    }
+
+    /// Run an ExifTool write command over stdin/stdout
+    fn write_bytes(&self, mut cmd: Command, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        if bmp::is_bmp(data) {
+            return Ok(clean_bmp(data));
+        }
+        self.check_exiftool_availability()?;
+
+        cmd.arg("-o").arg("-").arg("-");
+
+        let output = self.run(cmd, Some(data), &[])?;
+
+        if !output.status.success() {
+            return Err(write_error(&output.stderr));
+        }
+
+        Ok(output.stdout)
+    }
+
+    /// Run ExifTool to completion, killing it if it exceeds the timeout
+    ///
+    /// Stdin is fed and stdout/stderr are drained on separate threads so a
+    /// full pipe can't deadlock us while we wait. When sandboxed, ExifTool may
+    /// only write beneath the `writable` directories.
+    fn run(&self, cmd: Command, input: Option<&[u8]>, writable: &[PathBuf]) -> Result<Output, Box<dyn std::error::Error>> {
+        // Windows passes one command line that each program splits itself, so arguments go
+        // through an ExifTool argument file instead, which reaches ExifTool verbatim
+        let (mut cmd, _argument_file) = match argument_file_contents(&cmd) {
+            Some(contents) if cfg!(windows) => {
+                let file = ArgumentFile::write(&contents)?;
+                let mut wrapped = Command::new(cmd.get_program());
+                wrapped.arg("-@").arg(&file.0);
+                (wrapped, Some(file))
+            }
+            _ => (cmd, None),
+        };
+        cmd.stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+           .stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+
+        let (mut child, _sandbox) = if self.sandbox {
+            let (child, attached) = sandbox::spawn(cmd, writable)?;
+            (child, Some(attached))
+        } else {
+            (cmd.spawn()?, None)
+        };
+
+        let writer = match input {
+            Some(data) => {
+                let mut stdin = child.stdin.take().ok_or("Failed to open ExifTool stdin")?;
+                let input = data.to_vec();
+                Some(thread::spawn(move || stdin.write_all(&input)))
+            }
+            None => None,
+        };
+        let stdout = drain(child.stdout.take());
+        let stderr = drain(child.stderr.take());
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break status;
+            }
+            if Instant::now() >= deadline {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(format!("ExifTool timed out after {}s and was killed", self.timeout.as_secs_f64()).into());
+            }
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        if let Some(writer) = writer {
+            writer.join().map_err(|_| "ExifTool stdin writer panicked")??;
+        }
+
+        Ok(Output {
+            status,
+            stdout: stdout.join().map_err(|_| "ExifTool stdout reader panicked")??,
+            stderr: stderr.join().map_err(|_| "ExifTool stderr reader panicked")??,
+        })
+    }
+
     /// Check if ExifTool is installed and accessible
     fn check_exiftool_availability(&self) -> Result<(), Box<dyn std::error::Error>> {
-        let output = Command::new("exiftool")
+        let output = capabilities::command()
             .arg("-ver")
             .output();
 
         match output {
             Ok(output) if output.status.success() => Ok(()),
             Ok(_) => Err("ExifTool found but returned error".into()),
-            Err(_) => Err("ExifTool not found. Please install ExifTool and ensure it's in your PATH".into()),
+            Err(_) => Err("ExifTool not found. Install it and ensure it's in your PATH, or pass --exiftool".into()),
         }
     }
 
-    /// Build the ExifTool command based on privacy level
-    fn build_exiftool_command(&self, privacy_level: &PrivacyLevel) -> Command {
-        let mut cmd = Command::new("exiftool");
-        
-        match privacy_level {
-            PrivacyLevel::Minimal => {
-                self.add_minimal_removal_args(&mut cmd);
-            }
-            PrivacyLevel::Standard => {
-                self.add_standard_removal_args(&mut cmd);
-            }
-            PrivacyLevel::Strict => {
-                self.add_strict_removal_args(&mut cmd);
+    /// Start an ExifTool command with the options every invocation needs
+    fn base_command(&self) -> Command {
+        let mut cmd = capabilities::command();
+
+        // Without these, ExifTool on Windows can't open non-ASCII or >260-character file names.
+        // Older releases ignore them, so they're only passed to releases that support them.
+        if cfg!(windows) {
+            if capabilities::supports(Feature::FileNameCharset) {
+                cmd.arg("-charset").arg("filename=utf8");
             }
-            PrivacyLevel::Paranoid => {
-                self.add_paranoid_removal_args(&mut cmd);
+            if capabilities::supports(Feature::WindowsLongPath) {
+                cmd.arg("-api").arg("WindowsLongPath=1");
             }
         }
 
         cmd
     }
 
-    /// Add arguments for minimal privacy (GPS only)
-    fn add_minimal_removal_args(&self, cmd: &mut Command) {
-        cmd.arg("-gps:all=");
+    /// Build the ExifTool command that removes all metadata
+    fn build_strip_all_command(&self) -> Command {
+        let mut cmd = self.base_command();
+        // -m lets ExifTool rewrite files with minor metadata errors instead of refusing them
+        cmd.arg("-m").arg("-all=");
+        cmd
     }
 
-    /// Add arguments for standard privacy
-    fn add_standard_removal_args(&self, cmd: &mut Command) {
-        cmd.arg("-gps:all=")
-           .arg("-SerialNumber=")
-           .arg("-InternalSerialNumber=")
-           .arg("-LensSerialNumber=")
-           .arg("-CameraOwnerName=")
-           .arg("-Artist=")
-           .arg("-Copyright=")
-           .arg("-UserComment=");
-    }
+    /// Build the ExifTool command from the privacy level's policy spec and the tag overrides
+    fn build_exiftool_command(&self, privacy_level: &PrivacyLevel) -> Command {
+        let spec = self.overrides.apply(PrivacyPolicy::spec(privacy_level));
+        let scoped = self.overrides.scoped_tags();
+        // Scoped exceptions only exist for TIFF tags, which live in IFD0 or IFD1
+        let removed_in = |tag: exif::Tag| -> Vec<Ifd> {
+            [Ifd::Ifd0, Ifd::Ifd1].into_iter()
+                .filter(|ifd| !self.overrides.should_preserve_tag_in(tag, *ifd, privacy_level))
+                .collect()
+        };
+        let mut cmd = self.base_command();
 
-    /// Add arguments for strict privacy
-    fn add_strict_removal_args(&self, cmd: &mut Command) {
-        // Include all standard removals
-        self.add_standard_removal_args(cmd);
-        
-        // Add additional strict removals
-        cmd.arg("-DateTime=")
-           .arg("-DateTimeOriginal=")
-           .arg("-DateTimeDigitized=")
-           .arg("-Software=")
-           .arg("-ProcessingSoftware=")
-           .arg("-HostComputer=")
-           .arg("-ImageDescription=")
-           .arg("-XMP:all=")
-           .arg("-IPTC:all=");
-    }
-
-    /// Add arguments for paranoid privacy (preserve only essential camera settings)
-    fn add_paranoid_removal_args(&self, cmd: &mut Command) {
-        // Remove all EXIF data first
-        cmd.arg("-all=");
-
-        // Then restore only essential camera settings
-        cmd.arg("-TagsFromFile").arg("@")
-           .arg("-ExposureTime")
-           .arg("-FNumber")
-           .arg("-ISO")
-           .arg("-ISOSpeedRatings")
-           .arg("-FocalLength")
-           .arg("-FocalLengthIn35mmFilm")
-           .arg("-ExposureProgram")
-           .arg("-MeteringMode")
-           .arg("-Flash")
-           .arg("-ColorSpace")
-           .arg("-WhiteBalance")
-           .arg("-ExposureMode")
-           .arg("-SceneCaptureType")
-           .arg("-Contrast")
-           .arg("-Saturation")
-           .arg("-Sharpness")
-           .arg("-Make")
-           .arg("-Model")
-           .arg("-Orientation")
-           .arg("-XResolution")
-           .arg("-YResolution")
-           .arg("-ResolutionUnit")
-           .arg("-ExifVersion")
-           .arg("-ComponentsConfiguration")
-           .arg("-PixelXDimension")
-           .arg("-PixelYDimension");
+        match spec.keep_only {
+            Some(ref keep) => {
+                // Remove everything, then restore only the allowed tags
+                cmd.arg("-all=");
+                cmd.arg("-TagsFromFile").arg("@");
+                let mut restored: Vec<String> = keep.iter()
+                    .filter(|tag| !scoped.contains(tag))
+                    .map(|tag| PrivacyPolicy::exiftool_name(*tag))
+                    .collect();
+                restored.dedup();
+                for name in restored {
+                    cmd.arg(format!("-{}", name));
+                }
+                for tag in &scoped {
+                    let name = PrivacyPolicy::exiftool_name(*tag);
+                    let removed = removed_in(*tag);
+                    for ifd in [Ifd::Ifd0, Ifd::Ifd1].into_iter().filter(|ifd| !removed.contains(ifd)) {
+                        let group = ifd.exiftool_group();
+                        cmd.arg(format!("-{}:{}<{}:{}", group, name, group, name));
+                    }
+                }
+                for group in &spec.keep_groups {
+                    cmd.arg(format!("-{}:all", group));
+                }
+            }
+            None => {
+                if self.unknown_tags.removes(privacy_level) {
+                    // ExifTool copies only tags it knows, so rebuilding the EXIF data drops the unknown ones
+                    cmd.arg("-EXIF:all=");
+                    cmd.arg("-TagsFromFile").arg("@");
+                    cmd.arg("-EXIF:all").arg("-MakerNotes").arg("-unsafe");
+                }
+                for group in &spec.remove_groups {
+                    cmd.arg(format!("-{}:all=", group));
+                }
+                // `-gps:all=` clears the whole GPS IFD, including tags this list doesn't name
+                let clears_gps = spec.remove_groups.contains(&"gps");
+                for tag in &spec.remove {
+                    if (clears_gps && tag.context() == exif::Context::Gps) || scoped.contains(tag) {
+                        continue;
+                    }
+                    let name = PrivacyPolicy::exiftool_name(*tag);
+                    if capabilities::can_write(&name) {
+                        cmd.arg(format!("-{}=", name));
+                    }
+                }
+                for tag in &scoped {
+                    for ifd in removed_in(*tag) {
+                        cmd.arg(format!("-{}:{}=", ifd.exiftool_group(), PrivacyPolicy::exiftool_name(*tag)));
+                    }
+                }
+            }
+        }
+
+        cmd
     }
 
     /// Get the ExifTool version (for diagnostics)
     pub fn get_exiftool_version(&self) -> Result<String, Box<dyn std::error::Error>> {
-        let output = Command::new("exiftool")
+        let output = capabilities::command()
             .arg("-ver")
             .output()?;
 
@@ -163,7 +451,7 @@ impl MetadataRemover {
 
     /// Test ExifTool with a simple operation (for validation)
     pub fn test_exiftool_operation(&self, test_file: &Path) -> Result<(), Box<dyn std::error::Error>> {
-        let output = Command::new("exiftool")
+        let output = capabilities::command()
             .arg("-j")  // JSON output
             .arg("-q")  // Quiet mode
             .arg(test_file)
@@ -178,6 +466,119 @@ impl MetadataRemover {
     }
 }
 
+/// ExifTool refused to rewrite an image because its structure is damaged
+#[derive(Debug)]
+pub struct CorruptStructure(pub String);
+
+impl std::fmt::Display for CorruptStructure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Image structure is corrupt: {}", self.0)
+    }
+}
+
+impl std::error::Error for CorruptStructure {}
+
+/// The error for a failed ExifTool write, from what it printed to stderr
+///
+/// Damaged files are told apart from other failures (a missing output
+/// directory, a full disk) by ExifTool's messages for them.
+fn write_error(stderr: &[u8]) -> Box<dyn std::error::Error> {
+    const DAMAGED: &[&str] = &["corrupt", "format error", "truncated", "bad format", "bad ifd"];
+    let stderr = String::from_utf8_lossy(stderr);
+    let lower = stderr.to_lowercase();
+    if DAMAGED.iter().any(|message| lower.contains(message)) {
+        Box::new(CorruptStructure(stderr.trim().to_string()))
+    } else {
+        format!("ExifTool failed: {}", stderr).into()
+    }
+}
+
+/// A command's arguments as an ExifTool argument file, one per line
+///
+/// `None` when an argument can't be represented: ExifTool trims each line,
+/// skips lines starting with `#` as comments, and a line can't hold a line break.
+fn argument_file_contents(cmd: &Command) -> Option<String> {
+    let mut contents = String::new();
+    for arg in cmd.get_args() {
+        let arg = arg.to_str()?;
+        if arg.contains(['\n', '\r']) || arg.trim() != arg || arg.starts_with('#') {
+            return None;
+        }
+        contents.push_str(arg);
+        contents.push('\n');
+    }
+    Some(contents)
+}
+
+/// A temporary ExifTool argument file, deleted when dropped
+struct ArgumentFile(PathBuf);
+
+impl ArgumentFile {
+    fn write(contents: &str) -> std::io::Result<Self> {
+        static NEXT: AtomicU64 = AtomicU64::new(0);
+        let name = format!("privacy-exif-cleaner-{}-{}.args", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed));
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, contents)?;
+        Ok(ArgumentFile(path))
+    }
+}
+
+impl Drop for ArgumentFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// The directory holding `path`, for the sandbox's writable set
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Read a child's output pipe to the end on a background thread
+fn drain<R: Read + Send + 'static>(pipe: Option<R>) -> JoinHandle<std::io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buf)?;
+        }
+        Ok(buf)
+    })
+}
+
+/// An exported JSON sidecar, ready for `exiftool -json=`
+///
+/// ExifTool only imports entries whose `SourceFile` matches the file being
+/// written, which a sidecar of the original never does; `*` matches any file.
+/// File-system tags are dropped.
+fn prepare_json_sidecar(json: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut entries: Vec<serde_json::Map<String, serde_json::Value>> = serde_json::from_slice(json)
+        .map_err(|e| format!("Not an exported metadata sidecar: {}", e))?;
+    for entry in &mut entries {
+        entry.retain(|key, _| {
+            key.split_once(':').map_or(true, |(group, _)| !FILE_GROUPS.contains(&group))
+        });
+        entry.insert("SourceFile".to_string(), serde_json::Value::from("*"));
+    }
+    Ok(serde_json::to_vec(&entries)?)
+}
+
+/// Temporary file ExifTool writes next to the original during `-overwrite_original`
+fn exiftool_temp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push("_exiftool_tmp");
+    PathBuf::from(name)
+}
+
+/// ExifTool can't write BMP; the only metadata a BMP carries, a linked profile path, is blanked here
+fn clean_bmp(data: &[u8]) -> Vec<u8> {
+    let mut cleaned = data.to_vec();
+    bmp::remove_linked_profile(&mut cleaned);
+    cleaned
+}
+
 impl Default for MetadataRemover {
     fn default() -> Self {
         Self::new()
@@ -188,6 +589,8 @@ impl Default for MetadataRemover {
 mod tests {
     use super::*;
     use std::ffi::OsStr;
+    use clap::ValueEnum;
+    use crate::privacy::GpsGroup;
 
     #[test]
     fn test_minimal_command_building() {
@@ -199,6 +602,16 @@ mod tests {
         assert!(cmd_str.contains("-gps:all="));
     }
 
+    #[test]
+    fn test_strip_all_command_building() {
+        let remover = MetadataRemover::new();
+        let cmd = remover.build_strip_all_command();
+
+        let cmd_str = format!("{:?}", cmd);
+        assert!(cmd_str.contains("-all="));
+        assert!(cmd_str.contains("-m"));
+    }
+
     #[test]
     fn test_standard_command_building() {
         let remover = MetadataRemover::new();
@@ -217,8 +630,9 @@ mod tests {
         
         let cmd_str = format!("{:?}", cmd);
         assert!(cmd_str.contains("-gps:all="));
-        assert!(cmd_str.contains("-DateTime="));
+        assert!(cmd_str.contains("-ModifyDate="));
         assert!(cmd_str.contains("-Software="));
+        assert!(cmd_str.contains("-XPAuthor="));
         assert!(cmd_str.contains("-XMP:all="));
     }
 
@@ -234,6 +648,165 @@ mod tests {
         assert!(cmd_str.contains("-FNumber"));
     }
 
+    #[test]
+    fn test_overrides_command_building() {
+        let overrides = PolicyOverride::new(vec![exif::Tag::DateTimeOriginal], vec![exif::Tag::LensModel]).unwrap();
+        let remover = MetadataRemover::new().with_overrides(overrides);
+        let cmd = remover.build_exiftool_command(&PrivacyLevel::Strict);
+
+        let args: Vec<&OsStr> = cmd.get_args().collect();
+        assert!(!args.contains(&OsStr::new("-DateTimeOriginal=")));
+        assert!(args.contains(&OsStr::new("-ModifyDate=")));
+        assert!(args.contains(&OsStr::new("-LensModel=")));
+
+        // Allowlist levels restore kept tags instead
+        let overrides = PolicyOverride::new(vec![exif::Tag::DateTimeOriginal], vec![]).unwrap();
+        let cmd = MetadataRemover::new().with_overrides(overrides).build_exiftool_command(&PrivacyLevel::Paranoid);
+        assert!(cmd.get_args().any(|arg| arg == "-DateTimeOriginal"));
+    }
+
+    #[test]
+    fn test_unknown_tags_command_building() {
+        let has_rebuild = |remover: &MetadataRemover, level| {
+            remover.build_exiftool_command(level).get_args().any(|arg| arg == "-EXIF:all=")
+        };
+
+        assert!(!has_rebuild(&MetadataRemover::new(), &PrivacyLevel::Strict));
+        let remover = MetadataRemover::new().with_unknown_tags(UnknownTags::RemoveAboveStrict);
+        assert!(!has_rebuild(&remover, &PrivacyLevel::Standard));
+        assert!(has_rebuild(&remover, &PrivacyLevel::Strict));
+
+        // Privacy data is still removed after the rebuild copies it back
+        let command = remover.build_exiftool_command(&PrivacyLevel::Strict);
+        let args: Vec<&OsStr> = command.get_args().collect();
+        let rebuild = args.iter().position(|arg| *arg == "-EXIF:all").unwrap();
+        let artist = args.iter().position(|arg| *arg == "-Artist=").unwrap();
+        assert!(rebuild < artist);
+    }
+
+    #[test]
+    fn test_scoped_overrides_command_building() {
+        let overrides = PolicyOverride::default()
+            .scoped(vec![(Ifd::Ifd0, exif::Tag::Software)], vec![])
+            .unwrap();
+        let remover = MetadataRemover::new().with_overrides(overrides.clone());
+        let cmd = remover.build_exiftool_command(&PrivacyLevel::Strict);
+        let args: Vec<&OsStr> = cmd.get_args().collect();
+        assert!(args.contains(&OsStr::new("-IFD1:Software=")));
+        assert!(!args.contains(&OsStr::new("-IFD0:Software=")));
+        assert!(!args.contains(&OsStr::new("-Software=")));
+
+        // Allowlist levels restore the tag from the kept IFD only
+        let cmd = remover.build_exiftool_command(&PrivacyLevel::WebShare);
+        let args: Vec<&OsStr> = cmd.get_args().collect();
+        assert!(args.contains(&OsStr::new("-IFD0:Software<IFD0:Software")));
+        assert!(!args.contains(&OsStr::new("-IFD1:Software<IFD1:Software")));
+    }
+
+    #[test]
+    fn test_partial_gps_command_building() {
+        let overrides = PolicyOverride::new(GpsGroup::Altitude.tags(), vec![]).unwrap();
+        let remover = MetadataRemover::new().with_overrides(overrides);
+        let cmd = remover.build_exiftool_command(&PrivacyLevel::Minimal);
+
+        let args: Vec<&OsStr> = cmd.get_args().collect();
+        assert!(!args.contains(&OsStr::new("-gps:all=")));
+        assert!(args.contains(&OsStr::new("-GPSLatitude=")));
+        assert!(!args.contains(&OsStr::new("-GPSAltitude=")));
+    }
+
+    #[test]
+    fn test_web_share_command_building() {
+        let remover = MetadataRemover::new();
+        let cmd = remover.build_exiftool_command(&PrivacyLevel::WebShare);
+
+        let cmd_str = format!("{:?}", cmd);
+        assert!(cmd_str.contains("-all="));
+        assert!(cmd_str.contains("-Orientation"));
+        assert!(cmd_str.contains("-ICC_Profile:all"));
+        assert!(!cmd_str.contains("-Make"));
+    }
+
+    #[test]
+    fn test_remover_matches_analyzer_policy() {
+        let remover = MetadataRemover::new();
+
+        for level in PrivacyLevel::value_variants() {
+            let args: Vec<String> = remover.build_exiftool_command(level).get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect();
+            let has = |arg: String| args.contains(&arg);
+
+            for (_, tags) in PrivacyPolicy::tag_groups() {
+                for tag in tags {
+                    let name = PrivacyPolicy::exiftool_name(tag);
+                    let removed = if has("-all=".to_string()) {
+                        !has(format!("-{}", name))
+                    } else {
+                        has(format!("-{}=", name))
+                            || (tag.context() == exif::Context::Gps && has("-gps:all=".to_string()))
+                    };
+                    assert_eq!(
+                        removed,
+                        !PrivacyPolicy::should_preserve_tag(tag, level),
+                        "{:?}: analyzer and remover disagree about {}", level, tag
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_json_sidecar_applies_to_any_file_without_file_tags() {
+        let exported = br#"[{"SourceFile": "photos/IMG_0001.jpg", "System:FileName": "IMG_0001.jpg",
+            "System:Directory": "photos", "Composite:GPSPosition": "48 deg N", "IFD0:Artist": "Jane Doe",
+            "GPS:GPSLatitude": 48.8584}]"#;
+
+        let prepared: serde_json::Value = serde_json::from_slice(&prepare_json_sidecar(exported).unwrap()).unwrap();
+        assert_eq!(prepared, serde_json::json!([{
+            "SourceFile": "*",
+            "IFD0:Artist": "Jane Doe",
+            "GPS:GPSLatitude": 48.8584,
+        }]));
+        assert!(prepare_json_sidecar(b"not json").is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_hung_process_is_killed() {
+        let remover = MetadataRemover::new().with_timeout(Duration::from_millis(200));
+        let mut cmd = Command::new("sleep");
+        cmd.arg("10");
+
+        let started = Instant::now();
+        let err = remover.run(cmd, None, &[]).unwrap_err();
+
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_collects_output() {
+        let remover = MetadataRemover::new();
+        let output = remover.run(Command::new("cat"), Some(b"image bytes"), &[]).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"image bytes");
+    }
+
+    #[test]
+    fn test_write_error_tells_damage_apart() {
+        assert!(write_error(b"Error: Corrupted JPEG image - photo.jpg\n").is::<CorruptStructure>());
+        assert!(write_error(b"Error: Bad format (0) for IFD0 entry 3 - scan.tif\n").is::<CorruptStructure>());
+        assert!(!write_error(b"Error creating file - out/photo.jpg\n").is::<CorruptStructure>());
+    }
+
+    #[test]
+    fn test_exiftool_temp_path() {
+        assert_eq!(exiftool_temp_path(Path::new("/a/b.jpg")), PathBuf::from("/a/b.jpg_exiftool_tmp"));
+    }
+
     #[test]
     fn test_exiftool_availability_check() {
         let remover = MetadataRemover::new();
@@ -265,4 +838,16 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_argument_file_contents() {
+        let mut cmd = Command::new("exiftool");
+        cmd.arg("-Artist=").arg("C:\\Photos\\Été \"2024\"\\photo.jpg");
+        assert_eq!(argument_file_contents(&cmd).unwrap(), "-Artist=\nC:\\Photos\\Été \"2024\"\\photo.jpg\n");
+
+        // Lines are trimmed and can't hold line breaks, so these stay on the command line
+        cmd.arg("trailing space ");
+        assert_eq!(argument_file_contents(&cmd), None);
+        assert_eq!(argument_file_contents(Command::new("exiftool").arg("two\nlines")), None);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,832 @@
+//! Library-wide reports built from analysis results.
+//!
+//! The compliance report maps EXIF findings onto personal-data categories in
+//! the spirit of a GDPR data-protection impact assessment: how many files
+//! disclose location, identifiers, or free text, and which files are worst.
+//!
+//! The GPS cluster report looks across files for places that recur, which
+//! reveals likely home and work locations even when each photo alone seems
+//! harmless.
+//!
+//! The device report links files that share serial numbers or other device
+//! identifiers, showing which images can be attributed to the same camera.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use exif::{In, Tag, Value};
+use serde::Serialize;
+use walkdir::WalkDir;
+use crate::analyzer::{ExifAnalyzer, PrivacyCategory, PrivacyField, UnparseableMetadata};
+use crate::formats;
+use crate::geo::{self, Coordinates};
+use crate::privacy::PrivacyLevel;
+use tracing::warn;
+
+/// How many files to list as worst offenders
+const WORST_OFFENDER_LIMIT: usize = 10;
+/// Clusters with fewer photos than this are not reported
+const MIN_CLUSTER_SIZE: usize = 3;
+/// How many example files to list per cluster
+const CLUSTER_SAMPLE_LIMIT: usize = 5;
+
+/// Personal-data categories used in compliance reporting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataCategory {
+    Location,
+    Identifiers,
+    FreeText,
+    Temporal,
+    Technical,
+}
+
+impl DataCategory {
+    /// Map an analyzer category to the personal-data category it discloses
+    pub fn from_privacy_category(category: &PrivacyCategory) -> Self {
+        match category {
+            PrivacyCategory::Location => DataCategory::Location,
+            PrivacyCategory::DeviceIdentifier => DataCategory::Identifiers,
+            PrivacyCategory::PersonalInfo => DataCategory::FreeText,
+            PrivacyCategory::Temporal => DataCategory::Temporal,
+            PrivacyCategory::Software | PrivacyCategory::Metadata | PrivacyCategory::Other => DataCategory::Technical,
+        }
+    }
+
+    /// Short explanation of why the category matters for data protection
+    pub fn description(&self) -> &'static str {
+        match self {
+            DataCategory::Location => "Location data that can place a person at a time and place",
+            DataCategory::Identifiers => "Device identifiers that link images to a specific device or owner",
+            DataCategory::FreeText => "Names, copyright notices, and comments that may identify people",
+            DataCategory::Temporal => "Timestamps revealing when a person was present",
+            DataCategory::Technical => "Software and descriptive metadata with indirect identifying value",
+        }
+    }
+}
+
+impl std::fmt::Display for DataCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DataCategory::Location => write!(f, "Location"),
+            DataCategory::Identifiers => write!(f, "Identifiers"),
+            DataCategory::FreeText => write!(f, "Free text"),
+            DataCategory::Temporal => write!(f, "Temporal"),
+            DataCategory::Technical => write!(f, "Technical"),
+        }
+    }
+}
+
+/// Totals for one personal-data category
+#[derive(Debug, Clone, Serialize)]
+pub struct CategoryFindings {
+    pub category: DataCategory,
+    pub description: &'static str,
+    pub files: usize,
+    pub fields: usize,
+}
+
+/// Findings for one file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileFindings {
+    pub path: String,
+    pub fields: usize,
+    pub categories: Vec<DataCategory>,
+}
+
+/// Aggregated compliance report for a set of images
+#[derive(Debug, Clone, Serialize)]
+pub struct ComplianceReport {
+    pub generated_at: u64,
+    pub source: String,
+    pub privacy_level: String,
+    pub files_scanned: usize,
+    pub files_with_findings: usize,
+    pub categories: Vec<CategoryFindings>,
+    pub worst_offenders: Vec<FileFindings>,
+    /// Files whose metadata couldn't be parsed, so their contents are unknown
+    pub unparseable: Vec<String>,
+    /// Files or directory entries that couldn't be read and are left out
+    pub unreadable: usize,
+}
+
+impl ComplianceReport {
+    /// Build a report from per-file analysis results
+    pub fn from_findings<'a, I>(source: &str, privacy_level: &PrivacyLevel, findings: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a Path, &'a [PrivacyField])>,
+    {
+        let mut totals: BTreeMap<DataCategory, (usize, usize)> = BTreeMap::new();
+        let mut files = Vec::new();
+        let mut files_scanned = 0;
+
+        for (path, fields) in findings {
+            files_scanned += 1;
+            if fields.is_empty() {
+                continue;
+            }
+
+            let mut categories: Vec<DataCategory> = fields.iter()
+                .map(|field| DataCategory::from_privacy_category(&field.category))
+                .collect();
+            for category in &categories {
+                totals.entry(*category).or_default().1 += 1;
+            }
+            categories.sort();
+            categories.dedup();
+            for category in &categories {
+                totals.entry(*category).or_default().0 += 1;
+            }
+
+            files.push(FileFindings {
+                path: path.display().to_string(),
+                fields: fields.len(),
+                categories,
+            });
+        }
+
+        let files_with_findings = files.len();
+
+        // Worst offenders: most sensitive categories first, then most fields
+        files.sort_by(|a, b| {
+            b.categories.contains(&DataCategory::Location).cmp(&a.categories.contains(&DataCategory::Location))
+                .then(b.categories.len().cmp(&a.categories.len()))
+                .then(b.fields.cmp(&a.fields))
+                .then(a.path.cmp(&b.path))
+        });
+        files.truncate(WORST_OFFENDER_LIMIT);
+
+        ComplianceReport {
+            generated_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            source: source.to_string(),
+            privacy_level: format!("{:?}", privacy_level),
+            files_scanned,
+            files_with_findings,
+            categories: totals.into_iter()
+                .map(|(category, (files, fields))| CategoryFindings {
+                    category,
+                    description: category.description(),
+                    files,
+                    fields,
+                })
+                .collect(),
+            worst_offenders: files,
+            unparseable: Vec::new(),
+            unreadable: 0,
+        }
+    }
+
+    /// Render the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Render the report as a standalone HTML document
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str(&format!(
+            "<p>Source: <code>{}</code><br>Privacy level: {}<br>Files scanned: {}<br>Files with personal data: {}</p>\n",
+            escape_html(&self.source), escape_html(&self.privacy_level), self.files_scanned, self.files_with_findings
+        ));
+
+        html.push_str("<h2>Personal-data categories</h2>\n<table>\n<tr><th>Category</th><th>Description</th><th>Files</th><th>Fields</th></tr>\n");
+        for finding in &self.categories {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                finding.category, escape_html(finding.description), finding.files, finding.fields
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Worst offenders</h2>\n<table>\n<tr><th>File</th><th>Fields</th><th>Categories</th></tr>\n");
+        for file in &self.worst_offenders {
+            let categories: Vec<String> = file.categories.iter().map(|c| c.to_string()).collect();
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&file.path), file.fields, categories.join(", ")
+            ));
+        }
+        html.push_str("</table>\n");
+
+        if !self.unparseable.is_empty() {
+            html.push_str("<h2>Unparseable metadata</h2>\n<p>These files could not be analyzed and may contain any kind of personal data.</p>\n<ul>\n");
+            for path in &self.unparseable {
+                html.push_str(&format!("<li>{}</li>\n", escape_html(path)));
+            }
+            html.push_str("</ul>\n");
+        }
+
+        html_document("Image Metadata Compliance Report", &html)
+    }
+}
+
+/// Time-of-week a photo was taken, for home/work heuristics
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CaptureMoment {
+    pub hour: u32,
+    /// 0 = Sunday .. 6 = Saturday
+    pub weekday: u32,
+}
+
+impl CaptureMoment {
+    /// Parse an EXIF `YYYY:MM:DD HH:MM:SS` timestamp
+    pub fn parse(timestamp: &str) -> Option<Self> {
+        let (date, time) = timestamp.trim().split_once(' ')?;
+        let mut date_parts = date.split([':', '-']).map(|p| p.parse::<u32>().ok());
+        let (year, month, day) = (date_parts.next()??, date_parts.next()??, date_parts.next()??);
+        let hour = time.split(':').next()?.parse::<u32>().ok()?;
+
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || year == 0 {
+            return None;
+        }
+
+        Some(Self { hour, weekday: day_of_week(year, month, day) })
+    }
+
+    /// Evenings, nights, and weekends: when people tend to be at home
+    pub fn is_home_time(&self) -> bool {
+        self.weekday == 0 || self.weekday == 6 || self.hour >= 20 || self.hour < 7
+    }
+
+    /// Weekday office hours
+    pub fn is_work_time(&self) -> bool {
+        (1..=5).contains(&self.weekday) && (9..17).contains(&self.hour)
+    }
+}
+
+/// One photo with GPS data
+#[derive(Debug, Clone)]
+pub struct GpsObservation {
+    pub path: PathBuf,
+    pub coords: Coordinates,
+    pub captured: Option<CaptureMoment>,
+}
+
+/// What a recurring location most likely is
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClusterLabel {
+    LikelyHome,
+    LikelyWork,
+    Frequent,
+}
+
+impl std::fmt::Display for ClusterLabel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClusterLabel::LikelyHome => write!(f, "Likely home"),
+            ClusterLabel::LikelyWork => write!(f, "Likely work"),
+            ClusterLabel::Frequent => write!(f, "Frequent location"),
+        }
+    }
+}
+
+/// A place that recurs across photos
+#[derive(Debug, Clone, Serialize)]
+pub struct LocationCluster {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub photos: usize,
+    pub label: ClusterLabel,
+    pub place: String,
+    pub home_time_photos: usize,
+    pub work_time_photos: usize,
+    pub sample_files: Vec<String>,
+}
+
+/// Recurring locations across a photo library
+#[derive(Debug, Clone, Serialize)]
+pub struct ClusterReport {
+    pub generated_at: u64,
+    pub source: String,
+    pub radius_m: f64,
+    pub files_scanned: usize,
+    pub files_with_gps: usize,
+    pub clusters: Vec<LocationCluster>,
+    /// Files or directory entries that couldn't be read and are left out
+    pub unreadable: usize,
+}
+
+impl ClusterReport {
+    /// Cluster observations that lie within `radius_m` of a cluster centre
+    pub fn from_observations(source: &str, radius_m: f64, files_scanned: usize, observations: &[GpsObservation]) -> Self {
+        let radius_km = radius_m / 1000.0;
+        let mut clusters: Vec<(Coordinates, Vec<&GpsObservation>)> = Vec::new();
+
+        for observation in observations {
+            let existing = clusters.iter_mut()
+                .find(|(centre, _)| centre.distance_km(observation.coords.latitude, observation.coords.longitude) <= radius_km);
+
+            match existing {
+                Some((centre, members)) => {
+                    members.push(observation);
+                    // Keep the centre at the running mean of its members
+                    let n = members.len() as f64;
+                    centre.latitude += (observation.coords.latitude - centre.latitude) / n;
+                    centre.longitude += (observation.coords.longitude - centre.longitude) / n;
+                }
+                None => clusters.push((observation.coords, vec![observation])),
+            }
+        }
+
+        let mut clusters: Vec<LocationCluster> = clusters.into_iter()
+            .filter(|(_, members)| members.len() >= MIN_CLUSTER_SIZE)
+            .map(|(centre, members)| LocationCluster {
+                latitude: centre.latitude,
+                longitude: centre.longitude,
+                photos: members.len(),
+                label: ClusterLabel::Frequent,
+                place: geo::describe_location(&centre).to_string(),
+                home_time_photos: members.iter().filter(|m| m.captured.map_or(false, |c| c.is_home_time())).count(),
+                work_time_photos: members.iter().filter(|m| m.captured.map_or(false, |c| c.is_work_time())).count(),
+                sample_files: members.iter().take(CLUSTER_SAMPLE_LIMIT).map(|m| m.path.display().to_string()).collect(),
+            })
+            .collect();
+        clusters.sort_by_key(|c| Reverse(c.photos));
+
+        label_cluster(&mut clusters, ClusterLabel::LikelyHome, |c| c.home_time_photos);
+        label_cluster(&mut clusters, ClusterLabel::LikelyWork, |c| c.work_time_photos);
+
+        ClusterReport {
+            generated_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            source: source.to_string(),
+            radius_m,
+            files_scanned,
+            files_with_gps: observations.len(),
+            clusters,
+            unreadable: 0,
+        }
+    }
+
+    /// Render the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Render the report as a standalone HTML document
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str(&format!(
+            "<p>Source: <code>{}</code><br>Files scanned: {}<br>Files with GPS data: {}<br>Cluster radius: {} m</p>\n",
+            escape_html(&self.source), self.files_scanned, self.files_with_gps, self.radius_m
+        ));
+
+        html.push_str("<table>\n<tr><th>Label</th><th>Place</th><th>Coordinates</th><th>Photos</th><th>Evenings/weekends</th><th>Office hours</th><th>Examples</th></tr>\n");
+        for cluster in &self.clusters {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{:.4}, {:.4}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                cluster.label, escape_html(&cluster.place), cluster.latitude, cluster.longitude,
+                cluster.photos, cluster.home_time_photos, cluster.work_time_photos,
+                escape_html(&cluster.sample_files.join(", "))
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html_document("Recurring Photo Locations", &html)
+    }
+}
+
+/// Give `label` to the unlabeled cluster with the highest non-zero score
+fn label_cluster(clusters: &mut [LocationCluster], label: ClusterLabel, score: impl Fn(&LocationCluster) -> usize) {
+    let best = clusters.iter_mut()
+        .filter(|c| c.label == ClusterLabel::Frequent && score(c) > 0)
+        .max_by_key(|c| score(c));
+
+    if let Some(cluster) = best {
+        cluster.label = label;
+    }
+}
+
+/// Files from one device: everything connected through shared identifiers
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkedDevice {
+    /// `Tag=value` pairs shared within the set
+    pub identifiers: Vec<String>,
+    /// Distinct make/model strings seen in the set
+    pub cameras: Vec<String>,
+    pub files: Vec<String>,
+}
+
+/// Which files can be linked to one another through device identifiers
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceReport {
+    pub generated_at: u64,
+    pub source: String,
+    pub files_scanned: usize,
+    pub files_with_identifiers: usize,
+    pub devices: Vec<LinkedDevice>,
+    /// Files or directory entries that couldn't be read and are left out
+    pub unreadable: usize,
+}
+
+/// Device identifiers found in one file
+#[derive(Debug, Clone)]
+pub struct DeviceObservation {
+    pub path: PathBuf,
+    pub camera: Option<String>,
+    /// `Tag=value` pairs
+    pub identifiers: Vec<String>,
+}
+
+impl DeviceReport {
+    /// Group files that share any identifier, directly or transitively
+    ///
+    /// A body serial seen with two different lens serials links all three sets
+    /// of photos, since an observer can make the same inference.
+    pub fn from_observations(source: &str, files_scanned: usize, observations: &[DeviceObservation]) -> Self {
+        let observations: Vec<&DeviceObservation> = observations.iter()
+            .filter(|o| !o.identifiers.is_empty())
+            .collect();
+
+        let mut parent: Vec<usize> = (0..observations.len()).collect();
+        let mut first_seen: HashMap<&str, usize> = HashMap::new();
+        for (index, observation) in observations.iter().enumerate() {
+            for identifier in &observation.identifiers {
+                match first_seen.get(identifier.as_str()) {
+                    Some(&other) => union(&mut parent, index, other),
+                    None => {
+                        first_seen.insert(identifier.as_str(), index);
+                    }
+                }
+            }
+        }
+
+        let mut groups: BTreeMap<usize, Vec<&DeviceObservation>> = BTreeMap::new();
+        for (index, observation) in observations.iter().enumerate() {
+            let root = find(&mut parent, index);
+            groups.entry(root).or_default().push(*observation);
+        }
+
+        let mut devices: Vec<LinkedDevice> = groups.into_values()
+            .map(|members| {
+                let identifiers: BTreeSet<&String> = members.iter().flat_map(|m| &m.identifiers).collect();
+                let cameras: BTreeSet<&String> = members.iter().filter_map(|m| m.camera.as_ref()).collect();
+                LinkedDevice {
+                    identifiers: identifiers.into_iter().cloned().collect(),
+                    cameras: cameras.into_iter().cloned().collect(),
+                    files: members.iter().map(|m| m.path.display().to_string()).collect(),
+                }
+            })
+            .collect();
+        devices.sort_by(|a, b| b.files.len().cmp(&a.files.len()).then(a.identifiers.cmp(&b.identifiers)));
+
+        DeviceReport {
+            generated_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            source: source.to_string(),
+            files_scanned,
+            files_with_identifiers: observations.len(),
+            devices,
+            unreadable: 0,
+        }
+    }
+
+    /// Render the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Render the report as a standalone HTML document
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str(&format!(
+            "<p>Source: <code>{}</code><br>Files scanned: {}<br>Files with device identifiers: {}<br>Distinct devices: {}</p>\n",
+            escape_html(&self.source), self.files_scanned, self.files_with_identifiers, self.devices.len()
+        ));
+
+        html.push_str("<table>\n<tr><th>Identifiers</th><th>Camera</th><th>Linked files</th></tr>\n");
+        for device in &self.devices {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{} ({})</td></tr>\n",
+                escape_html(&device.identifiers.join(", ")),
+                escape_html(&device.cameras.join(", ")),
+                device.files.len(),
+                escape_html(&device.files.join(", "))
+            ));
+        }
+        html.push_str("</table>\n");
+
+        html_document("Device Correlation Report", &html)
+    }
+}
+
+fn find(parent: &mut [usize], mut index: usize) -> usize {
+    while parent[index] != index {
+        parent[index] = parent[parent[index]];
+        index = parent[index];
+    }
+    index
+}
+
+fn union(parent: &mut [usize], a: usize, b: usize) {
+    let (root_a, root_b) = (find(parent, a), find(parent, b));
+    if root_a != root_b {
+        parent[root_a.max(root_b)] = root_a.min(root_b);
+    }
+}
+
+/// Day of week for a Gregorian date, 0 = Sunday (Sakamoto's method)
+fn day_of_week(year: u32, month: u32, day: u32) -> u32 {
+    const OFFSETS: [u32; 12] = [0, 3, 2, 5, 0, 3, 5, 1, 4, 6, 2, 4];
+    let year = if month < 3 { year - 1 } else { year };
+    (year + year / 4 - year / 100 + year / 400 + OFFSETS[(month - 1) as usize] + day) % 7
+}
+
+/// Analyze every supported image under a directory and build a compliance report
+pub fn scan_directory(
+    input_dir: &Path,
+    recursive: bool,
+    privacy_level: &PrivacyLevel,
+) -> Result<ComplianceReport, Box<dyn std::error::Error>> {
+    let analyzer = ExifAnalyzer::new();
+
+    let mut results = Vec::new();
+    let mut unparseable = Vec::new();
+    let mut unreadable = 0;
+    let mut unreadable_files = 0;
+    for path in readable_images(input_dir, recursive, &mut unreadable) {
+        match analyzer.analyze_file(&path, privacy_level) {
+            Ok(fields) => results.push((path, fields)),
+            Err(e) if e.is::<UnparseableMetadata>() => unparseable.push(path.display().to_string()),
+            Err(e) => {
+                warn!(path = %path.display(), "Cannot read file, leaving it out of the report: {}", e);
+                unreadable_files += 1;
+            }
+        }
+    }
+
+    let mut report = ComplianceReport::from_findings(
+        &input_dir.display().to_string(),
+        privacy_level,
+        results.iter().map(|(path, fields)| (path.as_path(), fields.as_slice())),
+    );
+    report.files_scanned += unparseable.len();
+    report.unparseable = unparseable;
+    report.unreadable = unreadable + unreadable_files;
+    Ok(report)
+}
+
+/// Collect GPS positions across a directory and report recurring locations
+pub fn scan_gps_clusters(
+    input_dir: &Path,
+    recursive: bool,
+    radius_m: f64,
+) -> Result<ClusterReport, Box<dyn std::error::Error>> {
+    let analyzer = ExifAnalyzer::new();
+    let mut observations = Vec::new();
+    let mut files_scanned = 0;
+    let mut unreadable = 0;
+
+    for path in readable_images(input_dir, recursive, &mut unreadable) {
+        files_scanned += 1;
+
+        let exif = match analyzer.parse_exif_file(&path) {
+            Ok(Some(exif)) => exif,
+            _ => continue,
+        };
+
+        if let Some(coords) = geo::coordinates_from_exif(&exif) {
+            let captured = exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)
+                .and_then(|field| match field.value {
+                    Value::Ascii(ref values) => values.first().and_then(|v| std::str::from_utf8(v).ok()),
+                    _ => None,
+                })
+                .and_then(CaptureMoment::parse);
+            observations.push(GpsObservation { path, coords, captured });
+        }
+    }
+
+    let mut report = ClusterReport::from_observations(
+        &input_dir.display().to_string(),
+        radius_m,
+        files_scanned,
+        &observations,
+    );
+    report.unreadable = unreadable;
+    Ok(report)
+}
+
+/// Collect device identifiers across a directory and link files by device
+pub fn scan_devices(input_dir: &Path, recursive: bool) -> Result<DeviceReport, Box<dyn std::error::Error>> {
+    let analyzer = ExifAnalyzer::new();
+    let mut observations = Vec::new();
+    let mut files_scanned = 0;
+    let mut unreadable = 0;
+
+    for path in readable_images(input_dir, recursive, &mut unreadable) {
+        files_scanned += 1;
+
+        let exif = match analyzer.parse_exif_file(&path) {
+            Ok(Some(exif)) => exif,
+            _ => continue,
+        };
+
+        let identifiers: Vec<String> = analyzer.device_identifiers(&exif).iter()
+            .map(|field| format!("{}={}", field.tag, field.value))
+            .collect();
+        if identifiers.is_empty() {
+            continue;
+        }
+
+        let camera_part = |tag| exif.get_field(tag, In::PRIMARY)
+            .map(|field| field.display_value().to_string().trim_matches('"').trim().to_string());
+        let camera = match (camera_part(Tag::Make), camera_part(Tag::Model)) {
+            (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+            (make, model) => make.or(model),
+        };
+
+        observations.push(DeviceObservation { path, camera, identifiers });
+    }
+
+    let mut report = DeviceReport::from_observations(&input_dir.display().to_string(), files_scanned, &observations);
+    report.unreadable = unreadable;
+    Ok(report)
+}
+
+/// Walk a directory yielding the paths of supported images
+pub fn supported_images(input_dir: &Path, recursive: bool) -> impl Iterator<Item = Result<PathBuf, walkdir::Error>> {
+    let walker = if recursive {
+        WalkDir::new(input_dir)
+    } else {
+        WalkDir::new(input_dir).max_depth(1)
+    };
+
+    walker.into_iter().filter_map(|entry| match entry {
+        Ok(entry) if entry.file_type().is_file() && formats::detect_image_format(entry.path()).is_some() => {
+            Some(Ok(entry.into_path()))
+        }
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    })
+}
+
+/// `supported_images` without the entries that can't be read, which are logged and counted
+fn readable_images<'a>(input_dir: &Path, recursive: bool, unreadable: &'a mut usize) -> impl Iterator<Item = PathBuf> + 'a {
+    supported_images(input_dir, recursive).filter_map(move |path| match path {
+        Ok(path) => Some(path),
+        Err(e) => {
+            warn!("Cannot read directory entry, leaving it out of the report: {}", e);
+            *unreadable += 1;
+            None
+        }
+    })
+}
+
+fn html_document(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>body{{font-family:sans-serif;margin:2em}}table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:4px 8px;text-align:left}}</style>\n\
+         </head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape_html(title),
+        body = body
+    )
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::Tag;
+
+    fn field(tag: Tag, category: PrivacyCategory) -> PrivacyField {
+        PrivacyField {
+            tag,
+            description: format!("{}", tag),
+            category,
+            ifd: None,
+            value: None,
+        }
+    }
+
+    #[test]
+    fn test_report_aggregates_categories() {
+        let gps_file = vec![
+            field(Tag::GPSLatitude, PrivacyCategory::Location),
+            field(Tag::GPSLongitude, PrivacyCategory::Location),
+        ];
+        let artist_file = vec![field(Tag::Artist, PrivacyCategory::PersonalInfo)];
+        let clean_file = vec![];
+
+        let findings = vec![
+            (Path::new("a.jpg"), gps_file.as_slice()),
+            (Path::new("b.jpg"), artist_file.as_slice()),
+            (Path::new("c.jpg"), clean_file.as_slice()),
+        ];
+        let report = ComplianceReport::from_findings("photos", &PrivacyLevel::Standard, findings);
+
+        assert_eq!(report.files_scanned, 3);
+        assert_eq!(report.files_with_findings, 2);
+
+        let location = report.categories.iter().find(|c| c.category == DataCategory::Location).unwrap();
+        assert_eq!(location.files, 1);
+        assert_eq!(location.fields, 2);
+
+        // Location findings rank first among worst offenders
+        assert_eq!(report.worst_offenders[0].path, "a.jpg");
+    }
+
+    #[test]
+    fn test_report_renders_json_and_html() {
+        let fields = vec![field(Tag::Artist, PrivacyCategory::PersonalInfo)];
+        let findings = vec![(Path::new("<script>.jpg"), fields.as_slice())];
+        let report = ComplianceReport::from_findings("photos", &PrivacyLevel::Strict, findings);
+
+        let json = report.to_json().unwrap();
+        assert!(json.contains("\"free_text\""));
+
+        let html = report.to_html();
+        assert!(html.contains("&lt;script&gt;.jpg"));
+        assert!(!html.contains("<script>"));
+    }
+
+    fn observation(name: &str, latitude: f64, longitude: f64, timestamp: &str) -> GpsObservation {
+        GpsObservation {
+            path: PathBuf::from(name),
+            coords: Coordinates { latitude, longitude, altitude: None },
+            captured: CaptureMoment::parse(timestamp),
+        }
+    }
+
+    #[test]
+    fn test_capture_moment_parse() {
+        // 2024-03-16 was a Saturday
+        let moment = CaptureMoment::parse("2024:03:16 21:15:00").unwrap();
+        assert_eq!(moment, CaptureMoment { hour: 21, weekday: 6 });
+        assert!(moment.is_home_time());
+        assert!(!moment.is_work_time());
+
+        // 2024-03-13 was a Wednesday
+        assert!(CaptureMoment::parse("2024:03:13 10:30:00").unwrap().is_work_time());
+
+        assert_eq!(CaptureMoment::parse("0000:00:00 00:00:00"), None);
+        assert_eq!(CaptureMoment::parse("garbage"), None);
+    }
+
+    #[test]
+    fn test_gps_clusters_label_home_and_work() {
+        let observations = vec![
+            // Home: evenings and weekends in Kreuzberg
+            observation("h1.jpg", 52.4990, 13.4030, "2024:03:16 21:00:00"),
+            observation("h2.jpg", 52.4991, 13.4032, "2024:03:17 10:00:00"),
+            observation("h3.jpg", 52.4989, 13.4029, "2024:03:12 22:30:00"),
+            observation("h4.jpg", 52.4990, 13.4031, "2024:03:14 06:00:00"),
+            // Work: weekday office hours in Mitte
+            observation("w1.jpg", 52.5200, 13.4050, "2024:03:12 10:00:00"),
+            observation("w2.jpg", 52.5201, 13.4049, "2024:03:13 14:00:00"),
+            observation("w3.jpg", 52.5199, 13.4051, "2024:03:14 11:00:00"),
+            // One-off trip, below the minimum cluster size
+            observation("t1.jpg", 48.8570, 2.3520, "2024:03:20 12:00:00"),
+        ];
+
+        let report = ClusterReport::from_observations("photos", 250.0, 10, &observations);
+
+        assert_eq!(report.files_with_gps, 8);
+        assert_eq!(report.clusters.len(), 2);
+        assert_eq!(report.clusters[0].label, ClusterLabel::LikelyHome);
+        assert_eq!(report.clusters[0].photos, 4);
+        assert_eq!(report.clusters[1].label, ClusterLabel::LikelyWork);
+        assert_eq!(report.clusters[1].place, "in Berlin, Germany");
+
+        assert!(report.to_html().contains("Likely work"));
+    }
+
+    fn device(name: &str, identifiers: &[&str]) -> DeviceObservation {
+        DeviceObservation {
+            path: PathBuf::from(name),
+            camera: Some("Canon EOS R5".to_string()),
+            identifiers: identifiers.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_device_report_links_transitively() {
+        let observations = vec![
+            device("a.jpg", &["BodySerialNumber=111", "LensSerialNumber=L1"]),
+            device("b.jpg", &["BodySerialNumber=111", "LensSerialNumber=L2"]),
+            // Same lens on a different body links to the first device
+            device("c.jpg", &["BodySerialNumber=222", "LensSerialNumber=L2"]),
+            device("d.jpg", &["BodySerialNumber=333"]),
+            device("e.jpg", &[]),
+        ];
+
+        let report = DeviceReport::from_observations("photos", 6, &observations);
+
+        assert_eq!(report.files_with_identifiers, 4);
+        assert_eq!(report.devices.len(), 2);
+        assert_eq!(report.devices[0].files, vec!["a.jpg", "b.jpg", "c.jpg"]);
+        assert_eq!(report.devices[0].identifiers.len(), 4);
+        assert_eq!(report.devices[1].files, vec!["d.jpg"]);
+    }
+
+    #[test]
+    fn test_category_mapping() {
+        assert_eq!(DataCategory::from_privacy_category(&PrivacyCategory::DeviceIdentifier), DataCategory::Identifiers);
+        assert_eq!(DataCategory::from_privacy_category(&PrivacyCategory::Software), DataCategory::Technical);
+    }
+}
@@ -0,0 +1,117 @@
+//! Putting originals back from backups (`restore` subcommand).
+//!
+//! `--backup` leaves `photo.jpg.bak` beside each image cleaned in place, plus
+//! numbered `.bak.N` copies from later runs. The `.bak` is the image as it was
+//! before it was first cleaned, so that is what restoring copies back. Backups
+//! stay where they are: restoring twice is harmless, and the next clean with
+//! `--backup` keeps them as they are. A backup is only restored over a
+//! supported image that is still there; any other `.bak` is left alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+use crate::cli::RestoreOptions;
+use crate::{formats, utils};
+
+/// What a restore did
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RestoreStats {
+    /// Images copied back from their backup (or that would be, in a dry run)
+    pub restored: usize,
+    pub failed: usize,
+}
+
+/// Restore every image in `options.input` that has a `.bak` backup
+pub fn restore(options: &RestoreOptions) -> RestoreStats {
+    let mut stats = RestoreStats::default();
+    let depth = if options.recursive { usize::MAX } else { 1 };
+
+    for entry in WalkDir::new(&options.input).max_depth(depth) {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                warn!("Cannot read directory entry: {}", e);
+                stats.failed += 1;
+                continue;
+            }
+        };
+        let Some(original) = original_path(entry.path()) else { continue };
+        if !entry.file_type().is_file() || !formats::is_supported_image(&original) {
+            continue;
+        }
+        if !original.exists() {
+            warn!(path = %entry.path().display(), "Skipping backup without a cleaned image beside it");
+            continue;
+        }
+
+        if options.dry_run {
+            info!(path = %original.display(), "Would restore from backup");
+            stats.restored += 1;
+            continue;
+        }
+        match fs::copy(utils::long_path(entry.path()), utils::long_path(&original)) {
+            Ok(_) => {
+                info!(path = %original.display(), "Restored from backup");
+                stats.restored += 1;
+            }
+            Err(e) => {
+                warn!(path = %original.display(), "Cannot restore from backup: {}", e);
+                stats.failed += 1;
+            }
+        }
+    }
+    stats
+}
+
+/// The image a backup belongs to: `photo.jpg` for `photo.jpg.bak`
+///
+/// Numbered backups (`photo.jpg.bak.1`) are later states, not originals, and
+/// give `None`.
+pub fn original_path(backup: &Path) -> Option<PathBuf> {
+    if backup.extension()? != "bak" {
+        return None;
+    }
+    Some(backup.with_file_name(backup.file_stem()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_original_path() {
+        assert_eq!(original_path(Path::new("a/photo.jpg.bak")), Some(PathBuf::from("a/photo.jpg")));
+        assert_eq!(original_path(Path::new("a/photo.jpg.bak.1")), None);
+        assert_eq!(original_path(Path::new("a/photo.jpg")), None);
+    }
+
+    #[test]
+    fn test_restore_copies_the_first_backup_back() {
+        let temp_dir = TempDir::new().unwrap();
+        let nested = temp_dir.path().join("nested");
+        fs::create_dir(&nested).unwrap();
+        fs::write(temp_dir.path().join("photo.jpg"), b"cleaned").unwrap();
+        fs::write(temp_dir.path().join("photo.jpg.bak"), b"original").unwrap();
+        fs::write(temp_dir.path().join("photo.jpg.bak.1"), b"second run").unwrap();
+        fs::write(nested.join("deep.jpg"), b"cleaned").unwrap();
+        fs::write(nested.join("deep.jpg.bak"), b"original").unwrap();
+        // Not images, or with no cleaned image left to restore over
+        fs::write(temp_dir.path().join("notes.txt.bak"), b"notes").unwrap();
+        fs::write(temp_dir.path().join("gone.jpg.bak"), b"original").unwrap();
+
+        let options = RestoreOptions { input: temp_dir.path().to_path_buf(), recursive: false, dry_run: true };
+        assert_eq!(restore(&options).restored, 1);
+        assert_eq!(fs::read(temp_dir.path().join("photo.jpg")).unwrap(), b"cleaned");
+
+        let stats = restore(&RestoreOptions { dry_run: false, ..options });
+        assert_eq!(stats, RestoreStats { restored: 1, failed: 0 });
+        assert_eq!(fs::read(temp_dir.path().join("photo.jpg")).unwrap(), b"original");
+        assert!(temp_dir.path().join("photo.jpg.bak").exists());
+        // Only a recursive restore reaches subdirectories
+        assert_eq!(fs::read(nested.join("deep.jpg")).unwrap(), b"cleaned");
+        assert!(!temp_dir.path().join("notes.txt").exists());
+        assert!(!temp_dir.path().join("gone.jpg").exists());
+    }
+}
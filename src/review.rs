@@ -0,0 +1,134 @@
+//! Interactive per-file review of removals.
+//!
+//! With `--interactive`, each file's findings are shown before anything is
+//! changed and the user decides whether to clean it, keep it as is, or leave
+//! it out. Prompts go to stderr and answers are read from stdin.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use crate::analyzer::PrivacyField;
+
+/// What to do with one reviewed file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewDecision {
+    /// Clean the file at the configured privacy level
+    Remove,
+    /// Leave the metadata alone; an output directory still gets an unchanged copy
+    Keep,
+    /// Leave the file out of the run entirely
+    Skip,
+}
+
+/// Asks the user about every file with findings
+pub struct InteractiveReview {
+    input: Mutex<Box<dyn BufRead + Send>>,
+    output: Mutex<Box<dyn Write + Send>>,
+    /// Answer given with an "all remaining files" shortcut
+    remembered: Mutex<Option<ReviewDecision>>,
+}
+
+impl InteractiveReview {
+    pub fn new<R, W>(input: R, output: W) -> Self
+    where
+        R: BufRead + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        Self {
+            input: Mutex::new(Box::new(input)),
+            output: Mutex::new(Box::new(output)),
+            remembered: Mutex::new(None),
+        }
+    }
+
+    /// Prompt on stderr and read answers from stdin
+    pub fn stdin() -> Self {
+        Self::new(BufReader::new(io::stdin()), io::stderr())
+    }
+
+    /// Show a file's findings and ask what to do with it
+    ///
+    /// Empty `fields` means the metadata couldn't be parsed and would be removed entirely.
+    pub fn decide(&self, path: &Path, fields: &[PrivacyField]) -> Result<ReviewDecision, Box<dyn std::error::Error>> {
+        let mut remembered = self.remembered.lock().map_err(|_| "Review state poisoned")?;
+        if let Some(decision) = *remembered {
+            return Ok(decision);
+        }
+
+        let mut input = self.input.lock().map_err(|_| "Review input poisoned")?;
+        let mut output = self.output.lock().map_err(|_| "Review output poisoned")?;
+
+        if fields.is_empty() {
+            writeln!(output, "\n{}: metadata could not be parsed; all of it would be removed", path.display())?;
+        } else {
+            writeln!(output, "\n{}: {} privacy-sensitive fields", path.display(), fields.len())?;
+        }
+        for field in fields {
+            writeln!(output, "  [{}] {}", field.category, field.description)?;
+        }
+
+        loop {
+            write!(output, "[r]emove, [k]eep, [s]kip (R/K/S for all remaining files) [r]: ")?;
+            output.flush()?;
+
+            let mut answer = String::new();
+            if input.read_line(&mut answer)? == 0 {
+                // Nobody is answering any more; never change files without consent
+                writeln!(output, "\nInput closed; skipping remaining files")?;
+                *remembered = Some(ReviewDecision::Skip);
+                return Ok(ReviewDecision::Skip);
+            }
+
+            match parse_answer(answer.trim()) {
+                Some((decision, for_all)) => {
+                    if for_all {
+                        *remembered = Some(decision);
+                    }
+                    return Ok(decision);
+                }
+                None => writeln!(output, "Please answer r, k, s, R, K, or S")?,
+            }
+        }
+    }
+}
+
+/// Parse an answer into a decision and whether it applies to all remaining files
+fn parse_answer(answer: &str) -> Option<(ReviewDecision, bool)> {
+    match answer {
+        "" | "r" | "remove" => Some((ReviewDecision::Remove, false)),
+        "k" | "keep" => Some((ReviewDecision::Keep, false)),
+        "s" | "skip" => Some((ReviewDecision::Skip, false)),
+        "R" => Some((ReviewDecision::Remove, true)),
+        "K" => Some((ReviewDecision::Keep, true)),
+        "S" => Some((ReviewDecision::Skip, true)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn review(answers: &str) -> InteractiveReview {
+        InteractiveReview::new(Cursor::new(answers.as_bytes().to_vec()), io::sink())
+    }
+
+    #[test]
+    fn test_answers_apply_per_file_until_shortcut() {
+        let review = review("k\nbogus\ns\nR\n");
+        let path = Path::new("photo.jpg");
+
+        assert_eq!(review.decide(path, &[]).unwrap(), ReviewDecision::Keep);
+        assert_eq!(review.decide(path, &[]).unwrap(), ReviewDecision::Skip);
+        assert_eq!(review.decide(path, &[]).unwrap(), ReviewDecision::Remove);
+        // No input left, but "R" applies to everything after it
+        assert_eq!(review.decide(path, &[]).unwrap(), ReviewDecision::Remove);
+    }
+
+    #[test]
+    fn test_closed_input_skips() {
+        let review = review("");
+        assert_eq!(review.decide(Path::new("photo.jpg"), &[]).unwrap(), ReviewDecision::Skip);
+    }
+}
@@ -0,0 +1,270 @@
+//! Confinement of the ExifTool processes the remover starts (`--sandbox`).
+//!
+//! ExifTool parses every format the cleaner accepts, so a crafted image that
+//! exploits one of its parsers would run with the cleaner's rights. Sandboxed:
+//!
+//! - on Linux, Landlock lets ExifTool read and execute anywhere but create,
+//!   change, or delete files only in the directories of the file it is working
+//!   on, and seccomp refuses it network sockets and ptrace;
+//! - on Windows, ExifTool starts suspended inside a job object that stops it
+//!   from starting other programs, caps its memory, and kills it along with the
+//!   cleaner. Windows has no unprivileged way to narrow one process's file
+//!   access, so there the job object is all the confinement there is.
+//!
+//! Requires the `sandbox` feature.
+
+use std::path::PathBuf;
+use std::process::{Child, Command};
+
+use imp::{attach, confine};
+pub use imp::{check, Attached};
+
+#[cfg(all(feature = "sandbox", target_os = "linux"))]
+mod imp {
+    use std::io;
+    use std::os::unix::process::CommandExt;
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, Command};
+    use landlock::{
+        Access, AccessFs, CompatLevel, Compatible, PathBeneath, PathFd, Ruleset, RulesetAttr,
+        RulesetCreated, RulesetCreatedAttr, ABI,
+    };
+    use seccompiler::{
+        BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter, SeccompRule,
+    };
+
+    /// Nothing to hold on Linux: the restrictions live in the process itself
+    pub struct Attached;
+
+    /// Fail unless the kernel supports Landlock and seccomp
+    pub fn check() -> Result<(), Box<dyn std::error::Error>> {
+        ruleset(&[])?;
+        seccomp_filter()?;
+        Ok(())
+    }
+
+    /// Restrict `cmd` when it starts to writing under `writable`
+    pub fn confine(cmd: &mut Command, writable: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut ruleset = Some(ruleset(writable)?);
+        let filter = seccomp_filter()?;
+        // Both are built here; the child, between fork and exec, only enforces them
+        unsafe {
+            cmd.pre_exec(move || {
+                if let Some(ruleset) = ruleset.take() {
+                    ruleset.restrict_self()
+                        .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e.to_string()))?;
+                }
+                seccompiler::apply_filter(&filter)
+                    .map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e.to_string()))
+            });
+        }
+        Ok(())
+    }
+
+    pub fn attach(_child: &Child) -> Result<Attached, Box<dyn std::error::Error>> {
+        Ok(Attached)
+    }
+
+    /// Read and execute anywhere; write only beneath `writable`
+    fn ruleset(writable: &[PathBuf]) -> Result<RulesetCreated, Box<dyn std::error::Error>> {
+        let mut ruleset = Ruleset::default()
+            .set_compatibility(CompatLevel::HardRequirement)
+            .handle_access(AccessFs::from_all(ABI::V1))
+            .map_err(|e| format!("Landlock is not available: {}", e))?
+            // Renames across directories and truncation are handled where the kernel knows them
+            .set_compatibility(CompatLevel::BestEffort)
+            .handle_access(AccessFs::from_all(ABI::V3))?
+            .create()
+            .map_err(|e| format!("Landlock is not available: {}", e))?
+            .add_rule(PathBeneath::new(PathFd::new(Path::new("/"))?, AccessFs::from_read(ABI::V3)))?;
+        for dir in writable {
+            ruleset = ruleset.add_rule(PathBeneath::new(PathFd::new(dir)?, AccessFs::from_all(ABI::V3)))?;
+        }
+        Ok(ruleset)
+    }
+
+    /// Refuse sockets other than Unix domain ones, and tracing other processes
+    fn seccomp_filter() -> Result<BpfProgram, Box<dyn std::error::Error>> {
+        let arch = std::env::consts::ARCH.try_into()
+            .map_err(|_| format!("seccomp filters are not supported on {}", std::env::consts::ARCH))?;
+        let not_unix = SeccompRule::new(vec![
+            SeccompCondition::new(0, SeccompCmpArgLen::Dword, SeccompCmpOp::Ne, libc::AF_UNIX as u64)?,
+        ])?;
+        let rules = [
+            (libc::SYS_socket, vec![not_unix]),
+            (libc::SYS_ptrace, vec![]),
+            (libc::SYS_process_vm_writev, vec![]),
+        ].into_iter().collect();
+
+        let filter = SeccompFilter::new(rules, SeccompAction::Allow, SeccompAction::Errno(libc::EPERM as u32), arch)?;
+        Ok(filter.try_into()?)
+    }
+}
+
+#[cfg(all(feature = "sandbox", windows))]
+mod imp {
+    use std::io;
+    use std::mem::{size_of, zeroed};
+    use std::os::windows::io::AsRawHandle;
+    use std::os::windows::process::CommandExt;
+    use std::path::PathBuf;
+    use std::process::{Child, Command};
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_ACTIVE_PROCESS,
+        JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+        JOB_OBJECT_LIMIT_PROCESS_MEMORY,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, CREATE_SUSPENDED, THREAD_SUSPEND_RESUME};
+
+    /// ExifTool's launcher and the Perl it runs
+    const MAX_PROCESSES: u32 = 2;
+    const MAX_MEMORY: usize = 2 << 30;
+
+    /// The job object holding a sandboxed ExifTool; closing it kills what is left inside
+    pub struct Attached(HANDLE);
+
+    impl Drop for Attached {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    pub fn check() -> Result<(), Box<dyn std::error::Error>> {
+        Ok(())
+    }
+
+    /// Start `cmd` suspended, so it can't run before it is in the job
+    pub fn confine(cmd: &mut Command, _writable: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+        cmd.creation_flags(CREATE_SUSPENDED);
+        Ok(())
+    }
+
+    /// Put a child started by `confine` in a job object, then let it run
+    pub fn attach(child: &Child) -> Result<Attached, Box<dyn std::error::Error>> {
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            let job = Attached(job);
+
+            let mut limits: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = zeroed();
+            limits.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_ACTIVE_PROCESS
+                | JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION
+                | JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE
+                | JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+            limits.BasicLimitInformation.ActiveProcessLimit = MAX_PROCESSES;
+            limits.ProcessMemoryLimit = MAX_MEMORY;
+            let set = SetInformationJobObject(
+                job.0,
+                JobObjectExtendedLimitInformation,
+                &limits as *const _ as *const std::ffi::c_void,
+                size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            );
+            if set == 0 || AssignProcessToJobObject(job.0, child.as_raw_handle() as HANDLE) == 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+
+            resume(child.id())?;
+            Ok(job)
+        }
+    }
+
+    /// Resume the threads of a process started suspended
+    unsafe fn resume(pid: u32) -> io::Result<()> {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+        let mut entry: THREADENTRY32 = zeroed();
+        entry.dwSize = size_of::<THREADENTRY32>() as u32;
+        let mut resumed = false;
+        let mut more = Thread32First(snapshot, &mut entry);
+        while more != 0 {
+            if entry.th32OwnerProcessID == pid {
+                let thread = OpenThread(THREAD_SUSPEND_RESUME, 0, entry.th32ThreadID);
+                if thread != 0 {
+                    resumed |= ResumeThread(thread) != u32::MAX;
+                    CloseHandle(thread);
+                }
+            }
+            more = Thread32Next(snapshot, &mut entry);
+        }
+        CloseHandle(snapshot);
+
+        if resumed {
+            Ok(())
+        } else {
+            Err(io::Error::new(io::ErrorKind::Other, "Could not resume the sandboxed ExifTool"))
+        }
+    }
+}
+
+#[cfg(not(all(feature = "sandbox", any(target_os = "linux", windows))))]
+mod imp {
+    use std::path::PathBuf;
+    use std::process::{Child, Command};
+
+    pub struct Attached;
+
+    pub fn check() -> Result<(), Box<dyn std::error::Error>> {
+        Err("--sandbox requires the sandbox feature on Linux or Windows".into())
+    }
+
+    pub fn confine(_cmd: &mut Command, _writable: &[PathBuf]) -> Result<(), Box<dyn std::error::Error>> {
+        check()
+    }
+
+    pub fn attach(_child: &Child) -> Result<Attached, Box<dyn std::error::Error>> {
+        Ok(Attached)
+    }
+}
+
+/// Start `cmd` in the sandbox, allowing writes beneath `writable`; keep the guard until it exits
+pub fn spawn(mut cmd: Command, writable: &[PathBuf]) -> Result<(Child, Attached), Box<dyn std::error::Error>> {
+    confine(&mut cmd, writable)?;
+    let mut child = cmd.spawn()?;
+    match attach(&child) {
+        Ok(attached) => Ok((child, attached)),
+        Err(e) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(all(feature = "sandbox", target_os = "linux"))]
+    fn test_writes_outside_the_working_set_fail() {
+        if check().is_err() {
+            return; // Kernel without Landlock
+        }
+        let allowed = tempfile::tempdir().unwrap();
+        let denied = tempfile::tempdir().unwrap();
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(r#"echo a > "$1/ok" && ! echo b > "$2/no""#)
+            .arg("sh").arg(allowed.path()).arg(denied.path());
+
+        let (mut child, _attached) = spawn(cmd, &[allowed.path().to_path_buf()]).unwrap();
+        assert!(child.wait().unwrap().success());
+        assert!(allowed.path().join("ok").exists());
+        assert!(!denied.path().join("no").exists());
+    }
+
+    #[test]
+    #[cfg(not(feature = "sandbox"))]
+    fn test_sandbox_requires_feature() {
+        assert!(check().is_err());
+        assert!(spawn(Command::new("true"), &[]).is_err());
+    }
+}
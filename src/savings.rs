@@ -0,0 +1,147 @@
+//! Estimates of the space metadata removal reclaims.
+//!
+//! Real runs measure savings as the difference between input and output
+//! size. Dry runs never produce output, so the savings are estimated from
+//! what the policy would drop: whole metadata segments for allowlist levels
+//! and `--force-strip`, otherwise the removed groups (XMP, IPTC, the GPS and
+//! thumbnail IFDs) and each removed tag's entry and value.
+
+use exif::{In, Tag, Value};
+use crate::jpeg;
+use crate::privacy::PolicySpec;
+
+/// Size of an IFD entry: tag, type, count, and value or offset
+const ENTRY_LEN: u64 = 12;
+/// Entry count and next-IFD offset around an IFD's entries
+const IFD_OVERHEAD: u64 = 6;
+/// APP14 carries Adobe's color transform, which decoding needs
+const APP14: u8 = 0xEE;
+/// Identifier that starts an ICC profile APP2 payload
+const ICC_HEADER: &[u8] = b"ICC_PROFILE\0";
+
+/// Estimated bytes cleaning `data` with `spec` would remove (everything but the image with `strip_all`)
+///
+/// `None` for formats other than JPEG, where no estimate is made.
+pub fn estimate_removed_bytes(data: &[u8], spec: &PolicySpec, strip_all: bool) -> Option<u64> {
+    let layout = jpeg::parse_layout(data).ok()?;
+
+    if strip_all || spec.keep_only.is_some() {
+        let keeps_icc = !strip_all && spec.keep_groups.contains(&"ICC_Profile");
+        return Some(layout.segments.iter()
+            .filter(|segment| segment.is_metadata() && segment.marker != jpeg::APP0 && segment.marker != APP14)
+            .filter(|segment| !(keeps_icc && segment.marker == jpeg::APP2 && segment.payload.starts_with(ICC_HEADER)))
+            .map(|segment| segment.encoded_len() as u64)
+            .sum());
+    }
+
+    let mut removed = 0;
+    if spec.remove_groups.contains(&"XMP") {
+        removed += layout.segments.iter()
+            .filter(|segment| segment.marker == jpeg::APP1)
+            .filter(|segment| {
+                segment.payload.starts_with(jpeg::XMP_HEADER) || segment.payload.starts_with(jpeg::XMP_EXTENSION_HEADER)
+            })
+            .map(|segment| segment.encoded_len() as u64)
+            .sum::<u64>();
+    }
+    if spec.remove_groups.contains(&"IPTC") {
+        removed += layout.segments.iter()
+            .filter(|segment| segment.marker == jpeg::APP13)
+            .map(|segment| segment.encoded_len() as u64)
+            .sum::<u64>();
+    }
+    if let Some(block) = jpeg::reassemble_exif(layout.app1_payloads()) {
+        removed += exif_removed_bytes(&block, spec);
+    }
+
+    Some(removed)
+}
+
+/// Bytes of the EXIF block taken up by the entries and IFDs `spec` removes
+fn exif_removed_bytes(block: &[u8], spec: &PolicySpec) -> u64 {
+    let exif = match exif::Reader::new().read_raw(block.to_vec()) {
+        Ok(exif) => exif,
+        Err(_) => return 0,
+    };
+    let removes_gps = spec.remove_groups.contains(&"gps");
+    let removes_thumbnail = spec.remove_groups.contains(&"IFD1");
+
+    let mut removed = 0;
+    let mut gps_entries = 0;
+    for field in exif.fields() {
+        let in_removed_ifd = (removes_gps && field.tag.context() == exif::Context::Gps)
+            || (removes_thumbnail && field.ifd_num == In::THUMBNAIL);
+        if !in_removed_ifd && !spec.remove.contains(&field.tag) {
+            continue;
+        }
+        if field.tag.context() == exif::Context::Gps {
+            gps_entries += 1;
+        }
+        let value_len = value_len(&field.value);
+        // Values of up to four bytes are stored in the entry itself
+        removed += ENTRY_LEN + if value_len > 4 { value_len } else { 0 };
+    }
+
+    if removes_gps && gps_entries > 0 {
+        // The GPS IFD itself, and the IFD0 entry pointing to it
+        removed += IFD_OVERHEAD + ENTRY_LEN;
+    }
+    if removes_thumbnail {
+        let thumbnail_len = exif.get_field(Tag::JPEGInterchangeFormatLength, In::THUMBNAIL)
+            .and_then(|field| field.value.get_uint(0));
+        removed += thumbnail_len.map_or(0, u64::from) + IFD_OVERHEAD;
+    }
+
+    removed
+}
+
+/// Size of a value as stored in a TIFF structure
+fn value_len(value: &Value) -> u64 {
+    let len = match value {
+        Value::Byte(bytes) | Value::Undefined(bytes, _) => bytes.len(),
+        Value::SByte(bytes) => bytes.len(),
+        Value::Ascii(strings) => strings.iter().map(|string| string.len() + 1).sum(),
+        Value::Short(values) => values.len() * 2,
+        Value::SShort(values) => values.len() * 2,
+        Value::Long(values) => values.len() * 4,
+        Value::SLong(values) => values.len() * 4,
+        Value::Float(values) => values.len() * 4,
+        Value::Rational(values) => values.len() * 8,
+        Value::SRational(values) => values.len() * 8,
+        Value::Double(values) => values.len() * 8,
+        Value::Unknown(..) => 0,
+    };
+    len as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::tests::{build_jpeg, exif_with_artist};
+    use crate::privacy::{PrivacyLevel, PrivacyPolicy};
+
+    #[test]
+    fn test_removed_tags_are_estimated_by_entry_and_value() {
+        let exif = exif_with_artist("Jane Doe");
+        let data = build_jpeg(&[(jpeg::APP0, b"JFIF\0"), (jpeg::APP1, &exif)], b"pixels");
+
+        let standard = PrivacyPolicy::spec(&PrivacyLevel::Standard);
+        assert_eq!(estimate_removed_bytes(&data, &standard, false), Some(ENTRY_LEN + 9));
+        let minimal = PrivacyPolicy::spec(&PrivacyLevel::Minimal);
+        assert_eq!(estimate_removed_bytes(&data, &minimal, false), Some(0));
+    }
+
+    #[test]
+    fn test_allowlist_and_strip_count_whole_segments() {
+        let exif = exif_with_artist("Jane Doe");
+        let xmp = [jpeg::XMP_HEADER, b"<x:xmpmeta/>"].concat();
+        let data = build_jpeg(&[(jpeg::APP0, b"JFIF\0"), (jpeg::APP1, &exif), (jpeg::APP1, &xmp)], b"pixels");
+        let segments = (exif.len() + 4 + xmp.len() + 4) as u64;
+
+        let paranoid = PrivacyPolicy::spec(&PrivacyLevel::Paranoid);
+        assert_eq!(estimate_removed_bytes(&data, &paranoid, false), Some(segments));
+        let minimal = PrivacyPolicy::spec(&PrivacyLevel::Minimal);
+        assert_eq!(estimate_removed_bytes(&data, &minimal, true), Some(segments));
+        assert_eq!(estimate_removed_bytes(b"GIF89a", &minimal, true), None);
+    }
+}
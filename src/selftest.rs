@@ -0,0 +1,206 @@
+//! Install checks against an embedded sample image.
+//!
+//! `--health-check` is a fast readiness probe for containers: it checks that
+//! ExifTool runs, that the temp directory is writable, and that cleaning the
+//! sample in memory really removes its privacy data. The report is JSON, and
+//! the exit code says whether every check passed.
+//!
+//! The `self-test` subcommand is the thorough version for users: it cleans a
+//! copy of the sample on disk at every privacy level, the way a real run does,
+//! and lists what each level removed and anything it missed.
+
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use clap::ValueEnum;
+use exif::Tag;
+use serde::Serialize;
+use crate::analyzer::ExifAnalyzer;
+use crate::cli::Config;
+use crate::privacy::PrivacyLevel;
+use crate::processor::ImageProcessor;
+use crate::remover::MetadataRemover;
+
+/// A 1x1 JPEG with camera make, artist, body serial number, capture time, and GPS position
+pub const SAMPLE_JPEG: &[u8] = include_bytes!("data/selftest.jpg");
+
+/// The outcome of one check
+#[derive(Debug, Clone, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    /// What was found, or why the check failed
+    pub detail: String,
+}
+
+impl Check {
+    fn from_result(name: &'static str, result: Result<String, Box<dyn std::error::Error>>) -> Self {
+        match result {
+            Ok(detail) => Check { name, ok: true, detail },
+            Err(e) => Check { name, ok: false, detail: e.to_string() },
+        }
+    }
+}
+
+/// The result of `--health-check`
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub healthy: bool,
+    pub checks: Vec<Check>,
+    pub duration_ms: u128,
+}
+
+/// Run every check, cleaning the sample at `level`
+pub fn health_check(level: &PrivacyLevel) -> HealthReport {
+    let started = Instant::now();
+    let remover = MetadataRemover::new();
+
+    let checks = vec![
+        Check::from_result("backend", remover.get_exiftool_version().map(|version| format!("ExifTool {}", version))),
+        Check::from_result("temp_dir", check_temp_dir()),
+        Check::from_result("round_trip", round_trip(&remover, level)),
+    ];
+
+    HealthReport {
+        healthy: checks.iter().all(|check| check.ok),
+        checks,
+        duration_ms: started.elapsed().as_millis(),
+    }
+}
+
+/// Create and remove a file in the temp directory, where cleaning stages files
+fn check_temp_dir() -> Result<String, Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    let dir = std::env::temp_dir();
+    let not_writable = |e: std::io::Error| format!("{} is not writable: {}", dir.display(), e);
+    // Removed when dropped
+    let mut probe = tempfile::NamedTempFile::new().map_err(not_writable)?;
+    probe.write_all(SAMPLE_JPEG).map_err(not_writable)?;
+    Ok(dir.display().to_string())
+}
+
+/// Clean the sample in memory and confirm nothing the level removes is left
+fn round_trip(remover: &MetadataRemover, level: &PrivacyLevel) -> Result<String, Box<dyn std::error::Error>> {
+    let analyzer = ExifAnalyzer::new();
+
+    let before = analyzer.analyze_bytes(SAMPLE_JPEG, level)?.fields;
+    if before.is_empty() {
+        return Err("The sample image has no privacy data at this level".into());
+    }
+    let cleaned = remover.remove_privacy_data_from_bytes(SAMPLE_JPEG, level)?;
+    let after = analyzer.analyze_bytes(&cleaned, level)?.fields;
+    if let Some(field) = after.first() {
+        return Err(format!("{} of {} privacy fields survived cleaning, e.g. {}", after.len(), before.len(), field.description).into());
+    }
+    Ok(format!("{} privacy fields removed", before.len()))
+}
+
+/// How cleaning the sample at one privacy level went
+#[derive(Debug, Clone, Serialize)]
+pub struct LevelResult {
+    pub level: String,
+    pub passed: bool,
+    /// Tags of the privacy fields that were removed
+    pub removed: Vec<String>,
+    /// Privacy fields still present after cleaning
+    pub remaining: Vec<String>,
+    /// Why cleaning failed outright
+    pub error: Option<String>,
+}
+
+/// The result of the `self-test` subcommand
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfTestReport {
+    pub version: &'static str,
+    /// ExifTool's version; `None` when it couldn't be run
+    pub exiftool: Option<String>,
+    pub passed: bool,
+    pub levels: Vec<LevelResult>,
+}
+
+/// Clean a copy of the sample at every privacy level in a scratch directory
+pub fn self_test() -> Result<SelfTestReport, Box<dyn std::error::Error>> {
+    let dir = tempfile::Builder::new().prefix("privacy-exif-cleaner-selftest-").tempdir()
+        .map_err(|e| format!("Cannot create a scratch directory: {}", e))?;
+    let levels: Vec<LevelResult> = PrivacyLevel::value_variants().iter()
+        .map(|level| test_level(dir.path(), level))
+        .collect();
+
+    Ok(SelfTestReport {
+        version: env!("CARGO_PKG_VERSION"),
+        exiftool: MetadataRemover::new().get_exiftool_version().ok(),
+        passed: levels.iter().all(|result| result.passed),
+        levels,
+    })
+}
+
+fn test_level(dir: &Path, level: &PrivacyLevel) -> LevelResult {
+    let name = level.to_possible_value().map(|value| value.get_name().to_string()).unwrap_or_default();
+    let analyzer = ExifAnalyzer::new();
+    let path = dir.join(format!("{}.jpg", name));
+    let findings = |data: &[u8]| analyzer.analyze_privacy_data(data, &path, level).unwrap_or_default();
+
+    let before = findings(SAMPLE_JPEG);
+    let mut result = LevelResult { level: name, passed: false, removed: Vec::new(), remaining: Vec::new(), error: None };
+
+    let mut config = Config::with_privacy_level(level.clone());
+    config.input = dir.to_path_buf();
+    let cleaned = fs::write(&path, SAMPLE_JPEG).map_err(Into::into)
+        .and_then(|()| ImageProcessor::new(config).process_image(&path))
+        .and_then(|_| Ok(fs::read(&path)?));
+    let after = match cleaned {
+        Ok(data) => findings(&data),
+        Err(e) => {
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+
+    result.removed = before.iter()
+        .filter(|field| !after.iter().any(|left| left.tag == field.tag))
+        .map(|field| field.tag.to_string())
+        .collect();
+    result.remaining = after.iter().map(|field| field.description.clone()).collect();
+    // GPS is removed at every level; the rest depends on the level's own findings
+    let gps_left = after.iter().any(|field| field.tag == Tag::GPSLatitude || field.tag == Tag::GPSLongitude);
+    result.passed = !before.is_empty() && after.is_empty() && !gps_left;
+    result
+}
+
+impl SelfTestReport {
+    /// Render the report as readable text
+    pub fn to_text(&self) -> String {
+        let exiftool = match self.exiftool {
+            Some(ref version) => format!("ExifTool {}", version),
+            None => "ExifTool not found".to_string(),
+        };
+        let mut text = format!("privacy-exif-cleaner {}, {}\n", self.version, exiftool);
+
+        for result in &self.levels {
+            let detail = match (&result.error, result.passed) {
+                (Some(e), _) => format!("error: {}", e),
+                (None, true) => format!("removed {}", result.removed.join(", ")),
+                (None, false) => format!("left {}", result.remaining.join("; ")),
+            };
+            text.push_str(&format!("  {:<10} {:<5} {}\n", result.level, if result.passed { "ok" } else { "FAIL" }, detail));
+        }
+
+        text.push_str(if self.passed { "Self-test passed\n" } else { "Self-test FAILED\n" });
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sample_has_privacy_data_at_every_level() {
+        let analyzer = ExifAnalyzer::new();
+        for level in [PrivacyLevel::Minimal, PrivacyLevel::Standard, PrivacyLevel::Strict, PrivacyLevel::Paranoid] {
+            let fields = analyzer.analyze_bytes(SAMPLE_JPEG, &level).unwrap().fields;
+            assert!(fields.iter().any(|field| field.tag == Tag::GPSLatitude), "{:?}", level);
+        }
+    }
+}
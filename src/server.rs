@@ -0,0 +1,235 @@
+//! HTTP sanitization service for the `serve` subcommand.
+//!
+//! `POST /clean` accepts a `multipart/form-data` upload with the image in a
+//! `file` (or `image`) field and responds with the sanitized image. The privacy
+//! level comes from a `privacy` query parameter or form field, falling back to
+//! the server default. Requires the `server` feature.
+
+use std::io::Read;
+use std::sync::Arc;
+use clap::ValueEnum;
+use multipart::server::Multipart;
+use tiny_http::{Header, Method, Request, Response, Server};
+use tracing::{info, info_span, warn};
+use crate::cli::{Config, ServeOptions};
+use crate::privacy::PrivacyLevel;
+use crate::processor::ImageProcessor;
+
+type HttpResponse = Response<std::io::Cursor<Vec<u8>>>;
+
+/// Start the service and block forever, handling requests on `options.workers` threads
+pub fn serve(options: &ServeOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let server = Arc::new(
+        Server::http(&options.bind).map_err(|e| format!("Failed to bind {}: {}", options.bind, e))?,
+    );
+    info!(bind = %options.bind, "Listening on http://{} (POST /clean)", options.bind);
+
+    let handles: Vec<_> = (0..options.workers.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let options = options.clone();
+            std::thread::spawn(move || {
+                for mut request in server.incoming_requests() {
+                    let _span = info_span!("request", method = %request.method(), url = %request.url()).entered();
+                    let response = handle_request(&mut request, &options);
+                    info!(status = response.status_code().0, "Handled request");
+                    if let Err(e) = request.respond(response) {
+                        warn!("Error sending response: {}", e);
+                    }
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        handle.join().map_err(|_| "Server worker panicked")?;
+    }
+
+    Ok(())
+}
+
+fn handle_request(request: &mut Request, options: &ServeOptions) -> HttpResponse {
+    let url = request.url().to_string();
+    let (path, query) = split_url(&url);
+
+    match (request.method(), path) {
+        (Method::Get, "/health") => text_response(200, "ok"),
+        (Method::Post, "/clean") => match clean_upload(request, query, options) {
+            Ok(response) => response,
+            Err((status, message)) => text_response(status, &message),
+        },
+        (_, "/clean") => text_response(405, "Use POST with a multipart image upload"),
+        _ => text_response(404, "Not found"),
+    }
+}
+
+fn clean_upload(
+    request: &mut Request,
+    query: &str,
+    options: &ServeOptions,
+) -> Result<HttpResponse, (u16, String)> {
+    if request.body_length().map_or(false, |len| len as u64 > options.max_upload_bytes) {
+        return Err((413, "Upload too large".to_string()));
+    }
+
+    let boundary = request.headers().iter()
+        .find(|h| h.field.equiv("Content-Type"))
+        .and_then(|h| multipart_boundary(h.value.as_str()))
+        .ok_or((400, "Expected multipart/form-data upload".to_string()))?;
+
+    let mut privacy_level = match query_param(query, "privacy") {
+        Some(value) => Some(parse_privacy_level(value)?),
+        None => None,
+    };
+    let mut upload: Option<(String, Vec<u8>)> = None;
+
+    let body = request.as_reader().take(options.max_upload_bytes + 1);
+    let mut multipart = Multipart::with_body(body, boundary);
+
+    while let Some(mut field) = multipart.read_entry().map_err(|e| (400, format!("Malformed upload: {}", e)))? {
+        let mut data = Vec::new();
+        field.data.read_to_end(&mut data).map_err(|e| (400, format!("Malformed upload: {}", e)))?;
+
+        if data.len() as u64 > options.max_upload_bytes {
+            return Err((413, "Upload too large".to_string()));
+        }
+
+        match &*field.headers.name {
+            "privacy" if privacy_level.is_none() => {
+                privacy_level = Some(parse_privacy_level(String::from_utf8_lossy(&data).trim())?);
+            }
+            "file" | "image" => {
+                let file_name = field.headers.filename.clone().unwrap_or_else(|| "upload.jpg".to_string());
+                upload = Some((file_name, data));
+            }
+            _ => {}
+        }
+    }
+
+    let (file_name, data) = upload.ok_or((400, "Missing `file` field".to_string()))?;
+    let privacy_level = privacy_level.unwrap_or_else(|| options.default_privacy_level.clone());
+
+    let processor = ImageProcessor::new(Config::with_privacy_level(privacy_level));
+    let result = processor.process_image_data(&data, std::path::Path::new(&file_name))
+        .map_err(|e| (422, format!("Failed to clean image: {}", e)))?;
+
+    let content_type = Header::from_bytes(&b"Content-Type"[..], content_type_for(&file_name).as_bytes())
+        .map_err(|_| (500, "Cannot build the response".to_string()))?;
+    let mut response = Response::from_data(result.output(&data).to_vec()).with_header(content_type);
+    if let Some(disposition) = content_disposition_for(&file_name) {
+        match Header::from_bytes(&b"Content-Disposition"[..], disposition.as_bytes()) {
+            Ok(header) => response = response.with_header(header),
+            Err(_) => warn!(file_name = %file_name, "Cannot name the download, sending it without a file name"),
+        }
+    }
+
+    Ok(response)
+}
+
+fn text_response(status: u16, message: &str) -> HttpResponse {
+    Response::from_string(message)
+        .with_status_code(status)
+}
+
+fn parse_privacy_level(value: &str) -> Result<PrivacyLevel, (u16, String)> {
+    PrivacyLevel::from_str(value, true)
+        .map_err(|_| (400, format!("Unknown privacy level '{}'", value)))
+}
+
+/// Split a request URL into path and query string
+fn split_url(url: &str) -> (&str, &str) {
+    url.split_once('?').unwrap_or((url, ""))
+}
+
+/// Find a parameter in a query string (no percent-decoding; values are simple identifiers)
+fn query_param<'a>(query: &'a str, name: &str) -> Option<&'a str> {
+    query.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
+/// Extract the boundary from a `multipart/form-data; boundary=...` content type
+fn multipart_boundary(content_type: &str) -> Option<String> {
+    let mut parts = content_type.split(';').map(str::trim);
+    if !parts.next()?.eq_ignore_ascii_case("multipart/form-data") {
+        return None;
+    }
+
+    parts
+        .filter_map(|part| part.strip_prefix("boundary="))
+        .map(|boundary| boundary.trim_matches('"').to_string())
+        .next()
+}
+
+fn content_type_for(file_name: &str) -> &'static str {
+    match crate::utils::get_file_extension(std::path::Path::new(file_name)).as_deref() {
+        Some("tif") | Some("tiff") => "image/tiff",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// An attachment header naming the download; header values are printable ASCII only
+fn content_disposition_for(file_name: &str) -> Option<String> {
+    let safe_name: String = crate::utils::sanitize_filename(file_name)
+        .chars()
+        .map(|c| if (c.is_ascii_graphic() && c != '"') || c == ' ' { c } else { '_' })
+        .collect();
+    if safe_name.is_empty() {
+        None
+    } else {
+        Some(format!("attachment; filename=\"{}\"", safe_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_url_and_query_param() {
+        let (path, query) = split_url("/clean?privacy=strict&x=1");
+        assert_eq!(path, "/clean");
+        assert_eq!(query_param(query, "privacy"), Some("strict"));
+        assert_eq!(query_param(query, "missing"), None);
+
+        assert_eq!(split_url("/health"), ("/health", ""));
+    }
+
+    #[test]
+    fn test_multipart_boundary() {
+        assert_eq!(
+            multipart_boundary("multipart/form-data; boundary=----abc123"),
+            Some("----abc123".to_string())
+        );
+        assert_eq!(
+            multipart_boundary("multipart/form-data; boundary=\"quoted\""),
+            Some("quoted".to_string())
+        );
+        assert_eq!(multipart_boundary("application/json"), None);
+    }
+
+    #[test]
+    fn test_parse_privacy_level() {
+        assert!(matches!(parse_privacy_level("Strict"), Ok(PrivacyLevel::Strict)));
+        assert_eq!(parse_privacy_level("extreme").unwrap_err().0, 400);
+    }
+
+    #[test]
+    fn test_content_type_for() {
+        assert_eq!(content_type_for("photo.JPG"), "image/jpeg");
+        assert_eq!(content_type_for("scan.tiff"), "image/tiff");
+        assert_eq!(content_type_for("upload"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_content_disposition_is_a_valid_header() {
+        assert_eq!(content_disposition_for("a\"b.jpg").unwrap(), "attachment; filename=\"a_b.jpg\"");
+        for name in ["Fotoğraf.jpg", "line\r\nbreak.jpg", "写真.jpg"] {
+            let disposition = content_disposition_for(name).unwrap();
+            assert!(Header::from_bytes(&b"Content-Disposition"[..], disposition.as_bytes()).is_ok(), "{}", disposition);
+        }
+        assert_eq!(content_disposition_for(""), None);
+    }
+}
@@ -0,0 +1,205 @@
+//! Long-running ExifTool processes for parallel runs (`--jobs`).
+//!
+//! Starting ExifTool means starting Perl and loading its modules, which takes
+//! longer than cleaning a typical photo. With `-stay_open` one process reads
+//! argument lists from stdin and runs each on `-execute`, so it starts once per
+//! run instead of once per file. Each worker thread keeps its own process,
+//! so workers never queue behind one another on a shared ExifTool.
+
+use std::cell::RefCell;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::capabilities;
+
+/// How long a process told to exit may take before it is killed
+const EXIT_GRACE: Duration = Duration::from_millis(500);
+
+thread_local! {
+    /// This thread's process, started by its first command
+    static PROCESS: RefCell<Option<StayOpen>> = const { RefCell::new(None) };
+}
+
+/// What one command printed
+#[derive(Debug, Default)]
+pub struct Response {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl Response {
+    /// Whether ExifTool reported an error, which makes a standalone run exit unsuccessfully
+    pub fn failed(&self) -> bool {
+        String::from_utf8_lossy(&self.stderr).lines().any(|line| line.starts_with("Error"))
+    }
+}
+
+/// Run one command on this thread's process, given as argument-file lines
+///
+/// A process that fails or exceeds `timeout` is killed, and the thread's next
+/// command starts a new one.
+pub fn execute(args: &str, timeout: Duration) -> Result<Response, Box<dyn std::error::Error>> {
+    PROCESS.with(|process| {
+        let mut process = process.borrow_mut();
+        let mut running = match process.take() {
+            Some(running) => running,
+            None => StayOpen::start()?,
+        };
+        let response = running.execute(args, timeout);
+        match response {
+            Ok(_) => *process = Some(running),
+            Err(_) => running.kill(),
+        }
+        response
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// An ExifTool process reading commands from stdin
+struct StayOpen {
+    child: Child,
+    stdin: ChildStdin,
+    output: Receiver<(Stream, Vec<u8>)>,
+    /// Output read but not yet claimed by a command
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    executed: u64,
+}
+
+impl StayOpen {
+    fn start() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut child = capabilities::command()
+            .arg("-stay_open").arg("True")
+            .arg("-@").arg("-")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("ExifTool not found ({}). Install it and ensure it's in your PATH, or pass --exiftool", e))?;
+        let stdin = child.stdin.take().ok_or("Failed to open ExifTool stdin")?;
+        let (sender, output) = mpsc::channel();
+        forward(child.stdout.take(), Stream::Stdout, sender.clone());
+        forward(child.stderr.take(), Stream::Stderr, sender);
+        Ok(StayOpen { child, stdin, output, stdout: Vec::new(), stderr: Vec::new(), executed: 0 })
+    }
+
+    /// Send one command and read both streams up to its numbered ready marker
+    fn execute(&mut self, args: &str, timeout: Duration) -> Result<Response, Box<dyn std::error::Error>> {
+        self.executed += 1;
+        let marker = format!("{{ready{}}}", self.executed);
+        // `-echo4` prints to stderr once the command is done, as `-executeN` does to stdout
+        write!(self.stdin, "{}-echo4\n{}\n-execute{}\n", args, marker, self.executed)?;
+        self.stdin.flush()?;
+
+        let deadline = Instant::now() + timeout;
+        let (mut stdout, mut stderr) = (None, None);
+        loop {
+            stdout = stdout.or_else(|| take_through(&mut self.stdout, &marker));
+            stderr = stderr.or_else(|| take_through(&mut self.stderr, &marker));
+            if let (Some(stdout), Some(stderr)) = (&mut stdout, &mut stderr) {
+                return Ok(Response { stdout: std::mem::take(stdout), stderr: std::mem::take(stderr) });
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match self.output.recv_timeout(remaining) {
+                Ok((Stream::Stdout, bytes)) => self.stdout.extend(bytes),
+                Ok((Stream::Stderr, bytes)) => self.stderr.extend(bytes),
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(format!("ExifTool timed out after {}s and was killed", timeout.as_secs_f64()).into());
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let stderr = String::from_utf8_lossy(&self.stderr);
+                    return Err(format!("ExifTool exited unexpectedly: {}", stderr.trim()).into());
+                }
+            }
+        }
+    }
+
+    fn kill(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for StayOpen {
+    /// Ask the process to exit, and kill it if it doesn't
+    fn drop(&mut self) {
+        let _ = self.stdin.write_all(b"-stay_open\nFalse\n").and_then(|_| self.stdin.flush());
+        let deadline = Instant::now() + EXIT_GRACE;
+        while Instant::now() < deadline {
+            if let Ok(Some(_)) = self.child.try_wait() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        self.kill();
+    }
+}
+
+/// Pass a pipe's output on in chunks as it arrives, until it closes
+fn forward<R: Read + Send + 'static>(pipe: Option<R>, stream: Stream, sender: Sender<(Stream, Vec<u8>)>) {
+    let Some(mut pipe) = pipe else { return };
+    thread::spawn(move || {
+        let mut buf = [0; 8192];
+        loop {
+            match pipe.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if sender.send((stream, buf[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+/// The output before `marker`, once the marker's line is complete; the line is consumed too
+fn take_through(buffer: &mut Vec<u8>, marker: &str) -> Option<Vec<u8>> {
+    let start = buffer.windows(marker.len()).position(|window| window == marker.as_bytes())?;
+    let line_end = start + buffer[start..].iter().position(|&byte| byte == b'\n')? + 1;
+    let output = buffer[..start].to_vec();
+    buffer.drain(..line_end);
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_through_marker() {
+        let mut buffer = b"    1 image files updated\n{ready1}\r\n{ready2".to_vec();
+        assert_eq!(take_through(&mut buffer, "{ready1}").unwrap(), b"    1 image files updated\n");
+        assert_eq!(buffer, b"{ready2");
+        // The marker's line isn't complete yet
+        assert_eq!(take_through(&mut buffer, "{ready2}"), None);
+        buffer.extend_from_slice(b"}\n");
+        assert_eq!(take_through(&mut buffer, "{ready2}").unwrap(), b"");
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_errors_fail_the_response() {
+        let response = Response { stdout: Vec::new(), stderr: b"Warning: [minor] Bad MakerNotes\n".to_vec() };
+        assert!(!response.failed());
+        let response = Response { stdout: Vec::new(), stderr: b"Error: File not found - a.jpg\n".to_vec() };
+        assert!(response.failed());
+    }
+
+    #[test]
+    #[ignore] // Run only when ExifTool is definitely available
+    fn test_process_is_reused() {
+        let first = execute("-ver\n", Duration::from_secs(10)).unwrap();
+        let second = execute("-ver\n", Duration::from_secs(10)).unwrap();
+        assert!(!first.stdout.is_empty());
+        assert_eq!(first.stdout, second.stdout);
+    }
+}
@@ -0,0 +1,108 @@
+//! Terminal colours (`--color`).
+//!
+//! Findings are tinted by severity and the summary picks out the categories
+//! that identify a person, so a long verbose run can be skimmed. Colour is on
+//! only for streams that are terminals, unless `--color always` asks for it,
+//! and never when `NO_COLOR` is set (see no-color.org) or `TERM` is `dumb`.
+//! Library users get plain text until they call `init`.
+
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::analyzer::Severity;
+use crate::cli::ColorChoice;
+
+static STDOUT: AtomicBool = AtomicBool::new(false);
+static STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Where styled text is written
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    /// Summaries and reports
+    Stdout,
+    /// Log lines
+    Stderr,
+}
+
+/// Decide for both streams whether to colour them
+pub fn init(choice: &ColorChoice) {
+    set(Stream::Stdout, wanted(choice, std::io::stdout().is_terminal()));
+    set(Stream::Stderr, wanted(choice, std::io::stderr().is_terminal()));
+}
+
+/// Turn colour on or off for one stream, e.g. off for JSON logs
+pub fn set(stream: Stream, enabled: bool) {
+    flag(stream).store(enabled, Ordering::Relaxed);
+}
+
+pub fn enabled(stream: Stream) -> bool {
+    flag(stream).load(Ordering::Relaxed)
+}
+
+/// Whether `choice` means colour for a stream that is (or isn't) a terminal
+pub fn wanted(choice: &ColorChoice, is_terminal: bool) -> bool {
+    let no_color = std::env::var_os("NO_COLOR").map_or(false, |value| !value.is_empty());
+    let dumb = std::env::var("TERM").map_or(false, |term| term == "dumb");
+    match choice {
+        ColorChoice::Always => !no_color,
+        ColorChoice::Auto => is_terminal && !no_color && !dumb,
+        ColorChoice::Never => false,
+    }
+}
+
+/// `text` in the colour of `severity`: high is bold red, medium yellow, low cyan, info dim
+pub fn severity(text: &str, severity: Severity, stream: Stream) -> String {
+    let code = match severity {
+        Severity::High => "1;31",
+        Severity::Medium => "33",
+        Severity::Low => "36",
+        Severity::Info => "2",
+    };
+    paint(text, code, stream)
+}
+
+/// `text` in red, for errors
+pub fn error(text: &str, stream: Stream) -> String {
+    paint(text, "31", stream)
+}
+
+/// `text` in bold, for headings
+pub fn bold(text: &str, stream: Stream) -> String {
+    paint(text, "1", stream)
+}
+
+fn paint(text: &str, code: &str, stream: Stream) -> String {
+    if enabled(stream) {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
+
+fn flag(stream: Stream) -> &'static AtomicBool {
+    match stream {
+        Stream::Stdout => &STDOUT,
+        Stream::Stderr => &STDERR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_never_and_plain_text() {
+        assert!(!wanted(&ColorChoice::Never, true));
+        // Nothing enables colour in tests, so text passes through unchanged
+        assert_eq!(severity("high", Severity::High, Stream::Stdout), "high");
+        assert_eq!(error("Errors: 2", Stream::Stderr), "Errors: 2");
+    }
+
+    #[test]
+    fn test_auto_needs_a_terminal() {
+        if std::env::var_os("NO_COLOR").is_none() && std::env::var("TERM").map_or(true, |term| term != "dumb") {
+            assert!(wanted(&ColorChoice::Auto, true));
+            assert!(wanted(&ColorChoice::Always, false));
+        }
+        assert!(!wanted(&ColorChoice::Auto, false));
+    }
+}
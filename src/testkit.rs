@@ -0,0 +1,320 @@
+//! Synthetic test images (`testkit` feature).
+//!
+//! Builds JPEGs and TIFFs with chosen EXIF, GPS, and XMP in memory, so tests
+//! (this crate's and downstream ones) can check cleaning behavior without
+//! shipping binary fixtures:
+//!
+//! ```ignore
+//! let jpeg = TestImage::jpeg()
+//!     .with_tag(Tag::Artist, "Jane Doe")
+//!     .with_tag(Tag::BodySerialNumber, "SN-1234")
+//!     .with_gps(48.8584, 2.2945)
+//!     .with_xmp(r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"/>"#)
+//!     .build();
+//! ```
+//!
+//! Images are 1x1 pixel, 8-bit grayscale. TIFFs have real (uncompressed)
+//! pixel data; JPEG scan data is filler, since nothing in the cleaner decodes it.
+
+use exif::{Context, Tag};
+
+/// Tags of the IFD pointers and the TIFF XMP packet
+const EXIF_IFD_POINTER: u16 = 0x8769;
+const GPS_IFD_POINTER: u16 = 0x8825;
+const XMP_TAG: u16 = 0x02BC;
+/// TIFF field types
+const BYTE: u16 = 1;
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+/// Identifies XMP in a JPEG APP1 segment
+const XMP_NAMESPACE: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+
+/// Container of a test image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestFormat {
+    Jpeg,
+    Tiff,
+}
+
+/// A synthetic image under construction
+#[derive(Debug, Clone)]
+pub struct TestImage {
+    format: TestFormat,
+    tags: Vec<(Tag, String)>,
+    gps: Option<(f64, f64)>,
+    xmp: Option<String>,
+}
+
+impl TestImage {
+    pub fn jpeg() -> Self {
+        Self::new(TestFormat::Jpeg)
+    }
+
+    pub fn tiff() -> Self {
+        Self::new(TestFormat::Tiff)
+    }
+
+    pub fn new(format: TestFormat) -> Self {
+        Self { format, tags: Vec::new(), gps: None, xmp: None }
+    }
+
+    /// Add a text tag of IFD0 (`Artist`, `Make`, ...) or the Exif IFD (`BodySerialNumber`, `DateTimeOriginal`, ...)
+    ///
+    /// Values are written as ASCII, whatever type the tag normally has. GPS
+    /// tags go through `with_gps`.
+    pub fn with_tag(mut self, tag: Tag, value: &str) -> Self {
+        assert!(
+            matches!(tag.context(), Context::Tiff | Context::Exif),
+            "{} is not an IFD0 or Exif IFD tag", tag
+        );
+        self.tags.push((tag, value.to_string()));
+        self
+    }
+
+    /// Add a GPS position in decimal degrees (negative for south and west)
+    pub fn with_gps(mut self, latitude: f64, longitude: f64) -> Self {
+        self.gps = Some((latitude, longitude));
+        self
+    }
+
+    /// Add an XMP packet: an APP1 segment in JPEGs, the XMP tag of IFD0 in TIFFs
+    pub fn with_xmp(mut self, packet: &str) -> Self {
+        self.xmp = Some(packet.to_string());
+        self
+    }
+
+    /// The complete image file
+    pub fn build(&self) -> Vec<u8> {
+        match self.format {
+            TestFormat::Jpeg => self.build_jpeg(),
+            TestFormat::Tiff => self.build_tiff(true),
+        }
+    }
+
+    /// The TIFF-structured EXIF block, as stored in a JPEG's APP1 segment
+    pub fn exif_block(&self) -> Vec<u8> {
+        self.build_tiff(false)
+    }
+
+    fn has_exif(&self) -> bool {
+        !self.tags.is_empty() || self.gps.is_some()
+    }
+
+    fn build_jpeg(&self) -> Vec<u8> {
+        let mut data = vec![0xFF, 0xD8];
+
+        if self.has_exif() {
+            let mut payload = b"Exif\0\0".to_vec();
+            payload.extend_from_slice(&self.exif_block());
+            push_segment(&mut data, 0xE1, &payload);
+        }
+        if let Some(ref xmp) = self.xmp {
+            let mut payload = XMP_NAMESPACE.to_vec();
+            payload.extend_from_slice(xmp.as_bytes());
+            push_segment(&mut data, 0xE1, &payload);
+        }
+
+        // 1x1 grayscale baseline frame and scan, with filler scan data
+        push_segment(&mut data, 0xC0, &[0x08, 0x00, 0x01, 0x00, 0x01, 0x01, 0x01, 0x11, 0x00]);
+        push_segment(&mut data, 0xDA, &[0x01, 0x01, 0x00, 0x00, 0x3F, 0x00]);
+        data.extend_from_slice(&[0x7F, 0xA0, 0x00]);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data
+    }
+
+    /// A little-endian TIFF; `with_image` adds the strip of a 1x1 grayscale image
+    fn build_tiff(&self, with_image: bool) -> Vec<u8> {
+        let mut ifd0 = Vec::new();
+        let mut exif_ifd = Vec::new();
+        for (tag, value) in &self.tags {
+            let mut bytes = value.as_bytes().to_vec();
+            bytes.push(0);
+            let entry = Entry { tag: tag.number(), kind: ASCII, count: bytes.len() as u32, data: bytes };
+            match tag.context() {
+                Context::Exif => exif_ifd.push(entry),
+                _ => ifd0.push(entry),
+            }
+        }
+        if let Some(xmp) = self.xmp.as_ref().filter(|_| self.format == TestFormat::Tiff) {
+            ifd0.push(Entry { tag: XMP_TAG, kind: BYTE, count: xmp.len() as u32, data: xmp.as_bytes().to_vec() });
+        }
+        let gps_ifd = self.gps.map(gps_entries).unwrap_or_default();
+
+        // The pixel goes right after the header, the IFDs after it
+        let pixel_offset = 8u32;
+        let ifd0_offset = if with_image { 10 } else { 8 };
+        if with_image {
+            ifd0.extend([
+                Entry::short(0x0100, 1),
+                Entry::short(0x0101, 1),
+                Entry::short(0x0102, 8),
+                Entry::short(0x0103, 1),
+                Entry::short(0x0106, 1),
+                Entry::long(0x0111, pixel_offset),
+                Entry::short(0x0115, 1),
+                Entry::long(0x0116, 1),
+                Entry::long(0x0117, 1),
+            ]);
+        }
+
+        // Pointers are fixed-size, so the IFD sizes are known before their offsets
+        let pointer_count = !exif_ifd.is_empty() as usize + !gps_ifd.is_empty() as usize;
+        let ifd0_len = ifd_len(&ifd0) + 12 * pointer_count as u32;
+        let exif_offset = ifd0_offset + ifd0_len;
+        let gps_offset = exif_offset + if exif_ifd.is_empty() { 0 } else { ifd_len(&exif_ifd) };
+        if !exif_ifd.is_empty() {
+            ifd0.push(Entry::long(EXIF_IFD_POINTER, exif_offset));
+        }
+        if !gps_ifd.is_empty() {
+            ifd0.push(Entry::long(GPS_IFD_POINTER, gps_offset));
+        }
+
+        let mut data = b"II*\0".to_vec();
+        data.extend_from_slice(&ifd0_offset.to_le_bytes());
+        if with_image {
+            data.extend_from_slice(&[0x80, 0x00]);
+        }
+        write_ifd(&mut data, ifd0);
+        if !exif_ifd.is_empty() {
+            write_ifd(&mut data, exif_ifd);
+        }
+        if !gps_ifd.is_empty() {
+            write_ifd(&mut data, gps_ifd);
+        }
+        data
+    }
+}
+
+/// One IFD entry with its value bytes, inline or not
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    data: Vec<u8>,
+}
+
+impl Entry {
+    fn short(tag: u16, value: u16) -> Self {
+        Entry { tag, kind: SHORT, count: 1, data: value.to_le_bytes().to_vec() }
+    }
+
+    fn long(tag: u16, value: u32) -> Self {
+        Entry { tag, kind: LONG, count: 1, data: value.to_le_bytes().to_vec() }
+    }
+
+    fn rationals(tag: u16, values: &[(u32, u32)]) -> Self {
+        let data = values.iter()
+            .flat_map(|(numerator, denominator)| numerator.to_le_bytes().into_iter().chain(denominator.to_le_bytes()))
+            .collect();
+        Entry { tag, kind: RATIONAL, count: values.len() as u32, data }
+    }
+
+    /// Bytes stored after the IFD, padded to a word boundary
+    fn out_of_line_len(&self) -> u32 {
+        if self.data.len() > 4 { (self.data.len() as u32 + 1) & !1 } else { 0 }
+    }
+}
+
+fn gps_entries((latitude, longitude): (f64, f64)) -> Vec<Entry> {
+    let reference = |value: f64, positive: u8, negative: u8| {
+        let letter = if value < 0.0 { negative } else { positive };
+        Entry { tag: 0, kind: ASCII, count: 2, data: vec![letter, 0] }
+    };
+    let degrees = |value: f64| {
+        let value = value.abs();
+        let minutes = value.fract() * 60.0;
+        let seconds = (minutes.fract() * 60.0 * 100.0).round() as u32;
+        [(value.trunc() as u32, 1), (minutes.trunc() as u32, 1), (seconds, 100)]
+    };
+
+    vec![
+        Entry { tag: 0x0000, kind: BYTE, count: 4, data: vec![2, 3, 0, 0] },
+        Entry { tag: 0x0001, ..reference(latitude, b'N', b'S') },
+        Entry::rationals(0x0002, &degrees(latitude)),
+        Entry { tag: 0x0003, ..reference(longitude, b'E', b'W') },
+        Entry::rationals(0x0004, &degrees(longitude)),
+    ]
+}
+
+fn ifd_len(entries: &[Entry]) -> u32 {
+    2 + 12 * entries.len() as u32 + 4 + entries.iter().map(Entry::out_of_line_len).sum::<u32>()
+}
+
+/// Append an IFD at the end of `data`, followed by its out-of-line values
+fn write_ifd(data: &mut Vec<u8>, mut entries: Vec<Entry>) {
+    entries.sort_by_key(|entry| entry.tag);
+    let mut value_offset = data.len() as u32 + 2 + 12 * entries.len() as u32 + 4;
+    let mut values = Vec::new();
+
+    data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    for entry in &entries {
+        data.extend_from_slice(&entry.tag.to_le_bytes());
+        data.extend_from_slice(&entry.kind.to_le_bytes());
+        data.extend_from_slice(&entry.count.to_le_bytes());
+        if entry.data.len() <= 4 {
+            let mut inline = [0u8; 4];
+            inline[..entry.data.len()].copy_from_slice(&entry.data);
+            data.extend_from_slice(&inline);
+        } else {
+            data.extend_from_slice(&value_offset.to_le_bytes());
+            values.extend_from_slice(&entry.data);
+            values.resize(values.len() + (entry.out_of_line_len() as usize - entry.data.len()), 0);
+            value_offset += entry.out_of_line_len();
+        }
+    }
+    data.extend_from_slice(&0u32.to_le_bytes());
+    data.extend_from_slice(&values);
+}
+
+fn push_segment(data: &mut Vec<u8>, marker: u8, payload: &[u8]) {
+    data.extend_from_slice(&[0xFF, marker]);
+    data.extend_from_slice(&((payload.len() + 2) as u16).to_be_bytes());
+    data.extend_from_slice(payload);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::{In, Reader};
+
+    fn ascii_value(exif: &exif::Exif, tag: Tag) -> String {
+        exif.get_field(tag, In::PRIMARY).unwrap().display_value().to_string()
+    }
+
+    #[test]
+    fn test_tiff_round_trips_through_exif_reader() {
+        let tiff = TestImage::tiff()
+            .with_tag(Tag::Artist, "Jane Doe")
+            .with_tag(Tag::BodySerialNumber, "SN-1234")
+            .with_gps(-33.8568, 151.2153)
+            .build();
+        let exif = Reader::new().read_raw(tiff).unwrap();
+
+        assert_eq!(ascii_value(&exif, Tag::Artist), "\"Jane Doe\"");
+        assert_eq!(ascii_value(&exif, Tag::BodySerialNumber), "\"SN-1234\"");
+        assert!(ascii_value(&exif, Tag::GPSLatitudeRef).contains('S'));
+        match exif.get_field(Tag::GPSLongitude, In::PRIMARY).unwrap().value {
+            exif::Value::Rational(ref dms) => {
+                assert_eq!((dms[0].num, dms[1].num, dms[2].num, dms[2].denom), (151, 12, 5508, 100));
+            }
+            ref other => panic!("GPSLongitude should be rational, got {:?}", other),
+        }
+        assert!(exif.get_field(Tag::ImageWidth, In::PRIMARY).is_some());
+    }
+
+    #[test]
+    fn test_jpeg_has_exif_and_xmp_segments() {
+        let jpeg = TestImage::jpeg()
+            .with_tag(Tag::Make, "Testcam")
+            .with_xmp("<x:xmpmeta xmlns:x=\"adobe:ns:meta/\"/>")
+            .build();
+        let layout = crate::jpeg::parse_layout(&jpeg).unwrap();
+        let block = crate::jpeg::reassemble_exif(layout.app1_payloads()).unwrap();
+
+        assert_eq!(ascii_value(&Reader::new().read_raw(block).unwrap(), Tag::Make), "\"Testcam\"");
+        assert!(jpeg.windows(XMP_NAMESPACE.len()).any(|window| window == XMP_NAMESPACE));
+        assert_eq!(TestImage::jpeg().build().len(), 2 + 13 + 10 + 3 + 2);
+    }
+}
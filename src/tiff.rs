@@ -0,0 +1,362 @@
+//! Compacting TIFF rewriter (`--compact-tiff`).
+//!
+//! Editing a TIFF in place leaves the old bytes of a removed or shortened value
+//! where they were, merely unreferenced, so "deleted" GPS coordinates or serial
+//! numbers can still be read with a hex editor. Rewriting the file copies only
+//! what its IFDs reference into a fresh file: the live entries and their
+//! values, nested IFDs, the strips or tiles of every page, and JPEG thumbnails.
+//! Free-space lists are dropped along with the space they list.
+//!
+//! Values are copied as opaque bytes, so a maker note that addresses its data
+//! by absolute file offset no longer lines up after the rewrite.
+//!
+//! The same walk tells the deep scan which bytes are orphaned.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::ops::Range;
+use crate::headers::{encode_u16, encode_u32, type_size};
+
+/// Upper bound on pages, guarding against corrupt next-IFD chains
+const MAX_PAGES: usize = 65536;
+/// Deepest nesting of IFDs followed, e.g. IFD0 > EXIF > Interop is 2
+const MAX_DEPTH: usize = 4;
+
+const STRIP_OFFSETS: u16 = 0x0111;
+const STRIP_BYTE_COUNTS: u16 = 0x0117;
+const FREE_OFFSETS: u16 = 0x0120;
+const FREE_BYTE_COUNTS: u16 = 0x0121;
+const TILE_OFFSETS: u16 = 0x0144;
+const TILE_BYTE_COUNTS: u16 = 0x0145;
+const SUB_IFDS: u16 = 0x014A;
+const JPEG_INTERCHANGE_FORMAT: u16 = 0x0201;
+const JPEG_INTERCHANGE_FORMAT_LENGTH: u16 = 0x0202;
+const EXIF_IFD_POINTER: u16 = 0x8769;
+const GPS_IFD_POINTER: u16 = 0x8825;
+const INTEROP_IFD_POINTER: u16 = 0xA005;
+
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const IFD: u16 = 13;
+
+/// Tags holding the offsets of data blocks, and the tags holding their lengths
+const BLOCK_TAGS: [(u16, u16); 3] = [
+    (STRIP_OFFSETS, STRIP_BYTE_COUNTS),
+    (TILE_OFFSETS, TILE_BYTE_COUNTS),
+    (JPEG_INTERCHANGE_FORMAT, JPEG_INTERCHANGE_FORMAT_LENGTH),
+];
+
+struct Ifd<'a> {
+    entries: Vec<Entry<'a>>,
+}
+
+struct Entry<'a> {
+    tag: u16,
+    field_type: u16,
+    count: u32,
+    /// The value as stored, in the file's byte order
+    value: &'a [u8],
+    /// IFDs the entry points to
+    children: Vec<Ifd<'a>>,
+    /// Image data the entry points to
+    blocks: Vec<&'a [u8]>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    little_endian: bool,
+    visited: HashSet<u32>,
+    /// Every range read so far
+    referenced: RefCell<Vec<Range<usize>>>,
+}
+
+impl<'a> Reader<'a> {
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8], Box<dyn std::error::Error>> {
+        let range = offset..offset.checked_add(len).ok_or("TIFF offset overflow")?;
+        let bytes = self.data.get(range.clone())
+            .ok_or_else(|| format!("TIFF data at offset {} runs past the end of the file", offset))?;
+        self.referenced.borrow_mut().push(range);
+        Ok(bytes)
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.little_endian { u16::from_le_bytes(bytes) } else { u16::from_be_bytes(bytes) }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.little_endian { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) }
+    }
+
+    /// The values of a SHORT, LONG, or IFD entry
+    fn numbers(&self, entry: &Entry) -> Result<Vec<u32>, Box<dyn std::error::Error>> {
+        match entry.field_type {
+            SHORT => Ok(entry.value.chunks_exact(2).map(|value| self.u16(value) as u32).collect()),
+            LONG | IFD => Ok(entry.value.chunks_exact(4).map(|value| self.u32(value)).collect()),
+            other => Err(format!("Tag 0x{:04X} has type {} where offsets or lengths belong", entry.tag, other).into()),
+        }
+    }
+
+    /// Read one IFD and everything it references; returns the IFD and its next-IFD offset
+    fn read_ifd(&mut self, offset: u32, depth: usize) -> Result<(Ifd<'a>, u32), Box<dyn std::error::Error>> {
+        if depth > MAX_DEPTH {
+            return Err("TIFF IFDs are nested too deeply".into());
+        }
+        if !self.visited.insert(offset) {
+            return Err(format!("TIFF IFD at offset {} is referenced twice", offset).into());
+        }
+
+        let start = offset as usize;
+        let count = self.u16(self.bytes(start, 2)?) as usize;
+        let table = self.bytes(start + 2, count * 12 + 4)?;
+        let mut entries = Vec::with_capacity(count);
+
+        for raw in table[..count * 12].chunks_exact(12) {
+            let tag = self.u16(&raw[0..2]);
+            let field_type = self.u16(&raw[2..4]);
+            let value_count = self.u32(&raw[4..8]);
+            if tag == FREE_OFFSETS || tag == FREE_BYTE_COUNTS {
+                continue;
+            }
+            // An entry of unknown type can't be sized, so it can't be copied
+            let Some(size) = type_size(field_type).map(|size| size as u64 * value_count as u64) else {
+                continue;
+            };
+            let value = if size <= 4 {
+                &raw[8..8 + size as usize]
+            } else {
+                self.bytes(self.u32(&raw[8..12]) as usize, size as usize)?
+            };
+            entries.push(Entry { tag, field_type, count: value_count, value, children: Vec::new(), blocks: Vec::new() });
+        }
+
+        for index in 0..entries.len() {
+            let entry = &entries[index];
+            let is_pointer = matches!(entry.tag, EXIF_IFD_POINTER | GPS_IFD_POINTER | INTEROP_IFD_POINTER | SUB_IFDS)
+                || entry.field_type == IFD;
+            if is_pointer {
+                let mut children = Vec::new();
+                for child in self.numbers(entry)? {
+                    children.push(self.read_ifd(child, depth + 1)?.0);
+                }
+                entries[index].children = children;
+            } else if let Some(&(_, length_tag)) = BLOCK_TAGS.iter().find(|(tag, _)| *tag == entry.tag) {
+                let lengths = entries.iter().find(|entry| entry.tag == length_tag)
+                    .ok_or_else(|| format!("Tag 0x{:04X} has no lengths in tag 0x{:04X}", entry.tag, length_tag))?;
+                let offsets = self.numbers(entry)?;
+                let lengths = self.numbers(lengths)?;
+                if offsets.len() != lengths.len() {
+                    return Err(format!("Tag 0x{:04X} has {} offsets but {} lengths", entry.tag, offsets.len(), lengths.len()).into());
+                }
+                let blocks = offsets.iter().zip(&lengths)
+                    .map(|(&offset, &length)| self.bytes(offset as usize, length as usize))
+                    .collect::<Result<_, _>>()?;
+                entries[index].blocks = blocks;
+            }
+        }
+
+        let next = self.u32(&table[count * 12..]);
+        Ok((Ifd { entries }, next))
+    }
+}
+
+struct Writer {
+    out: Vec<u8>,
+    little_endian: bool,
+}
+
+impl Writer {
+    /// Offsets in TIFF files must be word-aligned
+    fn align(&mut self) {
+        if self.out.len() % 2 != 0 {
+            self.out.push(0);
+        }
+    }
+
+    /// Append a value that doesn't fit in its entry, returning its offset
+    fn append(&mut self, bytes: &[u8]) -> u32 {
+        self.align();
+        let offset = self.out.len() as u32;
+        self.out.extend_from_slice(bytes);
+        offset
+    }
+
+    /// A LONG (or IFD) array of `values`, inline if it fits, as the entry's type, count, and value field
+    fn offsets(&mut self, field_type: u16, values: &[u32]) -> (u16, u32, [u8; 4]) {
+        let bytes: Vec<u8> = values.iter().flat_map(|&value| encode_u32(value, self.little_endian)).collect();
+        let field = match bytes.len() {
+            0..=4 => {
+                let mut inline = [0u8; 4];
+                inline[..bytes.len()].copy_from_slice(&bytes);
+                inline
+            }
+            _ => encode_u32(self.append(&bytes), self.little_endian),
+        };
+        (field_type, values.len() as u32, field)
+    }
+
+    /// Append an IFD with everything it references, returning its offset and where its next-IFD field is
+    fn write_ifd(&mut self, ifd: &Ifd) -> (u32, usize) {
+        self.align();
+        let start = self.out.len();
+        let table_len = 2 + ifd.entries.len() * 12 + 4;
+        self.out.resize(start + table_len, 0);
+        let count = encode_u16(ifd.entries.len() as u16, self.little_endian);
+        self.out[start..start + 2].copy_from_slice(&count);
+
+        let mut entries: Vec<&Entry> = ifd.entries.iter().collect();
+        entries.sort_by_key(|entry| entry.tag);
+        for (index, entry) in entries.into_iter().enumerate() {
+            let (field_type, count, field) = if !entry.children.is_empty() {
+                let children: Vec<u32> = entry.children.iter().map(|child| self.write_ifd(child).0).collect();
+                self.offsets(if entry.field_type == IFD { IFD } else { LONG }, &children)
+            } else if !entry.blocks.is_empty() || BLOCK_TAGS.iter().any(|(tag, _)| *tag == entry.tag) {
+                let blocks: Vec<u32> = entry.blocks.iter().map(|block| self.append(block)).collect();
+                self.offsets(LONG, &blocks)
+            } else if entry.value.len() <= 4 {
+                let mut inline = [0u8; 4];
+                inline[..entry.value.len()].copy_from_slice(entry.value);
+                (entry.field_type, entry.count, inline)
+            } else {
+                (entry.field_type, entry.count, encode_u32(self.append(entry.value), self.little_endian))
+            };
+
+            let pos = start + 2 + index * 12;
+            self.out[pos..pos + 2].copy_from_slice(&encode_u16(entry.tag, self.little_endian));
+            self.out[pos + 2..pos + 4].copy_from_slice(&encode_u16(field_type, self.little_endian));
+            self.out[pos + 4..pos + 8].copy_from_slice(&encode_u32(count, self.little_endian));
+            self.out[pos + 8..pos + 12].copy_from_slice(&field);
+        }
+
+        (start as u32, start + table_len - 4)
+    }
+}
+
+/// Read every page of a TIFF and everything its IFDs reference
+fn read_pages(data: &[u8]) -> Result<(Reader<'_>, Vec<Ifd<'_>>), Box<dyn std::error::Error>> {
+    let little_endian = match data.get(..4) {
+        Some([b'I', b'I', 0x2A, 0x00]) => true,
+        Some([b'M', b'M', 0x00, 0x2A]) => false,
+        Some([b'I', b'I', 0x2B, 0x00]) | Some([b'M', b'M', 0x00, 0x2B]) => return Err("BigTIFF files are not supported".into()),
+        _ => return Err("Not a TIFF file".into()),
+    };
+    let mut reader = Reader { data, little_endian, visited: HashSet::new(), referenced: RefCell::new(vec![Range { start: 0, end: 4 }]) };
+
+    let mut pages = Vec::new();
+    let mut offset = reader.u32(reader.bytes(4, 4)?);
+    while offset != 0 {
+        if pages.len() >= MAX_PAGES {
+            return Err("Too many TIFF pages".into());
+        }
+        let (page, next) = reader.read_ifd(offset, 0)?;
+        pages.push(page);
+        offset = next;
+    }
+    if pages.is_empty() {
+        return Err("TIFF file has no IFDs".into());
+    }
+    Ok((reader, pages))
+}
+
+/// The byte ranges of a TIFF that its header and IFDs reference, sorted and merged
+///
+/// Everything else in the file is orphaned: free space, or data left behind by edits.
+pub fn referenced_ranges(data: &[u8]) -> Result<Vec<Range<usize>>, Box<dyn std::error::Error>> {
+    let (reader, _) = read_pages(data)?;
+    let mut ranges = reader.referenced.into_inner();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    Ok(merged)
+}
+
+/// Rewrite a TIFF with only the data its IFDs reference
+pub fn compact(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let (reader, pages) = read_pages(data)?;
+    let little_endian = reader.little_endian;
+
+    let mut writer = Writer { out: data[..4].to_vec(), little_endian };
+    writer.out.extend_from_slice(&[0; 4]);
+    let mut next_field = 4;
+    for page in &pages {
+        let (start, next) = writer.write_ifd(page);
+        writer.out[next_field..next_field + 4].copy_from_slice(&encode_u32(start, little_endian));
+        next_field = next;
+    }
+    Ok(writer.out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A little-endian TIFF with one strip, an EXIF IFD, and orphaned bytes after the header
+    fn tiff_with_orphan() -> Vec<u8> {
+        let mut data = b"II*\0".to_vec();
+        data.extend_from_slice(&32u32.to_le_bytes());
+        // Left behind by an earlier edit
+        data.extend_from_slice(b"GHOST-SN-12\0GHOST-GPS\0\0\0");
+        assert_eq!(data.len(), 32);
+
+        let entry = |data: &mut Vec<u8>, tag: u16, field_type: u16, count: u32, value: u32| {
+            data.extend_from_slice(&tag.to_le_bytes());
+            data.extend_from_slice(&field_type.to_le_bytes());
+            data.extend_from_slice(&count.to_le_bytes());
+            data.extend_from_slice(&value.to_le_bytes());
+        };
+        // IFD0 at 32: 4 entries, ends at 32 + 2 + 48 + 4 = 86
+        data.extend_from_slice(&4u16.to_le_bytes());
+        entry(&mut data, 0x0100, SHORT, 1, 2);
+        entry(&mut data, STRIP_OFFSETS, LONG, 1, 104);
+        entry(&mut data, STRIP_BYTE_COUNTS, SHORT, 1, 4);
+        entry(&mut data, EXIF_IFD_POINTER, LONG, 1, 86);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        // EXIF IFD at 86: one inline entry, ends at 86 + 2 + 12 + 4 = 104
+        data.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut data, 0xA402, SHORT, 1, 0);
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(b"PIXL");
+        data
+    }
+
+    #[test]
+    fn test_compact_drops_unreferenced_bytes() {
+        let original = tiff_with_orphan();
+        let compacted = compact(&original).unwrap();
+
+        assert!(!compacted.windows(5).any(|window| window == b"GHOST"));
+        assert!(compacted.windows(4).any(|window| window == b"PIXL"));
+        assert!(compacted.len() < original.len());
+
+        let exif = exif::Reader::new().read_raw(compacted.clone()).unwrap();
+        let strip = exif.get_field(exif::Tag::StripOffsets, exif::In::PRIMARY).unwrap().value.get_uint(0).unwrap() as usize;
+        assert_eq!(&compacted[strip..strip + 4], b"PIXL");
+        assert!(exif.get_field(exif::Tag::ExposureMode, exif::In::PRIMARY).is_some());
+
+        // Compacting is stable
+        assert_eq!(compact(&compacted).unwrap(), compacted);
+    }
+
+    #[test]
+    fn test_compact_rejects_broken_files() {
+        let mut truncated = tiff_with_orphan();
+        truncated.truncate(100);
+        assert!(compact(&truncated).is_err());
+        assert!(compact(b"II+\0\x10\0\0\0").is_err());
+        assert!(compact(b"not a tiff").is_err());
+    }
+
+    #[test]
+    fn test_referenced_ranges_leave_out_orphans() {
+        let ranges = referenced_ranges(&tiff_with_orphan()).unwrap();
+        // Header, then the IFDs and the strip in one run
+        assert_eq!(ranges, vec![0..8, 32..108]);
+    }
+}
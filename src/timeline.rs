@@ -0,0 +1,136 @@
+//! Capture time extraction for library consumers.
+//!
+//! Cleaned output usually has no timestamps left, so archiving tools read the
+//! capture time from the original first. EXIF spreads it over three tags: the
+//! date and time to the second, the fraction of a second, and, since EXIF 2.31,
+//! the offset from UTC. This module combines them.
+
+use std::path::Path;
+use chrono::{DateTime, FixedOffset, NaiveDateTime};
+use exif::{Exif, In, Tag, Value};
+use crate::analyzer::{self, ExifAnalyzer};
+
+/// Date-time tags in order of preference, with their sub-second and offset tags
+const SOURCES: [(Tag, Tag, Tag); 3] = [
+    (Tag::DateTimeOriginal, Tag::SubSecTimeOriginal, Tag::OffsetTimeOriginal),
+    (Tag::DateTimeDigitized, Tag::SubSecTimeDigitized, Tag::OffsetTimeDigitized),
+    (Tag::DateTime, Tag::SubSecTime, Tag::OffsetTime),
+];
+
+/// When a photo was taken, as recorded by the camera
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaptureTime {
+    /// Wall-clock time where the photo was taken, sub-seconds included
+    pub local: NaiveDateTime,
+    /// Offset from UTC, when the camera recorded one
+    pub offset: Option<FixedOffset>,
+    /// The date-time tag the time was read from
+    pub source: Tag,
+}
+
+impl CaptureTime {
+    /// The capture time as an instant, when the offset is known
+    pub fn to_datetime(&self) -> Option<DateTime<FixedOffset>> {
+        self.local.and_local_timezone(self.offset?).single()
+    }
+}
+
+/// The capture time of an image, from `DateTimeOriginal` or else the digitized or modification time
+///
+/// `None` when the image has no parseable date-time tag or its metadata can't be parsed.
+pub fn extract_capture_time(data: &[u8]) -> Option<CaptureTime> {
+    let exif = ExifAnalyzer::new().read_exif(data).ok()??;
+    capture_time_from_exif(&exif)
+}
+
+/// `extract_capture_time` for an image file, reading only its metadata
+pub fn extract_capture_time_from_file(path: &Path) -> Option<CaptureTime> {
+    let exif = ExifAnalyzer::new().parse_exif_file(path).ok()??;
+    capture_time_from_exif(&exif)
+}
+
+/// The capture time from parsed EXIF data
+pub fn capture_time_from_exif(exif: &Exif) -> Option<CaptureTime> {
+    SOURCES.iter().find_map(|&(tag, subsec, offset)| {
+        let mut datetime = exif::DateTime::from_ascii(ascii(exif, tag)?).ok()?;
+        // A malformed fraction or offset loses precision, not the time itself
+        if let Some(subsec) = ascii(exif, subsec) {
+            let _ = datetime.parse_subsec(subsec);
+        }
+        if let Some(offset) = ascii(exif, offset) {
+            let _ = datetime.parse_offset(offset);
+        }
+
+        Some(CaptureTime {
+            local: analyzer::naive_datetime(&datetime)?,
+            offset: datetime.offset.and_then(|minutes| FixedOffset::east_opt(i32::from(minutes) * 60)),
+            source: tag,
+        })
+    })
+}
+
+/// The first string of an ASCII tag in the primary image
+fn ascii(exif: &Exif, tag: Tag) -> Option<&[u8]> {
+    match exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(ref values) => values.first().map(Vec::as_slice),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use crate::jpeg::{self, tests::build_jpeg};
+
+    /// Little-endian EXIF whose Exif IFD holds `DateTimeOriginal` with sub-seconds and an offset
+    fn exif_with_capture_time(offset: &[u8; 7]) -> Vec<u8> {
+        let entry = |payload: &mut Vec<u8>, tag: u16, count: u32, value: [u8; 4]| {
+            payload.extend_from_slice(&tag.to_le_bytes());
+            payload.extend_from_slice(&2u16.to_le_bytes());
+            payload.extend_from_slice(&count.to_le_bytes());
+            payload.extend_from_slice(&value);
+        };
+
+        let mut payload = b"Exif\0\0II*\0".to_vec();
+        payload.extend_from_slice(&8u32.to_le_bytes());
+        payload.extend_from_slice(&1u16.to_le_bytes());
+        payload.extend_from_slice(&0x8769u16.to_le_bytes());
+        payload.extend_from_slice(&4u16.to_le_bytes());
+        payload.extend_from_slice(&1u32.to_le_bytes());
+        payload.extend_from_slice(&26u32.to_le_bytes());
+        payload.extend_from_slice(&0u32.to_le_bytes());
+
+        payload.extend_from_slice(&3u16.to_le_bytes());
+        entry(&mut payload, 0x9003, 20, 68u32.to_le_bytes());
+        entry(&mut payload, 0x9011, 7, 88u32.to_le_bytes());
+        entry(&mut payload, 0x9291, 3, *b"25\0\0");
+        payload.extend_from_slice(&0u32.to_le_bytes());
+
+        payload.extend_from_slice(b"2024:05:17 14:03:09\0");
+        payload.extend_from_slice(offset);
+        payload
+    }
+
+    #[test]
+    fn test_capture_time_combines_subseconds_and_offset() {
+        let data = build_jpeg(&[(jpeg::APP1, &exif_with_capture_time(b"+02:00\0"))], b"pixels");
+
+        let taken = extract_capture_time(&data).unwrap();
+        let local = NaiveDate::from_ymd_opt(2024, 5, 17).unwrap().and_hms_milli_opt(14, 3, 9, 250).unwrap();
+        assert_eq!(taken.local, local);
+        assert_eq!(taken.offset, FixedOffset::east_opt(2 * 3600));
+        assert_eq!(taken.source, Tag::DateTimeOriginal);
+        assert_eq!(taken.to_datetime().unwrap().to_rfc3339(), "2024-05-17T14:03:09.250+02:00");
+    }
+
+    #[test]
+    fn test_capture_time_without_valid_offset_is_local() {
+        let data = build_jpeg(&[(jpeg::APP1, &exif_with_capture_time(b"bogus!\0"))], b"pixels");
+
+        let taken = extract_capture_time(&data).unwrap();
+        assert_eq!(taken.offset, None);
+        assert_eq!(taken.to_datetime(), None);
+        assert_eq!(extract_capture_time(&build_jpeg(&[], b"pixels")), None);
+    }
+}
@@ -0,0 +1,514 @@
+//! Privacy audits of images received from others.
+//!
+//! Backs the `audit` subcommand. Journalists and moderators who receive
+//! images need to know what a file gives away about its source before they
+//! publish or forward it, without modifying the evidence. Each file gets one
+//! report covering EXIF, XMP, IPTC, the thumbnail, data trailing the image,
+//! metadata remnants outside any structure, and its file name, with a
+//! severity per finding and an overall risk score. With `--anomalies`,
+//! containers that could hide data are noted as well. Directories are
+//! audited as a batch, ranking files by risk score and totalling findings per
+//! category for triage.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use regex::Regex;
+use serde::Serialize;
+use crate::analyzer::{ExifAnalyzer, PrivacyCategory, PrivacyField, Severity};
+use crate::anomaly;
+use crate::iptc;
+use crate::jpeg;
+use crate::multipicture;
+use crate::pii;
+use crate::privacy::{self, Ifd, PolicySpec, PrivacyLevel, UnknownTags};
+use crate::report;
+use crate::style::{self, Stream};
+use crate::utils;
+
+/// Where in the file a finding was made
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Source {
+    Exif,
+    Xmp,
+    Iptc,
+    Thumbnail,
+    Trailer,
+    #[serde(rename = "File name")]
+    FileName,
+    /// Other text metadata: PNG text chunks, GIF comments, BMP profile paths
+    Text,
+    /// Bytes outside any metadata structure
+    Remnant,
+    /// A container that could hide data
+    Anomaly,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Exif => write!(f, "EXIF"),
+            Source::Xmp => write!(f, "XMP"),
+            Source::Iptc => write!(f, "IPTC"),
+            Source::Thumbnail => write!(f, "Thumbnail"),
+            Source::Trailer => write!(f, "Trailer"),
+            Source::FileName => write!(f, "File name"),
+            Source::Text => write!(f, "Text"),
+            Source::Remnant => write!(f, "Remnant"),
+            Source::Anomaly => write!(f, "Anomaly"),
+        }
+    }
+}
+
+/// One thing a file discloses
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditFinding {
+    pub source: Source,
+    /// Privacy category, as in cleaning findings
+    pub category: String,
+    pub severity: Severity,
+    pub description: String,
+}
+
+/// Everything one file discloses
+#[derive(Debug, Clone, Serialize)]
+pub struct FileAudit {
+    pub path: String,
+    pub findings: Vec<AuditFinding>,
+    /// Sum of the findings' severity weights; higher means more revealing
+    pub risk_score: u32,
+}
+
+/// Findings of one category across a batch
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CategoryTotals {
+    pub findings: usize,
+    /// Files with at least one finding of the category
+    pub files: usize,
+}
+
+/// The audit of many files, for triage
+#[derive(Debug, Clone, Serialize)]
+pub struct BatchAudit {
+    pub files_audited: usize,
+    /// Files with any finding
+    pub files_with_findings: usize,
+    /// Files that could not be read
+    pub failed: Vec<String>,
+    /// The riskiest files, highest score first
+    pub ranked: Vec<FileAudit>,
+    pub by_category: BTreeMap<String, CategoryTotals>,
+    pub by_severity: BTreeMap<Severity, usize>,
+}
+
+/// Audit image files and every supported image in directories, keeping the `top` riskiest
+pub fn audit_paths(
+    paths: &[PathBuf],
+    recursive: bool,
+    top: usize,
+    anomalies: bool,
+) -> Result<BatchAudit, Box<dyn std::error::Error>> {
+    let mut audits = Vec::new();
+    let mut failed = Vec::new();
+
+    for path in paths {
+        let files: Vec<PathBuf> = if path.is_dir() {
+            report::supported_images(path, recursive).collect::<Result<_, _>>()?
+        } else {
+            vec![path.clone()]
+        };
+        for file in files {
+            match audit_file(&file, anomalies) {
+                Ok(audit) => audits.push(audit),
+                Err(e) => failed.push(format!("{}: {}", file.display(), e)),
+            }
+        }
+    }
+
+    Ok(BatchAudit::from_audits(audits, failed, top))
+}
+
+/// Audit an image file without modifying it; `anomalies` also notes containers that could hide data
+pub fn audit_file(path: &Path, anomalies: bool) -> Result<FileAudit, Box<dyn std::error::Error>> {
+    let data = utils::map_file(path)?;
+    Ok(audit_data(path, &data, anomalies))
+}
+
+/// Audit an in-memory image; `path` supplies the file name
+pub fn audit_data(path: &Path, data: &[u8], anomalies: bool) -> FileAudit {
+    let analyzer = ExifAnalyzer::new()
+        .with_strip_originals(true)
+        .with_drop_motion(true)
+        .with_unknown_tags(UnknownTags::Remove)
+        .with_deep_scan(true);
+
+    let mut findings = Vec::new();
+    let fields = match analyzer.analyze_privacy_data(data, path, &audit_level()) {
+        Ok(fields) => fields,
+        Err(e) => {
+            findings.push(AuditFinding {
+                source: Source::Exif,
+                category: PrivacyCategory::Other.to_string(),
+                // What unparseable metadata holds is unknown, so assume the worst
+                severity: Severity::High,
+                description: e.to_string(),
+            });
+            Vec::new()
+        }
+    };
+    let reported = |tags: &[exif::Tag]| fields.iter().any(|field| tags.contains(&field.tag));
+    // Stale thumbnails and recognized trailers are already reported with what they hold
+    if !reported(&[exif::Tag::JPEGInterchangeFormat]) {
+        findings.extend(thumbnail_finding(&analyzer, data));
+    }
+    if !reported(&[privacy::EDIT_TRAILER, privacy::MOTION_VIDEO]) {
+        findings.extend(trailer_finding(data));
+    }
+    findings.extend(fields.iter().map(field_finding));
+    findings.extend(block_findings(&analyzer, data));
+    if anomalies {
+        findings.extend(anomaly_findings(&analyzer, data));
+    }
+    if let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) {
+        findings.extend(file_name_findings(name));
+    }
+    findings.sort_by_key(|finding| Reverse(finding.severity));
+
+    FileAudit {
+        path: path.display().to_string(),
+        risk_score: findings.iter().map(|finding| weight(finding.severity)).sum(),
+        findings,
+    }
+}
+
+/// Strict, but with XMP and IPTC kept so their text is scanned for personal data
+///
+/// Their presence is reported separately by `block_findings`.
+fn audit_level() -> PrivacyLevel {
+    let mut spec = PolicySpec::from_level(&PrivacyLevel::Strict);
+    spec.remove_groups.retain(|group| *group != "XMP" && *group != "IPTC");
+    PrivacyLevel::Custom(spec)
+}
+
+/// Contribution of a finding to the risk score
+fn weight(severity: Severity) -> u32 {
+    match severity {
+        Severity::Info => 0,
+        Severity::Low => 1,
+        Severity::Medium => 3,
+        Severity::High => 10,
+    }
+}
+
+fn field_finding(field: &PrivacyField) -> AuditFinding {
+    let source = match field.tag {
+        privacy::XMP_PACKET | privacy::XMP_ORIGINAL | privacy::MOTION_VIDEO => Source::Xmp,
+        privacy::IPTC_NAA => Source::Iptc,
+        privacy::EDIT_TRAILER => Source::Trailer,
+        privacy::PNG_TEXT | privacy::GIF_COMMENT | privacy::LINKED_PROFILE => Source::Text,
+        privacy::REMNANT => Source::Remnant,
+        exif::Tag::JPEGInterchangeFormat => Source::Thumbnail,
+        _ if field.ifd == Some(Ifd::Ifd1) => Source::Thumbnail,
+        _ => Source::Exif,
+    };
+    AuditFinding {
+        source,
+        category: field.category.to_string(),
+        severity: field.category.severity(),
+        description: field.description.clone(),
+    }
+}
+
+/// The XMP and IPTC blocks themselves, which often name the author or the software that made the file
+fn block_findings(analyzer: &ExifAnalyzer, data: &[u8]) -> Vec<AuditFinding> {
+    let mut findings = Vec::new();
+    let block = |source, description| AuditFinding {
+        source,
+        category: PrivacyCategory::Metadata.to_string(),
+        severity: Severity::Low,
+        description,
+    };
+
+    let xmp = analyzer.xmp_bytes(data);
+    if !xmp.is_empty() {
+        let properties = pii::xmp_text_fields(&String::from_utf8_lossy(&xmp)).len();
+        findings.push(block(Source::Xmp, format!("XMP metadata ({} bytes, {} text properties)", xmp.len(), properties)));
+    }
+
+    let iim = jpeg::parse_layout(data).ok().and_then(|layout| {
+        layout.segments.iter()
+            .filter(|segment| segment.marker == jpeg::APP13)
+            .find_map(|segment| iptc::from_photoshop(segment.payload))
+            .map(|iim| iim.to_vec())
+    });
+    if let Some(iim) = iim {
+        let captions = iptc::captions(&iim).len();
+        findings.push(block(Source::Iptc, format!("IPTC metadata ({} bytes, {} captions)", iim.len(), captions)));
+    }
+
+    findings
+}
+
+/// Containers that could hide data, as informational findings
+fn anomaly_findings(analyzer: &ExifAnalyzer, data: &[u8]) -> Vec<AuditFinding> {
+    let xmp = String::from_utf8_lossy(&analyzer.xmp_bytes(data)).into_owned();
+    let known: Vec<_> = multipicture::motion_video(data, &xmp).into_iter().collect();
+    anomaly::scan(data, &known).into_iter()
+        .map(|anomaly| AuditFinding {
+            source: Source::Anomaly,
+            category: PrivacyCategory::Other.to_string(),
+            severity: Severity::Info,
+            description: format!("{} (offset {})", anomaly.description, anomaly.offset),
+        })
+        .collect()
+}
+
+/// An EXIF thumbnail, which editing tools may leave showing the unedited image
+fn thumbnail_finding(analyzer: &ExifAnalyzer, data: &[u8]) -> Option<AuditFinding> {
+    let exif = analyzer.read_exif(data).ok()??;
+    let length = exif.get_field(exif::Tag::JPEGInterchangeFormatLength, exif::In::THUMBNAIL)?
+        .value
+        .get_uint(0)?;
+    Some(AuditFinding {
+        source: Source::Thumbnail,
+        category: PrivacyCategory::Metadata.to_string(),
+        severity: Severity::Medium,
+        description: format!("EXIF thumbnail ({} bytes); compare it with the image for removed content", length),
+    })
+}
+
+/// Data after a JPEG's end-of-image marker, where phones keep videos, depth maps, and edit history
+fn trailer_finding(data: &[u8]) -> Option<AuditFinding> {
+    let layout = jpeg::parse_layout(data).ok()?;
+    // Entropy-coded data escapes 0xFF bytes, so the first EOI after the scan ends the image
+    let eoi = data[layout.scan_offset..].windows(2).position(|pair| pair == [0xFF, jpeg::EOI])? + layout.scan_offset + 2;
+    let trailing = data.len() - eoi;
+    (trailing > 0).then(|| AuditFinding {
+        source: Source::Trailer,
+        category: PrivacyCategory::Other.to_string(),
+        severity: Severity::Medium,
+        description: format!("{} bytes of data after the end of the image", trailing),
+    })
+}
+
+/// Personal data, capture times, and camera defaults in a file name
+fn file_name_findings(name: &str) -> Vec<AuditFinding> {
+    static PATTERNS: OnceLock<(Regex, Regex)> = OnceLock::new();
+    let (timestamp, camera_default) = PATTERNS.get_or_init(|| {
+        (
+            Regex::new(r"(?:19|20)\d{2}[-_.]?[01]\d[-_.]?[0-3]\d[-_ T]?[0-2]\d[-_.]?[0-5]\d[-_.]?[0-5]\d").unwrap(),
+            Regex::new(r"^(?i:IMG|DSC[NF]?|PXL|GOPR|DJI|MVIMG|SAM|P\d{3})[_-]").unwrap(),
+        )
+    });
+    let finding = |category: PrivacyCategory, severity, description| AuditFinding {
+        source: Source::FileName,
+        category: category.to_string(),
+        severity,
+        description,
+    };
+
+    let mut findings: Vec<AuditFinding> = pii::scan(name).into_iter()
+        .map(|found| finding(
+            PrivacyCategory::PersonalInfo,
+            Severity::High,
+            format!("File name contains {} ({})", found.kind, found.redacted),
+        ))
+        .collect();
+    if timestamp.is_match(name) {
+        findings.push(finding(PrivacyCategory::Temporal, Severity::Medium, "File name records the capture time".to_string()));
+    }
+    if camera_default.is_match(name) {
+        findings.push(finding(
+            PrivacyCategory::DeviceIdentifier,
+            Severity::Low,
+            "File name is a camera default, hinting at the device".to_string(),
+        ));
+    }
+    findings
+}
+
+impl BatchAudit {
+    /// Rank and total file audits, keeping the `top` riskiest files
+    pub fn from_audits(mut audits: Vec<FileAudit>, failed: Vec<String>, top: usize) -> Self {
+        let mut by_category: BTreeMap<String, CategoryTotals> = BTreeMap::new();
+        let mut by_severity = BTreeMap::new();
+        for audit in &audits {
+            let mut categories: Vec<&str> = Vec::new();
+            for finding in &audit.findings {
+                let totals = by_category.entry(finding.category.clone()).or_default();
+                totals.findings += 1;
+                if !categories.contains(&finding.category.as_str()) {
+                    categories.push(&finding.category);
+                    totals.files += 1;
+                }
+                *by_severity.entry(finding.severity).or_insert(0) += 1;
+            }
+        }
+
+        let files_audited = audits.len();
+        audits.retain(|audit| !audit.findings.is_empty());
+        let files_with_findings = audits.len();
+        audits.sort_by(|a, b| b.risk_score.cmp(&a.risk_score).then_with(|| a.path.cmp(&b.path)));
+        audits.truncate(top);
+
+        BatchAudit {
+            files_audited,
+            files_with_findings,
+            failed,
+            ranked: audits,
+            by_category,
+            by_severity,
+        }
+    }
+
+    /// Render the batch audit as a readable triage summary
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Audited {} files: {} with findings, {} unreadable\n",
+            self.files_audited,
+            self.files_with_findings,
+            self.failed.len()
+        );
+
+        let severities: Vec<String> = self.by_severity.iter().rev()
+            .map(|(severity, count)| style::severity(&format!("{} {}", count, severity), *severity, Stream::Stdout))
+            .collect();
+        if !severities.is_empty() {
+            text.push_str(&format!("Findings: {}\n", severities.join(", ")));
+        }
+
+        if !self.by_category.is_empty() {
+            text.push_str("\nBy category:\n");
+        }
+        for (category, totals) in &self.by_category {
+            text.push_str(&format!("  {:<22} {:>6} findings in {:>6} files\n", category, totals.findings, totals.files));
+        }
+
+        if !self.ranked.is_empty() {
+            text.push_str(&format!("\nRiskiest {} files:\n", self.ranked.len()));
+        }
+        for (rank, audit) in self.ranked.iter().enumerate() {
+            let worst = audit.findings.first().map(|finding| finding.description.as_str()).unwrap_or_default();
+            text.push_str(&format!("  {:>4}. {:>5}  {}  ({})\n", rank + 1, audit.risk_score, audit.path, worst));
+        }
+
+        for failure in &self.failed {
+            text.push_str(&format!("Unreadable: {}\n", failure));
+        }
+        text
+    }
+}
+
+impl FileAudit {
+    /// Render the audit as a readable report
+    pub fn to_text(&self) -> String {
+        let mut text = format!("{} (risk score {})\n", self.path, self.risk_score);
+
+        if self.findings.is_empty() {
+            text.push_str("  Nothing revealing found\n");
+        }
+        for finding in &self.findings {
+            // Padded before colouring, since escape codes would count toward the width
+            let severity = format!("{:<7}", finding.severity.to_string().to_uppercase());
+            text.push_str(&format!(
+                "  {} {:<10} {:<22} {}\n",
+                style::severity(&severity, finding.severity, Stream::Stdout),
+                finding.source,
+                finding.category,
+                finding.description
+            ));
+        }
+
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jpeg::tests::build_jpeg;
+
+    #[test]
+    fn test_trailer_and_xmp_are_reported() {
+        let xmp = [jpeg::XMP_HEADER, b"<x:xmpmeta/>"].concat();
+        let mut data = build_jpeg(&[(jpeg::APP1, &xmp)], b"pixels");
+        data.extend_from_slice(b"trailing video");
+
+        let audit = audit_data(Path::new("photo.jpg"), &data, false);
+        let sources: Vec<Source> = audit.findings.iter().map(|finding| finding.source).collect();
+        assert!(sources.contains(&Source::Xmp));
+        assert!(sources.contains(&Source::Trailer));
+        assert_eq!(audit.risk_score, weight(Severity::Medium) + weight(Severity::Low));
+        assert!(audit.to_text().contains("14 bytes of data after the end of the image"));
+    }
+
+    #[test]
+    fn test_anomalies_are_informational() {
+        let mut data = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        data.extend_from_slice(b"PK\x03\x04hidden.txt");
+
+        assert!(audit_data(Path::new("upload.jpg"), &data, false).findings.iter().all(|finding| finding.source != Source::Anomaly));
+        let audit = audit_data(Path::new("upload.jpg"), &data, true);
+        let anomaly = audit.findings.iter().find(|finding| finding.source == Source::Anomaly).unwrap();
+        assert_eq!(anomaly.severity, Severity::Info);
+        assert!(anomaly.description.contains("ZIP archive"));
+        // Only the trailer itself counts
+        assert_eq!(audit.risk_score, weight(Severity::Medium));
+    }
+
+    #[test]
+    fn test_file_name_leaks() {
+        let findings = file_name_findings("PXL_20240517_140309123");
+        let severities: Vec<Severity> = findings.iter().map(|finding| finding.severity).collect();
+        assert_eq!(severities, vec![Severity::Medium, Severity::Low]);
+
+        let findings = file_name_findings("for jane.doe@example.com");
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::High);
+        assert!(!findings[0].description.contains("jane.doe@example.com"));
+
+        assert!(file_name_findings("holiday").is_empty());
+    }
+
+    #[test]
+    fn test_batch_ranks_files_and_totals_categories() {
+        let finding = |category: PrivacyCategory| AuditFinding {
+            source: Source::Exif,
+            category: category.to_string(),
+            severity: category.severity(),
+            description: String::new(),
+        };
+        let audit = |path: &str, findings: Vec<AuditFinding>| FileAudit {
+            path: path.to_string(),
+            risk_score: findings.iter().map(|finding| weight(finding.severity)).sum(),
+            findings,
+        };
+        let audits = vec![
+            audit("a.jpg", vec![finding(PrivacyCategory::Temporal)]),
+            audit("b.jpg", vec![finding(PrivacyCategory::Location), finding(PrivacyCategory::Location)]),
+            audit("c.jpg", vec![]),
+            audit("d.jpg", vec![finding(PrivacyCategory::Temporal), finding(PrivacyCategory::Software)]),
+        ];
+
+        let batch = BatchAudit::from_audits(audits, vec![], 2);
+        assert_eq!(batch.files_audited, 4);
+        assert_eq!(batch.files_with_findings, 3);
+        let ranked: Vec<&str> = batch.ranked.iter().map(|audit| audit.path.as_str()).collect();
+        assert_eq!(ranked, vec!["b.jpg", "d.jpg"]);
+        assert_eq!(batch.by_category["Location Data"], CategoryTotals { findings: 2, files: 1 });
+        assert_eq!(batch.by_category["Timestamp"], CategoryTotals { findings: 2, files: 2 });
+        assert_eq!(batch.by_severity[&Severity::High], 2);
+        assert!(batch.to_text().contains("Riskiest 2 files"));
+    }
+
+    #[test]
+    fn test_clean_image_has_no_findings() {
+        let data = build_jpeg(&[(jpeg::APP0, b"JFIF\0")], b"pixels");
+        let audit = audit_data(Path::new("upload.jpg"), &data, false);
+
+        assert!(audit.findings.is_empty());
+        assert_eq!(audit.risk_score, 0);
+        assert!(audit.to_text().contains("Nothing revealing found"));
+    }
+}
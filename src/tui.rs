@@ -0,0 +1,220 @@
+//! Terminal dashboard for large runs (`--tui`).
+//!
+//! Shows the most recent files, findings by category, throughput, and errors
+//! while a directory run is in progress. `p` pauses and resumes, `q` or Esc
+//! cancels the run after the current file.
+
+use std::collections::VecDeque;
+use std::io::{self, Stdout};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::execute;
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListItem, Paragraph, Row, Table};
+use ratatui::Terminal;
+use crate::processor::CategoryTally;
+use crate::progress::{FileOutcome, Progress, Skipped};
+use crate::utils;
+
+/// How many recent files the table keeps
+const RECENT_FILES: usize = 200;
+/// How many recent errors stay on screen
+const RECENT_ERRORS: usize = 50;
+/// Minimum time between redraws, so tiny files don't spend their time drawing
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Dashboard {
+    terminal: Terminal<CrosstermBackend<Stdout>>,
+    tally: Arc<CategoryTally>,
+    recent: VecDeque<(String, FileOutcome)>,
+    errors: Vec<String>,
+    files: u64,
+    cleaned: u64,
+    bytes: u64,
+    started: Instant,
+    last_draw: Option<Instant>,
+    paused: bool,
+    cancelled: bool,
+    /// Whether the terminal is still in raw mode on the alternate screen
+    active: bool,
+}
+
+impl Dashboard {
+    /// Take over the terminal; it is restored by `finish` or when the dashboard is dropped
+    pub fn start(tally: Arc<CategoryTally>) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        let mut stdout = io::stdout();
+        execute!(stdout, EnterAlternateScreen)?;
+
+        Ok(Self {
+            terminal: Terminal::new(CrosstermBackend::new(stdout))?,
+            tally,
+            recent: VecDeque::with_capacity(RECENT_FILES),
+            errors: Vec::new(),
+            files: 0,
+            cleaned: 0,
+            bytes: 0,
+            started: Instant::now(),
+            last_draw: None,
+            paused: false,
+            cancelled: false,
+            active: true,
+        })
+    }
+
+    /// Restore the terminal and return every error seen, for printing afterwards
+    pub fn finish(mut self) -> io::Result<Vec<String>> {
+        self.restore()?;
+        Ok(std::mem::take(&mut self.errors))
+    }
+
+    fn restore(&mut self) -> io::Result<()> {
+        if !std::mem::replace(&mut self.active, false) {
+            return Ok(());
+        }
+        terminal::disable_raw_mode()?;
+        execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
+        self.terminal.show_cursor()
+    }
+
+    /// Handle pending key presses, waiting up to `timeout` for one
+    fn handle_keys(&mut self, timeout: Duration) -> io::Result<()> {
+        if !event::poll(timeout)? {
+            return Ok(());
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind != KeyEventKind::Press {
+                return Ok(());
+            }
+            match key.code {
+                KeyCode::Char('p') | KeyCode::Char(' ') => self.paused = !self.paused,
+                KeyCode::Char('q') | KeyCode::Esc => self.cancelled = true,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let elapsed = self.started.elapsed().as_secs_f64().max(0.001);
+        let status = if self.cancelled {
+            "cancelling"
+        } else if self.paused {
+            "PAUSED (p to resume)"
+        } else {
+            "running (p pause, q cancel)"
+        };
+        let header = format!(
+            "{} files, {} with privacy data, {} errors | {:.1} files/s, {}/s | {}",
+            self.files,
+            self.cleaned,
+            self.errors.len(),
+            self.files as f64 / elapsed,
+            utils::format_file_size((self.bytes as f64 / elapsed) as u64),
+            status,
+        );
+
+        let file_rows: Vec<Row> = self.recent.iter().rev()
+            .map(|(path, outcome)| {
+                let (label, color) = match outcome {
+                    FileOutcome::Cleaned => ("cleaned", Color::Yellow),
+                    FileOutcome::Skipped(Skipped::AlreadyClean) => ("clean", Color::Green),
+                    FileOutcome::Skipped(Skipped::Duplicate) => ("duplicate", Color::Green),
+                    FileOutcome::Skipped(Skipped::Reviewed) => ("reviewed", Color::DarkGray),
+                    FileOutcome::Unsupported => ("unsupported", Color::DarkGray),
+                    FileOutcome::Failed(_) => ("error", Color::Red),
+                };
+                Row::new(vec![label.to_string(), path.clone()]).style(Style::default().fg(color))
+            })
+            .collect();
+        let category_rows: Vec<Row> = self.tally.counts().into_iter()
+            .map(|(category, count)| Row::new(vec![category.to_string(), count.to_string()]))
+            .collect();
+        let error_items: Vec<ListItem> = self.errors.iter().rev().take(RECENT_ERRORS)
+            .map(|error| ListItem::new(error.as_str()))
+            .collect();
+
+        self.terminal.draw(|frame| {
+            let rows = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(3), Constraint::Min(6), Constraint::Length(8)])
+                .split(frame.size());
+            let columns = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Min(40), Constraint::Length(36)])
+                .split(rows[1]);
+
+            frame.render_widget(
+                Paragraph::new(header).block(Block::default().borders(Borders::ALL).title("privacy-exif-cleaner")),
+                rows[0],
+            );
+            frame.render_widget(
+                Table::new(file_rows, [Constraint::Length(12), Constraint::Min(20)])
+                    .header(Row::new(vec!["Result", "File"]).style(Style::default().add_modifier(Modifier::BOLD)))
+                    .block(Block::default().borders(Borders::ALL).title("Files")),
+                columns[0],
+            );
+            frame.render_widget(
+                Table::new(category_rows, [Constraint::Min(22), Constraint::Length(10)])
+                    .header(Row::new(vec!["Category", "Fields"]).style(Style::default().add_modifier(Modifier::BOLD)))
+                    .block(Block::default().borders(Borders::ALL).title("Findings")),
+                columns[1],
+            );
+            frame.render_widget(
+                List::new(error_items).block(Block::default().borders(Borders::ALL).title("Errors")),
+                rows[2],
+            );
+        })?;
+
+        self.last_draw = Some(Instant::now());
+        Ok(())
+    }
+
+    fn draw_if_due(&mut self) -> io::Result<()> {
+        match self.last_draw {
+            Some(last) if last.elapsed() < REDRAW_INTERVAL => Ok(()),
+            _ => self.draw(),
+        }
+    }
+}
+
+impl Progress for Dashboard {
+    fn file_done(&mut self, path: &Path, size: u64, outcome: &FileOutcome) {
+        self.files += 1;
+        self.bytes += size;
+        match outcome {
+            FileOutcome::Cleaned => self.cleaned += 1,
+            FileOutcome::Failed(error) => self.errors.push(format!("{}: {}", path.display(), error)),
+            FileOutcome::Skipped(_) | FileOutcome::Unsupported => {}
+        }
+
+        if self.recent.len() == RECENT_FILES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back((path.display().to_string(), outcome.clone()));
+    }
+
+    fn keep_going(&mut self) -> bool {
+        // A broken terminal shouldn't stop the run; the summary is still printed at the end
+        let _ = self.handle_keys(Duration::ZERO);
+        let _ = self.draw_if_due();
+
+        while self.paused && !self.cancelled {
+            let _ = self.handle_keys(REDRAW_INTERVAL);
+            let _ = self.draw();
+        }
+
+        !self.cancelled
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        let _ = self.restore();
+    }
+}
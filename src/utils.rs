@@ -1,14 +1,9 @@
-use std::path::Path;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
 
-/// Check if a file is a supported image format
-pub fn is_supported_image(path: &Path) -> bool {
-    if let Some(extension) = path.extension() {
-        let ext = extension.to_string_lossy().to_lowercase();
-        matches!(ext.as_str(), "jpg" | "jpeg" | "tif" | "tiff")
-    } else {
-        false
-    }
-}
+/// Paths at least this long need the `\\?\` prefix on Windows (CreateDirectory's limit)
+#[cfg(windows)]
+const WINDOWS_PATH_LIMIT: usize = 248;
 
 /// Get a human-readable file size string
 pub fn format_file_size(bytes: u64) -> String {
@@ -28,6 +23,68 @@ pub fn format_file_size(bytes: u64) -> String {
     }
 }
 
+/// Parse a size like `500`, `64K`, `10M`, or `2GB` (binary units) into bytes
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    let upper = trimmed.to_ascii_uppercase();
+    let number = upper.trim_end_matches('B');
+    let (digits, multiplier) = match number.char_indices().last() {
+        Some((i, 'K')) => (&number[..i], 1u64 << 10),
+        Some((i, 'M')) => (&number[..i], 1 << 20),
+        Some((i, 'G')) => (&number[..i], 1 << 30),
+        Some((i, 'T')) => (&number[..i], 1 << 40),
+        _ => (number, 1),
+    };
+
+    digits.trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|value| value.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid size '{}' (expected e.g. 500M or 2G)", trimmed))
+}
+
+/// Parse a point in time: a UTC date (`2024-05-01`), date and time
+/// (`2024-05-01T18:30:00`), or an age relative to now (`30m`, `12h`, `7d`, `2w`)
+///
+/// Units are lowercase only, so `30M` is rejected rather than read as minutes.
+pub fn parse_time_bound(input: &str) -> Result<std::time::SystemTime, String> {
+    use chrono::NaiveDate;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    let input = input.trim();
+    let error = || format!("Invalid time '{}' (expected e.g. 2024-05-01 or 7d)", input);
+
+    if let Some((i, unit)) = input.char_indices().last().filter(|(_, c)| c.is_ascii_alphabetic()) {
+        let amount: u64 = input[..i].parse().map_err(|_| error())?;
+        let seconds = match unit {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return Err(error()),
+        };
+        let age = Duration::from_secs(amount.checked_mul(seconds).ok_or_else(error)?);
+        return SystemTime::now().checked_sub(age).ok_or_else(error);
+    }
+
+    let (date, time) = input.split_once(['T', ' ']).unwrap_or((input, "00:00:00"));
+    let date: Vec<u32> = date.split('-').map(str::parse).collect::<Result<_, _>>().map_err(|_| error())?;
+    let time: Vec<u32> = time.split(':').map(str::parse).collect::<Result<_, _>>().map_err(|_| error())?;
+
+    let (&[year, month, day], &[hour, minute, ref second @ ..]) = (date.as_slice(), time.as_slice()) else {
+        return Err(error());
+    };
+    let second = second.first().copied().unwrap_or(0);
+    let timestamp = i32::try_from(year).ok()
+        .and_then(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .and_then(|date| date.and_hms_opt(hour, minute, second))
+        .map(|date_time| date_time.and_utc().timestamp())
+        .ok_or_else(error)?;
+    let seconds = u64::try_from(timestamp).map_err(|_| error())?;
+    Ok(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
 /// Validate that a directory exists and is readable
 pub fn validate_directory(path: &Path) -> Result<(), String> {
     if !path.exists() {
@@ -79,6 +136,218 @@ pub fn get_file_info(path: &Path) -> Result<FileInfo, std::io::Error> {
     })
 }
 
+/// Append a suffix to a path's file name (`a.jpg` → `a.jpg.bak`) without a lossy UTF-8 round trip
+pub fn append_to_file_name(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Check for names Windows reserves for devices (`CON`, `NUL`, `COM1`, ...), with or without an extension
+pub fn is_reserved_windows_name(file_name: &OsStr) -> bool {
+    let name = file_name.to_string_lossy();
+    let stem = name.split('.').next().unwrap_or_default().trim_end_matches(' ').to_ascii_uppercase();
+
+    match stem.as_str() {
+        "CON" | "PRN" | "AUX" | "NUL" => true,
+        _ => {
+            let (prefix, digit) = stem.split_at(stem.len().min(3));
+            matches!(prefix, "COM" | "LPT") && matches!(digit, "1" | "2" | "3" | "4" | "5" | "6" | "7" | "8" | "9")
+        }
+    }
+}
+
+/// Make a file name creatable on Windows
+///
+/// Reserved device names get a leading underscore, and trailing dots and
+/// spaces (which Windows silently strips) are replaced with underscores.
+pub fn windows_safe_file_name(file_name: &OsStr) -> OsString {
+    let mut safe = if is_reserved_windows_name(file_name) {
+        let mut prefixed = OsString::from("_");
+        prefixed.push(file_name);
+        prefixed
+    } else {
+        file_name.to_owned()
+    };
+
+    if let Some(name) = safe.to_str() {
+        let trimmed = name.trim_end_matches(['.', ' ']);
+        if trimmed.len() != name.len() {
+            safe = format!("{}{}", trimmed, "_".repeat(name.len() - trimmed.len())).into();
+        }
+    }
+
+    safe
+}
+
+/// Prefix long Windows paths with `\\?\` so they aren't limited to MAX_PATH
+///
+/// Relative paths are made absolute and `.`/`..` components resolved first,
+/// since extended-length paths are passed to the filesystem verbatim.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> PathBuf {
+    use std::path::{Component, Prefix};
+
+    if path.as_os_str().len() < WINDOWS_PATH_LIMIT {
+        return path.to_path_buf();
+    }
+    let absolute = match std::path::absolute(path) {
+        Ok(absolute) => absolute,
+        Err(_) => return path.to_path_buf(),
+    };
+
+    let mut result = OsString::new();
+    let mut parts: Vec<&OsStr> = Vec::new();
+    for component in absolute.components() {
+        match component {
+            Component::Prefix(prefix) => match prefix.kind() {
+                Prefix::Disk(letter) => result.push(format!(r"\\?\{}:", letter as char)),
+                Prefix::UNC(server, share) => {
+                    result.push(r"\\?\UNC\");
+                    result.push(server);
+                    result.push(r"\");
+                    result.push(share);
+                }
+                // Already verbatim, or a device path we shouldn't rewrite
+                _ => return path.to_path_buf(),
+            },
+            Component::RootDir | Component::CurDir => {}
+            Component::ParentDir => {
+                parts.pop();
+            }
+            Component::Normal(part) => parts.push(part),
+        }
+    }
+
+    for part in parts {
+        result.push(r"\");
+        result.push(part);
+    }
+    PathBuf::from(result)
+}
+
+/// Other platforms have no MAX_PATH limit to work around
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> PathBuf {
+    path.to_path_buf()
+}
+
+/// Run `f` with write access to `path`, restoring its original permissions afterwards
+///
+/// The permissions are restored even when `f` fails, and also apply to a file
+/// that `f` replaced (ExifTool swaps in a rewritten copy rather than editing in place).
+pub fn with_write_access<T>(
+    path: &Path,
+    f: impl FnOnce() -> Result<T, Box<dyn std::error::Error>>,
+) -> Result<T, Box<dyn std::error::Error>> {
+    let original = std::fs::metadata(path)?.permissions();
+    if !original.readonly() {
+        return f();
+    }
+
+    std::fs::set_permissions(path, writable(&original))?;
+    let result = f();
+    let restored = std::fs::set_permissions(path, original);
+
+    let value = result?;
+    restored?;
+    Ok(value)
+}
+
+/// The given permissions with the owner allowed to write
+#[cfg(unix)]
+fn writable(permissions: &std::fs::Permissions) -> std::fs::Permissions {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::Permissions::from_mode(permissions.mode() | 0o200)
+}
+
+/// The given permissions with the read-only attribute cleared
+#[cfg(not(unix))]
+fn writable(permissions: &std::fs::Permissions) -> std::fs::Permissions {
+    let mut permissions = permissions.clone();
+    // Only the owner-facing read-only attribute exists here, so this can't widen access
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    permissions
+}
+
+/// Lower this process's CPU priority, like `nice -n 10`, and on Linux its disk
+/// priority, like `ionice -c2 -n7`
+///
+/// ExifTool processes started afterwards inherit both.
+#[cfg(unix)]
+pub fn lower_priority() -> Result<(), String> {
+    // SAFETY: plain syscalls on the calling process, no pointers involved
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 10) } != 0 {
+        return Err(format!("setpriority failed: {}", std::io::Error::last_os_error()));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+        const IOPRIO_BEST_EFFORT_LOWEST: libc::c_long = (2 << 13) | 7;
+        // SAFETY: as above
+        if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, IOPRIO_BEST_EFFORT_LOWEST) } != 0 {
+            return Err(format!("ioprio_set failed: {}", std::io::Error::last_os_error()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn lower_priority() -> Result<(), String> {
+    Err("Lowering process priority is only supported on Unix".to_string())
+}
+
+/// Replace a file's contents by writing a sibling temporary file and renaming it into place
+///
+/// Readers that still have the old file open or mapped keep seeing the old contents.
+pub fn write_atomically(path: &Path, data: &[u8]) -> Result<(), std::io::Error> {
+    let temp_path = long_path(&append_to_file_name(path, ".cleaner-tmp"));
+    std::fs::write(&temp_path, data)?;
+    std::fs::rename(&temp_path, long_path(path)).inspect_err(|_| {
+        let _ = std::fs::remove_file(&temp_path);
+    })
+}
+
+/// Read-only contents of a file, memory-mapped where possible
+///
+/// Mapping avoids copying multi-hundred-MB TIFFs onto the heap; only the pages
+/// actually touched (usually just the metadata) are loaded from disk.
+#[derive(Debug)]
+pub enum FileData {
+    Mapped(memmap2::Mmap),
+    Owned(Vec<u8>),
+}
+
+impl std::ops::Deref for FileData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            FileData::Mapped(mmap) => mmap,
+            FileData::Owned(data) => data,
+        }
+    }
+}
+
+/// Open a file for reading, mapping it into memory unless it is empty or can't be mapped
+pub fn map_file(path: &Path) -> Result<FileData, std::io::Error> {
+    let path = long_path(path);
+    let file = std::fs::File::open(&path)?;
+    if file.metadata()?.len() == 0 {
+        return Ok(FileData::Owned(Vec::new()));
+    }
+
+    // SAFETY: the mapping is read-only, and ExifTool replaces files by renaming
+    // a new copy over them rather than writing in place, so the mapped pages
+    // stay valid for as long as we hold them.
+    match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(mmap) => Ok(FileData::Mapped(mmap)),
+        Err(_) => std::fs::read(&path).map(FileData::Owned),
+    }
+}
+
 #[derive(Debug)]
 pub struct FileInfo {
     pub size: u64,
@@ -198,17 +467,15 @@ mod tests {
     use tempfile::TempDir;
 
     #[test]
-    fn test_is_supported_image() {
-        assert!(is_supported_image(Path::new("test.jpg")));
-        assert!(is_supported_image(Path::new("test.jpeg")));
-        assert!(is_supported_image(Path::new("test.JPEG")));
-        assert!(is_supported_image(Path::new("test.tiff")));
-        assert!(is_supported_image(Path::new("photo.TIF")));
-        
-        assert!(!is_supported_image(Path::new("test.png")));
-        assert!(!is_supported_image(Path::new("test.gif")));
-        assert!(!is_supported_image(Path::new("test.txt")));
-        assert!(!is_supported_image(Path::new("test")));
+    fn test_write_atomically() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("photo.jpg");
+        fs::write(&file_path, b"old").unwrap();
+
+        write_atomically(&file_path, b"new").unwrap();
+
+        assert_eq!(fs::read(&file_path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
     }
 
     #[test]
@@ -221,6 +488,42 @@ mod tests {
         assert_eq!(format_file_size(1024 * 1024 * 1024), "1.0 GB");
     }
 
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024"), Ok(1024));
+        assert_eq!(parse_size("64K"), Ok(64 * 1024));
+        assert_eq!(parse_size("10m"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("2GB"), Ok(2 * 1024 * 1024 * 1024));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+        assert!(parse_size("").is_err());
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("99999999999T").is_err());
+    }
+
+    #[test]
+    fn test_parse_time_bound() {
+        use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+        assert_eq!(parse_time_bound("1970-01-02"), Ok(UNIX_EPOCH + Duration::from_secs(86_400)));
+        assert_eq!(
+            parse_time_bound("2024-03-01T12:30:15"),
+            Ok(UNIX_EPOCH + Duration::from_secs(1_709_296_215))
+        );
+
+        let week_ago = parse_time_bound("7d").unwrap();
+        let age = SystemTime::now().duration_since(week_ago).unwrap();
+        assert!(age >= Duration::from_secs(7 * 86_400) && age < Duration::from_secs(7 * 86_400 + 60));
+
+        assert!(parse_time_bound("2024-13-01").is_err());
+        assert!(parse_time_bound("2024-02-31").is_err());
+        assert!(parse_time_bound("2023-02-29").is_err());
+        assert!(parse_time_bound("2024-02-29").is_ok());
+        assert!(parse_time_bound("1969-12-31").is_err());
+        assert!(parse_time_bound("30M").is_err());
+        assert!(parse_time_bound("yesterday").is_err());
+        assert!(parse_time_bound("7y").is_err());
+    }
+
     #[test]
     fn test_get_file_extension() {
         assert_eq!(get_file_extension(Path::new("test.jpg")), Some("jpg".to_string()));
@@ -253,6 +556,86 @@ mod tests {
         assert!(validate_directory(&temp_file).is_err());
     }
 
+    #[test]
+    fn test_append_to_file_name() {
+        assert_eq!(append_to_file_name(Path::new("dir/photo.jpg"), ".bak"), PathBuf::from("dir/photo.jpg.bak"));
+        assert_eq!(append_to_file_name(Path::new("dir/photo"), ".bak"), PathBuf::from("dir/photo.bak"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_append_to_non_utf8_file_name() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = Path::new(OsStr::from_bytes(b"dir/caf\xe9.jpg"));
+        let backup = append_to_file_name(path, ".bak");
+        assert_eq!(backup.as_os_str().as_bytes(), b"dir/caf\xe9.jpg.bak");
+    }
+
+    #[test]
+    fn test_reserved_windows_names() {
+        for name in ["CON", "con.jpg", "Nul.tar.gz", "COM1.jpg", "lpt9", "AUX .jpg"] {
+            assert!(is_reserved_windows_name(OsStr::new(name)), "{} should be reserved", name);
+        }
+        for name in ["CONSOLE.jpg", "COM0.jpg", "COM10.jpg", "photo.jpg", "my con.jpg", "日本.jpg"] {
+            assert!(!is_reserved_windows_name(OsStr::new(name)), "{} should not be reserved", name);
+        }
+    }
+
+    #[test]
+    fn test_windows_safe_file_name() {
+        assert_eq!(windows_safe_file_name(OsStr::new("CON.jpg")), OsString::from("_CON.jpg"));
+        assert_eq!(windows_safe_file_name(OsStr::new("photo.jpg. ")), OsString::from("photo.jpg__"));
+        assert_eq!(windows_safe_file_name(OsStr::new("fotografía.jpg")), OsString::from("fotografía.jpg"));
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn test_long_path() {
+        let short = Path::new(r"C:\photos\a.jpg");
+        assert_eq!(long_path(short), short);
+
+        let long = format!(r"C:\photos\{}\..\{}\a.jpg", "x".repeat(200), "y".repeat(100));
+        let expected = format!(r"\\?\C:\photos\{}\a.jpg", "y".repeat(100));
+        assert_eq!(long_path(Path::new(&long)), PathBuf::from(expected));
+
+        let unc = format!(r"\\server\share\{}.jpg", "z".repeat(250));
+        let expected = format!(r"\\?\UNC\server\share\{}.jpg", "z".repeat(250));
+        assert_eq!(long_path(Path::new(&unc)), PathBuf::from(expected));
+    }
+
+    #[test]
+    fn test_with_write_access_restores_read_only() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("card.jpg");
+        fs::write(&file_path, b"original").unwrap();
+        let mut permissions = fs::metadata(&file_path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&file_path, permissions).unwrap();
+
+        with_write_access(&file_path, || Ok(fs::write(&file_path, b"cleaned")?)).unwrap();
+
+        assert_eq!(fs::read(&file_path).unwrap(), b"cleaned");
+        assert!(fs::metadata(&file_path).unwrap().permissions().readonly());
+
+        let failed: Result<(), _> = with_write_access(&file_path, || Err("ExifTool failed".into()));
+        assert!(failed.is_err());
+        assert!(fs::metadata(&file_path).unwrap().permissions().readonly());
+    }
+
+    #[test]
+    fn test_map_file() {
+        let temp_dir = TempDir::new().unwrap();
+        let file_path = temp_dir.path().join("image.tif");
+        fs::write(&file_path, b"II*\0 tiff data").unwrap();
+        let empty_path = temp_dir.path().join("empty.tif");
+        fs::write(&empty_path, b"").unwrap();
+
+        assert_eq!(&*map_file(&file_path).unwrap(), b"II*\0 tiff data");
+        assert!(map_file(&empty_path).unwrap().is_empty());
+        assert!(map_file(&temp_dir.path().join("missing.tif")).is_err());
+    }
+
     #[test]
     fn test_progress_tracker() {
         let mut tracker = ProgressTracker::new(100);
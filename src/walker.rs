@@ -0,0 +1,470 @@
+//! Directory traversal for batch runs.
+//!
+//! Centralizes how input directories are walked (recursion, symlink
+//! handling, limits on how much a run may touch, and per-directory policy
+//! files) and how hard-linked inputs are recognised, so their cleaned copies
+//! can share one file in the output directory too.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use clap::ValueEnum;
+use exif::Tag;
+use serde::Deserialize;
+use walkdir::{DirEntry, WalkDir};
+use crate::cli::{Config, SortOrder};
+use crate::privacy::{self, PolicySpec, PrivacyLevel};
+use crate::utils;
+
+/// Name of the per-directory policy file
+pub const POLICY_FILE: &str = ".privacy-clean.toml";
+
+/// Build the directory walker for a run
+///
+/// Symlinks are only followed when `follow_symlinks` is set; walkdir then
+/// reports symlink cycles as errors instead of looping. With `--sort`, each
+/// directory's entries are read in full and sorted before any is processed.
+/// Files the cleaner wrote itself (see `is_cleaner_file`) are left out.
+pub fn walk(config: &Config) -> impl Iterator<Item = walkdir::Result<DirEntry>> {
+    let walker = WalkDir::new(&config.input).follow_links(config.follow_symlinks);
+    let walker = match config.sort {
+        Some(SortOrder::Name) => walker.sort_by_file_name(),
+        Some(SortOrder::Mtime) => walker.sort_by(|a, b| by_key(a, b, |metadata| metadata.modified().ok())),
+        Some(SortOrder::Size) => walker.sort_by(|a, b| by_key(a, b, |metadata| Some(metadata.len()))),
+        None => walker,
+    };
+    let walker = match (config.recursive, config.max_depth) {
+        // Depth 0 is the input directory itself, so N levels below it is depth N + 1
+        (true, Some(max_depth)) => walker.max_depth(max_depth.saturating_add(1)),
+        (true, None) => walker,
+        (false, _) => walker.max_depth(1),
+    };
+    walker.into_iter().filter_entry(|entry| !(entry.file_type().is_file() && is_cleaner_file(entry.path())))
+}
+
+/// Whether a file is one the cleaner leaves beside the images it cleans
+///
+/// Backups (`photo.jpg.bak`, `.bak.N`) hold the originals and look like
+/// images by content, so cleaning them would lose exactly what they keep.
+/// `.cleaner-tmp` files are unfinished writes and `.error.txt` files are
+/// quarantine notes.
+pub fn is_cleaner_file(path: &Path) -> bool {
+    let Some(name) = path.file_name() else { return false };
+    let name = name.to_string_lossy();
+    let numbered_backup = name.rsplit_once(".bak.")
+        .map_or(false, |(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    name.ends_with(".bak") || numbered_backup || name.ends_with(".cleaner-tmp") || name.ends_with(".error.txt")
+}
+
+/// Compare two entries by a metadata key, then by name so ties still have one order
+fn by_key<K: Ord>(a: &DirEntry, b: &DirEntry, key: impl Fn(&Metadata) -> Option<K>) -> Ordering {
+    let value = |entry: &DirEntry| entry.metadata().ok().and_then(|metadata| key(&metadata));
+    value(a).cmp(&value(b)).then_with(|| a.file_name().cmp(b.file_name()))
+}
+
+/// Check a file against the date and size selectors (`--newer-than`, `--min-size`, ...)
+pub fn is_selected(config: &Config, metadata: &Metadata) -> bool {
+    let size = metadata.len();
+    if config.min_size.map_or(false, |min| size < min) || config.max_size.map_or(false, |max| size > max) {
+        return false;
+    }
+
+    if config.newer_than.is_none() && config.older_than.is_none() {
+        return true;
+    }
+    // A file whose modification time can't be read can't be shown to be in range
+    let Ok(modified) = metadata.modified() else {
+        return false;
+    };
+    config.newer_than.map_or(true, |after| modified > after)
+        && config.older_than.map_or(true, |before| modified < before)
+}
+
+/// A `--max-files` or `--max-total-size` guard that stopped the run
+#[derive(Debug, Clone, PartialEq)]
+pub enum LimitReached {
+    Files(u64),
+    TotalSize(u64),
+}
+
+impl std::fmt::Display for LimitReached {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitReached::Files(limit) => write!(f, "--max-files limit of {} files reached", limit),
+            LimitReached::TotalSize(limit) => {
+                write!(f, "--max-total-size limit of {} reached", utils::format_file_size(*limit))
+            }
+        }
+    }
+}
+
+/// Running totals checked against the configured file-count and size limits
+#[derive(Debug, Default)]
+pub struct WalkLimits {
+    max_files: Option<u64>,
+    max_total_size: Option<u64>,
+    files: u64,
+    bytes: u64,
+}
+
+impl WalkLimits {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            max_files: config.max_files,
+            max_total_size: config.max_total_size,
+            ..Self::default()
+        }
+    }
+
+    /// Count a file of `size` bytes, or report the limit it would exceed
+    pub fn admit(&mut self, size: u64) -> Result<(), LimitReached> {
+        if let Some(max_files) = self.max_files {
+            if self.files >= max_files {
+                return Err(LimitReached::Files(max_files));
+            }
+        }
+        if let Some(max_total_size) = self.max_total_size {
+            if self.bytes.saturating_add(size) > max_total_size {
+                return Err(LimitReached::TotalSize(max_total_size));
+            }
+        }
+
+        self.files += 1;
+        self.bytes += size;
+        Ok(())
+    }
+}
+
+/// Identity of a file on disk, shared by all hard links to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FileId {
+    device: u64,
+    inode: u64,
+}
+
+/// Identity of a file with more than one hard link, or `None` for singly-linked files
+#[cfg(unix)]
+pub fn hardlinked_file_id(metadata: &Metadata) -> Option<FileId> {
+    use std::os::unix::fs::MetadataExt;
+
+    (metadata.nlink() > 1).then(|| FileId {
+        device: metadata.dev(),
+        inode: metadata.ino(),
+    })
+}
+
+/// Hard-link identities aren't available through stable APIs on this platform
+#[cfg(not(unix))]
+pub fn hardlinked_file_id(_metadata: &Metadata) -> Option<FileId> {
+    None
+}
+
+/// Contents of a `.privacy-clean.toml`
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    privacy_level: Option<String>,
+    #[serde(default)]
+    keep_tags: Vec<String>,
+    #[serde(default)]
+    remove_tags: Vec<String>,
+}
+
+/// A directory's policy after merging every policy file from the input directory down to it
+#[derive(Debug, Clone, Default, PartialEq)]
+struct DirectoryPolicy {
+    level: Option<PrivacyLevel>,
+    keep: Vec<Tag>,
+    remove: Vec<Tag>,
+}
+
+impl DirectoryPolicy {
+    /// Layer a deeper directory's policy file over this one
+    fn merge(&mut self, file: PolicyFile) -> Result<(), String> {
+        if let Some(ref level) = file.privacy_level {
+            self.level = Some(PrivacyLevel::from_str(level, true).map_err(|_| format!("Unknown privacy level '{}'", level))?);
+        }
+        for name in &file.keep_tags {
+            let tag = privacy::parse_tag(name)?;
+            self.remove.retain(|removed| *removed != tag);
+            if !self.keep.contains(&tag) {
+                self.keep.push(tag);
+            }
+        }
+        for name in &file.remove_tags {
+            let tag = privacy::parse_tag(name)?;
+            self.keep.retain(|kept| *kept != tag);
+            if !self.remove.contains(&tag) {
+                self.remove.push(tag);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Privacy levels set by `.privacy-clean.toml` files, resolved per directory
+///
+/// A file applies to its directory and everything below it. Deeper files
+/// replace the privacy level and add to the kept and removed tags.
+pub struct DirectoryPolicies {
+    root: PathBuf,
+    base_level: PrivacyLevel,
+    resolved: HashMap<PathBuf, DirectoryPolicy>,
+}
+
+impl DirectoryPolicies {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            root: config.input.clone(),
+            base_level: config.privacy_level.clone(),
+            resolved: HashMap::new(),
+        }
+    }
+
+    /// The privacy level for `file`, or `None` when no policy file applies to it
+    pub fn level_for(&mut self, file: &Path) -> Result<Option<PrivacyLevel>, Box<dyn std::error::Error>> {
+        let policy = match file.parent() {
+            Some(dir) => self.resolve(dir)?,
+            None => DirectoryPolicy::default(),
+        };
+        if policy == DirectoryPolicy::default() {
+            return Ok(None);
+        }
+
+        let level = policy.level.unwrap_or_else(|| self.base_level.clone());
+        if policy.keep.is_empty() && policy.remove.is_empty() {
+            return Ok(Some(level));
+        }
+        let spec = PolicySpec::from_level(&level).keeping_tags(policy.keep).removing_tags(policy.remove);
+        Ok(Some(PrivacyLevel::Custom(spec)))
+    }
+
+    fn resolve(&mut self, dir: &Path) -> Result<DirectoryPolicy, Box<dyn std::error::Error>> {
+        if let Some(policy) = self.resolved.get(dir) {
+            return Ok(policy.clone());
+        }
+
+        // Policy files above the input directory don't apply
+        let mut policy = match dir.parent() {
+            Some(parent) if dir != self.root && parent.starts_with(&self.root) => self.resolve(parent)?,
+            _ => DirectoryPolicy::default(),
+        };
+
+        let path = dir.join(POLICY_FILE);
+        if path.is_file() {
+            let file: PolicyFile = toml::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            policy.merge(file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        }
+
+        self.resolved.insert(dir.to_path_buf(), policy.clone());
+        Ok(policy)
+    }
+
+    /// Forget the policies of directories other than `dir` and its ancestors
+    ///
+    /// Keeps the cache small on huge trees once the walk has moved on.
+    pub fn retain_ancestors(&mut self, dir: &Path) {
+        self.resolved.retain(|resolved, _| dir.starts_with(resolved));
+    }
+}
+
+/// Output path written for each hard-linked input, safe to share between threads
+#[derive(Debug, Default)]
+pub struct HardlinkTracker {
+    outputs: Mutex<HashMap<FileId, PathBuf>>,
+}
+
+impl HardlinkTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output already written for another link to the same file
+    pub fn find(&self, id: FileId) -> Option<PathBuf> {
+        self.outputs.lock().ok()?.get(&id).cloned()
+    }
+
+    pub fn insert(&self, id: FileId, output: PathBuf) {
+        if let Ok(mut outputs) = self.outputs.lock() {
+            outputs.entry(id).or_insert(output);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_walk_respects_recursion() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir(temp_dir.path().join("nested")).unwrap();
+        fs::write(temp_dir.path().join("nested/photo.jpg"), b"jpeg").unwrap();
+
+        let mut config = Config { input: temp_dir.path().to_path_buf(), ..Config::default() };
+        let count = |config: &Config| walk(config).into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file()).count();
+
+        assert_eq!(count(&config), 0);
+        config.recursive = true;
+        assert_eq!(count(&config), 1);
+    }
+
+    #[test]
+    fn test_walk_sorts_entries() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join("b.jpg"), b"jpeg").unwrap();
+        fs::write(temp_dir.path().join("c.jpg"), b"j").unwrap();
+        fs::write(temp_dir.path().join("a.jpg"), b"a larger jpeg").unwrap();
+
+        let mut config = Config { input: temp_dir.path().to_path_buf(), ..Config::default() };
+        let names = |config: &Config| -> Vec<String> {
+            walk(config).into_iter()
+                .filter_map(Result::ok)
+                .filter(|e| e.file_type().is_file())
+                .map(|e| e.file_name().to_string_lossy().into_owned())
+                .collect()
+        };
+
+        config.sort = Some(SortOrder::Name);
+        assert_eq!(names(&config), ["a.jpg", "b.jpg", "c.jpg"]);
+        config.sort = Some(SortOrder::Size);
+        assert_eq!(names(&config), ["c.jpg", "b.jpg", "a.jpg"]);
+    }
+
+    #[test]
+    fn test_is_cleaner_file() {
+        assert!(is_cleaner_file(Path::new("a/photo.jpg.bak")));
+        assert!(is_cleaner_file(Path::new("a/photo.jpg.bak.12")));
+        assert!(is_cleaner_file(Path::new("a/photo.jpg.cleaner-tmp")));
+        assert!(is_cleaner_file(Path::new("quarantine/photo.jpg.error.txt")));
+        assert!(!is_cleaner_file(Path::new("a/photo.jpg")));
+        assert!(!is_cleaner_file(Path::new("a/photo.bak.jpg")));
+        assert!(!is_cleaner_file(Path::new("a/photo.jpg.bak.old")));
+    }
+
+    #[test]
+    fn test_walk_max_depth() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("a/b")).unwrap();
+        fs::write(temp_dir.path().join("top.jpg"), b"jpeg").unwrap();
+        fs::write(temp_dir.path().join("a/one.jpg"), b"jpeg").unwrap();
+        fs::write(temp_dir.path().join("a/b/two.jpg"), b"jpeg").unwrap();
+
+        let config = Config {
+            input: temp_dir.path().to_path_buf(),
+            recursive: true,
+            max_depth: Some(1),
+            ..Config::default()
+        };
+        let files = walk(&config).into_iter().filter_map(Result::ok).filter(|e| e.file_type().is_file()).count();
+
+        assert_eq!(files, 2);
+    }
+
+    #[test]
+    fn test_is_selected() {
+        use std::time::{Duration, SystemTime};
+
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("photo.jpg");
+        fs::write(&path, vec![0u8; 2048]).unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        let hour = Duration::from_secs(3600);
+
+        assert!(is_selected(&Config::default(), &metadata));
+        assert!(is_selected(&Config { min_size: Some(1024), max_size: Some(4096), ..Config::default() }, &metadata));
+        assert!(!is_selected(&Config { min_size: Some(4096), ..Config::default() }, &metadata));
+        assert!(!is_selected(&Config { max_size: Some(1024), ..Config::default() }, &metadata));
+
+        let now = SystemTime::now();
+        assert!(is_selected(&Config { newer_than: Some(now - hour), ..Config::default() }, &metadata));
+        assert!(!is_selected(&Config { newer_than: Some(now + hour), ..Config::default() }, &metadata));
+        assert!(!is_selected(&Config { older_than: Some(now - hour), ..Config::default() }, &metadata));
+    }
+
+    #[test]
+    fn test_walk_limits() {
+        let config = Config { max_files: Some(2), max_total_size: Some(100), ..Config::default() };
+
+        let mut limits = WalkLimits::new(&config);
+        assert!(limits.admit(40).is_ok());
+        assert_eq!(limits.admit(70), Err(LimitReached::TotalSize(100)));
+        assert!(limits.admit(60).is_ok());
+        assert_eq!(limits.admit(0), Err(LimitReached::Files(2)));
+
+        assert!(WalkLimits::new(&Config::default()).admit(u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn test_policy_files_merge_down_the_tree() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::create_dir_all(temp_dir.path().join("work/clients")).unwrap();
+        fs::create_dir_all(temp_dir.path().join("family")).unwrap();
+        fs::write(temp_dir.path().join("work").join(POLICY_FILE), "privacy_level = \"paranoid\"\n").unwrap();
+        fs::write(temp_dir.path().join("work/clients").join(POLICY_FILE), "remove_tags = [\"Make\"]\n").unwrap();
+        fs::write(temp_dir.path().join("family").join(POLICY_FILE), "privacy_level = \"minimal\"\n").unwrap();
+
+        let config = Config { input: temp_dir.path().to_path_buf(), ..Config::default() };
+        let mut policies = DirectoryPolicies::new(&config);
+
+        assert_eq!(policies.level_for(&temp_dir.path().join("top.jpg")).unwrap(), None);
+        assert_eq!(policies.level_for(&temp_dir.path().join("work/a.jpg")).unwrap(), Some(PrivacyLevel::Paranoid));
+        assert_eq!(policies.level_for(&temp_dir.path().join("family/b.jpg")).unwrap(), Some(PrivacyLevel::Minimal));
+
+        let nested = policies.level_for(&temp_dir.path().join("work/clients/c.jpg")).unwrap().unwrap();
+        assert!(!privacy::PrivacyPolicy::should_preserve_tag(Tag::Make, &nested));
+        assert!(privacy::PrivacyPolicy::should_preserve_tag(Tag::FNumber, &nested));
+    }
+
+    #[test]
+    fn test_invalid_policy_file_is_an_error() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(temp_dir.path().join(POLICY_FILE), "privacy_level = \"extreme\"\n").unwrap();
+
+        let config = Config { input: temp_dir.path().to_path_buf(), ..Config::default() };
+        let error = DirectoryPolicies::new(&config).level_for(&temp_dir.path().join("a.jpg")).unwrap_err();
+        assert!(error.to_string().contains("Unknown privacy level"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_hardlinked_file_id() {
+        let temp_dir = TempDir::new().unwrap();
+        let original = temp_dir.path().join("a.jpg");
+        let link = temp_dir.path().join("b.jpg");
+        let single = temp_dir.path().join("c.jpg");
+        fs::write(&original, b"jpeg").unwrap();
+        fs::hard_link(&original, &link).unwrap();
+        fs::write(&single, b"jpeg").unwrap();
+
+        let id = hardlinked_file_id(&fs::metadata(&original).unwrap());
+        assert!(id.is_some());
+        assert_eq!(id, hardlinked_file_id(&fs::metadata(&link).unwrap()));
+        assert_eq!(hardlinked_file_id(&fs::metadata(&single).unwrap()), None);
+
+        let tracker = HardlinkTracker::new();
+        tracker.insert(id.unwrap(), PathBuf::from("out/a.jpg"));
+        assert_eq!(tracker.find(id.unwrap()), Some(PathBuf::from("out/a.jpg")));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_symlink_cycle_is_reported() {
+        let temp_dir = TempDir::new().unwrap();
+        std::os::unix::fs::symlink(temp_dir.path(), temp_dir.path().join("loop")).unwrap();
+
+        let config = Config {
+            input: temp_dir.path().to_path_buf(),
+            recursive: true,
+            follow_symlinks: true,
+            ..Config::default()
+        };
+
+        assert!(walk(&config).into_iter().any(|e| e.map_or_else(|e| e.loop_ancestor().is_some(), |_| false)));
+    }
+}
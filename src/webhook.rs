@@ -0,0 +1,16 @@
+//! Run report delivery to an HTTP endpoint when a run completes (`--webhook`).
+
+use std::time::Duration;
+
+/// How long to wait for the endpoint before giving up
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// POST a JSON document to `url`
+pub fn post(url: &str, report: &serde_json::Value) -> Result<(), Box<dyn std::error::Error>> {
+    ureq::post(url)
+        .timeout(TIMEOUT)
+        .set("Content-Type", "application/json")
+        .set("User-Agent", concat!("privacy-exif-cleaner/", env!("CARGO_PKG_VERSION")))
+        .send_string(&report.to_string())?;
+    Ok(())
+}
@@ -0,0 +1,163 @@
+//! Golden-file integration tests: every privacy level over a synthetic corpus.
+//!
+//! `tests/golden/expected.txt` lists, per fixture and level, the tags that
+//! must be gone after cleaning and the ones that must survive unchanged.
+//! Output is re-parsed with the `exif` crate directly rather than through the
+//! cleaner's analyzer, so a bug shared by analyzer and remover can't hide
+//! itself. Cleaning needs ExifTool:
+//!
+//! ```text
+//! cargo test --features testkit --test golden -- --include-ignored
+//! ```
+
+use std::io::Cursor;
+use clap::ValueEnum;
+use exif::{In, Reader, Tag};
+use privacy_exif_cleaner::privacy::parse_tag;
+use privacy_exif_cleaner::testkit::TestImage;
+use privacy_exif_cleaner::{MetadataRemover, PrivacyLevel};
+
+const EXPECTATIONS: &str = include_str!("golden/expected.txt");
+/// Property that marks the fixtures' XMP packet
+const XMP_MARKER: &str = "xmp:CreatorTool=\"Darkroom 4.2\"";
+
+/// A tag in the golden file: an EXIF tag, or the XMP packet as a whole
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Expected {
+    Tag(Tag),
+    Xmp,
+}
+
+struct Case {
+    line: usize,
+    fixture: String,
+    level: PrivacyLevel,
+    removed: Vec<Expected>,
+    kept: Vec<Expected>,
+}
+
+fn fixture(name: &str) -> Vec<u8> {
+    match name {
+        "jpeg-gps" => TestImage::jpeg()
+            .with_tag(Tag::Make, "Testcam")
+            .with_tag(Tag::Model, "T-100")
+            .with_tag(Tag::Artist, "Jane Doe")
+            .with_tag(Tag::Software, "Darkroom 4.2")
+            .with_tag(Tag::DateTimeOriginal, "2024:05:01 12:30:00")
+            .with_gps(48.8584, 2.2945)
+            .build(),
+        "tiff-serials" => TestImage::tiff()
+            .with_tag(Tag::Make, "Testcam")
+            .with_tag(Tag::ImageDescription, "Bench 3, lab B")
+            .with_tag(Tag::BodySerialNumber, "SN-1234")
+            .with_tag(Tag::LensSerialNumber, "LN-5678")
+            .build(),
+        "jpeg-xmp" => TestImage::jpeg()
+            .with_tag(Tag::Make, "Testcam")
+            .with_xmp(&format!(
+                r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#"><rdf:Description xmlns:xmp="http://ns.adobe.com/xap/1.0/" {}/></rdf:RDF></x:xmpmeta>"#,
+                XMP_MARKER
+            ))
+            .build(),
+        other => panic!("Unknown fixture '{}'", other),
+    }
+}
+
+fn parse_list(list: &str) -> Vec<Expected> {
+    if list == "-" {
+        return Vec::new();
+    }
+    list.split(',')
+        .map(str::trim)
+        .map(|name| match name {
+            "XMP" => Expected::Xmp,
+            name => Expected::Tag(parse_tag(name).unwrap()),
+        })
+        .collect()
+}
+
+fn cases() -> Vec<Case> {
+    EXPECTATIONS.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.starts_with('#'))
+        .map(|(index, line)| {
+            let columns: Vec<&str> = line.split('|').map(str::trim).collect();
+            assert_eq!(columns.len(), 4, "line {}: expected 4 columns", index + 1);
+            Case {
+                line: index + 1,
+                fixture: columns[0].to_string(),
+                level: PrivacyLevel::from_str(columns[1], true).unwrap(),
+                removed: parse_list(columns[2]),
+                kept: parse_list(columns[3]),
+            }
+        })
+        .collect()
+}
+
+/// The field's value as the EXIF reader sees it, or `None` when the tag is absent
+fn value(data: &[u8], tag: Tag) -> Option<String> {
+    let exif = Reader::new().read_from_container(&mut Cursor::new(data)).ok()?;
+    exif.get_field(tag, In::PRIMARY).map(|field| format!("{:?}", field.value))
+}
+
+fn has_xmp(data: &[u8]) -> bool {
+    data.windows(XMP_MARKER.len()).any(|window| window == XMP_MARKER.as_bytes())
+}
+
+/// Mismatches between a cleaned fixture and its expectations
+fn check(case: &Case, original: &[u8], cleaned: &[u8]) -> Vec<String> {
+    let mut failures = Vec::new();
+    let at = format!("line {} ({} at {:?})", case.line, case.fixture, case.level);
+
+    for expected in &case.removed {
+        let present = match expected {
+            Expected::Tag(tag) => value(cleaned, *tag).is_some(),
+            Expected::Xmp => has_xmp(cleaned),
+        };
+        if present {
+            failures.push(format!("{}: {:?} should have been removed", at, expected));
+        }
+    }
+    for expected in &case.kept {
+        match expected {
+            Expected::Tag(tag) if value(cleaned, *tag) != value(original, *tag) => failures.push(format!(
+                "{}: {} changed from {:?} to {:?}", at, tag, value(original, *tag), value(cleaned, *tag)
+            )),
+            Expected::Xmp if !has_xmp(cleaned) => failures.push(format!("{}: XMP should have been kept", at)),
+            _ => {}
+        }
+    }
+    failures
+}
+
+#[test]
+fn test_fixtures_have_every_expected_tag() {
+    let cases = cases();
+    assert_eq!(cases.len(), 15);
+
+    for case in &cases {
+        let original = fixture(&case.fixture);
+        for expected in case.removed.iter().chain(&case.kept) {
+            let present = match expected {
+                Expected::Tag(tag) => value(&original, *tag).is_some(),
+                Expected::Xmp => has_xmp(&original),
+            };
+            assert!(present, "line {}: fixture {} lacks {:?}", case.line, case.fixture, expected);
+        }
+    }
+}
+
+#[test]
+#[ignore] // Needs ExifTool
+fn test_golden_expectations() {
+    let remover = MetadataRemover::new();
+    let failures: Vec<String> = cases().iter()
+        .flat_map(|case| {
+            let original = fixture(&case.fixture);
+            let cleaned = remover.remove_privacy_data_from_bytes(&original, &case.level).unwrap();
+            check(case, &original, &cleaned)
+        })
+        .collect();
+
+    assert!(failures.is_empty(), "{} golden expectations failed:\n{}", failures.len(), failures.join("\n"));
+}
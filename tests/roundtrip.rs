@@ -0,0 +1,106 @@
+//! Round-trip properties of the removal backend, over generated images.
+//!
+//! For any mix of text tags, GPS, and privacy level, cleaning must leave an
+//! image that still decodes the same (same frame, same pixel data), and
+//! cleaning the result again must change nothing. Every case runs ExifTool:
+//!
+//! ```text
+//! cargo test --features testkit --test roundtrip -- --include-ignored
+//! ```
+
+use std::io::Cursor;
+use exif::{In, Reader, Tag};
+use proptest::prelude::*;
+use privacy_exif_cleaner::jpeg;
+use privacy_exif_cleaner::testkit::{TestFormat, TestImage};
+use privacy_exif_cleaner::{MetadataRemover, PrivacyLevel};
+
+/// Tags the generated images draw from: one or more of each privacy category
+const TAGS: &[Tag] = &[
+    Tag::Make,
+    Tag::Model,
+    Tag::Artist,
+    Tag::Copyright,
+    Tag::Software,
+    Tag::ImageDescription,
+    Tag::DateTimeOriginal,
+    Tag::BodySerialNumber,
+    Tag::LensSerialNumber,
+    Tag::CameraOwnerName,
+];
+
+fn format() -> impl Strategy<Value = TestFormat> {
+    prop_oneof![Just(TestFormat::Jpeg), Just(TestFormat::Tiff)]
+}
+
+fn level() -> impl Strategy<Value = PrivacyLevel> {
+    prop_oneof![
+        Just(PrivacyLevel::Minimal),
+        Just(PrivacyLevel::Standard),
+        Just(PrivacyLevel::Strict),
+        Just(PrivacyLevel::WebShare),
+        Just(PrivacyLevel::Paranoid),
+    ]
+}
+
+/// An image with a random subset of `TAGS`, printable ASCII values, and maybe a GPS position
+fn image() -> impl Strategy<Value = Vec<u8>> {
+    (
+        format(),
+        proptest::sample::subsequence(TAGS, 0..=TAGS.len()),
+        proptest::collection::vec("[ -~]{1,40}", TAGS.len()),
+        proptest::option::of((-90.0..90.0f64, -180.0..180.0f64)),
+    )
+        .prop_map(|(format, tags, values, gps)| {
+            let mut image = TestImage::new(format);
+            for (tag, value) in tags.into_iter().zip(values) {
+                image = image.with_tag(tag, &value);
+            }
+            if let Some((latitude, longitude)) = gps {
+                image = image.with_gps(latitude, longitude);
+            }
+            image.build()
+        })
+}
+
+/// What a decoder would see: frame size and the bytes it decodes
+fn image_data(data: &[u8]) -> Result<(u32, u32, Vec<u8>), String> {
+    if jpeg::is_jpeg(data) {
+        let layout = jpeg::parse_layout(data).map_err(|e| e.to_string())?;
+        let (width, height) = layout.frame_size().ok_or("no frame header")?;
+        return Ok((width as u32, height as u32, data[layout.scan_offset..].to_vec()));
+    }
+
+    let exif = Reader::new().read_from_container(&mut Cursor::new(data)).map_err(|e| e.to_string())?;
+    let uint = |tag| {
+        exif.get_field(tag, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .ok_or(format!("no {}", tag))
+    };
+    let (offset, count) = (uint(Tag::StripOffsets)? as usize, uint(Tag::StripByteCounts)? as usize);
+    let strip = data.get(offset..offset + count).ok_or("strip outside the file")?;
+    Ok((uint(Tag::ImageWidth)?, uint(Tag::ImageLength)?, strip.to_vec()))
+}
+
+proptest! {
+    // Each case starts ExifTool twice
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    #[ignore] // Needs ExifTool
+    fn cleaning_preserves_image_data(data in image(), level in level()) {
+        let cleaned = MetadataRemover::new().remove_privacy_data_from_bytes(&data, &level).unwrap();
+        let original = image_data(&data).unwrap();
+        prop_assert_eq!(image_data(&cleaned), Ok(original));
+    }
+
+    #[test]
+    #[ignore] // Needs ExifTool
+    fn cleaning_is_idempotent(data in image(), level in level()) {
+        let remover = MetadataRemover::new();
+        let once = remover.remove_privacy_data_from_bytes(&data, &level).unwrap();
+        let twice = remover.remove_privacy_data_from_bytes(&once, &level).unwrap();
+        let changed = once.iter().zip(&twice).filter(|(a, b)| a != b).count() + once.len().abs_diff(twice.len());
+        prop_assert!(changed == 0, "second pass changed {} of {} bytes", changed, once.len());
+    }
+}